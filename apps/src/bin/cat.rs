@@ -1,45 +1,60 @@
 #![no_std]
 #![no_main]
 
-extern crate alloc;
 #[macro_use]
 extern crate user_lib;
 
-use alloc::vec;
-use user_lib::fs::{OpenFlags, Stat, StatMode, close, fstat, open, read};
+use user_lib::fs::{OpenFlags, Stat, StatMode, close, fstat, open, read, write};
+
+const STDIN: usize = 0;
+const STDOUT: usize = 1;
+
+/// Copies `fd` to the standard output until a read returns `0`
+///
+/// A regular file ends at its size, the console never does, so `cat` without
+/// arguments keeps waiting for input.
+fn copy(fd: usize) -> bool {
+    let mut buf = [0u8; 512];
+    loop {
+        match read(fd, &mut buf) {
+            0 => return true,
+            len if len < 0 => return false,
+            len => {
+                write(STDOUT, &buf[..len as usize]);
+            }
+        }
+    }
+}
 
 #[no_mangle]
 extern "Rust" fn main(_argc: usize, argv: &[&str]) -> i32 {
+    if argv.len() == 1 {
+        return if copy(STDIN) { 0 } else { 1 };
+    }
+
+    let mut exit_code = 0;
     for filename in &argv[1..] {
         let fd = open(filename, OpenFlags::RDONLY);
         if fd == -1 {
             println!("{}: No such file or directory", filename);
+            exit_code = 1;
             continue;
         }
-
-        let mut stat = Stat::new();
-        match fstat(fd as usize, &mut stat) {
-            0 => {}
-            -1 => {
-                println!("{}: Bad file descriptor", fd);
-                continue;
-            }
-            _ => panic!(),
-        }
-
         let fd = fd as usize;
-        let size = stat.size as usize;
 
-        match stat.mode {
-            StatMode::REG => {
-                let mut buf = vec![0u8; size];
-                read(fd as usize, &mut buf);
-                print!("{}", core::str::from_utf8(&buf[..size]).unwrap());
-            }
-            _ => println!("{}: Is not a file", filename),
+        let mut stat = Stat::new();
+        if fstat(fd, &mut stat) == -1 {
+            println!("{}: Bad file descriptor", fd);
+            exit_code = 1;
+        } else if stat.mode != StatMode::REG {
+            println!("{}: Is not a file", filename);
+            exit_code = 1;
+        } else if !copy(fd) {
+            println!("{}: Read error", filename);
+            exit_code = 1;
         }
 
         close(fd);
     }
-    0
+    exit_code
 }
@@ -0,0 +1,70 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec;
+use user_lib::{
+    fs::{close, copy_file_range, open, read, unlink, write, OpenFlags},
+    process::get_time,
+};
+
+/// Bytes copied by each method
+const SIZE: usize = 1 << 20;
+/// The buffer of the read/write loop
+const BUFFER_SIZE: usize = 4096;
+const SRC_FILE: &str = "/copybench_src";
+const DST_FILE: &str = "/copybench_dst";
+
+/// Opens a fresh [`DST_FILE`], copies [`SRC_FILE`] into it with `copy` and returns the ms taken
+fn measure(copy: impl Fn(usize, usize) -> usize) -> isize {
+    let src = open(SRC_FILE, OpenFlags::RDONLY);
+    let dst = open(
+        DST_FILE,
+        OpenFlags::CREATE | OpenFlags::TRUNC | OpenFlags::WRONLY,
+    );
+    assert!(src >= 0 && dst >= 0, "failed to open the benchmark files");
+    let start = get_time();
+    assert_eq!(copy(src as usize, dst as usize), SIZE);
+    let time = get_time() - start;
+    close(src as usize);
+    close(dst as usize);
+    time
+}
+
+#[no_mangle]
+extern "Rust" fn main() -> i32 {
+    let buffer: [u8; BUFFER_SIZE] = core::array::from_fn(|i| i as u8);
+    let fd = open(
+        SRC_FILE,
+        OpenFlags::CREATE | OpenFlags::TRUNC | OpenFlags::WRONLY,
+    );
+    assert!(fd >= 0, "failed to create {SRC_FILE}");
+    for _ in 0..SIZE / BUFFER_SIZE {
+        assert_eq!(write(fd as usize, &buffer), BUFFER_SIZE as isize);
+    }
+    close(fd as usize);
+
+    let looped = measure(|src, dst| {
+        let mut buffer = vec![0u8; BUFFER_SIZE];
+        let mut copied = 0;
+        loop {
+            let len = read(src, &mut buffer);
+            if len <= 0 {
+                return copied;
+            }
+            assert_eq!(write(dst, &buffer[..len as usize]), len);
+            copied += len as usize;
+        }
+    });
+    let in_kernel = measure(|src, dst| copy_file_range(src, None, dst, None, SIZE) as usize);
+
+    println!("Copying {} KiB:", SIZE >> 10);
+    println!("  read + write:    {} ms", looped);
+    println!("  copy_file_range: {} ms", in_kernel);
+    unlink(SRC_FILE, 0);
+    unlink(DST_FILE, 0);
+    0
+}
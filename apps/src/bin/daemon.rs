@@ -4,7 +4,13 @@
 #[macro_use]
 extern crate user_lib;
 
-use user_lib::process::{exec, fork, wait, yield_};
+use user_lib::{
+    fs::{close, open, redirect_output, OpenFlags},
+    process::{exec, fork, wait, yield_},
+};
+
+/// A file to send the output of the shell and everything it starts to, the console if `None`
+const OUTPUT: Option<&str> = None;
 
 const BANNER: &str = "
 ██╗     ███████╗███╗   ███╗ ██████╗ ███╗   ██╗ ██████╗ ██████╗ ██████╗ ███████╗
@@ -18,6 +24,21 @@ const BANNER: &str = "
 #[no_mangle]
 extern "Rust" fn main() -> i32 {
     println!("{}", BANNER);
+    if let Some(path) = OUTPUT {
+        let fd = open(
+            path,
+            OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::APPEND,
+        );
+        if fd < 0 || redirect_output(fd) != 0 {
+            println!(
+                "[daemon] cannot send output to {}, keeping the console",
+                path
+            );
+        }
+        if fd >= 0 {
+            close(fd as usize);
+        }
+    }
     if fork() == 0 {
         exec("/bin/shell", &["/bin/shell"]);
     } else {
@@ -5,8 +5,11 @@ extern crate alloc;
 #[macro_use]
 extern crate user_lib;
 
-use alloc::vec;
-use user_lib::fs::{close, fstat, open, read, Dirent, OpenFlags, Stat, StatMode, DIRENT_SIZE};
+use alloc::format;
+use user_lib::fs::{
+    close, fstat, getdents, open, Dirent, OpenFlags, Stat, StatMode, DIRENT_SIZE, DT_DIR,
+    DT_UNKNOWN,
+};
 
 #[no_mangle]
 extern "Rust" fn main(argc: usize, argv: &[&str]) -> i32 {
@@ -39,19 +42,39 @@ fn list(target: &str) {
             println!("{}", target);
         }
         StatMode::DIR => {
-            let size = stat.size as usize;
-            let mut buf = vec![0u8; size];
-            read(fd as usize, &mut buf);
-            let entries = buf.chunks_exact(DIRENT_SIZE);
-            for entry in entries.skip(2) {
-                let dirent: &Dirent = unsafe { &entry.as_ptr().cast::<Dirent>().read_unaligned() };
-                let name_len = dirent.name.iter().take_while(|&&c| c != 0).count();
-                let name = core::str::from_utf8(&dirent.name[..name_len])
-                    .expect("Invalid UTF-8 in directory name");
-                print!("{}\n", name);
+            let mut buf = [0u8; 16 * DIRENT_SIZE];
+            loop {
+                let len = getdents(fd as usize, &mut buf);
+                if len <= 0 {
+                    break;
+                }
+                for entry in buf[..len as usize].chunks_exact(DIRENT_SIZE) {
+                    let dirent = unsafe { entry.as_ptr().cast::<Dirent>().read_unaligned() };
+                    let name = dirent.name();
+                    if name == "." || name == ".." {
+                        continue;
+                    }
+                    let is_dir = match dirent.file_type {
+                        // only images older than entry types need the inode itself
+                        DT_UNKNOWN => stat_mode(&format!("{target}/{name}")) == Some(StatMode::DIR),
+                        file_type => file_type == DT_DIR,
+                    };
+                    print!("{}{}\n", name, if is_dir { "/" } else { "" });
+                }
             }
         }
         _ => panic!("Unknown mode"),
     }
     close(fd as usize);
 }
+
+fn stat_mode(path: &str) -> Option<StatMode> {
+    let fd = open(path, OpenFlags::RDONLY);
+    if fd < 0 {
+        return None;
+    }
+    let mut stat = Stat::new();
+    let ret = fstat(fd as usize, &mut stat);
+    close(fd as usize);
+    (ret == 0).then_some(stat.mode)
+}
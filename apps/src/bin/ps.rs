@@ -0,0 +1,39 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::process::{process_info, ProcessState};
+
+#[no_mangle]
+extern "Rust" fn main() -> i32 {
+    println!("{:>5} {:>5} {:<8} NAME", "PID", "PPID", "STATE");
+    for info in process_info() {
+        let state = match info.state {
+            ProcessState::Ready => "ready",
+            ProcessState::Running => "running",
+            ProcessState::Blocked => "blocked",
+            ProcessState::Zombie => "zombie",
+        };
+        if info.state == ProcessState::Zombie {
+            println!(
+                "{:>5} {:>5} {:<8} {} (exit {})",
+                info.pid,
+                info.ppid,
+                state,
+                info.name(),
+                info.exit_code
+            );
+        } else {
+            println!(
+                "{:>5} {:>5} {:<8} {}",
+                info.pid,
+                info.ppid,
+                state,
+                info.name()
+            );
+        }
+    }
+    0
+}
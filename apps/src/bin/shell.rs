@@ -8,17 +8,23 @@ extern crate alloc;
 use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
+use core::fmt::Write;
 
 use user_lib::{
-    console::getchar,
-    fs::{chdir, close, dup2, fstat, getcwd, open, OpenFlags, Stat, StatMode},
-    process::{exec, fork, waitpid},
+    fs::{
+        chdir, close, dup2, fstat, getcwd, open, BufReader, BufWriter, OpenFlags, Stat, StatMode,
+    },
+    process::{exec, fork, setpgid, tcsetpgrp, wait4, wifsignaled, wtermsig},
 };
 
+const STDIN: usize = 0;
+const STDOUT: usize = 1;
+
 const LF: u8 = 0x0au8;
 const CR: u8 = 0x0du8;
 const DL: u8 = 0x7fu8;
 const BS: u8 = 0x08u8;
+const ETX: u8 = 0x03u8;
 
 struct CommandArguments {
     argc: usize,
@@ -63,9 +69,13 @@ impl CommandArguments {
 extern "Rust" fn main() -> i32 {
     let mut cwd = String::new();
     getcwd(&mut cwd);
+    let mut stdin = BufReader::new(STDIN);
+    let mut echo = BufWriter::new(STDOUT);
     loop {
-        print!("[root@lemon-core:{cwd}]$ ");
-        let line = getline();
+        let _ = write!(echo, "[root@lemon-core:{cwd}]$ ");
+        let Some(line) = getline(&mut stdin, &mut echo) else {
+            break;
+        };
         if line.is_empty() {
             continue;
         }
@@ -93,6 +103,8 @@ extern "Rust" fn main() -> i32 {
                 } else {
                     let pid = fork();
                     if pid == 0 {
+                        // each job leads its own group, so Ctrl-C reaches it but not the shell
+                        setpgid(0, 0);
                         if let Some(input) = cmd_args.input_file {
                             redirect_io(&input, 0, OpenFlags::RDONLY);
                         }
@@ -104,13 +116,24 @@ extern "Rust" fn main() -> i32 {
                         } else {
                             format!("/bin/{path}")
                         };
-                        exec(&path, &cmd_args.argv);
-                        println!("{}: command not found", path);
+                        if exec(&path, &cmd_args.argv) == -2 {
+                            println!("{}: not an executable", path);
+                        } else {
+                            println!("{}: command not found", path);
+                        }
                         return -1;
                     }
-                    let mut exit_code: i32 = 0;
-                    let exit_pid = waitpid(pid as usize, &mut exit_code);
+                    // set the group on both sides, whichever runs first
+                    setpgid(pid as usize, 0);
+                    tcsetpgrp(pid as usize);
+                    let mut status: i32 = 0;
+                    let exit_pid = wait4(pid, Some(&mut status), 0, None);
                     assert_eq!(pid, exit_pid);
+                    if wifsignaled(status) {
+                        println!("Killed by signal {}", wtermsig(status));
+                    }
+                    // with no group in the foreground, Ctrl-C is read by the shell
+                    tcsetpgrp(0);
                 }
             }
         }
@@ -129,22 +152,35 @@ fn redirect_io(file_name: &str, fd: usize, flags: OpenFlags) {
     close(file_fd);
 }
 
-fn getline() -> String {
+/// Reads a line from `stdin`, echoing it to `echo`, or `None` at end of input
+///
+/// The echo is flushed whenever the input buffered so far has been handled, so
+/// typing is echoed at once while pasted or redirected input costs a few syscalls.
+fn getline(stdin: &mut BufReader, echo: &mut BufWriter) -> Option<String> {
     let mut input = String::new();
     loop {
-        match getchar() {
+        if stdin.buffer().is_empty() {
+            echo.flush();
+        }
+        match stdin.read_byte()? {
             DL => {
                 if !input.is_empty() {
                     input.pop();
-                    print!("{} {}", BS as char, BS as char);
+                    echo.write(&[BS, b' ', BS]);
                 }
             }
             LF | CR => {
-                print!("\n");
-                break input;
+                echo.write(b"\n");
+                echo.flush();
+                break Some(input);
+            }
+            ETX => {
+                echo.write(b"^C\n");
+                echo.flush();
+                break Some(String::new());
             }
             ch => {
-                print!("{}", ch as char);
+                echo.write(&[ch]);
                 input.push(ch as char);
             }
         }
@@ -0,0 +1,56 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::process::{exec, exit, fork, get_time, spawn, waitpid};
+
+/// Programs launched by each method
+const ROUNDS: usize = 50;
+/// The program launched, this one again, which exits right away when given `exit`
+const PROGRAM: &str = "/bin/spawnbench";
+const ARGS: [&str; 2] = ["spawnbench", "exit"];
+
+/// Launches [`PROGRAM`] [`ROUNDS`] times with `launch` and waits for each, returning the total ms
+fn measure(launch: impl Fn() -> isize) -> isize {
+    let start = get_time();
+    for _ in 0..ROUNDS {
+        let pid = launch();
+        assert!(pid > 0, "failed to launch {PROGRAM}");
+        let mut exit_code = 0;
+        waitpid(pid as usize, &mut exit_code);
+        assert_eq!(exit_code, 0);
+    }
+    get_time() - start
+}
+
+#[no_mangle]
+extern "Rust" fn main(_argc: usize, argv: &[&str]) -> i32 {
+    if argv.get(1) == Some(&"exit") {
+        return 0;
+    }
+
+    let fork_exec = measure(|| {
+        let pid = fork();
+        if pid == 0 {
+            exec(PROGRAM, &ARGS);
+            exit(-1);
+        }
+        pid
+    });
+    let spawned = measure(|| spawn(PROGRAM, &ARGS));
+
+    println!("Launching {} {} times:", PROGRAM, ROUNDS);
+    println!(
+        "  fork + exec: {} ms, {} ms each",
+        fork_exec,
+        fork_exec / ROUNDS as isize
+    );
+    println!(
+        "  spawn:       {} ms, {} ms each",
+        spawned,
+        spawned / ROUNDS as isize
+    );
+    0
+}
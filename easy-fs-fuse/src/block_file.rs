@@ -1,4 +1,4 @@
-use easy_fs::{BlockDevice, BLOCK_SIZE};
+use easy_fs::{BlockDevice, BlockError, BLOCK_SIZE};
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::sync::Mutex;
@@ -6,22 +6,31 @@ use std::sync::Mutex;
 pub struct BlockFile(pub Mutex<File>);
 
 impl BlockDevice for BlockFile {
-    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) -> Result<(), BlockError> {
         let mut file = self.0.lock().unwrap();
         file.seek(SeekFrom::Start((block_id * BLOCK_SIZE) as u64))
-            .expect("Error when seeking!");
-        assert_eq!(file.read(buf).unwrap(), BLOCK_SIZE, "Not a complete block!");
+            .map_err(|_| BlockError::Io)?;
+        match file.read(buf) {
+            Ok(BLOCK_SIZE) => Ok(()),
+            _ => Err(BlockError::Io),
+        }
     }
 
-    fn write_block(&self, block_id: usize, buf: &[u8]) {
+    fn write_block(&self, block_id: usize, buf: &[u8]) -> Result<(), BlockError> {
         let mut file = self.0.lock().unwrap();
         file.seek(SeekFrom::Start((block_id * BLOCK_SIZE) as u64))
-            .expect("Error when seeking!");
-        assert_eq!(
-            file.write(buf).unwrap(),
-            BLOCK_SIZE,
-            "Not a complete block!"
-        );
+            .map_err(|_| BlockError::Io)?;
+        match file.write(buf) {
+            Ok(BLOCK_SIZE) => Ok(()),
+            _ => Err(BlockError::Io),
+        }
+    }
+
+    fn num_blocks(&self) -> usize {
+        let file = self.0.lock().unwrap();
+        file.metadata().map_or(0, |metadata| {
+            usize::try_from(metadata.len() / BLOCK_SIZE as u64).unwrap_or(usize::MAX)
+        })
     }
 
     fn handle_irq(&self) {
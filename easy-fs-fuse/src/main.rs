@@ -1,10 +1,14 @@
 use block_file::BlockFile;
 use clap::Parser;
-use easy_fs::{BlockDevice, EasyFileSystem, Inode};
+use easy_fs::{
+    set_cache_mode, validate_name, BlockDevice, CacheMode, Clock, CreateError, EasyFileSystem,
+    Inode,
+};
 use std::fs::{read_dir, File, OpenOptions};
-use std::io::Read;
+use std::io::{self, Read};
 use std::path::Path;
 use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 mod block_file;
 
@@ -18,6 +22,19 @@ struct Cli {
     output: String,
 }
 
+/// The host's wall clock, in milliseconds since the Unix epoch
+struct HostClock;
+
+impl Clock for HostClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| {
+                u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX)
+            })
+    }
+}
+
 fn main() -> std::io::Result<()> {
     let cli = Cli::parse();
     let root_path = Path::new(&cli.root);
@@ -29,6 +46,8 @@ fn main() -> std::io::Result<()> {
         output_path.to_path_buf()
     };
 
+    // the image is only complete once synced at the end, so nothing needs writing sooner
+    set_cache_mode(CacheMode::WriteBack);
     println!("Initializing the easy-fs image...");
     let block_file: Arc<dyn BlockDevice> = Arc::new(BlockFile(Mutex::new({
         let f = OpenOptions::new()
@@ -42,12 +61,16 @@ fn main() -> std::io::Result<()> {
     })));
 
     // 256 MiB, at most 4095 files
-    let efs = EasyFileSystem::create(&block_file, 256 * 2048, 1);
+    let efs = EasyFileSystem::create_with_clock(&block_file, 256 * 2048, 1, Arc::new(HostClock))
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err.to_string()))?;
     let root_inode = Arc::new(EasyFileSystem::root_inode(&efs));
     root_inode.set_default_dirent(root_inode.inode_id());
 
     println!("Packing files from {root_path:?} into the easy-fs image...");
     pack_directory(&root_inode, root_path)?;
+    efs.lock()
+        .sync()
+        .map_err(|err| io::Error::other(format!("failed to write the image: {err:?}")))?;
 
     println!(
         "The easy-fs image has been saved to: {}",
@@ -57,21 +80,44 @@ fn main() -> std::io::Result<()> {
     Ok(())
 }
 
+/// Pack the files under `path` into `parent_inode`, recursively
+///
+/// Entries are packed sorted by name rather than in the order `read_dir` returns them,
+/// which depends on the host file system, so the same tree always yields the same image.
+/// An entry whose name easy-fs cannot hold, or that does not fit in the image, fails the
+/// packing, naming the offending path.
 fn pack_directory(parent_inode: &Arc<Inode>, path: &Path) -> std::io::Result<()> {
-    for entry in read_dir(path)? {
-        let entry_path = entry?.path();
+    let mut entry_paths = read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entry_paths.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+
+    for entry_path in entry_paths {
         let entry_name = entry_path.file_name().unwrap().to_str().unwrap();
 
         if entry_name.starts_with('.') {
             continue;
         }
+        validate_name(entry_name).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("cannot pack {}: {err}", entry_path.display()),
+            )
+        })?;
+        let pack_error = |err: &dyn std::fmt::Display| {
+            io::Error::other(format!("cannot pack {}: {err}", entry_path.display()))
+        };
 
         if entry_path.is_dir() {
-            let dir_inode = parent_inode.create_dir(entry_name).unwrap();
+            let dir_inode = parent_inode
+                .try_create_dir(entry_name)
+                .map_err(|err| pack_error(&err))?;
             pack_directory(&dir_inode, &entry_path)?;
         } else if entry_path.is_file() {
             let mut file = File::open(&entry_path)?;
-            let inode = parent_inode.create(entry_name).unwrap();
+            let inode = parent_inode
+                .try_create(entry_name)
+                .map_err(|err| pack_error(&err))?;
 
             let mut buffer = vec![0; 8 << 20];
             let mut offset = 0;
@@ -80,7 +126,12 @@ fn pack_directory(parent_inode: &Arc<Inode>, path: &Path) -> std::io::Result<()>
                 if bytes_read == 0 {
                     break;
                 }
-                inode.write_at(offset, &buffer[..bytes_read]);
+                let written = inode
+                    .try_write_at(offset, &buffer[..bytes_read])
+                    .map_err(|err| pack_error(&format!("{err:?}")))?;
+                if written < bytes_read {
+                    return Err(pack_error(&CreateError::NoSpace));
+                }
                 offset += bytes_read;
             }
         }
@@ -92,28 +143,60 @@ fn pack_directory(parent_inode: &Arc<Inode>, path: &Path) -> std::io::Result<()>
 #[cfg(test)]
 mod tests {
     use super::*;
-    use easy_fs::BLOCK_SIZE;
+    use easy_fs::{
+        cache_mode, BlockError, DirEntryType, LayoutError, NameError, OpenError, XattrError,
+        BLOCK_SIZE, DIRENT_LIMIT, DIRENT_SIZE, NAME_LENGTH_LIMIT, XATTR_NAME_MAX, XATTR_VALUE_MAX,
+    };
+    use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+    use std::sync::{MutexGuard, PoisonError};
+
+    /// Tests take turns, as the block cache they share holds blocks by number alone
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    /// A file system freshly created on `target/fs.img`, no other test runs until it is dropped
+    struct Fixture {
+        block_file: Arc<dyn BlockDevice>,
+        root_inode: Inode,
+        _serial: MutexGuard<'static, ()>,
+    }
+
+    impl Fixture {
+        fn new() -> std::io::Result<Self> {
+            // a test that failed does not keep the others from running
+            let serial = SERIAL.lock().unwrap_or_else(PoisonError::into_inner);
+            // nor does it leave them its cache mode, or blocks of its image
+            set_cache_mode(CacheMode::WriteThrough);
+            easy_fs::invalidate_all();
+            // create a block device
+            let block_file: Arc<dyn BlockDevice> = Arc::new(BlockFile(Mutex::new({
+                let f = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .open("target/fs.img")?;
+                f.set_len(8192 * 512)?;
+                f
+            })));
+            EasyFileSystem::create(&block_file, 4096, 1).unwrap();
+
+            // open the file system from the block device
+            let efs = EasyFileSystem::open(&block_file).unwrap();
+
+            // get the Inode of the root directory
+            let root_inode = EasyFileSystem::root_inode(&efs);
+            Ok(Self {
+                block_file,
+                root_inode,
+                _serial: serial,
+            })
+        }
+    }
 
     #[test]
     fn efs_test() -> std::io::Result<()> {
-        // create a block device
-        let block_file: Arc<dyn BlockDevice> = Arc::new(BlockFile(Mutex::new({
-            let f = OpenOptions::new()
-                .read(true)
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .open("target/fs.img")?;
-            f.set_len(8192 * 512)?;
-            f
-        })));
-        EasyFileSystem::create(&block_file, 4096, 1);
-
-        // open the file system from the block device
-        let efs = EasyFileSystem::open(&block_file);
-
-        // get the Inode of the root directory
-        let root_inode = EasyFileSystem::root_inode(&efs);
+        let fixture = Fixture::new()?;
+        let root_inode = &fixture.root_inode;
 
         // test create and ls
         root_inode.create("filea");
@@ -170,4 +253,1118 @@ mod tests {
 
         Ok(())
     }
+
+    /// Files shrink and grow through every level of indirection, grown regions read as zeros
+    #[test]
+    fn set_len_test() -> std::io::Result<()> {
+        let fixture = Fixture::new()?;
+        let filea = fixture.root_inode.create("filea").unwrap();
+
+        // test set_len, cycling through sizes covering every level of indirection
+        // leaks would soon exhaust the data area, double frees panic in the bitmap
+        let data: Vec<u8> = (1..=251).cycle().take(200 * BLOCK_SIZE).collect();
+        filea.write_at(0, &data);
+        for _ in 0..4 {
+            for &size in &[
+                2000 * BLOCK_SIZE + 7,
+                150 * BLOCK_SIZE + 3,
+                2000 * BLOCK_SIZE,
+                20 * BLOCK_SIZE,
+                2000 * BLOCK_SIZE + BLOCK_SIZE / 2,
+                BLOCK_SIZE / 3,
+            ] {
+                filea.set_len(u32::try_from(size).unwrap());
+                assert_eq!(filea.file_size() as usize, size);
+            }
+        }
+        // the kept prefix survives, and everything past it reads back as zeros
+        let kept = BLOCK_SIZE / 3;
+        let grown = 3 * BLOCK_SIZE;
+        filea.set_len(u32::try_from(grown).unwrap());
+        let mut read_buffer = vec![0xffu8; grown];
+        assert_eq!(filea.read_at(0, &mut read_buffer), grown);
+        assert_eq!(read_buffer[..kept], data[..kept]);
+        assert!(read_buffer[kept..].iter().all(|&byte| byte == 0));
+        // writing past the end leaves a zeroed gap, even over stale bytes in the last block
+        filea.set_len(u32::try_from(kept).unwrap());
+        assert_eq!(filea.write_at(grown, b"end"), 3);
+        assert_eq!(filea.file_size() as usize, grown + 3);
+        assert_eq!(filea.read_at(0, &mut read_buffer), grown);
+        assert_eq!(read_buffer[..kept], data[..kept]);
+        assert!(read_buffer[kept..].iter().all(|&byte| byte == 0));
+        filea.set_len(0);
+        assert_eq!(filea.read_at(0, &mut read_buffer), 0);
+
+        Ok(())
+    }
+
+    /// The running count of free blocks matches a fresh scan of the bitmap
+    #[test]
+    fn free_count_test() -> std::io::Result<()> {
+        let fixture = Fixture::new()?;
+        let efs = fixture.root_inode.fs();
+        let file = fixture.root_inode.create("counted").unwrap();
+        assert_eq!(file.write_at(0, &[1u8; 4 * BLOCK_SIZE]), 4 * BLOCK_SIZE);
+        let free = efs.lock().free_data_blocks();
+        assert!(free < efs.lock().data_area_blocks());
+        assert_eq!(
+            EasyFileSystem::open(&fixture.block_file)
+                .unwrap()
+                .lock()
+                .free_data_blocks(),
+            free
+        );
+        Ok(())
+    }
+
+    /// Layouts that do not fit are rejected before anything is written
+    #[test]
+    fn layout_test() -> std::io::Result<()> {
+        let fixture = Fixture::new()?;
+        let block_file = &fixture.block_file;
+        // rejected before anything is written, so the image is left alone
+        let err = EasyFileSystem::create(block_file, 4, 1).err().unwrap();
+        assert_eq!(
+            err,
+            LayoutError::TooSmall {
+                needed: 1028,
+                total: 4
+            }
+        );
+        assert!(err.to_string().contains("4 blocks are too few"));
+        assert_eq!(
+            EasyFileSystem::create(block_file, 4096, 0).err(),
+            Some(LayoutError::NoInodes)
+        );
+        // the inode area alone would overflow a u32 block count
+        assert!(matches!(
+            EasyFileSystem::create(block_file, u32::MAX, u32::MAX / 2),
+            Err(LayoutError::TooSmall { .. })
+        ));
+        // the image holds 8192 blocks, and nothing is written for a larger file system
+        let err = EasyFileSystem::create(block_file, 8193, 1).err().unwrap();
+        assert_eq!(
+            err,
+            LayoutError::ExceedsDevice {
+                total: 8193,
+                device: 8192
+            }
+        );
+        assert!(err.to_string().contains("on a device of 8192 blocks"));
+        assert!(EasyFileSystem::create(block_file, 8192, 1).is_ok());
+        // just enough room for one data block
+        assert!(EasyFileSystem::create(block_file, 1027, 1).is_err());
+        assert!(EasyFileSystem::create(block_file, 1028, 1).is_ok());
+        Ok(())
+    }
+
+    /// Fill the data area, growing must then fail without corrupting anything
+    #[test]
+    fn full_test() -> std::io::Result<()> {
+        let fixture = Fixture::new()?;
+        let root_inode = &fixture.root_inode;
+        let file = root_inode.create("full").unwrap();
+        let chunk = vec![0x5au8; 64 * BLOCK_SIZE];
+        let mut size = 0;
+        // narrow the writes down so the last free block gets used
+        for len in [chunk.len(), BLOCK_SIZE, 1] {
+            loop {
+                let written = file.write_at(size, &chunk[..len]);
+                size += written;
+                if written < len {
+                    break;
+                }
+            }
+        }
+        assert_eq!(file.file_size() as usize, size);
+        assert_eq!(file.write_at(size, b"more"), 0);
+        assert!(!file.set_len(u32::try_from(size + BLOCK_SIZE).unwrap()));
+        assert_eq!(file.file_size() as usize, size);
+
+        // writes inside the file still work, and freeing space makes room again
+        assert_eq!(file.write_at(0, b"head"), 4);
+        file.clear();
+        root_inode.delete("full");
+        let file = root_inode.create("full").unwrap();
+        assert_eq!(file.write_at(0, &chunk), chunk.len());
+        file.clear();
+        root_inode.delete("full");
+        Ok(())
+    }
+
+    /// Forwards to a [`BlockFile`], counting the blocks read
+    struct CountingDevice {
+        inner: BlockFile,
+        reads: AtomicUsize,
+    }
+
+    impl BlockDevice for CountingDevice {
+        fn read_block(&self, block_id: usize, buf: &mut [u8]) -> Result<(), BlockError> {
+            self.reads.fetch_add(1, Ordering::Relaxed);
+            self.inner.read_block(block_id, buf)
+        }
+
+        fn write_block(&self, block_id: usize, buf: &[u8]) -> Result<(), BlockError> {
+            self.inner.write_block(block_id, buf)
+        }
+
+        fn num_blocks(&self) -> usize {
+            self.inner.num_blocks()
+        }
+
+        fn handle_irq(&self) {
+            unimplemented!()
+        }
+    }
+
+    /// Reopen the file system with a cold cache, the root directory must be served from the preload
+    #[test]
+    fn preload_test() -> std::io::Result<()> {
+        let fixture = Fixture::new()?;
+        let root_inode = &fixture.root_inode;
+        root_inode.create("preloaded").unwrap();
+        // push the root directory out of the cache
+        let big = root_inode.create("big").unwrap();
+        big.write_at(0, &vec![1u8; 64 * BLOCK_SIZE]);
+        let mut buffer = [0u8; BLOCK_SIZE];
+        for block in 0..64 {
+            big.read_at(block * BLOCK_SIZE, &mut buffer);
+        }
+        drop(big);
+        root_inode.delete("big");
+
+        let counting = Arc::new(CountingDevice {
+            inner: BlockFile(Mutex::new(
+                OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .open("target/fs.img")?,
+            )),
+            reads: AtomicUsize::new(0),
+        });
+        let device: Arc<dyn BlockDevice> = counting.clone();
+        let efs = EasyFileSystem::open(&device).unwrap();
+        let preloaded = counting.reads.load(Ordering::Relaxed);
+        assert!(preloaded > 1);
+
+        let root_inode = EasyFileSystem::root_inode(&efs);
+        let mut entries = vec![0u8; root_inode.file_size() as usize];
+        assert_eq!(root_inode.read_at(0, &mut entries), entries.len());
+        assert!(root_inode.find("preloaded").is_some());
+        assert_eq!(counting.reads.load(Ordering::Relaxed), preloaded);
+
+        // mounting again drops the cache, as the device may have changed, and preloads afresh
+        EasyFileSystem::open(&device).unwrap();
+        assert_eq!(counting.reads.load(Ordering::Relaxed), 2 * preloaded);
+        Ok(())
+    }
+
+    /// Reads and writes of nothing, at the end of a file and at block boundaries
+    #[test]
+    fn boundary_test() -> std::io::Result<()> {
+        let _fixture = Fixture::new()?;
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open("target/boundary.img")?;
+        file.set_len(2048 * BLOCK_SIZE as u64)?;
+        // the cache holds blocks by number, so none of the other image may linger
+        easy_fs::invalidate_all();
+        let counting = Arc::new(CountingDevice {
+            inner: BlockFile(Mutex::new(file)),
+            reads: AtomicUsize::new(0),
+        });
+        let device: Arc<dyn BlockDevice> = counting.clone();
+        let efs = EasyFileSystem::create(&device, 2048, 1).unwrap();
+        let root_inode = EasyFileSystem::root_inode(&efs);
+        let file = root_inode.create("boundary").unwrap();
+        let data: Vec<u8> = (1..=251).cycle().take(2 * BLOCK_SIZE).collect();
+        assert_eq!(file.write_at(0, &data), data.len());
+        let free = efs.lock().free_data_blocks();
+
+        // a write of nothing changes nothing, at the end or far past it
+        for offset in [0, BLOCK_SIZE, 2 * BLOCK_SIZE, 10 * BLOCK_SIZE] {
+            assert_eq!(file.write_at(offset, &[]), 0);
+            assert_eq!(file.file_size() as usize, 2 * BLOCK_SIZE);
+        }
+        assert_eq!(efs.lock().free_data_blocks(), free);
+
+        // reads stop at the end, even mid-buffer
+        let mut buf = [0u8; 16];
+        assert_eq!(file.read_at(2 * BLOCK_SIZE - 4, &mut buf), 4);
+        assert_eq!(buf[..4], data[2 * BLOCK_SIZE - 4..]);
+        assert_eq!(file.read_at(2 * BLOCK_SIZE, &mut buf), 0);
+        assert_eq!(file.read_at(usize::MAX - 4, &mut buf), 0);
+        assert_eq!(file.read_at(0, &mut []), 0);
+
+        // a read at or past the end touches no data block
+        let efs = EasyFileSystem::open(&device).unwrap();
+        let file = EasyFileSystem::root_inode(&efs).find("boundary").unwrap();
+        let reads = counting.reads.load(Ordering::Relaxed);
+        assert_eq!(file.read_at(2 * BLOCK_SIZE, &mut buf), 0);
+        assert_eq!(file.read_at(5 * BLOCK_SIZE + 1, &mut buf), 0);
+        assert_eq!(counting.reads.load(Ordering::Relaxed), reads);
+        assert_eq!(file.read_at(BLOCK_SIZE - 1, &mut buf[..2]), 2);
+        assert_eq!(buf[..2], data[BLOCK_SIZE - 1..=BLOCK_SIZE]);
+        assert_eq!(counting.reads.load(Ordering::Relaxed), reads + 2);
+
+        // a write at the exact end takes a new block, one across a boundary lands in both
+        assert_eq!(file.write_at(2 * BLOCK_SIZE, b"x"), 1);
+        assert_eq!(file.file_size() as usize, 2 * BLOCK_SIZE + 1);
+        assert_eq!(efs.lock().free_data_blocks(), free - 1);
+        assert_eq!(file.write_at(BLOCK_SIZE - 1, b"ab"), 2);
+        assert_eq!(file.read_at(BLOCK_SIZE - 1, &mut buf[..2]), 2);
+        assert_eq!(&buf[..2], b"ab");
+        assert_eq!(file.read_at(2 * BLOCK_SIZE, &mut buf), 1);
+        assert_eq!(buf[0], b'x');
+        easy_fs::invalidate_all();
+        Ok(())
+    }
+
+    /// Creates failing for want of an entry, an inode or a block say so, and leave nothing behind
+    #[test]
+    fn space_test() -> std::io::Result<()> {
+        let _fixture = Fixture::new()?;
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open("target/space.img")?;
+        file.set_len(1400 * BLOCK_SIZE as u64)?;
+        easy_fs::invalidate_all();
+        let device: Arc<dyn BlockDevice> = Arc::new(BlockFile(Mutex::new(file)));
+        let efs = EasyFileSystem::create(&device, 1400, 1).unwrap();
+        let root_inode = EasyFileSystem::root_inode(&efs);
+
+        // a directory holds a bounded number of entries, whichever way they arrive
+        let dir = root_inode.try_create_dir("crowded").unwrap();
+        for i in 2..DIRENT_LIMIT {
+            dir.try_create(&format!("entry{i}")).unwrap();
+        }
+        let size = dir.file_size();
+        let free = efs.lock().free_data_blocks();
+        assert_eq!(
+            dir.try_create("more").err(),
+            Some(CreateError::DirectoryFull)
+        );
+        assert_eq!(
+            dir.try_create_dir("more").err(),
+            Some(CreateError::DirectoryFull)
+        );
+        root_inode.try_create("outside").unwrap();
+        assert!(!root_inode.rename("outside", &dir, "outside"));
+        assert!(root_inode.find("outside").is_some());
+        assert_eq!(dir.file_size(), size);
+        assert_eq!(efs.lock().free_data_blocks(), free);
+        // the other reasons are still told apart
+        assert_eq!(dir.try_create("entry2").err(), Some(CreateError::Exists));
+        assert_eq!(
+            dir.try_create("a/b").err(),
+            Some(CreateError::InvalidName(NameError::Forbidden('/')))
+        );
+
+        // leave the root directory one free slot short of a new block, then fill the data area
+        let filler = root_inode.try_create("filler").unwrap();
+        let per_block = BLOCK_SIZE / DIRENT_SIZE;
+        let mut pads = 0;
+        while root_inode.file_size() as usize / DIRENT_SIZE % per_block != per_block - 1 {
+            root_inode.try_create(&format!("pad{pads}")).unwrap();
+            pads += 1;
+        }
+        let chunk = vec![0x5au8; 64 * BLOCK_SIZE];
+        let mut end = 0;
+        for len in [chunk.len(), BLOCK_SIZE, 1] {
+            loop {
+                let written = filler.write_at(end, &chunk[..len]);
+                end += written;
+                if written < len {
+                    break;
+                }
+            }
+        }
+        // the lowest free inode, the one every create below tries first
+        let next_inode = root_inode.try_create("probe").unwrap().inode_id();
+        root_inode.delete("probe");
+
+        // the entry fits, but the directory gets no block for `.` and `..`
+        let size = root_inode.file_size();
+        assert_eq!(
+            root_inode.try_create_dir("no_room").err(),
+            Some(CreateError::NoSpace)
+        );
+        assert_eq!(root_inode.file_size(), size);
+        assert!(root_inode.find("no_room").is_none());
+        // with the last slot taken by a move, the entry itself gets no block
+        assert!(dir.rename("entry2", &root_inode, "moved"));
+        assert_eq!(
+            root_inode.try_create("no_block").err(),
+            Some(CreateError::NoSpace)
+        );
+        assert_eq!(
+            root_inode.file_size(),
+            size + u32::try_from(DIRENT_SIZE).unwrap()
+        );
+        assert!(root_inode.find("no_block").is_none());
+        // neither kept the inode it took, and freeing space makes room again
+        filler.set_len(0);
+        assert_eq!(
+            root_inode.try_create_dir("roomy").unwrap().inode_id(),
+            next_inode
+        );
+
+        // packing more than fits reports the host path rather than panicking
+        let host = Path::new("target/space_root");
+        let _ = std::fs::remove_dir_all(host);
+        std::fs::create_dir_all(host)?;
+        let too_big = (efs.lock().data_area_blocks() as usize + 1) * BLOCK_SIZE;
+        std::fs::write(host.join("too_big"), vec![1u8; too_big])?;
+        let packed = root_inode.try_create_dir("packed").unwrap();
+        let err = pack_directory(&packed, host).unwrap_err();
+        assert!(err.to_string().contains("too_big"));
+        assert!(err.to_string().contains("filesystem full"));
+        std::fs::remove_dir_all(host)?;
+        easy_fs::invalidate_all();
+        Ok(())
+    }
+
+    /// Forwards to a [`BlockFile`], counting the blocks written alone and in batches
+    struct BatchingDevice {
+        inner: BlockFile,
+        singles: AtomicUsize,
+        batches: AtomicUsize,
+        batched: AtomicUsize,
+    }
+
+    impl BatchingDevice {
+        fn open(path: &str) -> std::io::Result<Self> {
+            Ok(Self {
+                inner: BlockFile(Mutex::new(
+                    OpenOptions::new().read(true).write(true).open(path)?,
+                )),
+                singles: AtomicUsize::new(0),
+                batches: AtomicUsize::new(0),
+                batched: AtomicUsize::new(0),
+            })
+        }
+
+        /// Blocks written so far, alone or in batches
+        fn written(&self) -> usize {
+            self.singles.load(Ordering::Relaxed) + self.batched.load(Ordering::Relaxed)
+        }
+    }
+
+    impl BlockDevice for BatchingDevice {
+        fn read_block(&self, block_id: usize, buf: &mut [u8]) -> Result<(), BlockError> {
+            self.inner.read_block(block_id, buf)
+        }
+
+        fn write_block(&self, block_id: usize, buf: &[u8]) -> Result<(), BlockError> {
+            self.singles.fetch_add(1, Ordering::Relaxed);
+            self.inner.write_block(block_id, buf)
+        }
+
+        fn write_blocks(&self, blocks: &[(usize, &[u8])]) -> Vec<Result<(), BlockError>> {
+            self.batches.fetch_add(1, Ordering::Relaxed);
+            self.batched.fetch_add(blocks.len(), Ordering::Relaxed);
+            blocks
+                .iter()
+                .map(|&(block_id, buf)| self.inner.write_block(block_id, buf))
+                .collect()
+        }
+
+        fn num_blocks(&self) -> usize {
+            self.inner.num_blocks()
+        }
+
+        fn handle_irq(&self) {
+            unimplemented!()
+        }
+    }
+
+    /// Dirty blocks leave the cache in batches, both when evicted and when synced
+    #[test]
+    fn batch_test() -> std::io::Result<()> {
+        let _fixture = Fixture::new()?;
+        let batching = Arc::new(BatchingDevice::open("target/fs.img")?);
+        let device: Arc<dyn BlockDevice> = batching.clone();
+        // write-through would write every block as it changes
+        set_cache_mode(CacheMode::WriteBack);
+        let efs = EasyFileSystem::open(&device).unwrap();
+        let root_inode = EasyFileSystem::root_inode(&efs);
+        let file = root_inode.create("batched").unwrap();
+        let data: Vec<u8> = (0..253).cycle().take(64 * BLOCK_SIZE).collect();
+        assert_eq!(file.write_at(0, &data), data.len());
+        assert_eq!(efs.lock().sync(), Ok(()));
+
+        let batches = batching.batches.load(Ordering::Relaxed);
+        let blocks = batching.batched.load(Ordering::Relaxed);
+        assert!(blocks >= 32, "only {blocks} blocks written in batches");
+        assert!(
+            blocks >= 4 * batches,
+            "{blocks} blocks in {batches} batches"
+        );
+
+        // the blocks reached the image
+        let reopened = EasyFileSystem::open(&device).unwrap();
+        let file = EasyFileSystem::root_inode(&reopened)
+            .find("batched")
+            .unwrap();
+        let mut buffer = vec![0u8; data.len()];
+        assert_eq!(file.read_at(0, &mut buffer), data.len());
+        assert!(buffer == data);
+        file.clear();
+        EasyFileSystem::root_inode(&reopened).delete("batched");
+        set_cache_mode(CacheMode::WriteThrough);
+        Ok(())
+    }
+
+    /// Write-through writes each block as it changes, write-back only once synced
+    #[test]
+    fn cache_mode_test() -> std::io::Result<()> {
+        let _fixture = Fixture::new()?;
+        let recording = Arc::new(BatchingDevice::open("target/fs.img")?);
+        let device: Arc<dyn BlockDevice> = recording.clone();
+        assert_eq!(cache_mode(), CacheMode::WriteThrough);
+        let efs = EasyFileSystem::open(&device).unwrap();
+        let root_inode = EasyFileSystem::root_inode(&efs);
+        let data = vec![7u8; 4 * BLOCK_SIZE];
+
+        let file = root_inode.create("through").unwrap();
+        let written = recording.written();
+        assert!(written > 0, "creating wrote nothing");
+        assert_eq!(file.write_at(0, &data), data.len());
+        // the data blocks at least, each as soon as it was written
+        assert!(recording.written() >= written + 4);
+        assert_eq!(recording.batches.load(Ordering::Relaxed), 0);
+        let written = recording.written();
+        assert_eq!(efs.lock().sync(), Ok(()));
+        assert_eq!(recording.written(), written);
+
+        set_cache_mode(CacheMode::WriteBack);
+        let file = root_inode.create("back").unwrap();
+        assert_eq!(file.write_at(0, &data), data.len());
+        assert_eq!(recording.written(), written);
+        // syncing through an inode flushes like syncing the file system
+        assert_eq!(file.sync(), Ok(()));
+        assert_eq!(recording.batches.load(Ordering::Relaxed), 1);
+        assert!(recording.written() >= written + 4);
+
+        // both files reached the image
+        assert_eq!(easy_fs::invalidate_all(), 0);
+        let reopened = EasyFileSystem::open(&device).unwrap();
+        let reopened_root = EasyFileSystem::root_inode(&reopened);
+        for name in ["through", "back"] {
+            let file = reopened_root.find(name).unwrap();
+            let mut buffer = vec![0u8; data.len()];
+            assert_eq!(file.read_at(0, &mut buffer), data.len());
+            assert!(buffer == data, "{name} did not reach the image");
+            file.clear();
+            reopened_root.delete(name);
+        }
+        assert_eq!(reopened.lock().sync(), Ok(()));
+        set_cache_mode(CacheMode::WriteThrough);
+        Ok(())
+    }
+
+    /// Files are written and read from several threads at once, while a directory changes
+    #[test]
+    fn concurrency_test() -> std::io::Result<()> {
+        let fixture = Fixture::new()?;
+        let root_inode = &fixture.root_inode;
+        let files: Vec<_> = (0..3)
+            .map(|i| root_inode.create(&format!("concurrent{i}")).unwrap())
+            .collect();
+        let dir = root_inode.create_dir("concurrent_dir").unwrap();
+        std::thread::scope(|scope| {
+            for (i, file) in files.iter().enumerate() {
+                scope.spawn(move || {
+                    let fill = |round: usize| u8::try_from(i * 31 + round).unwrap();
+                    // growing allocates under the `fs` lock, the rest only takes the inode's
+                    let mut len = 0;
+                    for round in 0..40 {
+                        let chunk = vec![fill(round); BLOCK_SIZE / 2 + round * 37];
+                        assert_eq!(file.write_at(len, &chunk), chunk.len());
+                        len += chunk.len();
+                    }
+                    for round in 0..40 {
+                        let chunk = vec![fill(round + 40); 3 * BLOCK_SIZE];
+                        let offset = round * 97 % (len - chunk.len());
+                        assert_eq!(file.write_at(offset, &chunk), chunk.len());
+                        let mut buffer = vec![0u8; chunk.len()];
+                        assert_eq!(file.read_at(offset, &mut buffer), chunk.len());
+                        assert!(buffer == chunk, "file {i} corrupted in round {round}");
+                    }
+                    assert_eq!(file.file_size() as usize, len);
+                });
+            }
+            scope.spawn(|| {
+                for round in 0..40 {
+                    let name = format!("entry{round}");
+                    dir.create(&name).unwrap();
+                    if round % 3 == 0 {
+                        dir.delete(&name);
+                    }
+                    assert!(dir.find(&name).is_some() != (round % 3 == 0));
+                }
+            });
+        });
+        assert_eq!(dir.list().len(), 2 + 26);
+
+        for (i, file) in files.iter().enumerate() {
+            file.clear();
+            root_inode.delete(&format!("concurrent{i}"));
+        }
+        for round in 0..40 {
+            dir.delete(&format!("entry{round}"));
+        }
+        root_inode.delete("concurrent_dir");
+        Ok(())
+    }
+
+    /// Appends from several threads through their own handles never overwrite each other
+    #[test]
+    fn append_test() -> std::io::Result<()> {
+        let fixture = Fixture::new()?;
+        let block_file = &fixture.block_file;
+        let root_inode = &fixture.root_inode;
+        let fs = root_inode.fs();
+        root_inode.create("appended").unwrap();
+        let free = fs.lock().free_data_blocks();
+        let records: Vec<(usize, usize, u8)> = std::thread::scope(|scope| {
+            let writers: Vec<_> = (0..4u8)
+                .map(|i| {
+                    let file = root_inode.find("appended").unwrap();
+                    scope.spawn(move || {
+                        (0..40u8)
+                            .map(|round| {
+                                let byte = i * 40 + round + 1;
+                                let record = vec![byte; BLOCK_SIZE / 3 + round as usize * 29];
+                                let (offset, len) = file.try_append(&record).unwrap();
+                                assert_eq!(len, record.len());
+                                (offset, len, byte)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            writers
+                .into_iter()
+                .flat_map(|writer| writer.join().unwrap())
+                .collect()
+        });
+
+        // the records tile the file, each read back whole
+        let file = root_inode.find("appended").unwrap();
+        let mut sorted = records.clone();
+        sorted.sort_unstable();
+        let mut end = 0;
+        for &(offset, len, fill) in &sorted {
+            assert_eq!(offset, end, "record {fill} does not follow the one before");
+            let mut buffer = vec![0u8; len];
+            assert_eq!(file.read_at(offset, &mut buffer), len);
+            assert!(
+                buffer.iter().all(|&byte| byte == fill),
+                "record {fill} overwritten"
+            );
+            end += len;
+        }
+        assert_eq!(file.file_size() as usize, end);
+        // no block was handed out twice, or the counts would drift apart and clearing free it twice
+        assert_eq!(
+            EasyFileSystem::open(block_file)
+                .unwrap()
+                .lock()
+                .free_data_blocks(),
+            fs.lock().free_data_blocks()
+        );
+        file.clear();
+        assert_eq!(fs.lock().free_data_blocks(), free);
+        root_inode.delete("appended");
+        Ok(())
+    }
+
+    /// Dirty blocks are written back before being dropped, and dropped blocks are read afresh
+    #[test]
+    fn invalidate_test() -> std::io::Result<()> {
+        let fixture = Fixture::new()?;
+        let block_file = fixture.block_file.as_ref();
+        let root_inode = &fixture.root_inode;
+        let file = root_inode.create("out_of_band").unwrap();
+        let data = b"written back on invalidate";
+        assert_eq!(file.write_at(0, data), data.len());
+        assert_eq!(easy_fs::invalidate_all(), 0);
+        let image = std::fs::read("target/fs.img")?;
+        let position = image
+            .windows(data.len())
+            .position(|window| window == data)
+            .expect("the dirty block was dropped without being written back");
+
+        // change the block behind the cache, the cached copy hides it until invalidated
+        let mut buffer = vec![0u8; data.len()];
+        assert_eq!(file.read_at(0, &mut buffer), data.len());
+        let (block_id, offset) = (position / BLOCK_SIZE, position % BLOCK_SIZE);
+        let mut block = [0u8; BLOCK_SIZE];
+        block_file.read_block(block_id, &mut block).unwrap();
+        block[offset..offset + 7].copy_from_slice(b"changed");
+        block_file.write_block(block_id, &block).unwrap();
+        file.read_at(0, &mut buffer);
+        assert_eq!(&buffer[..], data);
+        assert!(easy_fs::invalidate(block_id));
+        file.read_at(0, &mut buffer);
+        assert_eq!(&buffer[..], b"changed back on invalidate");
+
+        file.clear();
+        root_inode.delete("out_of_band");
+        Ok(())
+    }
+
+    /// Residency is reported block by block, and asking loads none of the blocks asked about
+    #[test]
+    fn cached_test() -> std::io::Result<()> {
+        let fixture = Fixture::new()?;
+        let root_inode = &fixture.root_inode;
+        let file = root_inode.create("resident").unwrap();
+        // past the direct blocks, so an index block is involved
+        let blocks = 40;
+        let data = vec![7u8; blocks * BLOCK_SIZE];
+        assert_eq!(file.write_at(0, &data), data.len());
+        easy_fs::invalidate_all();
+        assert_eq!(file.cached_blocks(0, 40), Ok(vec![false; blocks]));
+        // the first call brought nothing into the cache
+        assert_eq!(file.cached_blocks(0, 40), Ok(vec![false; blocks]));
+
+        let mut buffer = [0u8; BLOCK_SIZE];
+        assert_eq!(file.read_at(30 * BLOCK_SIZE, &mut buffer), BLOCK_SIZE);
+        let mut expected = vec![false; blocks];
+        expected[30] = true;
+        assert_eq!(file.cached_blocks(0, 40), Ok(expected));
+        // blocks past the end are never cached
+        let mut expected = vec![false; 14];
+        expected[1] = true;
+        assert_eq!(file.cached_blocks(29, 14), Ok(expected));
+        assert_eq!(file.cached_blocks(u32::MAX, 2), Ok(vec![false; 2]));
+
+        file.clear();
+        root_inode.delete("resident");
+        Ok(())
+    }
+
+    /// The same tree packs into identical images, whatever order its entries were created in
+    #[test]
+    fn reproducible_test() -> std::io::Result<()> {
+        let fixture = Fixture::new()?;
+        let block_file = &fixture.block_file;
+        let build = |root: &Path, reversed: bool| -> std::io::Result<()> {
+            let ordered = |names: &[&'static str]| {
+                let mut names = names.to_vec();
+                if reversed {
+                    names.reverse();
+                }
+                names
+            };
+            let _ = std::fs::remove_dir_all(root);
+            std::fs::create_dir_all(root)?;
+            for name in ordered(&["b", "a", "sub", "c.txt", "zz"]) {
+                if name == "sub" {
+                    std::fs::create_dir(root.join(name))?;
+                    for inner in ordered(&["y", "x", "w"]) {
+                        std::fs::write(root.join(name).join(inner), inner.repeat(700))?;
+                    }
+                } else {
+                    std::fs::write(root.join(name), name.repeat(300))?;
+                }
+            }
+            Ok(())
+        };
+
+        let mut images = Vec::new();
+        for reversed in [false, true] {
+            let root = Path::new("target/reproducible_root");
+            build(root, reversed)?;
+            let efs = EasyFileSystem::create(block_file, 4096, 1).unwrap();
+            let root_inode = Arc::new(EasyFileSystem::root_inode(&efs));
+            root_inode.set_default_dirent(root_inode.inode_id());
+            pack_directory(&root_inode, root)?;
+            assert_eq!(efs.lock().sync(), Ok(()));
+            let names = |dir: &Inode| -> Vec<String> {
+                dir.list().into_iter().map(|(name, _)| name).collect()
+            };
+            assert_eq!(
+                names(&root_inode),
+                [".", "..", "a", "b", "c.txt", "sub", "zz"]
+            );
+            assert_eq!(
+                names(&root_inode.find("sub").unwrap()),
+                [".", "..", "w", "x", "y"]
+            );
+            images.push(std::fs::read("target/fs.img")?);
+            std::fs::remove_dir_all(root)?;
+        }
+        assert!(images[0] == images[1], "the images differ");
+        Ok(())
+    }
+
+    /// Delete entries of a directory in various orders, then compact it
+    #[test]
+    fn dir_test() -> std::io::Result<()> {
+        let fixture = Fixture::new()?;
+        let root_inode = &fixture.root_inode;
+        // test delete, covering the last, first and middle entries and a missing name
+        let dir = root_inode.create_dir("dir").unwrap();
+        let names: Vec<String> = (0..8).map(|i| format!("entry{i}")).collect();
+        for name in &names {
+            dir.create(name).unwrap();
+        }
+        let mut remaining = names.clone();
+        let check = |remaining: &[String]| {
+            assert_eq!(
+                dir.file_size() as usize,
+                (remaining.len() + 2) * DIRENT_SIZE
+            );
+            for name in &names {
+                assert_eq!(dir.find(name).is_some(), remaining.contains(name));
+            }
+            assert!(dir.find(".").is_some() && dir.find("..").is_some());
+        };
+        for name in [
+            "entry7", "entry0", "entry3", "missing", "entry6", "entry1", "entry2", "entry4",
+            "entry5",
+        ] {
+            dir.delete(name);
+            remaining.retain(|n| n != name);
+            check(&remaining);
+        }
+
+        // test compact_dir, zeroed entries are dropped and the others keep their order
+        dir.create("entry0").unwrap();
+        dir.set_len(u32::try_from(5 * DIRENT_SIZE).unwrap());
+        dir.create("entry1").unwrap();
+        assert_eq!(dir.compact_dir(), 2);
+        assert_eq!(dir.file_size() as usize, 4 * DIRENT_SIZE);
+        assert!(dir.find("entry0").is_some() && dir.find("entry1").is_some());
+        assert_eq!(dir.compact_dir(), 0);
+
+        // test is_empty_dir, only `.` and `..` may be left whatever the size says
+        let empty = dir.create_dir("empty").unwrap();
+        assert!(empty.is_empty_dir());
+        empty.create("entry").unwrap();
+        empty.create_dir("subdir").unwrap();
+        assert!(!empty.is_empty_dir());
+        empty.delete("entry");
+        assert!(!empty.is_empty_dir());
+        empty.delete("subdir");
+        assert!(empty.is_empty_dir());
+        empty.set_len(u32::try_from(4 * DIRENT_SIZE).unwrap());
+        assert!(empty.is_empty_dir());
+        empty.create("late").unwrap();
+        assert!(!empty.is_empty_dir());
+        assert!(!dir.find("entry0").unwrap().is_empty_dir());
+        Ok(())
+    }
+
+    /// A directory cannot be moved below itself, ancestry is found through the `..` entries
+    #[test]
+    fn cycle_test() -> std::io::Result<()> {
+        let fixture = Fixture::new()?;
+        let root_inode = &fixture.root_inode;
+        let a = root_inode.create_dir("cycle_a").unwrap();
+        let b = a.create_dir("b").unwrap();
+        let c = b.create_dir("c").unwrap();
+        assert!(root_inode.is_ancestor_of(&c) && a.is_ancestor_of(&c) && a.is_ancestor_of(&a));
+        assert!(!c.is_ancestor_of(&a) && !b.is_ancestor_of(root_inode));
+
+        // `mv a a/b`, into itself, and deeper down are all refused, leaving the tree alone
+        assert!(!root_inode.rename("cycle_a", &b, "a"));
+        assert!(!root_inode.rename("cycle_a", &a, "a"));
+        assert!(!root_inode.rename("cycle_a", &c, "a"));
+        assert_eq!(root_inode.find("cycle_a").unwrap().inode_id(), a.inode_id());
+        assert_eq!(b.find("..").unwrap().inode_id(), a.inode_id());
+
+        // moving up is fine, and the moved directory leaves the old ancestry
+        assert!(b.rename("c", root_inode, "cycle_c"));
+        assert!(root_inode.is_ancestor_of(&c) && !a.is_ancestor_of(&c));
+        // so is moving a file anywhere
+        a.create("file").unwrap();
+        assert!(a.rename("file", &c, "file"));
+        assert!(c.find("file").is_some());
+        Ok(())
+    }
+
+    /// Exchanged entries keep their names and swap inodes, in one directory or across two
+    #[test]
+    fn exchange_test() -> std::io::Result<()> {
+        let fixture = Fixture::new()?;
+        let root_inode = &fixture.root_inode;
+        let dir = root_inode.create_dir("swap_dir").unwrap();
+        let file = dir.create("file").unwrap();
+        let other = dir.create("other").unwrap();
+        assert!(dir.exchange("file", &dir, "other"));
+        assert_eq!(dir.find("file").unwrap().inode_id(), other.inode_id());
+        assert_eq!(dir.find("other").unwrap().inode_id(), file.inode_id());
+        assert!(dir.exchange("file", &dir, "file"));
+        assert!(!dir.exchange("file", &dir, "missing"));
+        assert!(!dir.exchange("..", &dir, "file"));
+
+        // across directories, a swapped directory follows its entry and its `..` with it
+        let subdir = root_inode.create_dir("swap_subdir").unwrap();
+        assert!(root_inode.exchange("swap_subdir", &dir, "file"));
+        assert_eq!(dir.find("file").unwrap().inode_id(), subdir.inode_id());
+        assert_eq!(subdir.find("..").unwrap().inode_id(), dir.inode_id());
+        assert_eq!(
+            root_inode.find("swap_subdir").unwrap().inode_id(),
+            other.inode_id()
+        );
+        assert!(dir
+            .list()
+            .contains(&(String::from("file"), DirEntryType::Directory)));
+        assert!(root_inode
+            .list()
+            .contains(&(String::from("swap_subdir"), DirEntryType::File)));
+
+        // neither side may end up below itself
+        assert!(!root_inode.exchange("swap_dir", &subdir, ".."));
+        assert!(!root_inode.exchange("swap_dir", &dir, "other"));
+        assert!(!dir.exchange("other", root_inode, "swap_dir"));
+        assert_eq!(dir.find("..").unwrap().inode_id(), root_inode.inode_id());
+        Ok(())
+    }
+
+    /// Entries carry the kind of their inode, `.` and `..` included, and keep it on rename
+    #[test]
+    fn dirent_type_test() -> std::io::Result<()> {
+        let fixture = Fixture::new()?;
+        let root_inode = &fixture.root_inode;
+        assert!(root_inode.fs().lock().has_dirent_types());
+        let dir = root_inode.create_dir("typed").unwrap();
+        dir.create("file").unwrap();
+        dir.create_dir("subdir").unwrap();
+        assert_eq!(
+            dir.list(),
+            [
+                (String::from("."), DirEntryType::Directory),
+                (String::from(".."), DirEntryType::Directory),
+                (String::from("file"), DirEntryType::File),
+                (String::from("subdir"), DirEntryType::Directory),
+            ]
+        );
+        let subdir = dir.find("subdir").unwrap();
+        assert!(dir.rename("file", &subdir, "moved"));
+        assert!(subdir.rename("moved", &dir, "file"));
+        assert!(dir.rename("subdir", root_inode, "typed_subdir"));
+        let list = root_inode.list();
+        assert!(list.contains(&(String::from("typed_subdir"), DirEntryType::Directory)));
+        assert!(dir
+            .list()
+            .contains(&(String::from("file"), DirEntryType::File)));
+        Ok(())
+    }
+
+    /// Names are checked before any entry is written, a name at the limit fits and one past it does not
+    #[test]
+    fn name_test() -> std::io::Result<()> {
+        let fixture = Fixture::new()?;
+        let root_inode = &fixture.root_inode;
+        let longest = "n".repeat(NAME_LENGTH_LIMIT);
+        let too_long = "n".repeat(NAME_LENGTH_LIMIT + 1);
+        assert_eq!(validate_name(&longest), Ok(()));
+        assert_eq!(
+            validate_name(&too_long),
+            Err(NameError::TooLong(NAME_LENGTH_LIMIT + 1))
+        );
+        assert_eq!(validate_name(""), Err(NameError::Empty));
+        assert_eq!(validate_name("a/b"), Err(NameError::Forbidden('/')));
+        assert_eq!(validate_name("a\0b"), Err(NameError::Forbidden('\0')));
+        // the limit counts bytes, a multi-byte character does not squeeze in
+        let accented = format!("{}é", "n".repeat(NAME_LENGTH_LIMIT - 1));
+        assert_eq!(
+            validate_name(&accented),
+            Err(NameError::TooLong(NAME_LENGTH_LIMIT + 1))
+        );
+
+        let dir = root_inode.create_dir("names").unwrap();
+        let size = dir.file_size();
+        assert!(dir.create(&too_long).is_none());
+        assert!(dir.create_dir("").is_none());
+        assert!(dir.create("a/b").is_none());
+        assert_eq!(dir.file_size(), size);
+        let file = dir.create(&longest).unwrap();
+        assert_eq!(dir.find(&longest).unwrap().inode_id(), file.inode_id());
+        assert!(dir.list().contains(&(longest.clone(), DirEntryType::File)));
+        // a rejected rename leaves the entry where it was
+        assert!(!dir.rename(&longest, &dir, &too_long));
+        assert!(dir.find(&longest).is_some());
+        assert!(dir.rename(&longest, &dir, "short"));
+
+        // packing reports the host path rather than panicking
+        let host = Path::new("target/long_name_root");
+        let _ = std::fs::remove_dir_all(host);
+        std::fs::create_dir_all(host)?;
+        std::fs::write(host.join(&too_long), b"data")?;
+        let packed = root_inode.create_dir("packed").unwrap();
+        let err = pack_directory(&packed, host).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(err.to_string().contains(&too_long));
+        assert_eq!(packed.list().len(), 2);
+        std::fs::remove_dir_all(host)?;
+        Ok(())
+    }
+
+    /// Attributes are replaced in place, bounded by one block per inode, and freed with the inode
+    #[test]
+    fn xattr_test() -> std::io::Result<()> {
+        let fixture = Fixture::new()?;
+        let root_inode = &fixture.root_inode;
+        let fs = root_inode.fs();
+        assert!(fs.lock().has_xattrs());
+        let file = root_inode.create("tagged").unwrap();
+        let free = fs.lock().free_data_blocks();
+        assert_eq!(file.list_xattrs(), Ok(Vec::new()));
+        assert_eq!(file.get_xattr("mime"), Err(XattrError::NotFound));
+
+        file.set_xattr("mime", b"text/plain").unwrap();
+        file.set_xattr("user.origin", b"fuse").unwrap();
+        file.set_xattr("mime", b"text/markdown").unwrap();
+        assert_eq!(file.get_xattr("mime").unwrap(), b"text/markdown");
+        assert_eq!(file.list_xattrs().unwrap(), ["mime", "user.origin"]);
+        // an attribute block was taken for the file, the index grew by one
+        assert_eq!(fs.lock().free_data_blocks(), free - 2);
+        // writing data leaves the attributes alone
+        assert_eq!(file.write_at(0, b"data"), 4);
+        assert_eq!(file.get_xattr("user.origin").unwrap(), b"fuse");
+
+        // names and values are bounded
+        let name = "n".repeat(XATTR_NAME_MAX);
+        file.set_xattr(&name, &[]).unwrap();
+        assert_eq!(
+            file.set_xattr(&"n".repeat(XATTR_NAME_MAX + 1), &[]),
+            Err(XattrError::InvalidName)
+        );
+        assert_eq!(file.set_xattr("", b"x"), Err(XattrError::InvalidName));
+        let value = vec![7u8; XATTR_VALUE_MAX];
+        assert_eq!(
+            file.set_xattr("big", &[0; XATTR_VALUE_MAX + 1]),
+            Err(XattrError::ValueTooLong)
+        );
+        // and together they fit in one block, a failed set leaves the others alone
+        let mut count = 0;
+        let err = loop {
+            if let Err(err) = file.set_xattr(&format!("big{count}"), &value) {
+                break err;
+            }
+            count += 1;
+        };
+        assert_eq!(err, XattrError::NoSpace);
+        assert!(count > 0);
+        assert_eq!(file.list_xattrs().unwrap().len(), 3 + count);
+        assert_eq!(file.get_xattr(&name).unwrap(), b"");
+
+        // inodes do not share attributes, and deleting one frees its block
+        let other = root_inode.create("untagged").unwrap();
+        assert_eq!(other.list_xattrs(), Ok(Vec::new()));
+        file.clear();
+        root_inode.delete("tagged");
+        assert_eq!(fs.lock().free_data_blocks(), free - 1);
+        // a file created on the freed inode starts without attributes
+        let reused = root_inode.create("tagged").unwrap();
+        assert_eq!(reused.list_xattrs(), Ok(Vec::new()));
+        root_inode.delete("tagged");
+        root_inode.delete("untagged");
+        Ok(())
+    }
+
+    /// A clock ticking once every time it is read
+    struct TickingClock(AtomicU64);
+
+    impl Clock for TickingClock {
+        fn now(&self) -> u64 {
+            self.0.fetch_add(1, Ordering::Relaxed)
+        }
+    }
+
+    /// The time is read from the clock given at creation or opening, 0 without one
+    #[test]
+    fn clock_test() -> std::io::Result<()> {
+        let fixture = Fixture::new()?;
+        let block_file = &fixture.block_file;
+        let efs = EasyFileSystem::create(block_file, 4096, 1).unwrap();
+        assert_eq!(efs.lock().now(), 0);
+        let efs = EasyFileSystem::open(block_file).unwrap();
+        assert_eq!(efs.lock().now(), 0);
+
+        let clock = Arc::new(TickingClock(AtomicU64::new(7)));
+        let efs = EasyFileSystem::create_with_clock(block_file, 4096, 1, Arc::clone(&clock) as _)
+            .unwrap();
+        assert_eq!(efs.lock().now(), 7);
+        assert_eq!(efs.lock().now(), 8);
+        let efs = EasyFileSystem::open_with_clock(block_file, clock).unwrap();
+        assert_eq!(efs.lock().now(), 9);
+
+        assert!(HostClock.now() > 0);
+        Ok(())
+    }
+
+    /// Images with a newer layout version or unknown feature flags are refused, older ones mount
+    #[test]
+    fn version_test() -> std::io::Result<()> {
+        const TOTAL_BLOCKS: usize = 4;
+        const FEATURES: usize = 24;
+        const VERSION: usize = 28;
+
+        let fixture = Fixture::new()?;
+        let block_file = &fixture.block_file;
+        EasyFileSystem::create(block_file, 4096, 1).unwrap();
+
+        // rewrite a superblock field behind the cache, opening drops the stale copy
+        let patch = |offset: usize, value: u32| {
+            let mut block = [0u8; BLOCK_SIZE];
+            block_file.read_block(0, &mut block).unwrap();
+            block[offset..offset + 4].copy_from_slice(&value.to_ne_bytes());
+            block_file.write_block(0, &block).unwrap();
+        };
+
+        patch(VERSION, 1000);
+        let err = EasyFileSystem::open(block_file).err().unwrap();
+        assert_eq!(err, OpenError::UnsupportedVersion(1000));
+        assert!(err.to_string().contains("layout version 1000"));
+        // written before the version was recorded
+        patch(VERSION, 0);
+        assert!(EasyFileSystem::open(block_file).is_ok());
+
+        patch(FEATURES, 0b101);
+        assert_eq!(
+            EasyFileSystem::open(block_file).err(),
+            Some(OpenError::UnsupportedFeatures(0b100))
+        );
+        patch(FEATURES, 0);
+        let efs = EasyFileSystem::open(block_file).unwrap();
+        assert!(!efs.lock().has_dirent_types());
+        // attributes need the index created along with the flag
+        assert!(!efs.lock().has_xattrs());
+        assert_eq!(
+            EasyFileSystem::root_inode(&efs).list_xattrs(),
+            Err(XattrError::Unsupported)
+        );
+
+        // the image claims more blocks than it has
+        patch(TOTAL_BLOCKS, 8193);
+        assert_eq!(
+            EasyFileSystem::open(block_file).err(),
+            Some(OpenError::ExceedsDevice {
+                total: 8193,
+                device: 8192
+            })
+        );
+        patch(TOTAL_BLOCKS, 4096);
+        assert!(EasyFileSystem::open(block_file).is_ok());
+
+        patch(0, 0xdead_beef);
+        assert_eq!(
+            EasyFileSystem::open(block_file).err(),
+            Some(OpenError::BadMagic(0xdead_beef))
+        );
+        Ok(())
+    }
 }
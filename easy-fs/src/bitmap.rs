@@ -56,10 +56,31 @@ impl Bitmap {
             });
     }
 
+    /// Count the allocated bits by reading every bitmap block
+    pub fn count_allocated(&self, block_device: &Arc<dyn BlockDevice>) -> usize {
+        (0..self.blocks)
+            .map(|block_id| {
+                block_cache::get(self.start_block_id + block_id, block_device)
+                    .lock()
+                    .read(0, |bitmap_block: &BitmapBlock| {
+                        bitmap_block
+                            .iter()
+                            .map(|bits64| bits64.count_ones() as usize)
+                            .sum::<usize>()
+                    })
+            })
+            .sum()
+    }
+
     /// Get the max number of allocatable blocks
     pub fn maximum(&self) -> usize {
         self.blocks * BLOCK_BITS
     }
+
+    /// Get the id of the first block holding the bitmap
+    pub fn start_block_id(&self) -> usize {
+        self.start_block_id
+    }
 }
 
 /// Decompose bits into (`block_id`, `bits64_id`, `inner_id`)
@@ -1,10 +1,10 @@
 use alloc::{sync::Arc, vec::Vec};
 use lazy_static::lazy_static;
-use spin::Mutex;
+use spin::{Mutex, MutexGuard};
 
 use crate::{
-    block_dev::BlockDevice,
-    config::{BLOCK_CACHE_SIZE, BLOCK_SIZE},
+    block_dev::{BlockDevice, BlockError},
+    config::{BLOCK_CACHE_SIZE, BLOCK_IO_RETRIES, BLOCK_SIZE},
 };
 
 /// Cached block inside memory
@@ -17,18 +17,24 @@ pub struct BlockCache {
     block_device: Arc<dyn BlockDevice>,
     /// whether the block is dirty
     modified: bool,
+    /// whether the block was loaded, a block that failed to load is never written back
+    valid: bool,
 }
 
 impl BlockCache {
     /// Load a new [`BlockCache`] from disk
+    ///
+    /// If the block cannot be read the error is recorded and the cache is
+    /// zero-filled, see [`take_error`].
     pub fn new(block_id: usize, block_device: Arc<dyn BlockDevice>) -> Self {
         let mut cache = [0u8; BLOCK_SIZE];
-        block_device.read_block(block_id, &mut cache);
+        let valid = with_retries(|| block_device.read_block(block_id, &mut cache)).is_ok();
         Self {
             cache,
             block_id,
             block_device,
             modified: false,
+            valid,
         }
     }
 
@@ -60,9 +66,9 @@ impl BlockCache {
     }
 
     pub fn sync(&mut self) {
-        if self.modified {
+        if self.modified && self.valid {
             self.modified = false;
-            self.block_device.write_block(self.block_id, &self.cache);
+            let _ = with_retries(|| self.block_device.write_block(self.block_id, &self.cache));
         }
     }
 
@@ -71,9 +77,14 @@ impl BlockCache {
         f(self.as_ref(offset))
     }
 
+    /// Change the block through `f`, writing it back at once in [`CacheMode::WriteThrough`]
     #[inline]
     pub fn modify<T, V>(&mut self, offset: usize, f: impl FnOnce(&mut T) -> V) -> V {
-        f(self.as_mut_ref(offset))
+        let value = f(self.as_mut_ref(offset));
+        if cache_mode() == CacheMode::WriteThrough {
+            self.sync();
+        }
+        value
     }
 }
 
@@ -103,28 +114,160 @@ impl BlockCacheManager {
         } else {
             // substitute
             if self.queue.len() == BLOCK_CACHE_SIZE {
-                if let Some((idx, _)) = self
+                let unpinned = |cache: &Arc<Mutex<BlockCache>>| Arc::strong_count(cache) == 1;
+                // clean blocks go first, then dirty ones are written back all together
+                let victim = self
                     .queue
                     .iter()
-                    .enumerate()
-                    .find(|(_, (_, cache))| Arc::strong_count(cache) == 1)
-                {
+                    .position(|(_, cache)| unpinned(cache) && !cache.lock().modified)
+                    .or_else(|| {
+                        sync_batch(
+                            self.queue
+                                .iter()
+                                .map(|(_, cache)| cache)
+                                .filter(|cache| unpinned(cache))
+                                .map(|cache| cache.lock()),
+                        );
+                        self.queue.iter().position(|(_, cache)| unpinned(cache))
+                    });
+                if let Some(idx) = victim {
                     self.queue.swap_remove(idx);
                 } else {
                     panic!("Run out of BlockCache");
                 }
             }
-            // load block into mem and push back
+            // load block into mem and push back, a block that failed to load is read again next time
             let block_cache = Arc::new(Mutex::new(BlockCache::new(block_id, block_device.clone())));
-            self.queue.push((block_id, Arc::clone(&block_cache)));
+            if block_cache.lock().valid {
+                self.queue.push((block_id, Arc::clone(&block_cache)));
+            }
             block_cache
         }
     }
+
+    /// Load `block_ids` into the cache in order, returning how many were read from the device
+    ///
+    /// Blocks already cached are skipped, so preloading twice reads nothing
+    /// the second time. Every block of `block_ids` is pinned until it
+    /// returns, cached ones included, so they never evict one another;
+    /// loading stops once no other block can be evicted.
+    pub fn preload(&mut self, block_ids: &[usize], block_device: &Arc<dyn BlockDevice>) -> usize {
+        let mut pinned = Vec::new();
+        let mut loaded = 0;
+        for &block_id in block_ids {
+            if let Some((_, cache)) = self.queue.iter().find(|(id, _)| *id == block_id) {
+                pinned.push(Arc::clone(cache));
+                continue;
+            }
+            if self.queue.len() == BLOCK_CACHE_SIZE
+                && self
+                    .queue
+                    .iter()
+                    .all(|(_, cache)| Arc::strong_count(cache) > 1)
+            {
+                break;
+            }
+            pinned.push(self.get(block_id, block_device));
+            loaded += 1;
+        }
+        loaded
+    }
+
+    /// The cached copy of `block_id`, without loading it if it is not cached
+    fn find(&self, block_id: usize) -> Option<Arc<Mutex<BlockCache>>> {
+        self.queue
+            .iter()
+            .find(|(id, _)| *id == block_id)
+            .map(|(_, cache)| Arc::clone(cache))
+    }
+
+    /// Drop the cached copy of `block_id`, writing it back first if it is dirty
+    ///
+    /// Returns whether the block is no longer cached. A block someone still
+    /// holds is left in place, as dropping it would not stop them using it.
+    pub fn invalidate(&mut self, block_id: usize) -> bool {
+        let Some(idx) = self.queue.iter().position(|(id, _)| *id == block_id) else {
+            return true;
+        };
+        if Arc::strong_count(&self.queue[idx].1) > 1 {
+            return false;
+        }
+        let (_, cache) = self.queue.swap_remove(idx);
+        cache.lock().sync();
+        true
+    }
+
+    /// Drop every cached block no one holds, writing the dirty ones back first
+    ///
+    /// Returns how many blocks were kept because they are in use.
+    pub fn invalidate_all(&mut self) -> usize {
+        let unpinned = |cache: &Arc<Mutex<BlockCache>>| Arc::strong_count(cache) == 1;
+        sync_batch(
+            self.queue
+                .iter()
+                .map(|(_, cache)| cache)
+                .filter(|cache| unpinned(cache))
+                .map(|cache| cache.lock()),
+        );
+        self.queue.retain(|(_, cache)| !unpinned(cache));
+        self.queue.len()
+    }
+}
+
+/// When the cache writes modified blocks back to the device
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Every change is written back before [`BlockCache::modify`] returns
+    #[default]
+    WriteThrough,
+    /// Changed blocks stay dirty until they are evicted or synced, see [`sync_all`]
+    WriteBack,
+}
+
+/// The mode of the global block cache
+static CACHE_MODE: Mutex<CacheMode> = Mutex::new(CacheMode::WriteThrough);
+
+/// The mode the cache is in, [`CacheMode::WriteThrough`] unless changed
+#[inline]
+pub fn cache_mode() -> CacheMode {
+    *CACHE_MODE.lock()
+}
+
+/// Switch the cache to `mode` for every block device
+///
+/// Blocks left dirty by write-back are not written by switching to write-through, only by
+/// their next change, their eviction or a sync.
+#[inline]
+pub fn set_cache_mode(mode: CacheMode) {
+    *CACHE_MODE.lock() = mode;
 }
 
 lazy_static! {
     /// The global block cache manager
     static ref BLOCK_CACHE_MANAGER: Mutex<BlockCacheManager> = Mutex::new(BlockCacheManager::new());
+    /// The first block device error since the last [`take_error`]
+    static ref BLOCK_ERROR: Mutex<Option<BlockError>> = Mutex::new(None);
+}
+
+/// Run a block device request, retrying transient errors and recording the final error
+fn with_retries(mut request: impl FnMut() -> Result<(), BlockError>) -> Result<(), BlockError> {
+    let mut result = request();
+    for _ in 0..BLOCK_IO_RETRIES {
+        if result != Err(BlockError::Transient) {
+            break;
+        }
+        result = request();
+    }
+    if let Err(error) = result {
+        BLOCK_ERROR.lock().get_or_insert(error);
+    }
+    result
+}
+
+/// Take the block device error recorded since the last call, if any
+#[inline]
+pub fn take_error() -> Option<BlockError> {
+    BLOCK_ERROR.lock().take()
 }
 
 #[inline]
@@ -132,10 +275,110 @@ pub fn get(block_id: usize, block_device: &Arc<dyn BlockDevice>) -> Arc<Mutex<Bl
     BLOCK_CACHE_MANAGER.lock().get(block_id, block_device)
 }
 
+/// Warm the cache with `block_ids`, see [`BlockCacheManager::preload`]
 #[inline]
-pub fn sync_all() {
+pub fn preload(block_ids: &[usize], block_device: &Arc<dyn BlockDevice>) -> usize {
+    BLOCK_CACHE_MANAGER.lock().preload(block_ids, block_device)
+}
+
+/// Read from `block_id` without bringing it into the cache
+///
+/// Uses the cached copy if there is one, otherwise reads the block into a copy of its own
+/// that is dropped afterwards, so nothing is evicted.
+///
+/// # Errors
+///
+/// Returns the [`BlockError`] of a block that is not cached and could not be read.
+pub fn peek<T, V>(
+    block_id: usize,
+    block_device: &Arc<dyn BlockDevice>,
+    offset: usize,
+    f: impl FnOnce(&T) -> V,
+) -> Result<V, BlockError> {
+    let cached = BLOCK_CACHE_MANAGER.lock().find(block_id);
+    if let Some(cache) = cached {
+        return Ok(cache.lock().read(offset, f));
+    }
+    // never valid, so dropping it writes nothing back
+    let mut copy = BlockCache {
+        cache: [0u8; BLOCK_SIZE],
+        block_id,
+        block_device: Arc::clone(block_device),
+        modified: false,
+        valid: false,
+    };
+    with_retries(|| block_device.read_block(block_id, &mut copy.cache))?;
+    Ok(copy.read(offset, f))
+}
+
+/// Whether each of `block_ids` is in the cache right now, all checked at once
+pub fn cached(block_ids: &[usize]) -> Vec<bool> {
     let manager = BLOCK_CACHE_MANAGER.lock();
-    for (_, cache) in &manager.queue {
-        cache.lock().sync();
+    block_ids
+        .iter()
+        .map(|&block_id| manager.queue.iter().any(|(id, _)| *id == block_id))
+        .collect()
+}
+
+/// Drop the cached copy of `block_id` so the next access reads it from the device
+///
+/// For devices changed behind the cache's back, see [`BlockCacheManager::invalidate`].
+#[inline]
+pub fn invalidate(block_id: usize) -> bool {
+    BLOCK_CACHE_MANAGER.lock().invalidate(block_id)
+}
+
+/// Drop every cached block no one holds, see [`BlockCacheManager::invalidate_all`]
+#[inline]
+pub fn invalidate_all() -> usize {
+    BLOCK_CACHE_MANAGER.lock().invalidate_all()
+}
+
+/// Write back the dirty blocks of `caches` one [`BlockDevice::write_blocks`] call per device
+///
+/// As with [`BlockCache::sync`] the blocks are clean afterwards even if they could not
+/// be written. Blocks that failed transiently are retried one at a time.
+fn sync_batch<'a>(caches: impl Iterator<Item = MutexGuard<'a, BlockCache>>) {
+    let mut dirty: Vec<_> = caches
+        .filter(|cache| cache.modified && cache.valid)
+        .collect();
+    while let Some(first) = dirty.first() {
+        let block_device = Arc::clone(&first.block_device);
+        let (mut batch, rest): (Vec<_>, Vec<_>) = dirty
+            .into_iter()
+            .partition(|cache| Arc::ptr_eq(&cache.block_device, &block_device));
+        dirty = rest;
+        let blocks: Vec<_> = batch
+            .iter()
+            .map(|cache| (cache.block_id, &cache.cache[..]))
+            .collect();
+        let results = block_device.write_blocks(&blocks);
+        drop(blocks);
+        for (cache, result) in batch.iter_mut().zip(results) {
+            match result {
+                Ok(()) => cache.modified = false,
+                Err(BlockError::Transient) => cache.sync(),
+                Err(error) => {
+                    cache.modified = false;
+                    BLOCK_ERROR.lock().get_or_insert(error);
+                }
+            }
+        }
     }
 }
+
+/// Write back every dirty block that is not in use
+///
+/// A block locked by another operation is left dirty for a later sync or its eviction.
+/// That operation may be waiting for the manager while holding the block, so waiting
+/// for the block here with the manager held could deadlock.
+#[inline]
+pub fn sync_all() {
+    let manager = BLOCK_CACHE_MANAGER.lock();
+    sync_batch(
+        manager
+            .queue
+            .iter()
+            .filter_map(|(_, cache)| cache.try_lock()),
+    );
+}
@@ -1,12 +1,44 @@
+use alloc::vec::Vec;
 use core::any::Any;
 
+/// Errors reported by a block device
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockError {
+    /// The request could not be served now and may succeed if retried
+    Transient,
+    /// The device failed to transfer the block
+    Io,
+}
+
 /// Trait for block devices
 /// which reads and writes data in the unit of blocks
 pub trait BlockDevice: Send + Sync + Any {
     /// Read data form block to buffer
-    fn read_block(&self, block_id: usize, buf: &mut [u8]);
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`BlockError`] if the block could not be read.
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) -> Result<(), BlockError>;
     /// Write data from buffer to block
-    fn write_block(&self, block_id: usize, buf: &[u8]);
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`BlockError`] if the block could not be written.
+    fn write_block(&self, block_id: usize, buf: &[u8]) -> Result<(), BlockError>;
+    /// Number of blocks on the device, the valid block ids are below it
+    fn num_blocks(&self) -> usize;
+    /// Write each `(block_id, buf)` of `blocks`, returning the outcome of each write
+    ///
+    /// Devices that keep several requests in flight override this to have them all
+    /// submitted at once. Writes to the same block land in the order they are given,
+    /// and every write has completed by the time this returns, so a later call is
+    /// never reordered before it.
+    fn write_blocks(&self, blocks: &[(usize, &[u8])]) -> Vec<Result<(), BlockError>> {
+        blocks
+            .iter()
+            .map(|&(block_id, buf)| self.write_block(block_id, buf))
+            .collect()
+    }
     /// Handle interrupt request
     fn handle_irq(&self);
 }
@@ -0,0 +1,26 @@
+//! Time source of a file system
+//!
+//! The crate has no clock of its own, whoever opens or creates an [`EasyFileSystem`]
+//! hands one in, the kernel counting from boot and the fuse tool from the Unix epoch.
+//! It is only stored for now: the disk inode has no timestamps yet, so nothing in the
+//! crate reads it besides [`EasyFileSystem::now`].
+//!
+//! [`EasyFileSystem`]: crate::EasyFileSystem
+//! [`EasyFileSystem::now`]: crate::EasyFileSystem::now
+
+/// A source of the current time, in milliseconds
+pub trait Clock: Send + Sync {
+    /// The current time, in milliseconds since whatever epoch the clock counts from
+    fn now(&self) -> u64;
+}
+
+/// The clock of environments without a time source, always reading 0
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoClock;
+
+impl Clock for NoClock {
+    #[inline]
+    fn now(&self) -> u64 {
+        0
+    }
+}
@@ -4,9 +4,25 @@ pub const BLOCK_SIZE: usize = 512;
 pub const BLOCK_BITS: usize = BLOCK_SIZE * 8;
 /// Use a block cache of 16 blocks
 pub const BLOCK_CACHE_SIZE: usize = 16;
+/// Times a transient block device error is retried before giving up
+pub const BLOCK_IO_RETRIES: usize = 3;
 
 /// Magic number for sanity check
 pub const EFS_MAGIC: u32 = 0x3b80_0001;
+/// Version of the on-disk layout this crate writes and reads up to
+///
+/// Images written before the superblock recorded a version read as version 0.
+pub const EFS_VERSION: u32 = 1;
+/// Superblock feature flag: every directory entry records the kind of its inode
+///
+/// Images written before the flag existed leave the type byte zeroed.
+pub const FEATURE_DIRENT_TYPE: u32 = 1;
+/// Superblock feature flag: inodes can carry extended attributes, kept in an index inode
+///
+/// See the `xattr` module for the format.
+pub const FEATURE_XATTR: u32 = 1 << 1;
+/// Every feature flag this crate handles, images with any other are refused
+pub const FEATURES_SUPPORTED: u32 = FEATURE_DIRENT_TYPE | FEATURE_XATTR;
 
 /// The max number of direct inodes
 pub const DIRECT_COUNT: usize = 27;
@@ -29,5 +45,14 @@ pub const INDIRECT2_BOUND: usize = INDIRECT1_BOUND + INDIRECT2_COUNT;
 /// The upper bound of indirect3 inode indexs
 pub const INDIRECT3_BOUND: usize = INDIRECT2_BOUND + INDIRECT3_COUNT;
 
-/// The max length of inode name
+/// The max length of inode name, in bytes, see [`validate_name`](crate::layout::validate_name)
 pub const NAME_LENGTH_LIMIT: usize = 27;
+/// The max number of entries in a directory, `.` and `..` included
+///
+/// Lookups scan the whole directory, the limit keeps that scan bounded.
+pub const DIRENT_LIMIT: usize = 1024;
+
+/// The max length of an extended attribute name
+pub const XATTR_NAME_MAX: usize = 32;
+/// The max length of an extended attribute value
+pub const XATTR_VALUE_MAX: usize = 128;
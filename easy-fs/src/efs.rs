@@ -1,15 +1,102 @@
-use alloc::sync::Arc;
-use spin::Mutex;
+use alloc::{
+    collections::BTreeMap,
+    sync::{Arc, Weak},
+};
+use core::fmt;
+use spin::{Mutex, RwLock};
 
 use crate::{
     bitmap::Bitmap,
     block_cache,
-    block_dev::BlockDevice,
-    config::BLOCK_SIZE,
+    block_dev::{BlockDevice, BlockError},
+    clock::{Clock, NoClock},
+    config::{
+        BLOCK_BITS, BLOCK_SIZE, DIRECT_COUNT, EFS_VERSION, FEATURE_DIRENT_TYPE, FEATURE_XATTR,
+    },
     layout::{DataBlock, DiskInode, DiskInodeKind, SuperBlock},
     vfs::Inode,
 };
 
+/// Reasons [`EasyFileSystem::create`] cannot lay out a file system
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayoutError {
+    /// No inode bitmap blocks were asked for, leaving no inode for the root directory
+    NoInodes,
+    /// The superblock, the inode bitmap and the inode area leave no block for data
+    TooSmall {
+        /// Blocks needed for a single data block and its bitmap
+        needed: u64,
+        /// Blocks available on the device
+        total: u32,
+    },
+    /// The file system would extend past the end of the device
+    ExceedsDevice {
+        /// Blocks asked for
+        total: u32,
+        /// Blocks on the device
+        device: usize,
+    },
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoInodes => write!(f, "the inode bitmap needs at least one block"),
+            Self::TooSmall { needed, total } => write!(
+                f,
+                "{total} blocks are too few for this layout, at least {needed} are needed"
+            ),
+            Self::ExceedsDevice { total, device } => {
+                write!(
+                    f,
+                    "{total} blocks do not fit on a device of {device} blocks"
+                )
+            }
+        }
+    }
+}
+
+/// Reasons [`EasyFileSystem::open`] refuses to mount an image
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpenError {
+    /// The superblock does not carry the easy-fs magic number
+    BadMagic(u32),
+    /// The image was written by a newer layout version
+    UnsupportedVersion(u32),
+    /// The image uses the feature flags left in here, which this crate does not know
+    UnsupportedFeatures(u32),
+    /// The superblock claims more blocks than the device has, the image was truncated
+    /// or belongs to a larger device
+    ExceedsDevice {
+        /// Blocks recorded in the superblock
+        total: u32,
+        /// Blocks on the device
+        device: usize,
+    },
+}
+
+impl fmt::Display for OpenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadMagic(magic) => write!(f, "not an easy-fs image (magic {magic:#x})"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "layout version {version} is newer than the supported {EFS_VERSION}"
+            ),
+            Self::UnsupportedFeatures(features) => {
+                write!(f, "unsupported feature flags {features:#x}")
+            }
+            Self::ExceedsDevice { total, device } => write!(
+                f,
+                "the image spans {total} blocks but the device has only {device}"
+            ),
+        }
+    }
+}
+
+/// Inode the extended attribute index is created on, right after the root directory
+const XATTR_INDEX_INODE: u32 = 1;
+
 /// An easy file system on block
 pub struct EasyFileSystem {
     /// Real device
@@ -20,24 +107,61 @@ pub struct EasyFileSystem {
     pub data_bitmap: Bitmap,
     inode_area_start_block: u32,
     data_area_start_block: u32,
+    data_area_blocks: u32,
+    free_data_blocks: u32,
+    features: u32,
+    /// Inode of the extended attribute index, `None` on images without [`FEATURE_XATTR`]
+    xattr_index: Option<u32>,
+    /// Locks of the inodes some [`Inode`] is open on, by inode id
+    inode_locks: BTreeMap<u32, Weak<RwLock<()>>>,
+    /// Where the current time is read from, kept for the timestamps to come
+    clock: Arc<dyn Clock>,
 }
 
 impl EasyFileSystem {
     /// Create and initialize a new `EasyFileSystem` on a given block device.
+    ///
+    /// The file system has no time source, see [`Self::create_with_clock`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`LayoutError`] before touching the device if the areas do not fit in
+    /// `total_blocks`, or `total_blocks` do not fit on the device.
     pub fn create(
         block_device: &Arc<dyn BlockDevice>,
         total_blocks: u32,
         inode_bitmap_blocks: u32,
-    ) -> Arc<Mutex<Self>> {
-        // calculate block size of areas & create bitmaps
-        let inode_bitmap = Bitmap::new(1, inode_bitmap_blocks as usize);
-        let inode_num = inode_bitmap.maximum();
-        let inode_area_blocks =
-            (inode_num * core::mem::size_of::<DiskInode>()).div_ceil(BLOCK_SIZE) as u32;
+    ) -> Result<Arc<Mutex<Self>>, LayoutError> {
+        Self::create_with_clock(
+            block_device,
+            total_blocks,
+            inode_bitmap_blocks,
+            Arc::new(NoClock),
+        )
+    }
+
+    /// Create and initialize a new `EasyFileSystem` reading the time from `clock`
+    ///
+    /// # Errors
+    ///
+    /// As [`Self::create`].
+    pub fn create_with_clock(
+        block_device: &Arc<dyn BlockDevice>,
+        total_blocks: u32,
+        inode_bitmap_blocks: u32,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Arc<Mutex<Self>>, LayoutError> {
+        let (inode_area_blocks, data_bitmap_blocks, data_area_blocks) =
+            Self::layout(total_blocks, inode_bitmap_blocks)?;
+        let device = block_device.num_blocks();
+        if total_blocks as usize > device {
+            return Err(LayoutError::ExceedsDevice {
+                total: total_blocks,
+                device,
+            });
+        }
         let inode_total_blocks = inode_bitmap_blocks + inode_area_blocks;
-        let data_total_blocks = total_blocks - 1 - inode_total_blocks;
-        let data_bitmap_blocks = (data_total_blocks + 4096) / 4097;
-        let data_area_blocks = data_total_blocks - data_bitmap_blocks;
+        let inode_bitmap = Bitmap::new(1, inode_bitmap_blocks as usize);
         let data_bitmap = Bitmap::new(
             (1 + inode_bitmap_blocks + inode_area_blocks) as usize,
             data_bitmap_blocks as usize,
@@ -48,6 +172,12 @@ impl EasyFileSystem {
             data_bitmap,
             inode_area_start_block: 1 + inode_bitmap_blocks,
             data_area_start_block: 1 + inode_total_blocks + data_bitmap_blocks,
+            data_area_blocks,
+            free_data_blocks: data_area_blocks,
+            features: FEATURE_DIRENT_TYPE | FEATURE_XATTR,
+            xattr_index: Some(XATTR_INDEX_INODE),
+            inode_locks: BTreeMap::new(),
+            clock,
         };
 
         // clear all blocks
@@ -70,30 +200,98 @@ impl EasyFileSystem {
                     inode_area_blocks,
                     data_bitmap_blocks,
                     data_area_blocks,
+                    XATTR_INDEX_INODE,
                 );
+                debug_assert!(super_block.is_valid());
             });
 
         // write back immediately
         // create a inode for root node "/"
-        assert_eq!(efs.alloc_inode(), 0);
+        assert_eq!(efs.alloc_inode(), Some(0));
         let (root_inode_block_id, root_inode_offset) = efs.disk_inode_position(0);
         block_cache::get(root_inode_block_id as usize, block_device)
             .lock()
             .modify(root_inode_offset, |disk_inode: &mut DiskInode| {
                 disk_inode.init(DiskInodeKind::Directory);
             });
+        // the attribute index, linked from no directory
+        assert_eq!(efs.alloc_inode(), Some(XATTR_INDEX_INODE));
+        let (index_block_id, index_offset) = efs.disk_inode_position(XATTR_INDEX_INODE);
+        block_cache::get(index_block_id as usize, block_device)
+            .lock()
+            .modify(index_offset, |disk_inode: &mut DiskInode| {
+                disk_inode.init(DiskInodeKind::File);
+            });
         block_cache::sync_all();
 
-        Arc::new(Mutex::new(efs))
+        Ok(Arc::new(Mutex::new(efs)))
+    }
+
+    /// Size the inode area, the data bitmap and the data area, in this order
+    fn layout(total_blocks: u32, inode_bitmap_blocks: u32) -> Result<(u32, u32, u32), LayoutError> {
+        if inode_bitmap_blocks == 0 {
+            return Err(LayoutError::NoInodes);
+        }
+        // wide enough that no block count below can overflow
+        let inode_num = u64::from(inode_bitmap_blocks) * BLOCK_BITS as u64;
+        let inode_area_blocks =
+            (inode_num * core::mem::size_of::<DiskInode>() as u64).div_ceil(BLOCK_SIZE as u64);
+        // the superblock, the inodes, and one data block with its bitmap block
+        let needed = 1 + u64::from(inode_bitmap_blocks) + inode_area_blocks + 2;
+        if needed > u64::from(total_blocks) {
+            return Err(LayoutError::TooSmall {
+                needed,
+                total: total_blocks,
+            });
+        }
+
+        // fits in u32 as it is below total_blocks
+        let inode_area_blocks = inode_area_blocks as u32;
+        let data_total_blocks = total_blocks - 1 - inode_bitmap_blocks - inode_area_blocks;
+        // each bitmap block covers itself and the BLOCK_BITS data blocks that follow
+        let data_bitmap_blocks = data_total_blocks.div_ceil(BLOCK_BITS as u32 + 1);
+        let data_area_blocks = data_total_blocks - data_bitmap_blocks;
+        Ok((inode_area_blocks, data_bitmap_blocks, data_area_blocks))
     }
 
     /// Open a block device as a filesystem
-    pub fn open(block_device: &Arc<dyn BlockDevice>) -> Arc<Mutex<Self>> {
-        // read SuperBlock
-        block_cache::get(0, block_device)
+    ///
+    /// Blocks cached from before are dropped first, as the device may have changed
+    /// since. The superblock, the root directory and the first bitmap blocks are
+    /// then preloaded into the block cache so the first lookups avoid the device.
+    /// The file system has no time source, see [`Self::open_with_clock`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`OpenError`] without mounting if the superblock is not one this
+    /// crate can handle, or describes a file system larger than the device.
+    pub fn open(block_device: &Arc<dyn BlockDevice>) -> Result<Arc<Mutex<Self>>, OpenError> {
+        Self::open_with_clock(block_device, Arc::new(NoClock))
+    }
+
+    /// Open a block device as a filesystem reading the time from `clock`
+    ///
+    /// # Errors
+    ///
+    /// As [`Self::open`].
+    pub fn open_with_clock(
+        block_device: &Arc<dyn BlockDevice>,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Arc<Mutex<Self>>, OpenError> {
+        block_cache::invalidate_all();
+        // read SuperBlock, keeping it cached while the rest is preloaded
+        let super_block_cache = block_cache::get(0, block_device);
+        let efs = super_block_cache
             .lock()
             .read(0, |super_block: &SuperBlock| {
-                assert!(super_block.is_valid(), "Error loading EFS!");
+                super_block.check()?;
+                let device = block_device.num_blocks();
+                if super_block.total_blocks as usize > device {
+                    return Err(OpenError::ExceedsDevice {
+                        total: super_block.total_blocks,
+                        device,
+                    });
+                }
                 let inode_total_blocks =
                     super_block.inode_bitmap_blocks + super_block.inode_area_blocks;
                 let efs = Self {
@@ -105,18 +303,75 @@ impl EasyFileSystem {
                     ),
                     inode_area_start_block: 1 + super_block.inode_bitmap_blocks,
                     data_area_start_block: 1 + inode_total_blocks + super_block.data_bitmap_blocks,
+                    data_area_blocks: super_block.data_area_blocks,
+                    free_data_blocks: 0,
+                    features: super_block.features,
+                    xattr_index: (super_block.features & FEATURE_XATTR != 0)
+                        .then_some(super_block.xattr_index),
+                    inode_locks: BTreeMap::new(),
+                    clock,
                 };
-                Arc::new(Mutex::new(efs))
-            })
+                Ok(Arc::new(Mutex::new(efs)))
+            })?;
+        {
+            let mut fs = efs.lock();
+            let used = fs.data_bitmap.count_allocated(block_device) as u32;
+            fs.free_data_blocks = fs.data_area_blocks - used;
+            fs.preload();
+        }
+        Ok(efs)
+    }
+
+    /// Warm the block cache with the blocks every path lookup starts from
+    fn preload(&self) {
+        let (root_block, root_offset) = self.disk_inode_position(0);
+        let root_cache = block_cache::get(root_block as usize, &self.block_device);
+        let mut block_ids = alloc::vec![0, root_block as usize];
+        root_cache.lock().read(root_offset, |root: &DiskInode| {
+            let blocks = root
+                .size
+                .div_ceil(BLOCK_SIZE as u32)
+                .min(DIRECT_COUNT as u32);
+            block_ids.extend((0..blocks).map(|i| root.block_id(i, &self.block_device) as usize));
+        });
+        block_ids.push(self.inode_bitmap.start_block_id());
+        block_ids.push(self.data_bitmap.start_block_id());
+        block_cache::preload(&block_ids, &self.block_device);
+    }
+
+    /// Write every modified cached block back to the device
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`BlockError`] of a block that could not be written back after retrying,
+    /// the remaining blocks are still written.
+    pub fn sync(&self) -> Result<(), BlockError> {
+        block_cache::take_error();
+        block_cache::sync_all();
+        block_cache::take_error().map_or(Ok(()), Err)
     }
 
     /// Get the root inode of the filesystem
     pub fn root_inode(efs: &Arc<Mutex<Self>>) -> Inode {
-        let block_device = Arc::clone(&efs.lock().block_device);
         // acquire efs lock temporarily
-        let (block_id, block_offset) = efs.lock().disk_inode_position(0);
-        // release efs lock
-        Inode::new(block_id, block_offset, Arc::clone(efs), block_device)
+        let mut fs = efs.lock();
+        let block_device = Arc::clone(&fs.block_device);
+        let (block_id, block_offset) = fs.disk_inode_position(0);
+        let lock = fs.inode_lock(0);
+        drop(fs);
+        Inode::new(block_id, block_offset, lock, Arc::clone(efs), block_device)
+    }
+
+    /// Get the lock of an inode, shared by every [`Inode`] open on it
+    pub(crate) fn inode_lock(&mut self, inode_id: u32) -> Arc<RwLock<()>> {
+        if let Some(lock) = self.inode_locks.get(&inode_id).and_then(Weak::upgrade) {
+            return lock;
+        }
+        // forget the inodes no longer open before adding one
+        self.inode_locks.retain(|_, lock| lock.strong_count() > 0);
+        let lock = Arc::new(RwLock::new(()));
+        self.inode_locks.insert(inode_id, Arc::downgrade(&lock));
+        lock
     }
 
     /// Get `block_id` and offset by `inode_id`
@@ -138,10 +393,12 @@ impl EasyFileSystem {
         block_relative * inodes_per_block + inode_index_within_block as u32
     }
 
-    /// Allocate a new inode
+    /// Allocate a new inode, or `None` if every inode is in use
     #[inline]
-    pub fn alloc_inode(&mut self) -> u32 {
-        self.inode_bitmap.alloc(&self.block_device).unwrap() as u32
+    pub fn alloc_inode(&mut self) -> Option<u32> {
+        self.inode_bitmap
+            .alloc(&self.block_device)
+            .map(|inode_id| inode_id as u32)
     }
 
     /// Deallocate a inode
@@ -151,10 +408,62 @@ impl EasyFileSystem {
             .dealloc(&self.block_device, inode_id as usize);
     }
 
-    /// Allocate a data block
+    /// Allocate a data block, or `None` if the data area is full
+    ///
+    /// The data bitmap has more bits than the data area has blocks, a bit past
+    /// the end of the area is handed back instead of being used.
+    pub fn try_alloc_data(&mut self) -> Option<u32> {
+        let bit = self.data_bitmap.alloc(&self.block_device)?;
+        if bit >= self.data_area_blocks as usize {
+            self.data_bitmap.dealloc(&self.block_device, bit);
+            return None;
+        }
+        self.free_data_blocks -= 1;
+        Some(bit as u32 + self.data_area_start_block)
+    }
+
+    /// Number of blocks in the data area
+    #[inline]
+    pub fn data_area_blocks(&self) -> u32 {
+        self.data_area_blocks
+    }
+
+    /// Number of data blocks not allocated to any file
+    #[inline]
+    pub fn free_data_blocks(&self) -> u32 {
+        self.free_data_blocks
+    }
+
+    /// Whether every directory entry records the kind of its inode
+    ///
+    /// Clear on images created before entries had a type, whose entries may
+    /// read as [`DirEntryType::Unknown`](crate::DirEntryType::Unknown).
+    #[inline]
+    pub fn has_dirent_types(&self) -> bool {
+        self.features & FEATURE_DIRENT_TYPE != 0
+    }
+
+    /// Whether inodes can carry extended attributes
+    ///
+    /// Clear on images created before attributes were supported.
+    #[inline]
+    pub fn has_xattrs(&self) -> bool {
+        self.xattr_index.is_some()
+    }
+
+    /// The current time from the clock the file system was opened with
+    ///
+    /// Reads 0 unless it was opened or created with a clock. Nothing on disk records
+    /// the time yet, this is for callers wanting the same clock as the file system.
+    #[inline]
+    pub fn now(&self) -> u64 {
+        self.clock.now()
+    }
+
+    /// Inode of the extended attribute index, if the image has one
     #[inline]
-    pub fn alloc_data(&mut self) -> u32 {
-        self.data_bitmap.alloc(&self.block_device).unwrap() as u32 + self.data_area_start_block
+    pub(crate) fn xattr_index(&self) -> Option<u32> {
+        self.xattr_index
     }
 
     /// Deallocate a data block
@@ -168,5 +477,6 @@ impl EasyFileSystem {
             &self.block_device,
             (block_id - self.data_area_start_block) as usize,
         );
+        self.free_data_blocks += 1;
     }
 }
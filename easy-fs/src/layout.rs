@@ -1,12 +1,15 @@
 use alloc::{sync::Arc, vec::Vec};
+use core::fmt;
 
 use crate::{
     block_cache,
-    block_dev::BlockDevice,
+    block_dev::{BlockDevice, BlockError},
     config::{
-        BLOCK_SIZE, DIRECT_BOUND, DIRECT_COUNT, EFS_MAGIC, INDIRECT1_BOUND, INDIRECT1_COUNT,
-        INDIRECT2_BOUND, INDIRECT2_COUNT, INDIRECT_COUNT, NAME_LENGTH_LIMIT,
+        BLOCK_SIZE, DIRECT_BOUND, DIRECT_COUNT, EFS_MAGIC, EFS_VERSION, FEATURES_SUPPORTED,
+        FEATURE_DIRENT_TYPE, FEATURE_XATTR, INDIRECT1_BOUND, INDIRECT1_COUNT, INDIRECT2_BOUND,
+        INDIRECT2_COUNT, INDIRECT_COUNT, NAME_LENGTH_LIMIT,
     },
+    efs::OpenError,
 };
 
 /// Super block of a filesystem
@@ -18,6 +21,12 @@ pub struct SuperBlock {
     pub inode_area_blocks: u32,
     pub data_bitmap_blocks: u32,
     pub data_area_blocks: u32,
+    /// Feature flags, zero on images that predate them
+    pub features: u32,
+    /// Layout version, zero on images that predate it
+    pub version: u32,
+    /// Inode of the extended attribute index, only meaningful with [`FEATURE_XATTR`]
+    pub xattr_index: u32,
 }
 
 impl SuperBlock {
@@ -30,6 +39,7 @@ impl SuperBlock {
         inode_area_blocks: u32,
         data_bitmap_blocks: u32,
         data_area_blocks: u32,
+        xattr_index: u32,
     ) {
         *self = Self {
             magic: EFS_MAGIC,
@@ -38,23 +48,67 @@ impl SuperBlock {
             inode_area_blocks,
             data_bitmap_blocks,
             data_area_blocks,
+            features: FEATURE_DIRENT_TYPE | FEATURE_XATTR,
+            version: EFS_VERSION,
+            xattr_index,
         }
     }
 
-    /// Check if a super block is valid using efs magic
+    /// Check that the super block describes an image this crate can mount
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`OpenError`] if the magic is wrong, or if the image has a newer
+    /// version or uses features this crate does not know.
+    pub fn check(&self) -> Result<(), OpenError> {
+        if self.magic != EFS_MAGIC {
+            return Err(OpenError::BadMagic(self.magic));
+        }
+        if self.version > EFS_VERSION {
+            return Err(OpenError::UnsupportedVersion(self.version));
+        }
+        let unknown = self.features & !FEATURES_SUPPORTED;
+        if unknown != 0 {
+            return Err(OpenError::UnsupportedFeatures(unknown));
+        }
+        Ok(())
+    }
+
+    /// Check if a super block is valid using efs magic, version and features
     #[inline]
     pub fn is_valid(&self) -> bool {
-        self.magic == EFS_MAGIC
+        self.check().is_ok()
     }
 }
 
 /// Type of a disk inode
-#[derive(PartialEq)]
+#[derive(Clone, PartialEq)]
 pub enum DiskInodeKind {
     File,
     Directory,
 }
 
+/// Kind of inode a directory entry points to, as recorded in the entry itself
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DirEntryType {
+    /// Not recorded, the entry predates [`FEATURE_DIRENT_TYPE`]
+    Unknown = 0,
+    /// A regular file
+    File = 1,
+    /// A directory
+    Directory = 2,
+}
+
+impl From<&DiskInodeKind> for DirEntryType {
+    fn from(kind: &DiskInodeKind) -> Self {
+        match kind {
+            DiskInodeKind::File => Self::File,
+            DiskInodeKind::Directory => Self::Directory,
+        }
+    }
+}
+
 /// A indirect block
 type IndirectBlock = [u32; BLOCK_SIZE / 4];
 /// A data block
@@ -62,6 +116,7 @@ pub type DataBlock = [u8; BLOCK_SIZE];
 
 /// A disk inode
 #[repr(C)]
+#[derive(Clone)]
 pub struct DiskInode {
     kind: DiskInodeKind,
     pub size: u32,
@@ -82,6 +137,12 @@ impl DiskInode {
         self.indirect2 = 0;
     }
 
+    /// Kind of the inode as recorded in directory entries
+    #[inline]
+    pub fn dirent_type(&self) -> DirEntryType {
+        DirEntryType::from(&self.kind)
+    }
+
     /// Whether this inode is a directory
     #[inline]
     pub fn is_dir(&self) -> bool {
@@ -138,6 +199,39 @@ impl DiskInode {
         }
     }
 
+    /// Get id of block given inner id, without loading index blocks into the cache
+    ///
+    /// See [`block_cache::peek`].
+    pub fn peek_block_id(
+        &self,
+        block_index: u32,
+        block_device: &Arc<dyn BlockDevice>,
+    ) -> Result<u32, BlockError> {
+        let entry = |block_id: u32, index: usize| {
+            block_cache::peek(
+                block_id as usize,
+                block_device,
+                0,
+                |block: &IndirectBlock| block[index],
+            )
+        };
+        let block_index = block_index as usize;
+        if block_index < DIRECT_BOUND {
+            Ok(self.direct[block_index])
+        } else if block_index < INDIRECT1_BOUND {
+            entry(self.indirect1, block_index - DIRECT_BOUND)
+        } else if block_index < INDIRECT2_BOUND {
+            let index = block_index - INDIRECT1_BOUND;
+            let indirect1 = entry(self.indirect2, index / INDIRECT1_COUNT)?;
+            entry(indirect1, index % INDIRECT1_COUNT)
+        } else {
+            let index = block_index - INDIRECT2_BOUND;
+            let indirect2 = entry(self.indirect3, index / INDIRECT2_COUNT)?;
+            let indirect1 = entry(indirect2, index % INDIRECT2_COUNT / INDIRECT1_COUNT)?;
+            entry(indirect1, index % INDIRECT2_COUNT % INDIRECT1_COUNT)
+        }
+    }
+
     #[inline]
     fn count_data_block(size: u32) -> u32 {
         size.div_ceil(BLOCK_SIZE as u32)
@@ -341,98 +435,88 @@ impl DiskInode {
         cur_leaf
     }
 
-    /// Decrease the size
+    /// Decrease the size of current disk inode and return the data and index blocks
+    /// past the new end, which should be deallocated.
     pub fn decrease_size(
         &mut self,
         new_size: u32,
         block_device: &Arc<dyn BlockDevice>,
     ) -> Vec<u32> {
-        let mut drop_data_blocks: Vec<u32> = Vec::new();
-        let mut block_index = Self::count_data_block(self.size) as usize;
-        self.size = new_size;
-        let mut recycled_blocks = Self::count_data_block(self.size) as usize;
+        assert!(new_size <= self.size);
+        let old_blocks = Self::count_data_block(self.size) as usize;
+        let new_blocks = Self::count_data_block(new_size) as usize;
+        let read_indirect = |block_id: u32| {
+            block_cache::get(block_id as usize, block_device)
+                .lock()
+                .read(0, |indirect_block: &IndirectBlock| *indirect_block)
+        };
+
+        // data blocks, looked up before any index block is released
+        let mut drop_blocks: Vec<u32> = (new_blocks..old_blocks)
+            .map(|block_index| self.block_id(block_index as u32, block_device))
+            .collect();
 
         // -------------------- Direct Blocks --------------------
-        let direct_recycle_count = block_index.min(DIRECT_COUNT);
-        while recycled_blocks < direct_recycle_count {
-            drop_data_blocks.push(self.direct[recycled_blocks]);
-            self.direct[recycled_blocks] = 0;
-            recycled_blocks += 1;
+        if new_blocks < DIRECT_BOUND {
+            self.direct[new_blocks..old_blocks.min(DIRECT_BOUND)].fill(0);
         }
         // ----------------- End of Direct Blocks ----------------
 
-        if recycled_blocks <= DIRECT_COUNT {
-            return drop_data_blocks;
-        }
-
         // -------------------- Indirect Level 1 -----------------
-        if block_index == DIRECT_COUNT {
-            drop_data_blocks.push(self.indirect1);
+        if new_blocks <= DIRECT_BOUND && old_blocks > DIRECT_BOUND {
+            drop_blocks.push(self.indirect1);
             self.indirect1 = 0;
         }
-        block_index -= DIRECT_COUNT;
-        recycled_blocks -= DIRECT_COUNT;
-
-        let cur_leaf = Self::collect_tree(
-            &mut drop_data_blocks,
-            self.indirect1,
-            0,
-            recycled_blocks,
-            0,
-            1,
-            block_device,
-        );
-        block_index = block_index.min(cur_leaf);
         // ----------------- End of Indirect Level 1 ------------
 
-        if recycled_blocks <= INDIRECT1_COUNT {
-            return drop_data_blocks;
-        }
-
         // -------------------- Indirect Level 2 -----------------
-        if block_index == INDIRECT1_COUNT {
-            drop_data_blocks.push(self.indirect2);
-            self.indirect2 = 0;
+        // an index block is released once all of the leaves it covers are gone
+        if old_blocks > INDIRECT1_BOUND {
+            for (i, &indirect1) in read_indirect(self.indirect2).iter().enumerate() {
+                let first_leaf = INDIRECT1_BOUND + i * INDIRECT1_COUNT;
+                if first_leaf >= old_blocks {
+                    break;
+                }
+                if first_leaf >= new_blocks {
+                    drop_blocks.push(indirect1);
+                }
+            }
+            if new_blocks <= INDIRECT1_BOUND {
+                drop_blocks.push(self.indirect2);
+                self.indirect2 = 0;
+            }
         }
-        block_index -= INDIRECT1_COUNT;
-        recycled_blocks -= INDIRECT1_COUNT;
-
-        let cur_leaf = Self::collect_tree(
-            &mut drop_data_blocks,
-            self.indirect2,
-            0,
-            recycled_blocks,
-            0,
-            2,
-            block_device,
-        );
-        block_index = block_index.min(cur_leaf);
         // ----------------- End of Indirect Level 2 ------------
 
-        if recycled_blocks <= INDIRECT2_COUNT {
-            return drop_data_blocks;
-        }
-
         // -------------------- Indirect Level 3 -----------------
-        if block_index == INDIRECT2_COUNT {
-            drop_data_blocks.push(self.indirect3);
-            self.indirect3 = 0;
+        if old_blocks > INDIRECT2_BOUND {
+            for (i, &indirect2) in read_indirect(self.indirect3).iter().enumerate() {
+                let first_leaf = INDIRECT2_BOUND + i * INDIRECT2_COUNT;
+                if first_leaf >= old_blocks {
+                    break;
+                }
+                for (j, &indirect1) in read_indirect(indirect2).iter().enumerate() {
+                    let first_leaf = first_leaf + j * INDIRECT1_COUNT;
+                    if first_leaf >= old_blocks {
+                        break;
+                    }
+                    if first_leaf >= new_blocks {
+                        drop_blocks.push(indirect1);
+                    }
+                }
+                if first_leaf >= new_blocks {
+                    drop_blocks.push(indirect2);
+                }
+            }
+            if new_blocks <= INDIRECT2_BOUND {
+                drop_blocks.push(self.indirect3);
+                self.indirect3 = 0;
+            }
         }
-        // block_index -= INDIRECT1_COUNT;
-        recycled_blocks -= INDIRECT1_COUNT;
-
-        Self::collect_tree(
-            &mut drop_data_blocks,
-            self.indirect3,
-            0,
-            recycled_blocks,
-            0,
-            3,
-            block_device,
-        );
         // ----------------- End of Indirect Level 3 ------------
 
-        drop_data_blocks
+        self.size = new_size;
+        drop_blocks
     }
 
     /// Clear size to zero and return blocks that should be deallocated.
@@ -514,6 +598,9 @@ impl DiskInode {
     }
 
     /// Read data from current disk inode
+    ///
+    /// Only the bytes before the end of the inode are read. A read of nothing, or one
+    /// starting at or past the end, returns `0` without looking at any block.
     pub fn read_at(
         &self,
         offset: usize,
@@ -521,7 +608,7 @@ impl DiskInode {
         block_device: &Arc<dyn BlockDevice>,
     ) -> usize {
         let mut start = offset;
-        let end = (offset + buf.len()).min(self.size as usize);
+        let end = offset.saturating_add(buf.len()).min(self.size as usize);
         if start >= end {
             return 0;
         }
@@ -558,7 +645,11 @@ impl DiskInode {
     }
 
     /// Write data into current disk inode
-    /// size must be adjusted properly beforehand
+    ///
+    /// The size must be adjusted beforehand, only the bytes before the end of the inode
+    /// are written. A write of nothing, or one starting at or past the end, returns `0`
+    /// without looking at any block; the block past the last one is not allocated, so
+    /// there is none to look at when the size is a multiple of [`BLOCK_SIZE`].
     pub fn write_at(
         &mut self,
         offset: usize,
@@ -566,8 +657,10 @@ impl DiskInode {
         block_device: &Arc<dyn BlockDevice>,
     ) -> usize {
         let mut start = offset;
-        let end = (offset + buf.len()).min(self.size as usize);
-        assert!(start <= end);
+        let end = offset.saturating_add(buf.len()).min(self.size as usize);
+        if start >= end {
+            return 0;
+        }
         let mut start_block = start / BLOCK_SIZE;
         let mut write_size = 0usize;
 
@@ -602,24 +695,80 @@ impl DiskInode {
     }
 }
 
+/// Reasons a name cannot be given to a directory entry
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NameError {
+    /// The name is empty
+    Empty,
+    /// The name takes more than [`NAME_LENGTH_LIMIT`] bytes
+    TooLong(usize),
+    /// The name holds a `/`, which separates path components, or a NUL, which would end
+    /// it early on disk
+    Forbidden(char),
+}
+
+impl fmt::Display for NameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "names cannot be empty"),
+            Self::TooLong(len) => write!(
+                f,
+                "the name takes {len} bytes, at most {NAME_LENGTH_LIMIT} are allowed"
+            ),
+            Self::Forbidden(c) => write!(f, "names cannot contain {c:?}"),
+        }
+    }
+}
+
+/// Check that `name` fits in a [`DirEntry`] and can be looked up again
+///
+/// The limit counts bytes rather than characters, a name of exactly
+/// [`NAME_LENGTH_LIMIT`] bytes is accepted.
+///
+/// # Errors
+///
+/// Returns the first [`NameError`] found, checking emptiness, then length, then characters.
+pub fn validate_name(name: &str) -> Result<(), NameError> {
+    if name.is_empty() {
+        return Err(NameError::Empty);
+    }
+    if name.len() > NAME_LENGTH_LIMIT {
+        return Err(NameError::TooLong(name.len()));
+    }
+    match name.chars().find(|&c| c == '/' || c == '\0') {
+        Some(c) => Err(NameError::Forbidden(c)),
+        None => Ok(()),
+    }
+}
+
 /// A directory entry
+///
+/// The type byte takes the place of the terminator a name of [`NAME_LENGTH_LIMIT`]
+/// bytes used to have, which was always zero, so older entries read as
+/// [`DirEntryType::Unknown`].
 #[repr(C)]
 pub struct DirEntry {
-    name: [u8; NAME_LENGTH_LIMIT + 1],
+    name: [u8; NAME_LENGTH_LIMIT],
+    file_type: u8,
     inode_number: u32,
 }
 
 /// Size of a directory entry
 pub const DIRENT_SIZE: usize = core::mem::size_of::<DirEntry>();
 
+const _: () = assert!(DIRENT_SIZE == 32);
+
 impl DirEntry {
-    /// Crate a directory entry from name and inode number
+    /// Crate a directory entry from name, inode number and the kind of the inode
+    ///
+    /// `name` must pass [`validate_name`], a longer one panics.
     #[inline]
-    pub fn new(name: &str, inode_number: u32) -> Self {
-        let mut bytes = [0u8; NAME_LENGTH_LIMIT + 1];
+    pub fn new(name: &str, inode_number: u32, file_type: DirEntryType) -> Self {
+        let mut bytes = [0u8; NAME_LENGTH_LIMIT];
         bytes[..name.len()].copy_from_slice(name.as_bytes());
         Self {
             name: bytes,
+            file_type: file_type as u8,
             inode_number,
         }
     }
@@ -628,7 +777,8 @@ impl DirEntry {
     #[inline]
     pub fn empty() -> Self {
         Self {
-            name: [0u8; NAME_LENGTH_LIMIT + 1],
+            name: [0u8; NAME_LENGTH_LIMIT],
+            file_type: DirEntryType::Unknown as u8,
             inode_number: 0,
         }
     }
@@ -662,4 +812,14 @@ impl DirEntry {
     pub fn inode_number(&self) -> u32 {
         self.inode_number
     }
+
+    /// Get the kind of inode the entry points to
+    #[inline]
+    pub fn file_type(&self) -> DirEntryType {
+        match self.file_type {
+            1 => DirEntryType::File,
+            2 => DirEntryType::Directory,
+            _ => DirEntryType::Unknown,
+        }
+    }
 }
@@ -8,13 +8,18 @@ extern crate log;
 mod bitmap;
 mod block_cache;
 mod block_dev;
+mod clock;
 mod config;
 mod efs;
 mod layout;
 mod vfs;
+mod xattr;
 
-pub use block_dev::BlockDevice;
-pub use config::BLOCK_SIZE;
-pub use efs::EasyFileSystem;
-pub use layout::DIRENT_SIZE;
-pub use vfs::Inode;
+pub use block_cache::{cache_mode, invalidate, invalidate_all, set_cache_mode, CacheMode};
+pub use block_dev::{BlockDevice, BlockError};
+pub use clock::{Clock, NoClock};
+pub use config::{BLOCK_SIZE, DIRENT_LIMIT, NAME_LENGTH_LIMIT, XATTR_NAME_MAX, XATTR_VALUE_MAX};
+pub use efs::{EasyFileSystem, LayoutError, OpenError};
+pub use layout::{validate_name, DirEntry, DirEntryType, NameError, DIRENT_SIZE};
+pub use vfs::{CreateError, Inode};
+pub use xattr::XattrError;
@@ -1,17 +1,56 @@
-use alloc::{sync::Arc, vec::Vec};
-use spin::{Mutex, MutexGuard};
+use alloc::{string::String, sync::Arc, vec, vec::Vec};
+use core::fmt;
+use spin::{Mutex, MutexGuard, RwLock};
 
 use crate::{
     block_cache,
-    block_dev::BlockDevice,
+    block_dev::{BlockDevice, BlockError},
+    config::{BLOCK_SIZE, DIRENT_LIMIT},
     efs::EasyFileSystem,
-    layout::{DirEntry, DiskInode, DiskInodeKind, DIRENT_SIZE},
+    layout::{
+        validate_name, DataBlock, DirEntry, DirEntryType, DiskInode, DiskInodeKind, NameError,
+        DIRENT_SIZE,
+    },
+    xattr::{self, XattrError},
 };
 
+/// Reasons a file or directory cannot be created
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CreateError {
+    /// The name fails [`validate_name`]
+    InvalidName(NameError),
+    /// The directory already has an entry of that name
+    Exists,
+    /// The directory already holds [`DIRENT_LIMIT`] entries
+    DirectoryFull,
+    /// No inode or data block is left
+    NoSpace,
+}
+
+impl fmt::Display for CreateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidName(err) => err.fmt(f),
+            Self::Exists => write!(f, "the name is already taken"),
+            Self::DirectoryFull => {
+                write!(f, "directories hold at most {DIRENT_LIMIT} entries")
+            }
+            Self::NoSpace => write!(f, "filesystem full, no inode or data block is left"),
+        }
+    }
+}
+
 /// Virtual filesystem layer over easy-fs
+///
+/// The data and size of an inode are guarded by its own lock, so I/O on different files
+/// proceeds in parallel, while the `fs` lock guards the bitmaps and is only held to
+/// allocate and free. Locks are taken in a fixed order: inode locks first, by increasing
+/// inode id, then the `fs` lock, then the locks of cached blocks.
 pub struct Inode {
     block_id: usize,
     block_offset: usize,
+    /// Shared by every `Inode` on the same disk inode
+    lock: Arc<RwLock<()>>,
     fs: Arc<Mutex<EasyFileSystem>>,
     block_device: Arc<dyn BlockDevice>,
 }
@@ -22,17 +61,31 @@ impl Inode {
     pub fn new(
         block_id: u32,
         block_offset: usize,
+        lock: Arc<RwLock<()>>,
         fs: Arc<Mutex<EasyFileSystem>>,
         block_device: Arc<dyn BlockDevice>,
     ) -> Self {
         Self {
             block_id: block_id as usize,
             block_offset,
+            lock,
             fs,
             block_device,
         }
     }
 
+    /// Create the Inode of `inode_id`, with the `fs` lock held
+    fn open(&self, inode_id: u32, fs: &mut MutexGuard<EasyFileSystem>) -> Arc<Inode> {
+        let (block_id, block_offset) = fs.disk_inode_position(inode_id);
+        Arc::new(Self::new(
+            block_id,
+            block_offset,
+            fs.inode_lock(inode_id),
+            self.fs.clone(),
+            self.block_device.clone(),
+        ))
+    }
+
     /// Call a function over a disk inode to read it
     fn read_disk_inode<V>(&self, f: impl FnOnce(&DiskInode) -> V) -> V {
         block_cache::get(self.block_id, &self.block_device)
@@ -47,22 +100,36 @@ impl Inode {
             .modify(self.block_offset, f)
     }
 
-    // Increase the size of a disk inode
+    // Increase the size of a disk inode, leaving it untouched and returning `false` if the data area is full
     fn increase_size(
         &self,
         new_size: u32,
         disk_inode: &mut DiskInode,
         fs: &mut MutexGuard<EasyFileSystem>,
-    ) {
+    ) -> bool {
         if new_size < disk_inode.size {
-            return;
+            return true;
         }
+        let old_size = disk_inode.size;
         let blocks_needed = disk_inode.blocks_num_needed(new_size);
         let mut v: Vec<u32> = Vec::new();
         for _ in 0..blocks_needed {
-            v.push(fs.alloc_data());
+            let Some(block_id) = fs.try_alloc_data() else {
+                for block_id in v {
+                    fs.dealloc_data(block_id);
+                }
+                return false;
+            };
+            v.push(block_id);
         }
         disk_inode.increase_size(new_size, v, &self.block_device);
+        // new blocks come zeroed, but the old last block may hold stale bytes past `old_size`
+        let tail_len = (BLOCK_SIZE - old_size as usize % BLOCK_SIZE) % BLOCK_SIZE;
+        let tail_len = tail_len.min((new_size - old_size) as usize);
+        if tail_len > 0 {
+            disk_inode.write_at(old_size as usize, &vec![0u8; tail_len], &self.block_device);
+        }
+        true
     }
 
     // Decrease the size of a disk inode
@@ -81,8 +148,77 @@ impl Inode {
             .for_each(|block_id| fs.dealloc_data(block_id));
     }
 
+    /// Append a `DirEntry` to a directory disk inode
+    ///
+    /// Fails, leaving the directory untouched, if it already holds [`DIRENT_LIMIT`] entries
+    /// or the data area is full.
+    fn append_dirent(
+        &self,
+        name: &str,
+        inode_id: u32,
+        file_type: DirEntryType,
+        dir_inode: &mut DiskInode,
+        fs: &mut MutexGuard<EasyFileSystem>,
+    ) -> Result<(), CreateError> {
+        let file_count = (dir_inode.size as usize) / DIRENT_SIZE;
+        if file_count >= DIRENT_LIMIT {
+            return Err(CreateError::DirectoryFull);
+        }
+        let new_size = (file_count + 1) * DIRENT_SIZE;
+        if !self.increase_size(new_size as u32, dir_inode, fs) {
+            return Err(CreateError::NoSpace);
+        }
+        let dirent = DirEntry::new(name, inode_id, file_type);
+        dir_inode.write_at(
+            file_count * DIRENT_SIZE,
+            dirent.as_bytes(),
+            &self.block_device,
+        );
+        Ok(())
+    }
+
+    /// Remove a `DirEntry` from a directory disk inode by name, returning it
+    ///
+    /// The last entry is moved into the freed slot, so the directory stays dense. The
+    /// vacated last slot is zeroed, so growing the directory never brings it back.
+    fn remove_dirent(
+        &self,
+        name: &str,
+        dir_inode: &mut DiskInode,
+        fs: &mut MutexGuard<EasyFileSystem>,
+    ) -> Option<DirEntry> {
+        assert!(dir_inode.is_dir());
+        let file_count = (dir_inode.size as usize) / DIRENT_SIZE;
+        let mut dirent = DirEntry::empty();
+        let i = (0..file_count).find(|&i| {
+            dir_inode.read_at(i * DIRENT_SIZE, dirent.as_mut_bytes(), &self.block_device);
+            dirent.name() == name
+        })?;
+
+        let last = file_count - 1;
+        if i != last {
+            let mut last_dirent = DirEntry::empty();
+            dir_inode.read_at(
+                last * DIRENT_SIZE,
+                last_dirent.as_mut_bytes(),
+                &self.block_device,
+            );
+            dir_inode.write_at(i * DIRENT_SIZE, last_dirent.as_bytes(), &self.block_device);
+        }
+        let empty = DirEntry::empty();
+        dir_inode.write_at(last * DIRENT_SIZE, empty.as_bytes(), &self.block_device);
+        self.decrease_size((last * DIRENT_SIZE) as u32, dir_inode, fs);
+        Some(dirent)
+    }
+
     /// Find inode under a disk inode by name
     fn find_inode_id(&self, name: &str, disk_inode: &DiskInode) -> Option<u32> {
+        self.find_dirent(name, disk_inode)
+            .map(|(_, dirent)| dirent.inode_number())
+    }
+
+    /// Find the entry of a disk inode by name, along with its index
+    fn find_dirent(&self, name: &str, disk_inode: &DiskInode) -> Option<(usize, DirEntry)> {
         // assert it is a directory
         assert!(disk_inode.is_dir());
         let file_count = (disk_inode.size as usize) / DIRENT_SIZE;
@@ -93,7 +229,7 @@ impl Inode {
                 DIRENT_SIZE,
             );
             if dirent.name() == name {
-                return Some(dirent.inode_number());
+                return Some((i, dirent));
             }
         }
         None
@@ -101,22 +237,83 @@ impl Inode {
 
     /// Find inode under current inode by name
     pub fn find(&self, name: &str) -> Option<Arc<Inode>> {
+        let _dir = self.lock.read();
+        let mut fs = self.fs.lock();
+        let inode_id = self.read_disk_inode(|disk_inode| self.find_inode_id(name, disk_inode))?;
+        Some(self.open(inode_id, &mut fs))
+    }
+
+    /// List the entries of the current directory with the kind of each
+    pub fn list(&self) -> Vec<(String, DirEntryType)> {
+        self.entries()
+            .iter()
+            .map(|dirent| (String::from(dirent.name()), dirent.file_type()))
+            .collect()
+    }
+
+    /// Snapshot the entries of the current directory in the order they are stored
+    ///
+    /// The kind comes from the entries themselves. Only entries without one, left
+    /// behind on an image created before entries were typed, cost a read of the
+    /// child inode.
+    pub fn entries(&self) -> Vec<DirEntry> {
+        let _dir = self.lock.read();
         let fs = self.fs.lock();
-        self.read_disk_inode(|disk_inode| {
-            self.find_inode_id(name, disk_inode).map(|inode_id| {
-                let (block_id, block_offset) = fs.disk_inode_position(inode_id);
-                Arc::new(Self::new(
-                    block_id,
-                    block_offset,
-                    self.fs.clone(),
-                    self.block_device.clone(),
-                ))
+        let mut dirents = self.read_disk_inode(|dir_inode| {
+            assert!(dir_inode.is_dir());
+            let file_count = (dir_inode.size as usize) / DIRENT_SIZE;
+            (0..file_count)
+                .map(|i| {
+                    let mut dirent = DirEntry::empty();
+                    dir_inode.read_at(i * DIRENT_SIZE, dirent.as_mut_bytes(), &self.block_device);
+                    dirent
+                })
+                .collect::<Vec<_>>()
+        });
+        if !fs.has_dirent_types() {
+            for dirent in &mut dirents {
+                if dirent.file_type() == DirEntryType::Unknown {
+                    let (block_id, block_offset) = fs.disk_inode_position(dirent.inode_number());
+                    let file_type = block_cache::get(block_id as usize, &self.block_device)
+                        .lock()
+                        .read(block_offset, DiskInode::dirent_type);
+                    *dirent = DirEntry::new(dirent.name(), dirent.inode_number(), file_type);
+                }
+            }
+        }
+        dirents
+    }
+
+    /// Whether the current directory holds nothing but `.` and `..`
+    ///
+    /// The entries are scanned rather than the size trusted, so zeroed slots, which
+    /// [`Inode::set_len`] leaves until [`Inode::compact_dir`] drops them, do not count.
+    /// Returns `false` if the inode is not a directory.
+    pub fn is_empty_dir(&self) -> bool {
+        let _dir = self.lock.read();
+        self.read_disk_inode(|dir_inode| {
+            if !dir_inode.is_dir() {
+                return false;
+            }
+            let mut dirent = DirEntry::empty();
+            (0..dir_inode.size as usize / DIRENT_SIZE).all(|i| {
+                dir_inode.read_at(i * DIRENT_SIZE, dirent.as_mut_bytes(), &self.block_device);
+                matches!(dirent.name(), "" | "." | "..")
             })
         })
     }
 
     /// Create inode under current inode by name
-    pub fn create_inode(&self, name: &str, kind: DiskInodeKind) -> Option<Arc<Inode>> {
+    ///
+    /// A create that fails leaves neither an entry nor an allocated inode behind.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CreateError`] if `name` fails [`validate_name`] or already exists, if the
+    /// directory is full, or if no inode or data block is left.
+    pub fn create_inode(&self, name: &str, kind: DiskInodeKind) -> Result<Arc<Inode>, CreateError> {
+        validate_name(name).map_err(CreateError::InvalidName)?;
+        let _dir = self.lock.write();
         let mut fs = self.fs.lock();
 
         let op = |dir_inode: &DiskInode| {
@@ -126,62 +323,74 @@ impl Inode {
             self.find_inode_id(name, dir_inode)
         };
         if self.read_disk_inode(op).is_some() {
-            return None;
+            return Err(CreateError::Exists);
         }
 
         // create a new file
         // alloc a inode with an indirect block
-        let new_inode_id = fs.alloc_inode();
+        let new_inode_id = fs.alloc_inode().ok_or(CreateError::NoSpace)?;
         // initialize inode
         let (new_inode_block_id, new_inode_block_offset) = fs.disk_inode_position(new_inode_id);
+        let file_type = DirEntryType::from(&kind);
         block_cache::get(new_inode_block_id as usize, &self.block_device)
             .lock()
             .modify(new_inode_block_offset, |new_inode: &mut DiskInode| {
                 new_inode.init(kind);
             });
 
-        self.modify_disk_inode(|dir_inode| {
-            // append file in the dirent
-            let file_count = (dir_inode.size as usize) / DIRENT_SIZE;
-            let new_size = (file_count + 1) * DIRENT_SIZE;
-            // increase size
-            self.increase_size(new_size as u32, dir_inode, &mut fs);
-            // write dirent
-            let dirent = DirEntry::new(name, new_inode_id);
-            dir_inode.write_at(
-                file_count * DIRENT_SIZE,
-                dirent.as_bytes(),
-                &self.block_device,
-            );
-        });
-
-        let (block_id, block_offset) = fs.disk_inode_position(new_inode_id);
-        block_cache::sync_all();
+        if let Err(err) = self.modify_disk_inode(|dir_inode| {
+            self.append_dirent(name, new_inode_id, file_type, dir_inode, &mut fs)
+        }) {
+            fs.dealloc_inode(new_inode_id);
+            return Err(err);
+        }
 
         // return inode
-        Some(Arc::new(Self::new(
-            block_id,
-            block_offset,
-            self.fs.clone(),
-            self.block_device.clone(),
-        )))
+        Ok(self.open(new_inode_id, &mut fs))
         // release efs lock automatically by compiler
     }
 
     /// Create regular file under current inode
+    ///
+    /// Failures are not told apart, see [`Inode::try_create`].
     pub fn create(&self, name: &str) -> Option<Arc<Inode>> {
+        self.try_create(name).ok()
+    }
+
+    /// Create regular file under current inode, reporting why it could not be
+    ///
+    /// # Errors
+    ///
+    /// As [`Inode::create_inode`].
+    pub fn try_create(&self, name: &str) -> Result<Arc<Inode>, CreateError> {
         self.create_inode(name, DiskInodeKind::File)
     }
 
     /// Create directory under current inode
+    ///
+    /// Failures are not told apart, see [`Inode::try_create_dir`].
     pub fn create_dir(&self, name: &str) -> Option<Arc<Inode>> {
+        self.try_create_dir(name).ok()
+    }
+
+    /// Create directory under current inode, reporting why it could not be
+    ///
+    /// # Errors
+    ///
+    /// As [`Inode::create_inode`]. A directory without room for its `.` and `..` entries
+    /// is removed again before [`CreateError::NoSpace`] is returned.
+    pub fn try_create_dir(&self, name: &str) -> Result<Arc<Inode>, CreateError> {
         let inode = self.create_inode(name, DiskInodeKind::Directory)?;
-        inode.set_default_dirent(self.inode_id());
-        Some(inode)
+        if !inode.set_default_dirent(self.inode_id()) {
+            self.delete(name);
+            return Err(CreateError::NoSpace);
+        }
+        Ok(inode)
     }
 
     /// Clear the data in current inode
     pub fn clear(&self) {
+        let _inode = self.lock.write();
         let mut fs = self.fs.lock();
         self.modify_disk_inode(|disk_inode| {
             let size = disk_inode.size;
@@ -194,73 +403,567 @@ impl Inode {
                 fs.dealloc_data(data_block);
             }
         });
-        block_cache::sync_all();
+    }
+
+    /// Set the size of current inode to `new_size`
+    ///
+    /// Shrinking frees the blocks past the new end. Growing always reads back
+    /// as zeros: the rest of the old last block is zeroed and the blocks added
+    /// are zeroed when allocated, so no stale data is ever exposed. Writing
+    /// past the end of the file behaves the same for the gap it leaves.
+    ///
+    /// Returns `false`, leaving the size unchanged, if the data area cannot hold `new_size`.
+    pub fn set_len(&self, new_size: u32) -> bool {
+        let _inode = self.lock.write();
+        let mut fs = self.fs.lock();
+        self.modify_disk_inode(|disk_inode| {
+            if new_size < disk_inode.size {
+                self.decrease_size(new_size, disk_inode, &mut fs);
+                true
+            } else {
+                self.increase_size(new_size, disk_inode, &mut fs)
+            }
+        })
     }
 
     /// Read data from current inode
+    ///
+    /// Block device errors are not reported, see [`Inode::try_read_at`].
     pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
-        let _fs = self.fs.lock();
-        self.read_disk_inode(|disk_inode| disk_inode.read_at(offset, buf, &self.block_device))
+        self.try_read_at(offset, buf).unwrap_or(0)
+    }
+
+    /// Read data from current inode, failing if the block device reports an error
+    ///
+    /// A read at or past the end of the file returns `0` without reading any data block.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`BlockError`] of a block that could not be read after retrying.
+    pub fn try_read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize, BlockError> {
+        let _inode = self.lock.read();
+        block_cache::take_error();
+        // a copy leaves the block free for the inodes sharing it while the data is read
+        let disk_inode = self.read_disk_inode(DiskInode::clone);
+        let size = disk_inode.read_at(offset, buf, &self.block_device);
+        block_cache::take_error().map_or(Ok(size), Err)
     }
 
     /// Write data to current inode
+    ///
+    /// Block device errors are not reported, see [`Inode::try_write_at`].
     pub fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
-        let mut fs = self.fs.lock();
-        let size = self.modify_disk_inode(|disk_inode| {
-            assert!(disk_inode.is_file());
-            self.increase_size((offset + buf.len()) as u32, disk_inode, &mut fs);
+        self.try_write_at(offset, buf).unwrap_or(0)
+    }
+
+    /// Write data to current inode, failing if the block device reports an error
+    ///
+    /// If the data area is too full to grow the file, only the bytes before the
+    /// current end are written, so a short count signals the file system is full.
+    /// A write of nothing leaves the file alone, even past its end, where any other
+    /// write grows the file with a zeroed gap.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`BlockError`] of a block that could not be read or written back after retrying.
+    pub fn try_write_at(&self, offset: usize, buf: &[u8]) -> Result<usize, BlockError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.write_locked(Some(offset), buf).map(|(_, size)| size)
+    }
+
+    /// Append data to the end of the file, returning where it went and how much was written
+    ///
+    /// The end is read under the same inode lock the data is written under, so appends
+    /// racing each other, or a write growing the file, never land on the same bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`BlockError`] of a block that could not be read or written back after retrying.
+    pub fn try_append(&self, buf: &[u8]) -> Result<(usize, usize), BlockError> {
+        self.write_locked(None, buf)
+    }
+
+    /// Write `buf` at `offset`, or at the end of the file if `None`, holding the inode lock
+    /// from choosing the offset until the data is written back
+    fn write_locked(
+        &self,
+        offset: Option<usize>,
+        buf: &[u8],
+    ) -> Result<(usize, usize), BlockError> {
+        let _inode = self.lock.write();
+        block_cache::take_error();
+        let mut disk_inode = self.read_disk_inode(DiskInode::clone);
+        assert!(disk_inode.is_file());
+        let offset = offset.unwrap_or(disk_inode.size as usize);
+        if buf.is_empty() {
+            return Ok((offset, 0));
+        }
+        let end = offset + buf.len();
+        // only growing allocates, so writes within the file leave the `fs` lock alone
+        if end > disk_inode.size as usize {
+            let mut fs = self.fs.lock();
+            disk_inode = self.modify_disk_inode(|disk_inode| {
+                self.increase_size(end as u32, disk_inode, &mut fs);
+                disk_inode.clone()
+            });
+        }
+        // the data goes through the copy, leaving the block free for the inodes sharing it
+        // without room to grow, only the part before the current end is written
+        let size = if offset >= disk_inode.size as usize {
+            0
+        } else {
             disk_inode.write_at(offset, buf, &self.block_device)
-        });
-        block_cache::sync_all();
-        size
+        };
+        block_cache::take_error().map_or(Ok((offset, size)), Err)
     }
 
-    /// Delete inode by name
+    /// Delete inode by name, along with its extended attributes
     pub fn delete(&self, name: &str) {
+        let _dir = self.lock.write();
         let mut fs = self.fs.lock();
-        self.modify_disk_inode(|dir_inode| {
-            assert!(dir_inode.is_dir());
-            self.find_inode_id(name, dir_inode);
+        let dirent =
+            self.modify_disk_inode(|dir_inode| self.remove_dirent(name, dir_inode, &mut fs));
+        // the index may share a block with the directory, which is no longer borrowed here
+        if let Some(dirent) = dirent {
+            fs.dealloc_inode(dirent.inode_number());
+            self.free_xattrs(dirent.inode_number(), &mut fs);
+        }
+    }
 
-            let mut last_dirent = DirEntry::empty();
-            dir_inode.read_at(
-                dir_inode.size as usize - DIRENT_SIZE,
-                last_dirent.as_mut_bytes(),
+    /// The inode of the `..` entry of the directory `inode_id`
+    ///
+    /// `..` always takes the second slot, so a single entry is read rather than the whole
+    /// directory searched. Returns `None` if the inode is not a directory or has no `..`.
+    fn parent_id(&self, inode_id: u32, fs: &EasyFileSystem) -> Option<u32> {
+        let (block_id, block_offset) = fs.disk_inode_position(inode_id);
+        block_cache::get(block_id as usize, &self.block_device)
+            .lock()
+            .read(block_offset, |disk_inode: &DiskInode| {
+                if !disk_inode.is_dir() || (disk_inode.size as usize) < 2 * DIRENT_SIZE {
+                    return None;
+                }
+                let mut dirent = DirEntry::empty();
+                disk_inode.read_at(DIRENT_SIZE, dirent.as_mut_bytes(), &self.block_device);
+                (dirent.name() == "..").then(|| dirent.inode_number())
+            })
+    }
+
+    /// Whether `ancestor_id` is the directory `inode_id` or one above it
+    ///
+    /// Walks up the `..` entries to the root, which is its own parent, taking one step per
+    /// level. The walk gives up after visiting as many directories as there are inodes,
+    /// so a cycle on a damaged image cannot hang it.
+    fn is_ancestor(&self, ancestor_id: u32, inode_id: u32, fs: &EasyFileSystem) -> bool {
+        let mut inode_id = inode_id;
+        for _ in 0..=fs.inode_bitmap.maximum() {
+            if inode_id == ancestor_id {
+                return true;
+            }
+            match self.parent_id(inode_id, fs) {
+                Some(parent_id) if parent_id != inode_id => inode_id = parent_id,
+                _ => return false,
+            }
+        }
+        false
+    }
+
+    /// Whether the current directory is `dir` or contains it, at any depth
+    ///
+    /// A directory moved under a directory it contains would be cut off from the root
+    /// in a cycle, see [`Inode::rename`].
+    pub fn is_ancestor_of(&self, dir: &Inode) -> bool {
+        let fs = self.fs.lock();
+        let ancestor_id = fs.disk_inode_id(self.block_id as u32, self.block_offset);
+        let inode_id = fs.disk_inode_id(dir.block_id as u32, dir.block_offset);
+        self.is_ancestor(ancestor_id, inode_id, &fs)
+    }
+
+    /// Move the entry `old_name` of the current directory to `new_name` in `new_parent`
+    ///
+    /// The inode itself is untouched, a moved directory gets its `..` entry pointed at
+    /// `new_parent`. Returns `false` if `old_name` does not exist, `new_name` already does or
+    /// fails [`validate_name`], if a directory would be moved into itself or a directory
+    /// below it, or if `new_parent` has no room for another entry.
+    pub fn rename(&self, old_name: &str, new_parent: &Inode, new_name: &str) -> bool {
+        if validate_name(new_name).is_err() {
+            return false;
+        }
+        let (first, second) = if self.inode_id() <= new_parent.inode_id() {
+            (self, new_parent)
+        } else {
+            (new_parent, self)
+        };
+        let _first = first.lock.write();
+        let _second = (!Arc::ptr_eq(&first.lock, &second.lock)).then(|| second.lock.write());
+        let mut fs = self.fs.lock();
+        if new_parent
+            .read_disk_inode(|dir_inode| new_parent.find_inode_id(new_name, dir_inode))
+            .is_some()
+        {
+            return false;
+        }
+        // only a move to another directory can put one below itself
+        let new_parent_id = fs.disk_inode_id(new_parent.block_id as u32, new_parent.block_offset);
+        if !Arc::ptr_eq(&self.lock, &new_parent.lock) {
+            let moved = self.read_disk_inode(|dir_inode| self.find_inode_id(old_name, dir_inode));
+            if moved.is_some_and(|moved| self.is_ancestor(moved, new_parent_id, &fs)) {
+                return false;
+            }
+        }
+        let Some(dirent) =
+            self.modify_disk_inode(|dir_inode| self.remove_dirent(old_name, dir_inode, &mut fs))
+        else {
+            return false;
+        };
+        let inode_id = dirent.inode_number();
+        if new_parent
+            .modify_disk_inode(|dir_inode| {
+                new_parent.append_dirent(new_name, inode_id, dirent.file_type(), dir_inode, &mut fs)
+            })
+            .is_err()
+        {
+            // the removal just made room for the entry where it came from
+            let restored = self.modify_disk_inode(|dir_inode| {
+                self.append_dirent(old_name, inode_id, dirent.file_type(), dir_inode, &mut fs)
+            });
+            debug_assert!(restored.is_ok());
+            return false;
+        }
+
+        self.set_parent_id(inode_id, new_parent_id, &fs);
+        true
+    }
+
+    /// Swap the entry `name` of the current directory with `other_name` in `other_parent`
+    ///
+    /// Both entries stay where they are, each now naming the inode the other did, so
+    /// neither name is ever missing. A directory that changes parent gets its `..` entry
+    /// pointed at the new one. Returns `false` if either entry does not exist or is `.` or
+    /// `..`, or if a directory would end up in itself or a directory below it.
+    pub fn exchange(&self, name: &str, other_parent: &Inode, other_name: &str) -> bool {
+        if [name, other_name]
+            .iter()
+            .any(|&name| name == "." || name == "..")
+        {
+            return false;
+        }
+        let (first, second) = if self.inode_id() <= other_parent.inode_id() {
+            (self, other_parent)
+        } else {
+            (other_parent, self)
+        };
+        let _first = first.lock.write();
+        let _second = (!Arc::ptr_eq(&first.lock, &second.lock)).then(|| second.lock.write());
+        let fs = self.fs.lock();
+        let Some((index, dirent)) =
+            self.read_disk_inode(|dir_inode| self.find_dirent(name, dir_inode))
+        else {
+            return false;
+        };
+        let Some((other_index, other_dirent)) = other_parent
+            .read_disk_inode(|dir_inode| other_parent.find_dirent(other_name, dir_inode))
+        else {
+            return false;
+        };
+        let same_dir = Arc::ptr_eq(&self.lock, &other_parent.lock);
+        if same_dir && index == other_index {
+            return true;
+        }
+        // either side may be a directory moving below the other
+        let parent_id = fs.disk_inode_id(self.block_id as u32, self.block_offset);
+        let other_parent_id =
+            fs.disk_inode_id(other_parent.block_id as u32, other_parent.block_offset);
+        if !same_dir
+            && (self.is_ancestor(dirent.inode_number(), other_parent_id, &fs)
+                || self.is_ancestor(other_dirent.inode_number(), parent_id, &fs))
+        {
+            return false;
+        }
+
+        let swapped = DirEntry::new(name, other_dirent.inode_number(), other_dirent.file_type());
+        self.modify_disk_inode(|dir_inode| {
+            dir_inode.write_at(index * DIRENT_SIZE, swapped.as_bytes(), &self.block_device);
+        });
+        let swapped = DirEntry::new(other_name, dirent.inode_number(), dirent.file_type());
+        other_parent.modify_disk_inode(|dir_inode| {
+            dir_inode.write_at(
+                other_index * DIRENT_SIZE,
+                swapped.as_bytes(),
                 &self.block_device,
             );
+        });
+        if !same_dir {
+            self.set_parent_id(dirent.inode_number(), other_parent_id, &fs);
+            self.set_parent_id(other_dirent.inode_number(), parent_id, &fs);
+        }
+        true
+    }
 
+    /// Point the `..` entry of `inode_id` at `parent_id`, if it is a directory
+    fn set_parent_id(&self, inode_id: u32, parent_id: u32, fs: &EasyFileSystem) {
+        let (block_id, block_offset) = fs.disk_inode_position(inode_id);
+        block_cache::get(block_id as usize, &self.block_device)
+            .lock()
+            .modify(block_offset, |disk_inode: &mut DiskInode| {
+                if disk_inode.is_dir() && disk_inode.size as usize >= 2 * DIRENT_SIZE {
+                    let dirent_parent = DirEntry::new("..", parent_id, DirEntryType::Directory);
+                    disk_inode.write_at(DIRENT_SIZE, dirent_parent.as_bytes(), &self.block_device);
+                }
+            });
+    }
+
+    /// Remove zeroed entries from the current directory and shrink it
+    ///
+    /// The remaining entries keep their order. Returns the number of entries removed.
+    pub fn compact_dir(&self) -> usize {
+        let _dir = self.lock.write();
+        let mut fs = self.fs.lock();
+        self.modify_disk_inode(|dir_inode| {
+            assert!(dir_inode.is_dir());
             let file_count = (dir_inode.size as usize) / DIRENT_SIZE;
+            let mut dirent = DirEntry::empty();
+            let mut kept = 0;
             for i in 0..file_count {
-                let mut dirent = DirEntry::empty();
                 dir_inode.read_at(i * DIRENT_SIZE, dirent.as_mut_bytes(), &self.block_device);
-                if dirent.name() == name {
-                    // delete
-                    fs.dealloc_inode(dirent.inode_number());
-                    dir_inode.write_at(i * DIRENT_SIZE, last_dirent.as_bytes(), &self.block_device);
-                    let new_size = (file_count - 1) * DIRENT_SIZE;
-                    self.decrease_size(new_size as u32, dir_inode, &mut fs);
+                if dirent.name().is_empty() {
+                    continue;
+                }
+                if kept != i {
+                    dir_inode.write_at(kept * DIRENT_SIZE, dirent.as_bytes(), &self.block_device);
                 }
+                kept += 1;
             }
-        });
+            let empty = DirEntry::empty();
+            for i in kept..file_count {
+                dir_inode.write_at(i * DIRENT_SIZE, empty.as_bytes(), &self.block_device);
+            }
+            self.decrease_size((kept * DIRENT_SIZE) as u32, dir_inode, &mut fs);
+            file_count - kept
+        })
     }
 
     /// Set the default `DirEntry` for the current file
-    pub fn set_default_dirent(&self, parent_inode_id: u32) {
+    ///
+    /// Returns `false` if the data area is full.
+    pub fn set_default_dirent(&self, parent_inode_id: u32) -> bool {
+        let _dir = self.lock.write();
         let mut fs = self.fs.lock();
         self.modify_disk_inode(|cur_dir_inode| {
             // increase size
-            self.increase_size(2 * DIRENT_SIZE as u32, cur_dir_inode, &mut fs);
+            if !self.increase_size(2 * DIRENT_SIZE as u32, cur_dir_inode, &mut fs) {
+                return false;
+            }
             // write . dirent
             let dirent_self = DirEntry::new(
                 ".",
                 fs.disk_inode_id(self.block_id as u32, self.block_offset),
+                DirEntryType::Directory,
             );
             cur_dir_inode.write_at(0, dirent_self.as_bytes(), &self.block_device);
 
             // write .. dirent
-            let dirent_parent = DirEntry::new("..", parent_inode_id);
+            let dirent_parent = DirEntry::new("..", parent_inode_id, DirEntryType::Directory);
             cur_dir_inode.write_at(DIRENT_SIZE, dirent_parent.as_bytes(), &self.block_device);
+            true
+        })
+    }
+
+    /// The index of extended attributes as an inode, with the `fs` lock held
+    fn xattr_index(&self, fs: &mut MutexGuard<EasyFileSystem>) -> Result<Arc<Inode>, XattrError> {
+        let index_id = fs.xattr_index().ok_or(XattrError::Unsupported)?;
+        Ok(self.open(index_id, fs))
+    }
+
+    /// Block holding the attributes of `inode_id` according to `index`, zero if it has none
+    fn xattr_block(&self, index: &Inode, inode_id: u32) -> u32 {
+        let mut entry = [0u8; 4];
+        index.read_disk_inode(|disk_inode| {
+            disk_inode.read_at(inode_id as usize * 4, &mut entry, &self.block_device);
         });
+        u32::from_le_bytes(entry)
+    }
+
+    /// Record `block_id` as the attribute block of `inode_id`, returning `false` if the
+    /// index cannot grow to hold it
+    fn set_xattr_block(
+        &self,
+        index: &Inode,
+        inode_id: u32,
+        block_id: u32,
+        fs: &mut MutexGuard<EasyFileSystem>,
+    ) -> bool {
+        let offset = inode_id as usize * 4;
+        index.modify_disk_inode(|disk_inode| {
+            if !index.increase_size((offset + 4) as u32, disk_inode, fs) {
+                return false;
+            }
+            disk_inode.write_at(offset, &block_id.to_le_bytes(), &self.block_device);
+            true
+        })
+    }
+
+    /// The attributes of the current inode, with its lock and the `fs` lock held
+    fn read_xattrs(
+        &self,
+        fs: &mut MutexGuard<EasyFileSystem>,
+    ) -> Result<Vec<(String, Vec<u8>)>, XattrError> {
+        let index = self.xattr_index(fs)?;
+        let inode_id = fs.disk_inode_id(self.block_id as u32, self.block_offset);
+        let block_id = self.xattr_block(&index, inode_id);
+        if block_id == 0 {
+            return Ok(Vec::new());
+        }
+        Ok(block_cache::get(block_id as usize, &self.block_device)
+            .lock()
+            .read(0, xattr::decode))
+    }
+
+    /// Free the attribute block of the deleted `inode_id`, with the `fs` lock held
+    fn free_xattrs(&self, inode_id: u32, fs: &mut MutexGuard<EasyFileSystem>) {
+        let Ok(index) = self.xattr_index(fs) else {
+            return;
+        };
+        let block_id = self.xattr_block(&index, inode_id);
+        if block_id != 0 {
+            self.set_xattr_block(&index, inode_id, 0, fs);
+            fs.dealloc_data(block_id);
+        }
+    }
+
+    /// Set the extended attribute `name` of the current inode to `value`, replacing any
+    /// value it had
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`XattrError`] without changing anything if the image has no attributes,
+    /// the name or value is out of bounds, or the attributes would outgrow their block.
+    pub fn set_xattr(&self, name: &str, value: &[u8]) -> Result<(), XattrError> {
+        xattr::validate(name, value)?;
+        let _inode = self.lock.write();
+        let mut fs = self.fs.lock();
+        let index = self.xattr_index(&mut fs)?;
+        let inode_id = fs.disk_inode_id(self.block_id as u32, self.block_offset);
+        let mut block_id = self.xattr_block(&index, inode_id);
+        let mut attrs = if block_id == 0 {
+            Vec::new()
+        } else {
+            block_cache::get(block_id as usize, &self.block_device)
+                .lock()
+                .read(0, xattr::decode)
+        };
+        match attrs.iter_mut().find(|(attr, _)| attr == name) {
+            Some((_, old)) => *old = value.to_vec(),
+            None => attrs.push((String::from(name), value.to_vec())),
+        }
+        let data = xattr::encode(&attrs).ok_or(XattrError::NoSpace)?;
+
+        if block_id == 0 {
+            block_id = fs.try_alloc_data().ok_or(XattrError::NoSpace)?;
+            if !self.set_xattr_block(&index, inode_id, block_id, &mut fs) {
+                fs.dealloc_data(block_id);
+                return Err(XattrError::NoSpace);
+            }
+        }
+        block_cache::get(block_id as usize, &self.block_device)
+            .lock()
+            .modify(0, |block: &mut DataBlock| *block = data);
+        Ok(())
+    }
+
+    /// Get the value of the extended attribute `name` of the current inode
+    ///
+    /// # Errors
+    ///
+    /// Returns [`XattrError::NotFound`] if the inode has no such attribute, or
+    /// [`XattrError::Unsupported`] if the image has no attributes.
+    pub fn get_xattr(&self, name: &str) -> Result<Vec<u8>, XattrError> {
+        let _inode = self.lock.read();
+        let mut fs = self.fs.lock();
+        self.read_xattrs(&mut fs)?
+            .into_iter()
+            .find(|(attr, _)| attr == name)
+            .map(|(_, value)| value)
+            .ok_or(XattrError::NotFound)
+    }
+
+    /// List the names of the extended attributes of the current inode, in the order they
+    /// were first set
+    ///
+    /// # Errors
+    ///
+    /// Returns [`XattrError::Unsupported`] if the image has no attributes.
+    pub fn list_xattrs(&self) -> Result<Vec<String>, XattrError> {
+        let _inode = self.lock.read();
+        let mut fs = self.fs.lock();
+        Ok(self
+            .read_xattrs(&mut fs)?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect())
+    }
+
+    /// Get the file system the inode lives on
+    #[inline]
+    pub fn fs(&self) -> Arc<Mutex<EasyFileSystem>> {
+        Arc::clone(&self.fs)
+    }
+
+    /// Whether each of the `count` data blocks from block `first` on is in the block cache
+    ///
+    /// Blocks past the end of the file are never cached. Nothing is brought into the
+    /// cache to find out, the inode and index blocks are read past it when they are
+    /// not cached, so asking never evicts a block it reports on. The answer is a
+    /// snapshot that may be stale as soon as it is returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`BlockError`] of an inode or index block that could not be read.
+    pub fn cached_blocks(&self, first: u32, count: u32) -> Result<Vec<bool>, BlockError> {
+        let _inode = self.lock.read();
+        let disk_inode = block_cache::peek(
+            self.block_id,
+            &self.block_device,
+            self.block_offset,
+            DiskInode::clone,
+        )?;
+        let end = disk_inode
+            .size
+            .div_ceil(BLOCK_SIZE as u32)
+            .min(first.saturating_add(count));
+        let block_ids = (first..end)
+            .map(|index| {
+                disk_inode
+                    .peek_block_id(index, &self.block_device)
+                    .map(|id| id as usize)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut cached = block_cache::cached(&block_ids);
+        cached.resize(count as usize, false);
+        Ok(cached)
+    }
+
+    /// Write every modified cached block back to the device, as [`EasyFileSystem::sync`]
+    ///
+    /// Only [`CacheMode::WriteBack`] leaves blocks modified once an operation returns.
+    /// Neither lock is taken, so syncing never waits for another operation to finish.
+    ///
+    /// [`CacheMode::WriteBack`]: crate::CacheMode::WriteBack
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`BlockError`] of a block that could not be written back after retrying,
+    /// the remaining blocks are still written.
+    pub fn sync(&self) -> Result<(), BlockError> {
+        block_cache::take_error();
+        block_cache::sync_all();
+        block_cache::take_error().map_or(Ok(()), Err)
+    }
+
+    /// Whether the inode or its file system is locked, so that I/O on it may have to wait
+    pub fn is_locked(&self) -> bool {
+        self.lock.try_write().is_none() || self.fs.is_locked()
     }
 
     /// Get `inode_id`
@@ -0,0 +1,105 @@
+//! Extended attributes, small named values attached to an inode
+//!
+//! On images with [`FEATURE_XATTR`](crate::config::FEATURE_XATTR) the superblock names an
+//! index inode, linked from no directory. Its data holds a little-endian `u32` per inode
+//! id, the data block holding the attributes of that inode, or zero if it has none. The
+//! index only grows, as far as the highest inode given attributes, and is guarded by the
+//! `fs` lock rather than an inode lock.
+//!
+//! An attribute block holds the attributes back to back, each as a byte with the length of
+//! the name, a byte with the length of the value, then the name and the value. A zero name
+//! length ends the list, so all the attributes of an inode together take at most
+//! [`BLOCK_SIZE`] bytes. The block is freed when the inode is deleted.
+
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+use crate::{
+    config::{BLOCK_SIZE, XATTR_NAME_MAX, XATTR_VALUE_MAX},
+    layout::DataBlock,
+};
+
+/// Bytes taken by each attribute besides its name and value
+const RECORD_HEADER: usize = 2;
+
+/// Reasons an extended attribute cannot be read or written
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XattrError {
+    /// The image was created without extended attribute support
+    Unsupported,
+    /// The name is empty or longer than [`XATTR_NAME_MAX`]
+    InvalidName,
+    /// The value is longer than [`XATTR_VALUE_MAX`]
+    ValueTooLong,
+    /// The attributes of the inode would not fit in one block, or the data area is full
+    NoSpace,
+    /// The inode has no attribute of that name
+    NotFound,
+}
+
+impl fmt::Display for XattrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unsupported => write!(f, "the file system has no extended attributes"),
+            Self::InvalidName => write!(f, "attribute names take 1 to {XATTR_NAME_MAX} bytes"),
+            Self::ValueTooLong => {
+                write!(f, "attribute values take at most {XATTR_VALUE_MAX} bytes")
+            }
+            Self::NoSpace => write!(f, "no room left for the attribute"),
+            Self::NotFound => write!(f, "no such attribute"),
+        }
+    }
+}
+
+/// Check an attribute before it is stored
+pub(crate) fn validate(name: &str, value: &[u8]) -> Result<(), XattrError> {
+    if name.is_empty() || name.len() > XATTR_NAME_MAX {
+        return Err(XattrError::InvalidName);
+    }
+    if value.len() > XATTR_VALUE_MAX {
+        return Err(XattrError::ValueTooLong);
+    }
+    Ok(())
+}
+
+/// The attributes stored in `block`, in the order they were added
+///
+/// A record running past the end of the block ends the list, so a damaged block never
+/// reads out of bounds.
+pub(crate) fn decode(block: &DataBlock) -> Vec<(String, Vec<u8>)> {
+    let mut attrs = Vec::new();
+    let mut pos = 0;
+    while pos + RECORD_HEADER <= BLOCK_SIZE {
+        let name_len = block[pos] as usize;
+        let value_len = block[pos + 1] as usize;
+        let end = pos + RECORD_HEADER + name_len + value_len;
+        if name_len == 0 || end > BLOCK_SIZE {
+            break;
+        }
+        let name = &block[pos + RECORD_HEADER..pos + RECORD_HEADER + name_len];
+        let value = &block[pos + RECORD_HEADER + name_len..end];
+        attrs.push((String::from_utf8_lossy(name).into_owned(), value.to_vec()));
+        pos = end;
+    }
+    attrs
+}
+
+/// Lay `attrs` out in a block, `None` if they do not fit
+pub(crate) fn encode(attrs: &[(String, Vec<u8>)]) -> Option<DataBlock> {
+    let mut block = [0u8; BLOCK_SIZE];
+    let mut pos = 0;
+    for (name, value) in attrs {
+        let end = pos + RECORD_HEADER + name.len() + value.len();
+        if end > BLOCK_SIZE {
+            return None;
+        }
+        // both lengths were checked against limits below 256
+        block[pos] = name.len() as u8;
+        block[pos + 1] = value.len() as u8;
+        block[pos + RECORD_HEADER..pos + RECORD_HEADER + name.len()]
+            .copy_from_slice(name.as_bytes());
+        block[pos + RECORD_HEADER + name.len()..end].copy_from_slice(value);
+        pos = end;
+    }
+    Some(block)
+}
@@ -1,13 +1,37 @@
 //! Configuration Constants
 
+use easy_fs::CacheMode;
+
 pub const USER_STACK_SIZE: usize = 4096 * 2;
 pub const KERNEL_STACK_SIZE: usize = 4096 * 2;
 pub const KERNEL_HEAP_SIZE: usize = 0x30_0000;
 
+/// Bytes of kernel log lines buffered until they are written to the log file
+pub const LOG_BUFFER_SIZE: usize = 4096;
+/// Size at which the kernel log file is rotated
+pub const LOG_FILE_MAX_SIZE: usize = 64 * 1024;
+
+/// When the block cache writes changes back, write-back trades durability for speed
+pub const BLOCK_CACHE_MODE: CacheMode = CacheMode::WriteThrough;
+
+/// Longest path a syscall accepts, counting its terminating `\0`
+pub const PATH_MAX: usize = 4096;
+
+/// Events an input device queues before dropping the oldest ones
+pub const INPUT_QUEUE_SIZE: usize = 256;
+
+/// Harts available to run tasks
+pub const HART_NUM: usize = 1;
+/// Affinity mask with every available hart set
+pub const ALL_HARTS: usize = (1 << HART_NUM) - 1;
+
 pub const PAGE_SIZE: usize = 0x1000;
 pub const PAGE_SIZE_BITS: usize = 0xc;
 
 pub const TRAMPOLINE: usize = usize::MAX - PAGE_SIZE + 1;
 pub const TRAP_CONTEXT_BASE: usize = TRAMPOLINE - PAGE_SIZE;
+/// Read-only page holding the time in milliseconds, mapped into every user
+/// space just below where user programs are linked
+pub const TICK_PAGE: usize = 0x10000 - PAGE_SIZE;
 
 pub use crate::board::{CLOCK_FREQ, MEMORY_END, MMIO};
@@ -21,6 +21,28 @@ pub fn print(args: Arguments) {
     Stdout.write_fmt(args).unwrap();
 }
 
+/// Writes straight to the UART, bypassing its lock
+struct PanicStdout;
+
+impl Write for PanicStdout {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for b in s.bytes() {
+            UART.write_unlocked(b);
+        }
+        Ok(())
+    }
+}
+
+/// Print for the panic handler, which must not wait on a lock the panicking code holds
+pub fn print_unlocked(args: Arguments) {
+    let _ = PanicStdout.write_fmt(args);
+}
+
+/// Wait until everything printed has left the UART
+pub fn flush() {
+    UART.flush();
+}
+
 /// print string
 #[macro_export]
 macro_rules! print {
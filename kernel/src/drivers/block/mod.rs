@@ -22,12 +22,16 @@ mod test {
 
     test!(test_block_device, {
         let block_device = BLOCK_DEVICE.clone();
+        let mut original = [0u8; 512];
         let mut write_buffer = [0u8; 512];
         let mut read_buffer = [0u8; 512];
         for i in 0..512 {
+            // put the block back afterwards, the file system lives on this device
+            test_assert!(block_device.read_block(i, &mut original).is_ok());
             write_buffer.fill(i as u8);
-            block_device.write_block(i, &write_buffer);
-            block_device.read_block(i, &mut read_buffer);
+            test_assert!(block_device.write_block(i, &write_buffer).is_ok());
+            test_assert!(block_device.read_block(i, &mut read_buffer).is_ok());
+            test_assert!(block_device.write_block(i, &original).is_ok());
             test_assert!(write_buffer == read_buffer);
         }
         Ok("passed")
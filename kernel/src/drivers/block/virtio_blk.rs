@@ -1,21 +1,31 @@
 //! `VirtIOBlock`
 
-use alloc::collections::BTreeMap;
-use easy_fs::BlockDevice;
-use virtio_drivers::{BlkResp, RespStatus, VirtIOBlk, VirtIOHeader};
+use alloc::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    vec,
+    vec::Vec,
+};
+use easy_fs::{BlockDevice, BlockError};
+use virtio_drivers::{BlkResp, Error, RespStatus, VirtIOBlk, VirtIOHeader};
 
 use crate::{
-    drivers::bus::virtio::VirtIOHal,
+    drivers::{bus::virtio::VirtIOHal, stats::BLOCK_STATS},
     sync::{Condvar, UPIntrFreeCell},
     task::schedule,
     DEV_NON_BLOCKING_ACCESS,
 };
 
 const VIRTIO0: usize = 0x1000_8000;
+/// The capacity in 512-byte sectors, the first field of the device configuration
+const VIRTIO_BLK_CAPACITY: usize = VIRTIO0 + 0x100;
 
 pub struct VirtIOBlock {
     virtio_blk: UPIntrFreeCell<VirtIOBlk<'static, VirtIOHal>>,
     condvars: BTreeMap<u16, Condvar>,
+    /// Tokens of batched requests that completed before anyone waited for them
+    completed: UPIntrFreeCell<BTreeSet<u16>>,
+    /// Sectors on the device, read once as it cannot be resized while running
+    capacity: usize,
 }
 
 impl VirtIOBlock {
@@ -31,63 +41,167 @@ impl VirtIOBlock {
             let condvar = Condvar::new();
             condvars.insert(i, condvar);
         }
+        let capacity =
+            unsafe { core::ptr::read_volatile(VIRTIO_BLK_CAPACITY as *const u64) } as usize;
         Self {
             virtio_blk,
             condvars,
+            completed: unsafe { UPIntrFreeCell::new(BTreeSet::new()) },
+            capacity,
+        }
+    }
+
+    /// Block until the request `token` completes, unless it already has
+    fn wait_for(&self, token: u16) {
+        let task_cx_ptr = self.virtio_blk.exclusive_session(|_| {
+            // interrupts stay off until the task is queued, so the signal cannot be missed
+            (!self.completed.exclusive_access().remove(&token))
+                .then(|| self.condvars.get(&token).unwrap().wait_no_sched())
+        });
+        if let Some(task_cx_ptr) = task_cx_ptr {
+            schedule(task_cx_ptr);
         }
     }
 }
 
 impl BlockDevice for VirtIOBlock {
-    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) -> Result<(), BlockError> {
         let nb = *DEV_NON_BLOCKING_ACCESS.exclusive_access();
         if nb {
             let mut resp = BlkResp::default();
-            let task_cx_ptr = self.virtio_blk.exclusive_session(|blk| {
-                let token = unsafe { blk.read_block_nb(block_id, buf, &mut resp).unwrap() };
-                self.condvars.get(&token).unwrap().wait_no_sched()
-            });
+            let task_cx_ptr =
+                self.virtio_blk
+                    .exclusive_session(|blk| -> Result<_, BlockError> {
+                        let token = unsafe { blk.read_block_nb(block_id, buf, &mut resp) }
+                            .map_err(|e| block_error(&e))?;
+                        Ok(self.condvars.get(&token).unwrap().wait_no_sched())
+                    })?;
             schedule(task_cx_ptr);
-            assert_eq!(
-                resp.status(),
-                RespStatus::Ok,
-                "Error when reading VirtIOBlk"
-            );
+            resp_result(&resp)?;
         } else {
             self.virtio_blk
                 .exclusive_access()
                 .read_block(block_id, buf)
-                .expect("Error when reading VirtIOBlk");
+                .map_err(|e| block_error(&e))?;
         }
+        BLOCK_STATS.read(buf.len());
+        Ok(())
     }
 
-    fn write_block(&self, block_id: usize, buf: &[u8]) {
+    fn write_block(&self, block_id: usize, buf: &[u8]) -> Result<(), BlockError> {
         let nb = *DEV_NON_BLOCKING_ACCESS.exclusive_access();
         if nb {
             let mut resp = BlkResp::default();
-            let task_cx_ptr = self.virtio_blk.exclusive_session(|blk| {
-                let token = unsafe { blk.write_block_nb(block_id, buf, &mut resp).unwrap() };
-                self.condvars.get(&token).unwrap().wait_no_sched()
-            });
+            let task_cx_ptr =
+                self.virtio_blk
+                    .exclusive_session(|blk| -> Result<_, BlockError> {
+                        let token = unsafe { blk.write_block_nb(block_id, buf, &mut resp) }
+                            .map_err(|e| block_error(&e))?;
+                        Ok(self.condvars.get(&token).unwrap().wait_no_sched())
+                    })?;
             schedule(task_cx_ptr);
-            assert_eq!(
-                resp.status(),
-                RespStatus::Ok,
-                "Error when writing VirtIOBlk"
-            );
+            resp_result(&resp)?;
         } else {
             self.virtio_blk
                 .exclusive_access()
                 .write_block(block_id, buf)
-                .expect("Error when writing VirtIOBlk");
+                .map_err(|e| block_error(&e))?;
+        }
+        BLOCK_STATS.write(buf.len());
+        Ok(())
+    }
+
+    fn num_blocks(&self) -> usize {
+        self.capacity
+    }
+
+    fn write_blocks(&self, blocks: &[(usize, &[u8])]) -> Vec<Result<(), BlockError>> {
+        let nb = *DEV_NON_BLOCKING_ACCESS.exclusive_access();
+        if !nb {
+            return blocks
+                .iter()
+                .map(|&(block_id, buf)| self.write_block(block_id, buf))
+                .collect();
+        }
+
+        // the device writes into these until the requests complete, they must not move
+        let mut resps: Vec<BlkResp> = blocks.iter().map(|_| BlkResp::default()).collect();
+        let mut results = vec![Ok(()); blocks.len()];
+        // blocks and tokens of the requests in flight, oldest first
+        let mut in_flight: VecDeque<(usize, u16)> = VecDeque::new();
+        for (i, &(block_id, buf)) in blocks.iter().enumerate() {
+            // an earlier write to the same block may otherwise complete after this one
+            if let Some(pos) = in_flight.iter().position(|&(id, _)| id == block_id) {
+                for (_, token) in in_flight.drain(..=pos) {
+                    self.wait_for(token);
+                }
+            }
+            loop {
+                let resp = &mut resps[i];
+                let submitted = self
+                    .virtio_blk
+                    .exclusive_session(|blk| unsafe { blk.write_block_nb(block_id, buf, resp) });
+                match submitted {
+                    Ok(token) => {
+                        in_flight.push_back((block_id, token));
+                        break;
+                    }
+                    Err(e) => {
+                        // the queue is full, wait for the oldest request to make room
+                        let Some((_, token)) = in_flight.pop_front() else {
+                            results[i] = Err(block_error(&e));
+                            break;
+                        };
+                        self.wait_for(token);
+                    }
+                }
+            }
+        }
+        for (_, token) in in_flight {
+            self.wait_for(token);
+        }
+
+        for ((result, resp), &(_, buf)) in results.iter_mut().zip(&resps).zip(blocks) {
+            if result.is_ok() {
+                *result = resp_result(resp);
+                if result.is_ok() {
+                    BLOCK_STATS.write(buf.len());
+                }
+            }
         }
+        results
     }
 
     fn handle_irq(&self) {
+        BLOCK_STATS.irq();
         self.virtio_blk.exclusive_session(|blk| {
             while let Ok(token) = blk.pop_used() {
-                self.condvars.get(&token).unwrap().signal();
+                let condvar = self.condvars.get(&token).unwrap();
+                if condvar.inner.exclusive_access().wait_queue.is_empty() {
+                    // only batched requests are not waited for as soon as they are submitted
+                    self.completed.exclusive_access().insert(token);
+                } else {
+                    condvar.signal();
+                }
             }
         });
     }
 }
+
+/// Classify a failed request, one the device was not ready for can be retried
+fn block_error(error: &Error) -> BlockError {
+    if matches!(error, Error::NotReady) {
+        BlockError::Transient
+    } else {
+        BlockError::Io
+    }
+}
+
+/// The outcome of a completed non-blocking request
+fn resp_result(resp: &BlkResp) -> Result<(), BlockError> {
+    match resp.status() {
+        RespStatus::Ok => Ok(()),
+        RespStatus::NotReady => Err(BlockError::Transient),
+        _ => Err(BlockError::Io),
+    }
+}
@@ -1,47 +1,20 @@
 //! `VirtIOHal`
 
-use alloc::vec::Vec;
-use lazy_static::lazy_static;
 use virtio_drivers::Hal;
 
-use crate::{
-    mm::{
-        frame_allocator, kernel_token, FrameTracker, PageTable, PhysAddr, PhysPageNum, StepByOne,
-        VirtAddr,
-    },
-    sync::UPIntrFreeCell,
-};
-
-lazy_static! {
-    static ref QUEUE_FRAMES: UPIntrFreeCell<Vec<FrameTracker>> =
-        unsafe { UPIntrFreeCell::new(Vec::new()) };
-}
+use crate::mm::{frame_allocator, kernel_token, PageTable, PhysAddr, VirtAddr};
 
 pub struct VirtIOHal;
 
 impl Hal for VirtIOHal {
+    /// Returns 0, which the driver reports as a DMA error, if no run of
+    /// `pages` contiguous frames is free.
     fn dma_alloc(pages: usize) -> usize {
-        let frame = frame_allocator::alloc().unwrap();
-        let ppn_base = frame.ppn;
-        QUEUE_FRAMES.exclusive_access().push(frame);
-
-        for i in 1..pages {
-            let frame = frame_allocator::alloc().unwrap();
-            assert_eq!(frame.ppn.0, ppn_base.0 + i);
-            QUEUE_FRAMES.exclusive_access().push(frame);
-        }
-
-        let pa: PhysAddr = ppn_base.into();
-        pa.0
+        frame_allocator::alloc_contiguous(pages).map_or(0, |ppn| PhysAddr::from(ppn).0)
     }
 
     fn dma_dealloc(pa: usize, pages: usize) -> i32 {
-        let pa = PhysAddr::from(pa);
-        let mut ppn_base: PhysPageNum = pa.into();
-        for _ in 0..pages {
-            frame_allocator::dealloc(ppn_base);
-            ppn_base.step();
-        }
+        frame_allocator::dealloc_contiguous(PhysAddr::from(pa).into(), pages);
         0
     }
 
@@ -11,6 +11,10 @@ pub trait CharDevice {
     fn init(&self);
     fn read(&self) -> u8;
     fn write(&self, ch: u8);
+    /// Write `ch` without taking any lock, for the panic handler only
+    fn write_unlocked(&self, ch: u8);
+    /// Wait until every byte written has left the device
+    fn flush(&self);
     fn handle_irq(&self);
 }
 
@@ -4,8 +4,9 @@
 //! - Ref: ns16550a datasheet: <https://datasheetspdf.com/pdf-file/605590/NationalSemiconductor/NS16550A/1>
 //! - Ref: ns16450 datasheet: <https://datasheetspdf.com/pdf-file/1311818/NationalSemiconductor/NS16450/1>
 use super::CharDevice;
+use crate::drivers::stats::UART_STATS;
 use crate::sync::{Condvar, UPIntrFreeCell};
-use crate::task::schedule;
+use crate::task::{manager::signal_foreground, schedule, SignalFlags};
 use alloc::collections::VecDeque;
 use bitflags::bitflags;
 use core::ops::Add;
@@ -13,6 +14,9 @@ use core::ptr::NonNull;
 use volatile::access::{ReadOnly, ReadWrite, WriteOnly};
 use volatile::VolatileRef;
 
+/// Ctrl-C, interrupts the foreground process group
+const CTRL_C: u8 = 0x03;
+
 bitflags! {
     /// InterruptEnableRegister
     #[derive(Clone, Copy)]
@@ -26,6 +30,8 @@ bitflags! {
     pub struct LSR: u8 {
         const DATA_AVAILABLE = 1 << 0;
         const THR_EMPTY = 1 << 5;
+        /// Both the holding and the shift register are empty, nothing is left to send
+        const TX_IDLE = 1 << 6;
     }
 
     /// Model Control Register
@@ -183,6 +189,12 @@ impl NS16550aRaw {
             }
         }
     }
+
+    /// Wait until the last byte written has been sent
+    pub fn flush(&mut self) {
+        let write_end = self.write_end();
+        while !write_end.lsr.as_ptr().read().contains(LSR::TX_IDLE) {}
+    }
 }
 
 struct NS16550aInner {
@@ -235,16 +247,33 @@ impl<const BASE_ADDR: usize> CharDevice for NS16550a<BASE_ADDR> {
     fn write(&self, ch: u8) {
         let mut inner = self.inner.exclusive_access();
         inner.ns16550a.write(ch);
+        UART_STATS.write(1);
+    }
+
+    /// Goes through registers of its own, so a write the panic interrupted cannot block it
+    fn write_unlocked(&self, ch: u8) {
+        NS16550aRaw::new(BASE_ADDR).write(ch);
+    }
+
+    fn flush(&self) {
+        NS16550aRaw::new(BASE_ADDR).flush();
     }
 
+    /// Ctrl-C is turned into `SIGINT` for the foreground process group, and only read as
+    /// input when no group is in the foreground.
     fn handle_irq(&self) {
         let mut count = 0;
         self.inner.exclusive_session(|inner| {
             while let Some(ch) = inner.ns16550a.read() {
                 count += 1;
+                if ch == CTRL_C && signal_foreground(SignalFlags::SIGINT) {
+                    continue;
+                }
                 inner.read_buffer.push_back(ch);
             }
         });
+        UART_STATS.irq();
+        UART_STATS.read(count);
         if count > 0 {
             self.condvar.signal();
         }
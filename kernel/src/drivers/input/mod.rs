@@ -1,7 +1,11 @@
 //! Input device drivers
 
-use super::bus::virtio::VirtIOHal;
+use super::{
+    bus::virtio::VirtIOHal,
+    stats::{DeviceStats, KEYBOARD_STATS, MOUSE_STATS},
+};
 use crate::{
+    config::INPUT_QUEUE_SIZE,
     sync::{Condvar, UPIntrFreeCell},
     task::schedule,
 };
@@ -15,8 +19,9 @@ const VIRTIO6: usize = 0x1000_6000;
 
 lazy_static! {
     pub static ref KEYBOARD_DEVICE: Arc<dyn InputDevice> =
-        Arc::new(VirtIOInputWrapper::new(VIRTIO5));
-    pub static ref MOUSE_DEVICE: Arc<dyn InputDevice> = Arc::new(VirtIOInputWrapper::new(VIRTIO6));
+        Arc::new(VirtIOInputWrapper::new(VIRTIO5, &KEYBOARD_STATS));
+    pub static ref MOUSE_DEVICE: Arc<dyn InputDevice> =
+        Arc::new(VirtIOInputWrapper::new(VIRTIO6, &MOUSE_STATS));
 }
 
 struct VirtIOInputInner {
@@ -27,6 +32,7 @@ struct VirtIOInputInner {
 struct VirtIOInputWrapper {
     inner: UPIntrFreeCell<VirtIOInputInner>,
     condvar: Condvar,
+    stats: &'static DeviceStats,
 }
 
 #[allow(clippy::module_name_repetitions)]
@@ -37,16 +43,17 @@ pub trait InputDevice: Send + Sync + Any {
 }
 
 impl VirtIOInputWrapper {
-    pub fn new(addr: usize) -> Self {
+    pub fn new(addr: usize, stats: &'static DeviceStats) -> Self {
         let inner = VirtIOInputInner {
             virtio_input: unsafe {
                 VirtIOInput::<VirtIOHal>::new(&mut *(addr as *mut VirtIOHeader)).unwrap()
             },
-            events: VecDeque::new(),
+            events: VecDeque::with_capacity(INPUT_QUEUE_SIZE),
         };
         Self {
             inner: unsafe { UPIntrFreeCell::new(inner) },
             condvar: Condvar::new(),
+            stats,
         }
     }
 }
@@ -70,6 +77,7 @@ impl InputDevice for VirtIOInputWrapper {
 
     fn handle_irq(&self) {
         let mut count = 0;
+        let mut dropped = 0;
 
         self.inner.exclusive_session(|inner| {
             while let Some((_token, event)) = inner.virtio_input.pop_pending_event() {
@@ -77,12 +85,50 @@ impl InputDevice for VirtIOInputWrapper {
                 let result = u64::from(event.event_type) << 48
                     | u64::from(event.code) << 32
                     | u64::from(event.value);
-                inner.events.push_back(result);
+                if push_event(&mut inner.events, INPUT_QUEUE_SIZE, result) {
+                    dropped += 1;
+                }
             }
         });
 
+        self.stats.irq();
+        self.stats.queue(count);
+        self.stats.drop_events(dropped);
         if count > 0 {
             self.condvar.signal();
         }
     }
 }
+
+/// Queue `event`, dropping the oldest event if `capacity` is reached
+///
+/// Returns whether an event was dropped.
+fn push_event(events: &mut VecDeque<u64>, capacity: usize, event: u64) -> bool {
+    let full = events.len() >= capacity;
+    if full {
+        events.pop_front();
+    }
+    events.push_back(event);
+    full
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{test, test_assert};
+
+    test!(test_push_event_drop_oldest, {
+        let mut events = VecDeque::new();
+        for event in 0..4 {
+            test_assert!(!push_event(&mut events, 4, event));
+        }
+        test_assert!(push_event(&mut events, 4, 4), "full queue dropped nothing");
+        test_assert!(push_event(&mut events, 4, 5), "full queue dropped nothing");
+        test_assert!(events.len() == 4);
+        test_assert!(
+            events.iter().copied().eq(2..6),
+            "oldest events were not the ones dropped"
+        );
+        Ok("passed")
+    });
+}
@@ -6,6 +6,7 @@ pub mod chardev;
 pub mod gpu;
 pub mod input;
 pub mod plic;
+pub mod stats;
 
 pub use block::BLOCK_DEVICE;
 pub use chardev::UART;
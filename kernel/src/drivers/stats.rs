@@ -0,0 +1,88 @@
+//! Driver statistics
+
+use alloc::{format, string::String};
+use core::{
+    fmt::Write,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Counters of a device
+///
+/// The counters are atomics so interrupt handlers can update them without
+/// taking the device lock a second time.
+pub struct DeviceStats {
+    name: &'static str,
+    irqs: AtomicUsize,
+    bytes_read: AtomicUsize,
+    bytes_written: AtomicUsize,
+    events_queued: AtomicUsize,
+    events_dropped: AtomicUsize,
+}
+
+impl DeviceStats {
+    const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            irqs: AtomicUsize::new(0),
+            bytes_read: AtomicUsize::new(0),
+            bytes_written: AtomicUsize::new(0),
+            events_queued: AtomicUsize::new(0),
+            events_dropped: AtomicUsize::new(0),
+        }
+    }
+
+    /// Count an interrupt handled by the device
+    pub fn irq(&self) {
+        self.irqs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count `len` bytes read from the device
+    pub fn read(&self, len: usize) {
+        self.bytes_read.fetch_add(len, Ordering::Relaxed);
+    }
+
+    /// Count `len` bytes written to the device
+    pub fn write(&self, len: usize) {
+        self.bytes_written.fetch_add(len, Ordering::Relaxed);
+    }
+
+    /// Count `count` events queued for readers
+    pub fn queue(&self, count: usize) {
+        self.events_queued.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Count `count` queued events dropped before they were read
+    pub fn drop_events(&self, count: usize) {
+        self.events_dropped.fetch_add(count, Ordering::Relaxed);
+    }
+}
+
+/// Statistics of the serial console
+pub static UART_STATS: DeviceStats = DeviceStats::new("uart");
+/// Statistics of the block device
+pub static BLOCK_STATS: DeviceStats = DeviceStats::new("block");
+/// Statistics of the keyboard
+pub static KEYBOARD_STATS: DeviceStats = DeviceStats::new("keyboard");
+/// Statistics of the mouse
+pub static MOUSE_STATS: DeviceStats = DeviceStats::new("mouse");
+
+/// Render the counters of all devices as a table, one device per line
+pub fn render() -> String {
+    let mut table = format!(
+        "{:<10}{:>12}{:>12}{:>12}{:>12}{:>12}\n",
+        "DEVICE", "IRQS", "READ", "WRITTEN", "QUEUED", "DROPPED"
+    );
+    for stats in [&UART_STATS, &BLOCK_STATS, &KEYBOARD_STATS, &MOUSE_STATS] {
+        let _ = writeln!(
+            table,
+            "{:<10}{:>12}{:>12}{:>12}{:>12}{:>12}",
+            stats.name,
+            stats.irqs.load(Ordering::Relaxed),
+            stats.bytes_read.load(Ordering::Relaxed),
+            stats.bytes_written.load(Ordering::Relaxed),
+            stats.events_queued.load(Ordering::Relaxed),
+            stats.events_dropped.load(Ordering::Relaxed),
+        );
+    }
+    table
+}
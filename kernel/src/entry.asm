@@ -1,6 +1,8 @@
     .section .text.entry
     .globl _start
 _start:
+    # keep the hart id from SBI in tp, which traps never touch
+    mv tp, a0
     la sp, boot_stack_top
     call rust_main
 
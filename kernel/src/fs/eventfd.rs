@@ -0,0 +1,138 @@
+use alloc::string::String;
+
+use super::{File, OpenFlags, PollEvents};
+use crate::{
+    mm::UserBuffer,
+    sync::{Condvar, UPIntrFreeCell},
+    task::{schedule, suspend_current_and_run_next},
+};
+
+/// Size of the counter transferred by every read and write.
+const EVENTFD_SIZE: usize = core::mem::size_of::<u64>();
+
+/// A 64-bit event counter for signaling between tasks without a pipe.
+///
+/// Writes add to the counter, reads return the counter and reset it to zero,
+/// blocking while it is zero.
+pub struct EventFd {
+    counter: UPIntrFreeCell<u64>,
+    status: UPIntrFreeCell<OpenFlags>,
+    condvar: Condvar,
+}
+
+impl EventFd {
+    /// Creates an event counter starting at `initval`.
+    pub fn new(initval: u64) -> Self {
+        Self {
+            counter: unsafe { UPIntrFreeCell::new(initval) },
+            status: unsafe { UPIntrFreeCell::new(OpenFlags::empty()) },
+            condvar: Condvar::new(),
+        }
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.status.exclusive_access().contains(OpenFlags::NONBLOCK)
+    }
+}
+
+impl File for EventFd {
+    fn is_readable(&self) -> bool {
+        true
+    }
+
+    fn is_writable(&self) -> bool {
+        true
+    }
+
+    fn status_flags(&self) -> OpenFlags {
+        OpenFlags::RDWR | *self.status.exclusive_access()
+    }
+
+    fn set_status_flags(&self, flags: OpenFlags) {
+        *self.status.exclusive_access() = flags & OpenFlags::NONBLOCK;
+    }
+
+    fn poll(&self, events: PollEvents) -> PollEvents {
+        let counter = *self.counter.exclusive_access();
+        let mut ready = PollEvents::empty();
+        if counter != 0 {
+            ready |= PollEvents::IN;
+        }
+        if counter < u64::MAX - 1 {
+            ready |= PollEvents::OUT;
+        }
+        ready & events
+    }
+
+    /// Reads the counter as 8 native-endian bytes and resets it.
+    ///
+    /// Returns `0` without blocking if `buf` is shorter than 8 bytes, or if the
+    /// counter is zero and the file is non-blocking.
+    fn read(&self, mut buf: UserBuffer) -> usize {
+        if buf.len() < EVENTFD_SIZE {
+            return 0;
+        }
+
+        let value = loop {
+            let mut counter = self.counter.exclusive_access();
+            if *counter != 0 {
+                break core::mem::take(&mut *counter);
+            }
+            if self.is_nonblocking() {
+                return 0;
+            }
+            let task_cx_ptr = self.condvar.wait_no_sched();
+            drop(counter);
+            schedule(task_cx_ptr);
+        };
+
+        for (p, byte) in buf.iter_mut().zip(value.to_ne_bytes()) {
+            unsafe { *p = byte };
+        }
+        EVENTFD_SIZE
+    }
+
+    /// Adds 8 native-endian bytes from `buf` to the counter and wakes a reader.
+    ///
+    /// Blocks while the sum would reach `u64::MAX`. Returns `0` if `buf` is
+    /// shorter than 8 bytes or holds `u64::MAX`, or instead of blocking if the
+    /// file is non-blocking.
+    fn write(&self, buf: UserBuffer) -> usize {
+        if buf.len() < EVENTFD_SIZE {
+            return 0;
+        }
+
+        let mut bytes = [0u8; EVENTFD_SIZE];
+        for (byte, p) in bytes.iter_mut().zip(buf.iter()) {
+            *byte = unsafe { *p };
+        }
+        let value = u64::from_ne_bytes(bytes);
+        if value == u64::MAX {
+            return 0;
+        }
+
+        loop {
+            let mut counter = self.counter.exclusive_access();
+            match counter.checked_add(value) {
+                Some(sum) if sum != u64::MAX => {
+                    *counter = sum;
+                    drop(counter);
+                    if value != 0 {
+                        self.condvar.signal();
+                    }
+                    return EVENTFD_SIZE;
+                }
+                _ if self.is_nonblocking() => return 0,
+                _ => {
+                    // wait for a reader to drain the counter
+                    drop(counter);
+                    suspend_current_and_run_next();
+                }
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        String::from("anon_inode:[eventfd]")
+    }
+}
@@ -1,11 +1,15 @@
-use alloc::{sync::Arc, vec::Vec};
+use alloc::{collections::BTreeMap, string::String, sync::Arc, vec, vec::Vec};
 use bitflags::bitflags;
-use easy_fs::{EasyFileSystem, Inode};
+use easy_fs::{set_cache_mode, BlockError, Clock, DirEntry, EasyFileSystem, Inode, BLOCK_SIZE};
 use lazy_static::lazy_static;
+use log::warn;
 
-use crate::{drivers::BLOCK_DEVICE, mm::UserBuffer, sync::UPIntrFreeCell};
+use crate::{
+    config::BLOCK_CACHE_MODE, drivers::BLOCK_DEVICE, mm::UserBuffer, sync::UPIntrFreeCell,
+    timer::get_time_ms, DEV_NON_BLOCKING_ACCESS,
+};
 
-use super::{File, StatMode};
+use super::{proc::PROC_FILES, File, StatMode};
 
 /// A wrapper around a filesystem inode
 /// to implement File trait atop
@@ -13,28 +17,100 @@ use super::{File, StatMode};
 pub struct OSInode {
     readable: bool,
     writable: bool,
+    /// The absolute path the file was opened at
+    path: String,
+    /// Id of the inode, read at open so dropping the file never needs the `fs` lock
+    inode_id: u32,
     inner: UPIntrFreeCell<OSInodeInner>,
 }
 
 /// The OS inode inner in '`UPIntrFreeCell`'
 pub struct OSInodeInner {
     offset: usize,
+    status: OpenFlags,
     inode: Arc<Inode>,
+    /// Whether an I/O error occurred since it was last reported
+    io_error: bool,
+    /// Whether a write ran out of space since it was last reported
+    no_space: bool,
+}
+
+/// Writes shorter than this are coalesced in a write-back buffer
+const WRITE_BUFFER_SIZE: usize = BLOCK_SIZE;
+
+/// Data copied between files in the kernel moves in chunks of this size
+pub const COPY_CHUNK_SIZE: usize = 8 * BLOCK_SIZE;
+
+/// Sequential appends to an inode that have not reached the disk yet
+struct WriteBuffer {
+    inode: Arc<Inode>,
+    offset: usize,
+    data: Vec<u8>,
+}
+
+impl WriteBuffer {
+    fn end(&self) -> usize {
+        self.offset + self.data.len()
+    }
+
+    fn flush(self) -> Result<(), BlockError> {
+        self.inode.try_write_at(self.offset, &self.data).map(|_| ())
+    }
+}
+
+/// Order in which [`OSInode::read_dirents`] reports the entry stored at `slot`
+///
+/// `.` and `..` keep the first two slots for good, the other entries are ordered by
+/// inode number since removing an entry moves the last one into its slot.
+fn dirent_cookie(slot: usize, inode_number: u32) -> usize {
+    if slot < 2 {
+        slot + 1
+    } else {
+        inode_number as usize + 3
+    }
 }
 
 impl OSInode {
-    /// Construct an OS inode from a inode
-    pub fn new(readable: bool, writable: bool, inode: Arc<Inode>) -> Self {
+    /// Construct an OS inode from a inode opened at `path`
+    pub fn new(readable: bool, writable: bool, inode: Arc<Inode>, path: &str) -> Self {
         Self {
             readable,
             writable,
-            inner: unsafe { UPIntrFreeCell::new(OSInodeInner { offset: 0, inode }) },
+            path: String::from(path),
+            inode_id: inode.inode_id(),
+            inner: unsafe {
+                UPIntrFreeCell::new(OSInodeInner {
+                    offset: 0,
+                    status: OpenFlags::empty(),
+                    inode,
+                    io_error: false,
+                    no_space: false,
+                })
+            },
+        }
+    }
+
+    /// The inode the file was opened on
+    pub fn inode(&self) -> Arc<Inode> {
+        self.inner.exclusive_access().inode.clone()
+    }
+
+    /// Write back the buffered data within `start..end`, widened to whole blocks
+    ///
+    /// A failure is kept to be reported by [`File::take_io_error`], as for [`File::sync`].
+    pub fn sync_range(&self, start: usize, end: usize) {
+        let start = start / BLOCK_SIZE * BLOCK_SIZE;
+        let end = end.div_ceil(BLOCK_SIZE).saturating_mul(BLOCK_SIZE);
+        let mut inner = self.inner.exclusive_access();
+        if flush_write_buffer_overlapping(inner.inode.inode_id(), start, end).is_err() {
+            inner.io_error = true;
         }
     }
 
     /// Read all data inside a inode into vector
     pub fn read_all(&self) -> Vec<u8> {
         let mut inner = self.inner.exclusive_access();
+        let _ = flush_write_buffer(inner.inode.inode_id());
         let mut buffer = [0u8; 512];
         let mut v: Vec<u8> = Vec::new();
         loop {
@@ -47,6 +123,103 @@ impl OSInode {
         }
         v
     }
+
+    /// Copy up to `count` bytes at `offset` of this file to `out`
+    ///
+    /// The data moves between the inodes without going through user memory. The copy
+    /// stops early at the end of this file and leaves its offset alone, `out` is written
+    /// at its offset or appended to if it was opened with [`OpenFlags::APPEND`].
+    pub fn copy_to(&self, offset: usize, out: &Self, count: usize) -> usize {
+        self.copy_range(offset, out, None, count)
+    }
+
+    /// Copy up to `count` bytes at `offset` of this file to `out_offset` of `out`
+    ///
+    /// Like [`OSInode::copy_to`], but with `out_offset` given `out` is written there and
+    /// its offset left alone.
+    pub fn copy_range(
+        &self,
+        offset: usize,
+        out: &Self,
+        out_offset: Option<usize>,
+        count: usize,
+    ) -> usize {
+        let mut buffer = vec![0u8; COPY_CHUNK_SIZE.min(count)];
+        let mut copied = 0;
+        while copied < count {
+            let len = (count - copied).min(COPY_CHUNK_SIZE);
+            let read_size = {
+                let mut inner = self.inner.exclusive_access();
+                let start = offset + copied;
+                let read = flush_write_buffer_before(inner.inode.inode_id(), start + len)
+                    .and_then(|()| inner.inode.try_read_at(start, &mut buffer[..len]));
+                let Ok(read_size) = read else {
+                    inner.io_error = true;
+                    break;
+                };
+                read_size
+            };
+            if read_size == 0 {
+                break;
+            }
+
+            let mut inner = out.inner.exclusive_access();
+            if flush_write_buffer(inner.inode.inode_id()).is_err() {
+                inner.io_error = true;
+                break;
+            }
+            let start = match out_offset {
+                Some(out_offset) => out_offset + copied,
+                None if inner.status.contains(OpenFlags::APPEND) => {
+                    inner.inode.file_size() as usize
+                }
+                None => inner.offset,
+            };
+            let Ok(write_size) = inner.inode.try_write_at(start, &buffer[..read_size]) else {
+                inner.io_error = true;
+                break;
+            };
+            if out_offset.is_none() {
+                inner.offset = start + write_size;
+            }
+            copied += write_size;
+            if write_size < read_size {
+                inner.no_space = true;
+                break;
+            }
+        }
+        copied
+    }
+
+    /// Take up to `count` entries of a directory following the last ones taken
+    ///
+    /// The offset of the file holds the [`dirent_cookie`] of the last entry taken,
+    /// and each call snapshots the entries afresh. Entries present throughout are
+    /// reported once however others are created or removed in between, and none is
+    /// reported twice.
+    ///
+    /// Returns `None` if the file is not a directory.
+    pub fn read_dirents(&self, count: usize) -> Option<Vec<DirEntry>> {
+        let mut inner = self.inner.exclusive_access();
+        if !inner.inode.is_dir() {
+            return None;
+        }
+        let offset = inner.offset;
+        let mut entries: Vec<_> = inner
+            .inode
+            .entries()
+            .into_iter()
+            .enumerate()
+            .map(|(slot, entry)| (dirent_cookie(slot, entry.inode_number()), entry))
+            .filter(|(cookie, _)| *cookie > offset)
+            .collect();
+        entries.sort_unstable_by_key(|(cookie, _)| *cookie);
+        entries.truncate(count);
+        if let Some((cookie, _)) = entries.last() {
+            inner.offset = *cookie;
+        }
+        Some(entries.into_iter().map(|(_, entry)| entry).collect())
+    }
 }
 
 impl File for OSInode {
@@ -58,28 +231,98 @@ impl File for OSInode {
         self.writable
     }
 
+    /// Reads from the offset, stopping at the end of the file
+    ///
+    /// The size is all there is to wait for: once it is reached, here or by an earlier
+    /// read, `0` is returned at once, even for an offset moved far past the end.
     fn read(&self, mut buf: UserBuffer) -> usize {
         let mut inner = self.inner.exclusive_access();
+        // buffered appends another file may have made must be visible to this read
+        let end = inner.offset.saturating_add(buf.len());
+        if flush_write_buffer_before(inner.inode.inode_id(), end).is_err() {
+            inner.io_error = true;
+            return 0;
+        }
         let mut total_read_size = 0usize;
         for slice in &mut buf.buffers {
-            let read_size = inner.inode.read_at(inner.offset, slice);
-            if read_size == 0 {
+            let Ok(read_size) = inner.inode.try_read_at(inner.offset, slice) else {
+                inner.io_error = true;
                 break;
-            }
+            };
             inner.offset += read_size;
             total_read_size += read_size;
+            // a short read means the end of the file was reached
+            if read_size < slice.len() {
+                break;
+            }
         }
         total_read_size
     }
 
     fn write(&self, buf: UserBuffer) -> usize {
         let mut inner = self.inner.exclusive_access();
+        let inode_id = inner.inode.inode_id();
+        let file_size = inner.inode.file_size() as usize;
+        if inner.status.contains(OpenFlags::APPEND) {
+            inner.offset = buffered_end(inode_id).unwrap_or(file_size);
+        }
+
+        let len = buf.len();
+        if len < WRITE_BUFFER_SIZE {
+            match buffer_write(&inner.inode, inode_id, inner.offset, &buf) {
+                Ok(true) => {
+                    inner.offset += len;
+                    return len;
+                }
+                Ok(false) => {}
+                Err(_) => {
+                    inner.io_error = true;
+                    return 0;
+                }
+            }
+        }
+
+        if flush_write_buffer(inode_id).is_err() {
+            inner.io_error = true;
+            return 0;
+        }
+        if inner.status.contains(OpenFlags::APPEND) {
+            // flushing may sleep, letting another append start a buffer behind this one
+            while buffered_end(inode_id).is_some() {
+                if flush_write_buffer(inode_id).is_err() {
+                    inner.io_error = true;
+                    return 0;
+                }
+            }
+            // the end is picked under the inode lock, another writer may have moved it
+            let data: Vec<u8> = buf
+                .buffers
+                .iter()
+                .flat_map(|slice| slice.iter().copied())
+                .collect();
+            let Ok((offset, write_size)) = inner.inode.try_append(&data) else {
+                inner.io_error = true;
+                return 0;
+            };
+            if write_size < len {
+                inner.no_space = true;
+            }
+            inner.offset = offset + write_size;
+            return write_size;
+        }
         let mut total_write_size = 0usize;
         for slice in &buf.buffers {
-            let write_size = inner.inode.write_at(inner.offset, slice);
-            assert_eq!(write_size, slice.len());
+            let Ok(write_size) = inner.inode.try_write_at(inner.offset, slice) else {
+                inner.io_error = true;
+                break;
+            };
             inner.offset += write_size;
             total_write_size += write_size;
+            // the file system is full, the bytes that fit are reported
+            if write_size < slice.len() {
+                inner.no_space = true;
+                break;
+            }
         }
         total_write_size
     }
@@ -92,12 +335,53 @@ impl File for OSInode {
         self.inner.exclusive_access().offset = offset;
     }
 
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn status_flags(&self) -> OpenFlags {
+        OpenFlags::access_mode(self.readable, self.writable) | self.inner.exclusive_access().status
+    }
+
+    fn set_status_flags(&self, flags: OpenFlags) {
+        self.inner.exclusive_access().status = flags & OpenFlags::SETTABLE;
+    }
+
     fn file_size(&self) -> u32 {
-        self.inner.exclusive_access().inode.file_size()
+        let inner = self.inner.exclusive_access();
+        let inode_id = inner.inode.inode_id();
+        buffered_end(inode_id).map_or_else(|| inner.inode.file_size(), |end| end as u32)
+    }
+
+    fn sync(&self) {
+        let mut inner = self.inner.exclusive_access();
+        // a write-back cache may still hold the blocks the buffer was flushed to
+        if flush_write_buffer(inner.inode.inode_id())
+            .and_then(|()| inner.inode.sync())
+            .is_err()
+        {
+            inner.io_error = true;
+        }
+    }
+
+    fn take_io_error(&self) -> bool {
+        core::mem::take(&mut self.inner.exclusive_access().io_error)
+    }
+
+    fn take_no_space(&self) -> bool {
+        core::mem::take(&mut self.inner.exclusive_access().no_space)
+    }
+
+    fn as_os_inode(&self) -> Option<&OSInode> {
+        Some(self)
     }
 
     fn inode_id(&self) -> u32 {
-        self.inner.exclusive_access().inode.inode_id()
+        self.inode_id
+    }
+
+    fn describe(&self) -> String {
+        self.path.clone()
     }
 
     fn mode(&self) -> StatMode {
@@ -112,9 +396,175 @@ impl File for OSInode {
     }
 }
 
+/// Closing a writable file writes its buffered appends back, read-only files have none
+///
+/// A task blocked on the device may be holding the lock of the inode or of its file
+/// system, and spinning on it here would never let that task run again. The flush is
+/// then left to [`flush_deferred`].
+impl Drop for OSInode {
+    fn drop(&mut self) {
+        if !self.writable {
+            return;
+        }
+        let inode = self.inner.exclusive_access().inode.clone();
+        if inode.is_locked() {
+            DEFERRED_FLUSHES.exclusive_access().push(self.inode_id);
+            return;
+        }
+        flush_dropped(self.inode_id);
+    }
+}
+
+/// Flush the appends to a file that was closed, without yielding
+fn flush_dropped(inode_id: u32) {
+    // files may be dropped with the process locked, so poll the device instead of yielding
+    let nb = core::mem::replace(&mut *DEV_NON_BLOCKING_ACCESS.exclusive_access(), false);
+    if flush_write_buffer(inode_id).is_err() {
+        warn!("[kernel] Lost buffered writes to inode {inode_id}");
+    }
+    *DEV_NON_BLOCKING_ACCESS.exclusive_access() = nb;
+}
+
+/// Write back the appends of files that were closed while they were busy
+///
+/// Called where no file system lock is held by the current task; inodes that are
+/// still busy are kept for the next call.
+pub fn flush_deferred() {
+    let deferred = core::mem::take(&mut *DEFERRED_FLUSHES.exclusive_access());
+    for inode_id in deferred {
+        let busy = WRITE_BUFFERS
+            .exclusive_access()
+            .get(&inode_id)
+            .is_some_and(|buffer| buffer.inode.is_locked());
+        if busy {
+            DEFERRED_FLUSHES.exclusive_access().push(inode_id);
+        } else {
+            flush_dropped(inode_id);
+        }
+    }
+}
+
+lazy_static! {
+    /// Write-back buffers by inode id, shared by every open file of an inode
+    static ref WRITE_BUFFERS: UPIntrFreeCell<BTreeMap<u32, WriteBuffer>> =
+        unsafe { UPIntrFreeCell::new(BTreeMap::new()) };
+    /// Inodes whose writable files were closed while they were busy
+    static ref DEFERRED_FLUSHES: UPIntrFreeCell<Vec<u32>> =
+        unsafe { UPIntrFreeCell::new(Vec::new()) };
+}
+
+/// End of the buffered appends of an inode, which is past its size on disk
+fn buffered_end(inode_id: u32) -> Option<usize> {
+    WRITE_BUFFERS
+        .exclusive_access()
+        .get(&inode_id)
+        .map(WriteBuffer::end)
+}
+
+/// Buffer a small write at `offset`, flushing the buffer once it fills up
+///
+/// Only appends at the end of the file or of the buffered data are taken,
+/// returns `false` if the write has to go to the disk instead.
+fn buffer_write(
+    inode: &Arc<Inode>,
+    inode_id: u32,
+    offset: usize,
+    buf: &UserBuffer,
+) -> Result<bool, BlockError> {
+    let file_size = inode.file_size() as usize;
+    let mut buffers = WRITE_BUFFERS.exclusive_access();
+    let buffer = match buffers.get_mut(&inode_id) {
+        Some(buffer) if buffer.end() == offset => buffer,
+        // a locked inode may be mid-write, about to move the end past `file_size`
+        None if offset == file_size && !inode.is_locked() => {
+            buffers.entry(inode_id).or_insert(WriteBuffer {
+                inode: inode.clone(),
+                offset,
+                data: Vec::with_capacity(WRITE_BUFFER_SIZE * 2),
+            })
+        }
+        _ => return Ok(false),
+    };
+    for slice in &buf.buffers {
+        buffer.data.extend_from_slice(slice);
+    }
+
+    if buffer.data.len() >= WRITE_BUFFER_SIZE {
+        let buffer = buffers.remove(&inode_id).unwrap();
+        drop(buffers);
+        buffer.flush()?;
+    }
+    Ok(true)
+}
+
+/// Write the buffered appends of an inode to the disk if they start before `end`
+fn flush_write_buffer_before(inode_id: u32, end: usize) -> Result<(), BlockError> {
+    flush_write_buffer_overlapping(inode_id, 0, end)
+}
+
+/// Write the buffered appends of an inode to the disk if they overlap `start..end`
+fn flush_write_buffer_overlapping(
+    inode_id: u32,
+    start: usize,
+    end: usize,
+) -> Result<(), BlockError> {
+    let buffer = {
+        let mut buffers = WRITE_BUFFERS.exclusive_access();
+        match buffers.get(&inode_id) {
+            Some(buffer) if buffer.offset < end && buffer.end() > start => {
+                buffers.remove(&inode_id)
+            }
+            _ => None,
+        }
+    };
+    buffer.map_or(Ok(()), WriteBuffer::flush)
+}
+
+/// Write the buffered appends of an inode to the disk
+pub fn flush_write_buffer(inode_id: u32) -> Result<(), BlockError> {
+    flush_write_buffer_before(inode_id, usize::MAX)
+}
+
+/// Write the buffered appends of every inode to the disk
+///
+/// An inode locked by a blocked task is skipped with a warning, writing to it would
+/// spin on its lock forever.
+pub fn flush_all_write_buffers() -> Result<(), BlockError> {
+    let inode_ids: Vec<u32> = WRITE_BUFFERS.exclusive_access().keys().copied().collect();
+    let mut result = Ok(());
+    for inode_id in inode_ids {
+        let busy = WRITE_BUFFERS
+            .exclusive_access()
+            .get(&inode_id)
+            .is_some_and(|buffer| buffer.inode.is_locked());
+        if busy {
+            warn!("[kernel] Inode {inode_id} busy, its buffered writes are not written back");
+            continue;
+        }
+        result = result.and(flush_write_buffer(inode_id));
+    }
+    result
+}
+
+/// Drop the buffered appends of an inode whose data is being thrown away
+pub fn discard_write_buffer(inode_id: u32) {
+    WRITE_BUFFERS.exclusive_access().remove(&inode_id);
+}
+
+/// The time since boot, as the file system reads it
+struct KernelClock;
+
+impl Clock for KernelClock {
+    fn now(&self) -> u64 {
+        get_time_ms() as u64
+    }
+}
+
 lazy_static! {
     pub static ref ROOT_INODE: Arc<Inode> = {
-        let efs = EasyFileSystem::open(&BLOCK_DEVICE);
+        set_cache_mode(BLOCK_CACHE_MODE);
+        let efs = EasyFileSystem::open_with_clock(&BLOCK_DEVICE, Arc::new(KernelClock))
+            .unwrap_or_else(|err| panic!("Failed to mount the root file system: {err}"));
         let root_inode = Arc::new(EasyFileSystem::root_inode(&efs));
         root_inode.set_default_dirent(root_inode.inode_id());
         root_inode
@@ -125,12 +575,16 @@ lazy_static! {
             .create_dir("proc")
             .expect("Failed to create inode for '/proc/'.");
         proc_inode.set_default_dirent(ROOT_INODE.inode_id());
+        for name in PROC_FILES {
+            proc_inode.create(name);
+        }
         proc_inode
     };
 }
 
 bitflags! {
     /// Open file flags
+    #[derive(Clone, Copy)]
     pub struct OpenFlags: u32 {
         /// Read only
         const RDONLY = 0;
@@ -142,6 +596,31 @@ bitflags! {
         const CREATE = 1 << 9;
         /// Clear file and return an empty one
         const TRUNC = 1 << 10;
+        /// Write at the end of file regardless of the offset
+        const APPEND = 1 << 11;
+        /// Return instead of blocking when no progress can be made
+        const NONBLOCK = 1 << 12;
+        /// Fail if the final component of the path is a symbolic link
+        const NOFOLLOW = 1 << 17;
+        /// Close the file descriptor on `exec`
+        const CLOEXEC = 1 << 19;
+        /// Only refer to the file, for `fstat`, `fchdir` and as the `dirfd` of the `*at`
+        /// calls, without reading or writing it
+        const PATH = 1 << 21;
+    }
+}
+
+impl OpenFlags {
+    /// Status flags that can be changed after a file is opened
+    pub const SETTABLE: Self = Self::APPEND.union(Self::NONBLOCK);
+
+    /// Access mode flags for a file with the given permissions
+    pub fn access_mode(readable: bool, writable: bool) -> Self {
+        match (readable, writable) {
+            (true, true) => Self::RDWR,
+            (false, true) => Self::WRONLY,
+            _ => Self::RDONLY,
+        }
     }
 }
 
@@ -155,3 +634,31 @@ pub fn find(path: &str) -> Option<Arc<Inode>> {
         }
     })
 }
+
+/// The absolute path of a directory, found by walking up its `..` entries
+///
+/// Returns `None` if the directory or one above it has been removed.
+pub fn path_of(dir: &Arc<Inode>) -> Option<String> {
+    let mut names = Vec::new();
+    let mut dir = dir.clone();
+    loop {
+        let parent = dir.find("..")?;
+        if parent.inode_id() == dir.inode_id() {
+            break;
+        }
+        let entry = parent.entries().into_iter().find(|entry| {
+            entry.inode_number() == dir.inode_id() && !matches!(entry.name(), "." | "..")
+        })?;
+        names.push(String::from(entry.name()));
+        dir = parent;
+    }
+    if names.is_empty() {
+        return Some(String::from("/"));
+    }
+    Some(
+        names
+            .iter()
+            .rev()
+            .fold(String::new(), |path, name| path + "/" + name),
+    )
+}
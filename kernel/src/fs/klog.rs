@@ -0,0 +1,122 @@
+//! Kernel log file sink
+//!
+//! Lines from [`crate::logging`] are appended to [`LOG_FILE`] once the file
+//! system is up. When the file would grow past [`LOG_FILE_MAX_SIZE`] its
+//! contents move to [`OLD_LOG_FILE`] and it starts over.
+
+use super::inode::ROOT_INODE;
+use crate::{config::LOG_FILE_MAX_SIZE, logging, sync::UPIntrFreeCell, DEV_NON_BLOCKING_ACCESS};
+use alloc::{sync::Arc, vec};
+use easy_fs::Inode;
+use lazy_static::lazy_static;
+
+/// Directory holding the log files
+const LOG_DIR: [&str; 2] = ["var", "log"];
+/// Name of the current log file
+const LOG_FILE: &str = "kernel";
+/// Name of the rotated log file
+const OLD_LOG_FILE: &str = "kernel.old";
+
+/// The log directory and the current log file in it
+struct LogFiles {
+    dir: Arc<Inode>,
+    file: Arc<Inode>,
+}
+
+lazy_static! {
+    /// The log files, set once the file system is up so no sync has to look them up
+    static ref LOG_FILES: UPIntrFreeCell<Option<LogFiles>> =
+        unsafe { UPIntrFreeCell::new(None) };
+}
+
+/// Find or create the log files and write out the lines buffered during boot
+pub fn init() {
+    let dir = LOG_DIR.iter().fold(ROOT_INODE.clone(), |parent, name| {
+        parent.find(name).unwrap_or_else(|| {
+            let dir = parent
+                .create_dir(name)
+                .expect("Failed to create the kernel log directory.");
+            dir.set_default_dirent(parent.inode_id());
+            dir
+        })
+    });
+    let file = dir
+        .find(LOG_FILE)
+        .or_else(|| dir.create(LOG_FILE))
+        .expect("Failed to create the kernel log file.");
+    *LOG_FILES.exclusive_access() = Some(LogFiles { dir, file });
+    sync();
+}
+
+/// Append the pending log lines to the log file
+///
+/// The device is accessed by polling, so this may be called with a task's
+/// resources borrowed but not with the file system itself in use by the caller.
+/// A task blocked on the device may hold the log files or the file system,
+/// spinning on them would never let it run again, so the lines are then left
+/// pending for a later call.
+pub fn sync() {
+    let Some((dir, file)) = LOG_FILES
+        .exclusive_access()
+        .as_ref()
+        .map(|log| (log.dir.clone(), log.file.clone()))
+    else {
+        return;
+    };
+    if dir.is_locked() || file.is_locked() {
+        return;
+    }
+    logging::drain(|data| {
+        let nb = core::mem::replace(&mut *DEV_NON_BLOCKING_ACCESS.exclusive_access(), false);
+        append(&dir, &file, data);
+        *DEV_NON_BLOCKING_ACCESS.exclusive_access() = nb;
+    });
+}
+
+/// Append `data` to the log `file` in `dir`, rotating it first if it would grow too large
+fn append(dir: &Arc<Inode>, file: &Arc<Inode>, data: &[u8]) {
+    let mut size = file.file_size() as usize;
+    if size > 0 && size + data.len() > LOG_FILE_MAX_SIZE {
+        rotate(dir, file);
+        size = 0;
+    }
+    file.write_at(size, data);
+}
+
+/// Move the contents of the log file to the old log file
+fn rotate(dir: &Arc<Inode>, file: &Arc<Inode>) {
+    if let Some(old) = dir.find(OLD_LOG_FILE).or_else(|| dir.create(OLD_LOG_FILE)) {
+        let mut data = vec![0u8; file.file_size() as usize];
+        let len = file.read_at(0, &mut data);
+        old.clear();
+        old.write_at(0, &data[..len]);
+    }
+    file.clear();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{test, test_assert};
+
+    test!(test_log_rotation, {
+        let (dir, file) = LOG_FILES
+            .exclusive_access()
+            .as_ref()
+            .map(|log| (log.dir.clone(), log.file.clone()))
+            .unwrap();
+        file.clear();
+
+        let chunk = [b'x'; 4096];
+        for _ in 0..LOG_FILE_MAX_SIZE / chunk.len() {
+            append(&dir, &file, &chunk);
+        }
+        test_assert!(file.file_size() as usize == LOG_FILE_MAX_SIZE);
+
+        append(&dir, &file, b"rotated\n");
+        test_assert!(file.file_size() as usize == b"rotated\n".len());
+        let old = dir.find(OLD_LOG_FILE).unwrap();
+        test_assert!(old.file_size() as usize == LOG_FILE_MAX_SIZE);
+        Ok("passed")
+    });
+}
@@ -0,0 +1,164 @@
+use super::{File, OpenFlags, StatMode};
+use crate::{
+    config::PAGE_SIZE,
+    mm::{frame_allocator, FrameTracker, UserBuffer},
+    sync::UPIntrFreeCell,
+};
+use alloc::{string::String, vec::Vec};
+
+/// An anonymous file kept in memory rather than on the block device.
+///
+/// The data lives in frames of its own, allocated as the file grows and zeroed, so the
+/// gap left by writing past the end reads back as zeros. Every descriptor of the file,
+/// including those inherited across `fork`, shares its data and offset, and the frames
+/// go back to the allocator once the last one is closed.
+pub struct MemFd {
+    inner: UPIntrFreeCell<MemFdInner>,
+}
+
+struct MemFdInner {
+    offset: usize,
+    size: usize,
+    status: OpenFlags,
+    frames: Vec<FrameTracker>,
+}
+
+impl MemFd {
+    /// Creates an empty file.
+    pub fn new() -> Self {
+        Self {
+            inner: unsafe {
+                UPIntrFreeCell::new(MemFdInner {
+                    offset: 0,
+                    size: 0,
+                    status: OpenFlags::empty(),
+                    frames: Vec::new(),
+                })
+            },
+        }
+    }
+}
+
+impl MemFdInner {
+    /// Truncates or extends the file to `len` bytes.
+    ///
+    /// Returns `false`, leaving the file unchanged, if there are not enough free frames.
+    fn resize(&mut self, len: usize) -> bool {
+        if u32::try_from(len).is_err() {
+            return false;
+        }
+        let pages = len.div_ceil(PAGE_SIZE);
+        if pages > self.frames.len() {
+            let old_pages = self.frames.len();
+            while self.frames.len() < pages {
+                let Some(frame) = frame_allocator::alloc() else {
+                    self.frames.truncate(old_pages);
+                    return false;
+                };
+                self.frames.push(frame);
+            }
+        } else {
+            self.frames.truncate(pages);
+            // the bytes cut off the last page must read back as zeros if the file grows again
+            if len < self.size && !len.is_multiple_of(PAGE_SIZE) {
+                let end = self.size.min(pages * PAGE_SIZE) - (pages - 1) * PAGE_SIZE;
+                self.frames[pages - 1].ppn.as_mut_bytes_array()[len % PAGE_SIZE..end].fill(0);
+            }
+        }
+        self.size = len;
+        true
+    }
+
+    /// The bytes of the page holding `pos`, from `pos` up to at most `len`
+    fn chunk(&self, pos: usize, len: usize) -> &'static mut [u8] {
+        let start = pos % PAGE_SIZE;
+        let end = PAGE_SIZE.min(start + len);
+        &mut self.frames[pos / PAGE_SIZE].ppn.as_mut_bytes_array()[start..end]
+    }
+}
+
+impl File for MemFd {
+    fn is_readable(&self) -> bool {
+        true
+    }
+
+    fn is_writable(&self) -> bool {
+        true
+    }
+
+    fn read(&self, mut buf: UserBuffer) -> usize {
+        let mut inner = self.inner.exclusive_access();
+        let start = inner.offset;
+        for slice in &mut buf.buffers {
+            let mut copied = 0;
+            while copied < slice.len() && inner.offset < inner.size {
+                let len = (slice.len() - copied).min(inner.size - inner.offset);
+                let src = inner.chunk(inner.offset, len);
+                slice[copied..copied + src.len()].copy_from_slice(src);
+                copied += src.len();
+                inner.offset += src.len();
+            }
+        }
+        inner.offset - start
+    }
+
+    /// Writes at the offset, or at the end with [`OpenFlags::APPEND`], growing the file.
+    ///
+    /// Returns `0` without writing anything if there are not enough free frames to grow.
+    fn write(&self, buf: UserBuffer) -> usize {
+        let mut inner = self.inner.exclusive_access();
+        if inner.status.contains(OpenFlags::APPEND) {
+            inner.offset = inner.size;
+        }
+        let end = inner.offset + buf.len();
+        if end > inner.size && !inner.resize(end) {
+            return 0;
+        }
+        for slice in &buf.buffers {
+            let mut copied = 0;
+            while copied < slice.len() {
+                let dst = inner.chunk(inner.offset, slice.len() - copied);
+                dst.copy_from_slice(&slice[copied..copied + dst.len()]);
+                copied += dst.len();
+                inner.offset += dst.len();
+            }
+        }
+        buf.len()
+    }
+
+    fn status_flags(&self) -> OpenFlags {
+        OpenFlags::RDWR | self.inner.exclusive_access().status
+    }
+
+    fn set_status_flags(&self, flags: OpenFlags) {
+        self.inner.exclusive_access().status = flags & OpenFlags::SETTABLE;
+    }
+
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn set_len(&self, len: usize) -> bool {
+        self.inner.exclusive_access().resize(len)
+    }
+
+    fn offset(&self) -> usize {
+        self.inner.exclusive_access().offset
+    }
+
+    fn set_offset(&self, offset: usize) {
+        self.inner.exclusive_access().offset = offset;
+    }
+
+    fn file_size(&self) -> u32 {
+        self.inner.exclusive_access().size as u32
+    }
+
+    fn mode(&self) -> StatMode {
+        StatMode::REG
+    }
+
+    fn describe(&self) -> String {
+        String::from("memfd:")
+    }
+}
@@ -1,13 +1,24 @@
 //! File system
 
+pub mod eventfd;
 pub mod inode;
+pub mod klog;
+pub mod memfd;
+pub mod pidfd;
 pub mod pipe;
+pub mod proc;
+#[cfg(all(test, feature = "fs_test"))]
+mod selftest;
 pub mod stdio;
 
-use crate::mm::UserBuffer;
+use crate::{mm::UserBuffer, DEV_NON_BLOCKING_ACCESS};
 use alloc::{string::String, sync::Arc, vec::Vec};
 use bitflags::bitflags;
+use easy_fs::{CreateError, DirEntry, EasyFileSystem, NameError, BLOCK_SIZE, NAME_LENGTH_LIMIT};
 use inode::OSInode;
+use log::warn;
+use pidfd::PidFd;
+use pipe::Pipe;
 
 pub use inode::{OpenFlags, PROC_INODE};
 pub use stdio::{Stdin, Stdout};
@@ -15,6 +26,11 @@ pub use stdio::{Stdin, Stdout};
 /// File trait
 pub trait File: Send + Sync {
     /// Read file to `UserBuffer`
+    ///
+    /// Returns the number of bytes read, `0` meaning the end of the file. Files with an end
+    /// of their own, regular files among them, return `0` there at once rather than block,
+    /// so a program reading one until `0` terminates. Pipes and the console wait for data
+    /// instead: a pipe reaches its end only once no write end is left, the console never.
     fn read(&self, buf: UserBuffer) -> usize;
     /// Write `UserBuffer` to file
     fn write(&self, buf: UserBuffer) -> usize;
@@ -22,11 +38,58 @@ pub trait File: Send + Sync {
     fn is_readable(&self) -> bool;
     /// If writable
     fn is_writable(&self) -> bool;
+    /// Status flags of the open file, including its access mode
+    fn status_flags(&self) -> OpenFlags {
+        OpenFlags::access_mode(self.is_readable(), self.is_writable())
+    }
+    /// Update the flags in [`OpenFlags::SETTABLE`], others are ignored
+    fn set_status_flags(&self, _flags: OpenFlags) {}
+    /// Write buffered data back to the device
+    fn sync(&self) {}
+    /// Whether an I/O error occurred since the last call, reported in place of a byte count
+    fn take_io_error(&self) -> bool {
+        false
+    }
+    /// Whether a write ran out of space on the device since the last call
+    fn take_no_space(&self) -> bool {
+        false
+    }
+    /// The regular file behind this file, used to copy data without going through user memory
+    fn as_os_inode(&self) -> Option<&OSInode> {
+        None
+    }
+    /// The process behind this file, if it is a pidfd
+    fn as_pidfd(&self) -> Option<&PidFd> {
+        None
+    }
+    /// The pipe end behind this file, used to copy between pipes without consuming data
+    fn as_pipe(&self) -> Option<&Pipe> {
+        None
+    }
+    /// Which of `events` the file is ready for, plus any error or hang-up
+    fn poll(&self, events: PollEvents) -> PollEvents {
+        let mut ready = PollEvents::empty();
+        if self.is_readable() {
+            ready |= PollEvents::IN;
+        }
+        if self.is_writable() {
+            ready |= PollEvents::OUT;
+        }
+        ready & events
+    }
     fn offset(&self) -> usize {
         0
     }
     #[allow(unused)]
     fn set_offset(&self, _offset: usize) {}
+    /// Whether the offset can be moved, pipes and terminals have none
+    fn is_seekable(&self) -> bool {
+        false
+    }
+    /// Truncate or extend the file to `len` bytes, `false` if it cannot be resized
+    fn set_len(&self, _len: usize) -> bool {
+        false
+    }
     fn file_size(&self) -> u32 {
         0
     }
@@ -36,6 +99,18 @@ pub trait File: Send + Sync {
     fn mode(&self) -> StatMode {
         StatMode::NULL
     }
+    /// Whether the file is a terminal rather than a regular file or pipe
+    fn is_tty(&self) -> bool {
+        self.mode() == StatMode::CHR
+    }
+    /// What the file refers to, as listed in `/proc/<pid>/fd`
+    fn describe(&self) -> String {
+        String::from("anon_inode")
+    }
+    /// Directory entries of a directory the kernel generates, advancing past them
+    fn read_dirents(&self, _count: usize) -> Option<Vec<DirEntry>> {
+        None
+    }
 }
 
 #[repr(C)]
@@ -60,16 +135,74 @@ impl From<Arc<dyn File + Send + Sync>> for Stat {
     }
 }
 
+/// Usage figures of a file system, as `statfs` and `fstatfs` report them
+#[repr(C)]
+#[derive(Default)]
+pub struct Statfs {
+    /// Size of a block in bytes
+    pub block_size: usize,
+    /// Blocks in the data area
+    pub blocks: usize,
+    /// Blocks in the data area not allocated to any file
+    pub free_blocks: usize,
+    /// Inodes the file system has room for
+    pub files: usize,
+    /// Inodes not in use
+    pub free_files: usize,
+    /// Longest name a directory entry can hold
+    pub name_max: usize,
+}
+
+impl From<&EasyFileSystem> for Statfs {
+    fn from(fs: &EasyFileSystem) -> Self {
+        let files = fs.inode_bitmap.maximum();
+        Self {
+            block_size: BLOCK_SIZE,
+            blocks: fs.data_area_blocks() as usize,
+            free_blocks: fs.free_data_blocks() as usize,
+            files,
+            free_files: files - fs.inode_bitmap.count_allocated(&fs.block_device),
+            name_max: NAME_LENGTH_LIMIT,
+        }
+    }
+}
+
 bitflags! {
     #[derive(PartialEq, Eq, Default)]
     pub struct StatMode: u32 {
         const NULL = 0;
+        const CHR = 0o020_000;
         const DIR = 0o040_000;
         const REG = 0o100_000;
         const LNK = 0o120_000;
     }
 }
 
+bitflags! {
+    /// Events a file can be polled for
+    #[derive(Clone, Copy, PartialEq, Eq, Default)]
+    pub struct PollEvents: u16 {
+        /// There is data to read
+        const IN = 1;
+        /// Writing would not block
+        const OUT = 1 << 2;
+        /// An error occurred, always reported
+        const ERR = 1 << 3;
+        /// The other end was closed, always reported
+        const HUP = 1 << 4;
+        /// The file descriptor is not open, always reported
+        const NVAL = 1 << 5;
+    }
+}
+
+/// A file descriptor to poll and the events it is ready for
+#[repr(C)]
+pub struct PollFd {
+    pub fd: i32,
+    pub events: PollEvents,
+    pub revents: PollEvents,
+}
+
 /// Calculate the absolute path of the input path
 pub fn get_full_path(cwd: &str, path: &str) -> String {
     let resolved_path = if path.starts_with('/') {
@@ -95,35 +228,81 @@ pub fn get_full_path(cwd: &str, path: &str) -> String {
     String::from("/") + &parts.join("/")
 }
 
+/// The value a syscall returns for a file or directory that could not be created
+pub fn create_error_code(err: CreateError) -> isize {
+    match err {
+        CreateError::Exists => -17,
+        CreateError::InvalidName(NameError::TooLong(_)) => -36,
+        CreateError::InvalidName(_) => -22,
+        CreateError::NoSpace => -28,
+        CreateError::DirectoryFull => -31,
+    }
+}
+
 /// Open file with flags
+///
+/// Fails with `-1` if the file, or the directory it would be created in, does not exist,
+/// and with the [`create_error_code`] of a file that could not be created.
 #[allow(clippy::needless_pass_by_value)]
-pub fn open_file(path: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
+pub fn open_file(path: &str, flags: OpenFlags) -> Result<Arc<OSInode>, isize> {
     let readable = flags.contains(OpenFlags::RDONLY) || flags.contains(OpenFlags::RDWR);
     let writable = flags.contains(OpenFlags::WRONLY) || flags.contains(OpenFlags::RDWR);
 
-    if flags.contains(OpenFlags::CREATE) {
+    if flags.contains(OpenFlags::PATH) {
+        // the file is only referred to, it is never created or truncated
+        inode::find(path)
+            .map(|inode| Arc::new(OSInode::new(false, false, inode, path)))
+            .ok_or(-1)
+    } else if flags.contains(OpenFlags::CREATE) {
         if let Some(inode) = inode::find(path) {
             if inode.is_file() {
                 // clear size
+                inode::discard_write_buffer(inode.inode_id());
                 inode.clear();
             }
-            Some(Arc::new(OSInode::new(readable, writable, inode)))
+            Ok(Arc::new(OSInode::new(readable, writable, inode, path)))
         } else {
             let (parent_path, target) = match path.rsplit_once('/') {
                 Some((parent_path, target)) => (parent_path, target),
                 None => ("", path),
             };
-            let parent_inode = inode::find(parent_path)?;
+            let parent_inode = inode::find(parent_path).ok_or(-1)?;
             parent_inode
-                .create(target)
-                .map(|inode| Arc::new(OSInode::new(readable, writable, inode)))
+                .try_create(target)
+                .map(|inode| Arc::new(OSInode::new(readable, writable, inode, path)))
+                .map_err(create_error_code)
         }
     } else {
-        inode::find(path).map(|inode| {
-            if flags.contains(OpenFlags::TRUNC) {
-                inode.clear();
-            }
-            Arc::new(OSInode::new(readable, writable, inode))
-        })
+        inode::find(path)
+            .map(|inode| {
+                if flags.contains(OpenFlags::TRUNC) {
+                    inode::discard_write_buffer(inode.inode_id());
+                    inode.clear();
+                }
+                Arc::new(OSInode::new(readable, writable, inode, path))
+            })
+            .ok_or(-1)
+    }
+}
+
+/// Write everything the kernel still buffers back to the block device, before powering off
+///
+/// The device is polled and every block gets a bounded number of retries, so a failing
+/// device delays the shutdown rather than hanging it. If a blocked task holds the file
+/// system lock nothing can be written safely, and the flush is given up; files it holds
+/// locked are left out of the flush.
+pub fn sync_all() {
+    let fs = inode::ROOT_INODE.fs();
+    if fs.try_lock().is_none() {
+        warn!("[kernel] File system busy, buffered data is not written back");
+        return;
+    }
+    *DEV_NON_BLOCKING_ACCESS.exclusive_access() = false;
+    if inode::flush_all_write_buffers().is_err() {
+        warn!("[kernel] Lost buffered file writes");
+    }
+    klog::sync();
+    if fs.lock().sync().is_err() {
+        warn!("[kernel] Failed to write back the block cache");
     }
 }
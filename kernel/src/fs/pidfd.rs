@@ -0,0 +1,65 @@
+use super::{File, PollEvents};
+use crate::{mm::UserBuffer, task::pcb::ProcessControlBlock};
+use alloc::{
+    string::String,
+    sync::{Arc, Weak},
+};
+
+/// A file referring to one particular process rather than to whichever process holds its PID.
+///
+/// Only a weak reference is kept, so an open pidfd never keeps a reaped process alive, and
+/// once the process is gone its PID may be reused without the pidfd following it. The file
+/// becomes readable for `poll` when the process exits.
+pub struct PidFd {
+    process: Weak<ProcessControlBlock>,
+}
+
+impl PidFd {
+    /// Creates a file referring to `process`.
+    pub fn new(process: &Arc<ProcessControlBlock>) -> Self {
+        Self {
+            process: Arc::downgrade(process),
+        }
+    }
+
+    /// The process, unless it has exited, whether or not it has been reaped yet.
+    pub fn process(&self) -> Option<Arc<ProcessControlBlock>> {
+        self.process
+            .upgrade()
+            .filter(|process| !process.inner_exclusive_access().is_zombie)
+    }
+}
+
+impl File for PidFd {
+    fn read(&self, _buf: UserBuffer) -> usize {
+        0
+    }
+
+    fn write(&self, _buf: UserBuffer) -> usize {
+        0
+    }
+
+    fn is_readable(&self) -> bool {
+        false
+    }
+
+    fn is_writable(&self) -> bool {
+        false
+    }
+
+    fn as_pidfd(&self) -> Option<&PidFd> {
+        Some(self)
+    }
+
+    fn poll(&self, events: PollEvents) -> PollEvents {
+        if self.process().is_none() {
+            PollEvents::IN & events
+        } else {
+            PollEvents::empty()
+        }
+    }
+
+    fn describe(&self) -> String {
+        String::from("anon_inode:[pidfd]")
+    }
+}
@@ -1,12 +1,19 @@
-use alloc::sync::{Arc, Weak};
+use core::sync::atomic::{AtomicUsize, Ordering};
 
-use super::File;
+use alloc::{
+    format,
+    string::String,
+    sync::{Arc, Weak},
+};
+
+use super::{File, OpenFlags, PollEvents};
 use crate::{mm::UserBuffer, sync::UPIntrFreeCell, task::suspend_current_and_run_next};
 
 /// Represents a unidirectional communication pipe with separate read and write ends.
 pub struct Pipe {
     readable: bool,
     writable: bool,
+    status: UPIntrFreeCell<OpenFlags>,
     buffer: Arc<UPIntrFreeCell<PipeRingBuffer>>,
 }
 
@@ -16,6 +23,7 @@ impl Pipe {
         Self {
             readable: true,
             writable: false,
+            status: unsafe { UPIntrFreeCell::new(OpenFlags::empty()) },
             buffer,
         }
     }
@@ -25,9 +33,62 @@ impl Pipe {
         Self {
             readable: false,
             writable: true,
+            status: unsafe { UPIntrFreeCell::new(OpenFlags::empty()) },
             buffer,
         }
     }
+
+    fn is_nonblocking(&self) -> bool {
+        self.status.exclusive_access().contains(OpenFlags::NONBLOCK)
+    }
+
+    /// Copy up to `len` bytes waiting in this read end into the `output` write end, leaving
+    /// them to be read here as well
+    ///
+    /// Waits for data unless this end is non-blocking, and for room unless `output` is.
+    /// Returns `Some(0)` once every write end is closed and nothing is left, `None` if
+    /// nothing could be copied without waiting.
+    pub fn tee(&self, output: &Pipe, len: usize) -> Option<usize> {
+        assert!(self.readable && output.writable);
+        loop {
+            let input_buffer = self.buffer.exclusive_access();
+            let available_to_read = input_buffer.available_to_read();
+            if available_to_read == 0 {
+                if input_buffer.all_write_ends_closed() {
+                    return Some(0);
+                }
+                if self.is_nonblocking() {
+                    return None;
+                }
+                drop(input_buffer);
+                suspend_current_and_run_next();
+                continue;
+            }
+
+            let mut output_buffer = output.buffer.exclusive_access();
+            let available_to_write = output_buffer.available_to_write();
+            if available_to_write == 0 {
+                if output.is_nonblocking() {
+                    return None;
+                }
+                drop(output_buffer);
+                drop(input_buffer);
+                suspend_current_and_run_next();
+                continue;
+            }
+
+            let copied = len.min(available_to_read).min(available_to_write);
+            for i in 0..copied {
+                output_buffer.write_byte(input_buffer.peek_byte(i));
+            }
+            return Some(copied);
+        }
+    }
+
+    /// Whether both pipe ends share one buffer
+    pub fn same_pipe(&self, other: &Pipe) -> bool {
+        Arc::ptr_eq(&self.buffer, &other.buffer)
+    }
 }
 
 impl File for Pipe {
@@ -39,6 +100,31 @@ impl File for Pipe {
         self.writable
     }
 
+    fn status_flags(&self) -> OpenFlags {
+        OpenFlags::access_mode(self.readable, self.writable) | *self.status.exclusive_access()
+    }
+
+    fn set_status_flags(&self, flags: OpenFlags) {
+        *self.status.exclusive_access() = flags & OpenFlags::NONBLOCK;
+    }
+
+    fn poll(&self, events: PollEvents) -> PollEvents {
+        let ring_buffer = self.buffer.exclusive_access();
+        let mut ready = PollEvents::empty();
+        if self.readable {
+            if ring_buffer.available_to_read() > 0 {
+                ready |= PollEvents::IN & events;
+            }
+            if ring_buffer.all_write_ends_closed() {
+                ready |= PollEvents::HUP;
+            }
+        }
+        if self.writable && ring_buffer.available_to_write() > 0 {
+            ready |= PollEvents::OUT & events;
+        }
+        ready
+    }
+
     fn read(&self, mut buf: UserBuffer) -> usize {
         assert!(self.is_readable());
         let want_to_read = buf.len();
@@ -50,7 +136,7 @@ impl File for Pipe {
             let available_to_read = ring_buffer.available_to_read();
 
             if available_to_read == 0 {
-                if ring_buffer.all_write_ends_closed() {
+                if ring_buffer.all_write_ends_closed() || self.is_nonblocking() {
                     return already_read;
                 }
                 drop(ring_buffer);
@@ -83,6 +169,9 @@ impl File for Pipe {
             let available_to_write = ring_buffer.available_to_write();
 
             if available_to_write == 0 {
+                if self.is_nonblocking() {
+                    return already_write;
+                }
                 drop(ring_buffer);
                 suspend_current_and_run_next();
                 continue;
@@ -101,10 +190,21 @@ impl File for Pipe {
             }
         }
     }
+
+    fn as_pipe(&self) -> Option<&Pipe> {
+        Some(self)
+    }
+
+    fn describe(&self) -> String {
+        format!("pipe:[{}]", self.buffer.exclusive_access().id)
+    }
 }
 
 const RING_BUFFER_SIZE: usize = 32;
 
+/// Identifier given to the next pipe, so the two ends can be matched up in `/proc/<pid>/fd`
+static NEXT_PIPE_ID: AtomicUsize = AtomicUsize::new(0);
+
 #[derive(Copy, Clone, PartialEq)]
 enum RingBufferStatus {
     Full,
@@ -120,6 +220,7 @@ pub struct PipeRingBuffer {
     tail: usize,
     status: RingBufferStatus,
     write_end: Option<Weak<Pipe>>,
+    id: usize,
 }
 
 impl PipeRingBuffer {
@@ -130,6 +231,7 @@ impl PipeRingBuffer {
             tail: 0,
             status: RingBufferStatus::Empty,
             write_end: None,
+            id: NEXT_PIPE_ID.fetch_add(1, Ordering::Relaxed),
         }
     }
 
@@ -156,6 +258,11 @@ impl PipeRingBuffer {
         c
     }
 
+    /// The byte `skip` bytes past the next one to be read, which stays in the buffer
+    pub fn peek_byte(&self, skip: usize) -> u8 {
+        self.arr[(self.head + skip) % RING_BUFFER_SIZE]
+    }
+
     pub fn available_to_read(&self) -> usize {
         if self.status == RingBufferStatus::Empty {
             0
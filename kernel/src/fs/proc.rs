@@ -0,0 +1,174 @@
+//! Files under `/proc` generated by the kernel
+
+use super::{File, StatMode};
+use crate::{
+    drivers::stats,
+    mm::UserBuffer,
+    sync::UPIntrFreeCell,
+    task::{pcb::ProcessControlBlock, pid2process},
+};
+use alloc::{
+    format,
+    string::ToString,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use easy_fs::{DirEntry, DirEntryType};
+
+/// Names of the generated files, created as placeholders in `/proc` so they can be listed
+pub const PROC_FILES: &[&str] = &["interrupts"];
+
+/// A read-only file holding a snapshot taken when it was opened
+pub struct ProcFile {
+    data: Vec<u8>,
+    offset: UPIntrFreeCell<usize>,
+}
+
+impl ProcFile {
+    fn new(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            offset: unsafe { UPIntrFreeCell::new(0) },
+        }
+    }
+}
+
+/// The `/proc/<pid>/fd` directory, listing the descriptors open when it is read
+///
+/// Only the process is referred to, so an open listing neither keeps it alive nor sees a
+/// later process reusing its PID. Its offset is the lowest descriptor not listed yet.
+pub struct ProcFdDir {
+    process: Weak<ProcessControlBlock>,
+    next_fd: UPIntrFreeCell<usize>,
+}
+
+/// Open the generated file at the absolute `path`, if there is one
+///
+/// Under `/proc/<pid>/fd` every open descriptor of the process has an entry named after
+/// it, holding a description of the file it refers to.
+pub fn open_proc_file(path: &str) -> Option<Arc<dyn File + Send + Sync>> {
+    let data = match path {
+        "/proc/interrupts" => stats::render(),
+        _ => return open_fd_file(path),
+    };
+    Some(Arc::new(ProcFile::new(data.into_bytes())))
+}
+
+/// Whether `path` is inside a generated directory, where nothing exists on disk
+pub fn is_generated(path: &str) -> bool {
+    let Some(rest) = path.strip_prefix("/proc/") else {
+        return false;
+    };
+    let mut parts = rest.split('/');
+    parts.next().is_some_and(|pid| pid.parse::<usize>().is_ok())
+        && parts.next() == Some("fd")
+        && parts.next().is_some()
+}
+
+fn open_fd_file(path: &str) -> Option<Arc<dyn File + Send + Sync>> {
+    let mut parts = path.strip_prefix("/proc/")?.split('/');
+    let pid = parts.next()?.parse().ok()?;
+    if parts.next()? != "fd" {
+        return None;
+    }
+    let process = pid2process(pid)?;
+    let Some(fd) = parts.next() else {
+        return Some(Arc::new(ProcFdDir {
+            process: Arc::downgrade(&process),
+            next_fd: unsafe { UPIntrFreeCell::new(0) },
+        }));
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    let fd_table = process.inner_exclusive_access().fd_table.clone();
+    // the descriptor may have been closed since it was listed
+    let file = fd_table.get(fd.parse().ok()?)?;
+    let data = format!("{}\n", file.describe());
+    Some(Arc::new(ProcFile::new(data.into_bytes())))
+}
+
+impl File for ProcFile {
+    fn is_readable(&self) -> bool {
+        true
+    }
+
+    fn is_writable(&self) -> bool {
+        false
+    }
+
+    fn read(&self, mut buf: UserBuffer) -> usize {
+        let mut offset = self.offset.exclusive_access();
+        let start = *offset;
+        for (p, &byte) in buf.iter_mut().zip(&self.data[start..]) {
+            unsafe { *p = byte };
+            *offset += 1;
+        }
+        *offset - start
+    }
+
+    fn write(&self, _buf: UserBuffer) -> usize {
+        0
+    }
+
+    fn offset(&self) -> usize {
+        *self.offset.exclusive_access()
+    }
+
+    fn file_size(&self) -> u32 {
+        self.data.len() as u32
+    }
+
+    fn mode(&self) -> StatMode {
+        StatMode::REG
+    }
+}
+
+impl File for ProcFdDir {
+    fn is_readable(&self) -> bool {
+        true
+    }
+
+    fn is_writable(&self) -> bool {
+        false
+    }
+
+    fn read(&self, _buf: UserBuffer) -> usize {
+        0
+    }
+
+    fn write(&self, _buf: UserBuffer) -> usize {
+        0
+    }
+
+    fn read_dirents(&self, count: usize) -> Option<Vec<DirEntry>> {
+        let mut next_fd = self.next_fd.exclusive_access();
+        // an exited process has no descriptors left to list
+        let Some(process) = self.process.upgrade() else {
+            return Some(Vec::new());
+        };
+        let fd_table = process.inner_exclusive_access().fd_table.clone();
+        let fds: Vec<usize> = fd_table
+            .open_fds()
+            .into_iter()
+            .filter(|&fd| fd >= *next_fd)
+            .take(count)
+            .collect();
+        if let Some(&fd) = fds.last() {
+            *next_fd = fd + 1;
+        }
+        Some(
+            fds.into_iter()
+                .map(|fd| DirEntry::new(&fd.to_string(), fd as u32, DirEntryType::File))
+                .collect(),
+        )
+    }
+
+    fn offset(&self) -> usize {
+        *self.next_fd.exclusive_access()
+    }
+
+    fn mode(&self) -> StatMode {
+        StatMode::DIR
+    }
+}
@@ -0,0 +1,173 @@
+//! File system self-tests
+//!
+//! Built into the unit test kernel with the `fs_test` feature. They run
+//! against the mounted root file system on [`BLOCK_DEVICE`] inside a scratch
+//! directory that is removed again, so the image is left as it was found.
+
+use super::{
+    inode::{OSInode, ROOT_INODE},
+    File,
+};
+use crate::drivers::BLOCK_DEVICE;
+use crate::mm::UserBuffer;
+use crate::{test, test_assert};
+use alloc::{sync::Arc, vec, vec::Vec};
+use easy_fs::{EasyFileSystem, Inode, BLOCK_SIZE};
+
+/// Scratch directory the tests work in
+const SCRATCH_DIR: &str = "fs_selftest";
+/// Last block reached through the direct, single and double indirect index levels
+const LEVEL_BLOCKS: [usize; 3] = [27, 27 + 128, 27 + 128 + 300];
+
+fn scratch_dir() -> Arc<Inode> {
+    ROOT_INODE
+        .find(SCRATCH_DIR)
+        .or_else(|| ROOT_INODE.create_dir(SCRATCH_DIR))
+        .expect("Failed to create the self-test directory.")
+}
+
+fn remove(dir: &Inode, name: &str) {
+    if let Some(file) = dir.find(name) {
+        file.clear();
+        dir.delete(name);
+    }
+}
+
+fn remove_scratch_dir() {
+    remove(&ROOT_INODE, SCRATCH_DIR);
+}
+
+/// Bytes that differ per block, so misplaced blocks are noticed
+fn pattern(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i / BLOCK_SIZE + i) as u8).collect()
+}
+
+test!(test_fs_index_levels, {
+    let dir = scratch_dir();
+    for blocks in LEVEL_BLOCKS {
+        let file = dir.create("levels").unwrap();
+        let data = pattern(blocks * BLOCK_SIZE + BLOCK_SIZE / 2);
+        test_assert!(file.write_at(0, &data) == data.len());
+
+        let mut read_back = vec![0u8; data.len()];
+        test_assert!(file.read_at(0, &mut read_back) == data.len());
+        test_assert!(read_back == data, "Data read back differs");
+
+        remove(&dir, "levels");
+        test_assert!(dir.find("levels").is_none());
+    }
+    remove_scratch_dir();
+    Ok("passed")
+});
+
+test!(test_fs_full, {
+    let dir = scratch_dir();
+    let file = dir.create("full").unwrap();
+    let chunk = vec![0x5au8; 256 * BLOCK_SIZE];
+    let mut size = 0;
+    // narrow the writes down so the last free block gets used
+    for len in [chunk.len(), BLOCK_SIZE, 1] {
+        loop {
+            let written = file.write_at(size, &chunk[..len]);
+            size += written;
+            if written < len {
+                break;
+            }
+        }
+    }
+    test_assert!(file.file_size() as usize == size);
+    test_assert!(file.write_at(size, b"more") == 0);
+    test_assert!(!file.set_len((size + BLOCK_SIZE) as u32));
+    test_assert!(dir.create_dir("no_room").is_none());
+    test_assert!(dir.find("no_room").is_none());
+
+    // freeing the file makes room again
+    remove(&dir, "full");
+    let file = dir.create("full").unwrap();
+    test_assert!(file.write_at(0, &chunk) == chunk.len());
+    remove(&dir, "full");
+    remove_scratch_dir();
+    Ok("passed")
+});
+
+test!(test_fs_reopen, {
+    let dir = scratch_dir();
+    let file = dir.create("persist").unwrap();
+    let data = pattern(3 * BLOCK_SIZE + 7);
+    test_assert!(file.write_at(0, &data) == data.len());
+
+    let efs = EasyFileSystem::open(&BLOCK_DEVICE).expect("Failed to reopen the file system.");
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    let file = root_inode
+        .find(SCRATCH_DIR)
+        .and_then(|dir| dir.find("persist"));
+    test_assert!(file.is_some(), "File lost after reopening");
+    let file = file.unwrap();
+    let mut read_back = vec![0u8; data.len()];
+    test_assert!(file.read_at(0, &mut read_back) == data.len());
+    test_assert!(read_back == data, "Data lost after reopening");
+
+    remove(&dir, "persist");
+    remove_scratch_dir();
+    Ok("passed")
+});
+
+/// Size of `name` in the scratch directory as a freshly opened file system sees it
+fn size_on_disk(name: &str) -> Option<u32> {
+    let efs = EasyFileSystem::open(&BLOCK_DEVICE).expect("Failed to reopen the file system.");
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    root_inode
+        .find(SCRATCH_DIR)
+        .and_then(|dir| dir.find(name))
+        .map(|file| file.file_size())
+}
+
+test!(test_fs_sync_on_drop, {
+    let dir = scratch_dir();
+    let inode = dir.create("unsynced").unwrap();
+    let writer = OSInode::new(false, true, inode.clone(), "");
+    let reader = OSInode::new(true, false, inode, "");
+    let mut data = *b"appended without fsync";
+    test_assert!(writer.write(unsafe { UserBuffer::from_kernel(&mut data) }) == data.len());
+    test_assert!(
+        size_on_disk("unsynced") == Some(0),
+        "Small appends were not buffered"
+    );
+
+    // closing a read-only file leaves the buffer alone
+    drop(reader);
+    test_assert!(size_on_disk("unsynced") == Some(0));
+    drop(writer);
+    test_assert!(
+        size_on_disk("unsynced") == Some(data.len() as u32),
+        "Buffered appends lost when the file was closed"
+    );
+
+    remove(&dir, "unsynced");
+    remove_scratch_dir();
+    Ok("passed")
+});
+
+test!(test_fs_sync_all, {
+    let dir = scratch_dir();
+    let inode = dir.create("shutdown").unwrap();
+    let writer = OSInode::new(false, true, inode, "");
+    let mut data = *b"written just before powering off";
+    test_assert!(writer.write(unsafe { UserBuffer::from_kernel(&mut data) }) == data.len());
+    test_assert!(
+        size_on_disk("shutdown") == Some(0),
+        "Small appends were not buffered"
+    );
+
+    // the file is still open, as it would be in a process alive at shutdown
+    super::sync_all();
+    test_assert!(
+        size_on_disk("shutdown") == Some(data.len() as u32),
+        "Buffered appends lost at shutdown"
+    );
+
+    drop(writer);
+    remove(&dir, "shutdown");
+    remove_scratch_dir();
+    Ok("passed")
+});
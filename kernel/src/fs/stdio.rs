@@ -1,9 +1,11 @@
+use alloc::string::String;
+
 use crate::{
     drivers::{chardev::CharDevice, UART},
     mm::UserBuffer,
 };
 
-use super::File;
+use super::{File, PollEvents, StatMode};
 
 ///Standard input
 pub struct Stdin;
@@ -20,18 +22,40 @@ impl File for Stdin {
         false
     }
 
+    /// Blocks for the first byte, then takes whatever else has already arrived
     fn read(&self, mut user_buf: UserBuffer) -> usize {
-        assert_eq!(user_buf.len(), 1);
-        let ch = UART.read();
-        unsafe {
-            user_buf.buffers[0].as_mut_ptr().write_volatile(ch);
+        let mut read_size = 0;
+        for p in user_buf.iter_mut() {
+            if read_size > 0 && UART.is_read_buffer_empty() {
+                break;
+            }
+            unsafe {
+                p.write_volatile(UART.read());
+            }
+            read_size += 1;
         }
-        1
+        read_size
     }
 
     fn write(&self, _user_buf: UserBuffer) -> usize {
         panic!("Cannot write to stdin!");
     }
+
+    fn poll(&self, events: PollEvents) -> PollEvents {
+        if UART.is_read_buffer_empty() {
+            PollEvents::empty()
+        } else {
+            PollEvents::IN & events
+        }
+    }
+
+    fn mode(&self) -> StatMode {
+        StatMode::CHR
+    }
+
+    fn describe(&self) -> String {
+        String::from("console")
+    }
 }
 
 impl File for Stdout {
@@ -53,4 +77,12 @@ impl File for Stdout {
         }
         user_buf.len()
     }
+
+    fn mode(&self) -> StatMode {
+        StatMode::CHR
+    }
+
+    fn describe(&self) -> String {
+        String::from("console")
+    }
 }
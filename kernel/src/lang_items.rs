@@ -1,20 +1,144 @@
 //! # The Panic Handler
+//!
+//! Besides the message, the handler dumps the running thread, the registers saved by the
+//! trap being handled and a backtrace of the kernel stack. It prints straight to the UART
+//! and only peeks at cells nobody is changing, so neither a lock held by the panicking
+//! code nor a double panic can keep the dump from coming out.
 
-use crate::sbi::shutdown;
-use core::panic::PanicInfo;
-use log::error;
+use crate::{
+    console::{flush, print_unlocked},
+    sbi::shutdown,
+    task::peek_current_tcb,
+    trap::kernel_trap_frame,
+};
+use core::{
+    arch::asm,
+    panic::PanicInfo,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Most frames the backtrace walks up
+const BACKTRACE_DEPTH: usize = 16;
+
+/// ABI names of `x0` to `x31`
+const REGISTER_NAMES: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+/// Set once a panic is being reported, a panic while dumping only prints its message
+static PANICKING: AtomicBool = AtomicBool::new(false);
+
+macro_rules! dump {
+    ($fmt: literal $(, $($arg: tt)+)?) => {
+        print_unlocked(format_args!(concat!($fmt, "\n") $(, $($arg)+)?))
+    };
+}
 
 #[panic_handler]
 fn panic_handler(info: &PanicInfo) -> ! {
     if let Some(location) = info.location() {
-        error!(
-            "[kernel] Panicked at {}:{} {}",
+        dump!(
+            "\u{1B}[31m[kernel] Panicked at {}:{} {}\u{1B}[0m",
             location.file(),
             location.line(),
             info.message(),
         );
     } else {
-        error!("[kernel] Panicked: {}", info.message());
+        dump!("\u{1B}[31m[kernel] Panicked: {}\u{1B}[0m", info.message());
     }
+    if !PANICKING.swap(true, Ordering::AcqRel) {
+        dump_task();
+        if let Some(frame) = kernel_trap_frame() {
+            // traps leave `tp` alone, so the live one is the one interrupted
+            let mut x = frame.x;
+            x[2] = frame.sp();
+            unsafe { asm!("mv {}, tp", out(reg) x[4]) };
+            dump!("[kernel] registers at the kernel trap:");
+            dump_registers(&x, frame.sepc);
+        }
+        backtrace();
+    }
+    flush();
     shutdown(true)
 }
+
+/// Print the running thread and the user registers saved when it last trapped
+fn dump_task() {
+    let dumped = peek_current_tcb(|task| {
+        match task.process.upgrade() {
+            Some(process) => dump!("[kernel] running pid {}", process.pid.0),
+            None => dump!("[kernel] running a thread of an exited process"),
+        }
+        let Some(inner) = task.try_inner_peek() else {
+            dump!("[kernel] thread state is being changed");
+            return;
+        };
+        let Some(res) = inner.res.as_ref() else {
+            dump!("[kernel] thread has exited");
+            return;
+        };
+        let cx = inner.trap_cx();
+        dump!("[kernel] tid {}, user registers at the last trap:", res.tid);
+        dump_registers(&cx.x, cx.sepc);
+    });
+    if dumped.is_none() {
+        dump!("[kernel] no thread running");
+    }
+}
+
+/// Print `sepc`, then `x1` to `x31` four to a line
+fn dump_registers(x: &[usize; 32], sepc: usize) {
+    dump!("  {:>4}: {:#018x}", "sepc", sepc);
+    for row in (1..32).step_by(4) {
+        for i in row..(row + 4).min(32) {
+            print_unlocked(format_args!("  {:>4}: {:#018x}", REGISTER_NAMES[i], x[i]));
+        }
+        dump!("");
+    }
+}
+
+/// Bounds of the kernel stack `addr` lies on, the boot stack or that of the running thread
+fn stack_bounds(addr: usize) -> Option<(usize, usize)> {
+    extern "C" {
+        fn boot_stack_lower_bound();
+        fn boot_stack_top();
+    }
+    let boot = (boot_stack_lower_bound as usize, boot_stack_top as usize);
+    let task = peek_current_tcb(|task| task.kstack.bounds());
+    [Some(boot), task]
+        .into_iter()
+        .flatten()
+        .find(|&(bottom, top)| (bottom..top).contains(&addr))
+}
+
+/// Print the return addresses of the innermost frames, following the frame pointers
+///
+/// Each frame keeps the return address right below the frame pointer and the frame
+/// pointer of its caller below that. The walk stops at the top of the stack, or at a
+/// frame pointer that is not above the last one, so it never reads off the stack.
+fn backtrace() {
+    let mut fp: usize;
+    unsafe { asm!("mv {}, s0", out(reg) fp) };
+    let Some((bottom, top)) = stack_bounds(fp) else {
+        dump!(
+            "[kernel] backtrace unavailable, fp {:#x} is on no known stack",
+            fp
+        );
+        return;
+    };
+    dump!("[kernel] backtrace:");
+    for depth in 0..BACKTRACE_DEPTH {
+        if fp < bottom + 16 || fp > top || fp % 8 != 0 {
+            break;
+        }
+        let (ra, caller_fp) =
+            unsafe { (*(fp as *const usize).sub(1), *(fp as *const usize).sub(2)) };
+        dump!("  #{:<2} {:#018x}", depth, ra);
+        if caller_fp <= fp {
+            break;
+        }
+        fp = caller_fp;
+    }
+}
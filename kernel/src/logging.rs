@@ -1,9 +1,65 @@
 //! # Simple Logging Module
 //!
 //! A simple logger implementation using the `log` crate.
+//!
+//! Besides the UART, log lines are kept in a buffer until the file sink in
+//! [`crate::fs::klog`] appends them to the log file.
 
+use crate::{config::LOG_BUFFER_SIZE, sync::UPIntrFreeCell};
+use alloc::vec::Vec;
+use core::{
+    fmt::{self, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
+use lazy_static::lazy_static;
 use log::{Level, LevelFilter, Log, Metadata, Record};
 
+/// Log lines waiting to be written to the log file
+struct PendingLog {
+    data: [u8; LOG_BUFFER_SIZE],
+    len: usize,
+    /// Bytes of lines that did not fit since the buffer was last drained
+    dropped: usize,
+}
+
+impl PendingLog {
+    /// Append `record` as one line, dropping it if the buffer is full
+    fn push(&mut self, record: &Record) {
+        let start = self.len;
+        if writeln!(self, "[{:>5}] {}", record.level(), record.args()).is_err() {
+            self.dropped += self.len - start;
+            self.len = start;
+        }
+    }
+}
+
+impl Write for PendingLog {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > LOG_BUFFER_SIZE {
+            self.dropped += s.len();
+            return Err(fmt::Error);
+        }
+        self.data[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+lazy_static! {
+    static ref PENDING_LOG: UPIntrFreeCell<PendingLog> = unsafe {
+        UPIntrFreeCell::new(PendingLog {
+            data: [0; LOG_BUFFER_SIZE],
+            len: 0,
+            dropped: 0,
+        })
+    };
+}
+
+/// Set while the pending lines are written out, so that logging done by the
+/// file system itself goes to the UART only instead of recursing
+static DRAINING: AtomicBool = AtomicBool::new(false);
+
 struct Logger;
 
 impl Log for Logger {
@@ -28,6 +84,9 @@ impl Log for Logger {
             record.level(),
             record.args(),
         );
+        if !DRAINING.load(Ordering::Acquire) {
+            PENDING_LOG.exclusive_access().push(record);
+        }
     }
 
     fn flush(&self) {}
@@ -47,3 +106,28 @@ pub fn init() {
         _ => LevelFilter::Off,
     });
 }
+
+/// Hand the pending log lines to `write`, appending a note if lines were dropped
+///
+/// Lines logged while `write` runs are not buffered.
+pub fn drain(write: impl FnOnce(&[u8])) {
+    if DRAINING.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    let data = {
+        let mut pending = PENDING_LOG.exclusive_access();
+        let mut data = Vec::from(&pending.data[..pending.len]);
+        if pending.dropped > 0 {
+            data.extend_from_slice(
+                alloc::format!("[kernel] {} bytes of log dropped\n", pending.dropped).as_bytes(),
+            );
+        }
+        pending.len = 0;
+        pending.dropped = 0;
+        data
+    };
+    if !data.is_empty() {
+        write(&data);
+    }
+    DRAINING.store(false, Ordering::Release);
+}
@@ -70,6 +70,7 @@ pub extern "C" fn rust_main() -> ! {
     timer::set_next_trigger();
     board::init();
     task::init();
+    fs::klog::init();
 
     #[cfg(test)]
     test_main();
@@ -16,7 +16,8 @@ use core::fmt::{self, Debug, Formatter};
 // |       Physical Page Number       | Page Offset |
 // +----------------------------------+-------------+
 const PA_WIDTH_SV39: usize = 56;
-const VA_WIDTH_SV39: usize = 39;
+/// Width of a virtual address under SV39
+pub const VA_WIDTH_SV39: usize = 39;
 const PPN_WIDTH_SV39: usize = PA_WIDTH_SV39 - PAGE_SIZE_BITS;
 const VPN_WIDTH_SV39: usize = VA_WIDTH_SV39 - PAGE_SIZE_BITS;
 
@@ -288,6 +289,19 @@ where
     pub fn end(&self) -> T {
         self.end
     }
+
+    /// Whether `value` lies in the half-open range `[start, end)`.
+    pub fn contains(&self, value: T) -> bool {
+        self.start <= value && value < self.end
+    }
+
+    /// Whether the two half-open ranges share any value.
+    ///
+    /// Adjacent ranges, where one ends exactly where the other starts, do not
+    /// overlap, and neither does an empty range.
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.start < other.end && other.start < self.end
+    }
 }
 
 impl<T> IntoIterator for SimpleRange<T>
@@ -40,6 +40,7 @@ trait FrameAllocator {
 /// An implementation for frame allocator
 #[allow(clippy::module_name_repetitions)]
 pub struct StackFrameAllocator {
+    start: usize,
     current: usize,
     end: usize,
     recycled: Vec<usize>,
@@ -47,14 +48,96 @@ pub struct StackFrameAllocator {
 
 impl StackFrameAllocator {
     pub fn init(&mut self, l: PhysPageNum, r: PhysPageNum) {
+        self.start = l.0;
         self.current = l.0;
         self.end = r.0;
     }
+
+    /// Allocate `count` physically adjacent frames and return the first.
+    ///
+    /// Runs of recycled frames are reused first so that fragments left behind
+    /// by earlier frees are consumed; otherwise the run is cut from the
+    /// untouched tail, leaving the recycled list for single-frame requests.
+    pub fn alloc_contiguous(&mut self, count: usize) -> Option<PhysPageNum> {
+        if count == 0 {
+            return None;
+        }
+
+        let mut recycled = self.recycled.clone();
+        recycled.sort_unstable();
+        let mut run_start = 0;
+        for i in 0..recycled.len() {
+            if i > 0 && recycled[i - 1] + 1 != recycled[i] {
+                run_start = i;
+            }
+            if i + 1 - run_start == count {
+                let first = recycled[run_start];
+                self.recycled
+                    .retain(|&ppn| !(first..first + count).contains(&ppn));
+                return Some(first.into());
+            }
+        }
+
+        if self.end - self.current < count {
+            return None;
+        }
+        self.current += count;
+        Some((self.current - count).into())
+    }
+
+    /// Summarize how many frames are free and how fragmented they are.
+    ///
+    /// Free frames are the recycled ones plus the untouched tail
+    /// `[current, end)`. Only the recycled list is walked; the tail is
+    /// accounted for from the pointers alone.
+    pub fn report(&self) -> FrameReport {
+        let tail = self.end - self.current;
+        let mut recycled = self.recycled.clone();
+        recycled.sort_unstable();
+
+        let mut largest_free_run = 0;
+        let mut run = 0;
+        let mut previous = None;
+        for &ppn in &recycled {
+            run = if previous.is_some_and(|p| p + 1 == ppn) {
+                run + 1
+            } else {
+                1
+            };
+            largest_free_run = largest_free_run.max(run);
+            previous = Some(ppn);
+        }
+        // a run of recycled frames ending at `current` joins the tail
+        let tail_run = if previous.is_some_and(|p| p + 1 == self.current) {
+            tail + run
+        } else {
+            tail
+        };
+
+        FrameReport {
+            total: self.end - self.start,
+            free: recycled.len() + tail,
+            largest_free_run: largest_free_run.max(tail_run),
+        }
+    }
+}
+
+/// A snapshot of the frame allocator's usage
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[allow(clippy::module_name_repetitions)]
+pub struct FrameReport {
+    /// Frames managed by the allocator
+    pub total: usize,
+    /// Frames currently available
+    pub free: usize,
+    /// Length of the longest run of physically contiguous free frames
+    pub largest_free_run: usize,
 }
 
 impl FrameAllocator for StackFrameAllocator {
     fn new() -> Self {
         Self {
+            start: 0,
             current: 0,
             end: 0,
             recycled: Vec::new(),
@@ -117,6 +200,30 @@ pub fn dealloc(ppn: PhysPageNum) {
     FRAME_ALLOCATOR.exclusive_access().dealloc(ppn);
 }
 
+/// Allocate `count` physically contiguous, zeroed frames
+///
+/// The frames are not tracked; release them with [`dealloc_contiguous`].
+pub fn alloc_contiguous(count: usize) -> Option<PhysPageNum> {
+    let first = FRAME_ALLOCATOR.exclusive_access().alloc_contiguous(count)?;
+    for ppn in first.0..first.0 + count {
+        PhysPageNum(ppn).as_mut_bytes_array().fill(0);
+    }
+    Some(first)
+}
+
+/// Deallocate `count` frames starting at `first`, as returned by [`alloc_contiguous`]
+pub fn dealloc_contiguous(first: PhysPageNum, count: usize) {
+    let mut allocator = FRAME_ALLOCATOR.exclusive_access();
+    for ppn in first.0..first.0 + count {
+        allocator.dealloc(PhysPageNum(ppn));
+    }
+}
+
+/// Report total, free and largest contiguous free frames
+pub fn report() -> FrameReport {
+    FRAME_ALLOCATOR.exclusive_access().report()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -156,6 +263,91 @@ mod test {
             "Alloc error"
         );
 
+        Ok("passed")
+    });
+    test!(test_frame_report, {
+        let mut allocator = StackFrameAllocator::new();
+        allocator.init(PhysPageNum(10), PhysPageNum(20));
+        let report = allocator.report();
+        test_assert!(report.total == 10 && report.free == 10 && report.largest_free_run == 10);
+
+        for _ in 0..8 {
+            allocator.alloc();
+        }
+        // free 11, 13, 14 and 17, leaving 18..20 untouched
+        for ppn in [13, 17, 11, 14] {
+            allocator.dealloc(PhysPageNum(ppn));
+        }
+        let report = allocator.report();
+        test_assert!(report.free == 6);
+        test_assert!(report.largest_free_run == 3);
+
+        allocator.dealloc(PhysPageNum(16));
+        test_assert!(allocator.report().largest_free_run == 4);
+
+        Ok("passed")
+    });
+
+    test!(test_frame_report_no_leak, {
+        let before = report();
+        {
+            let mut frames = Vec::new();
+            for _ in 0..64 {
+                frames.push(alloc().expect("No space"));
+            }
+            // drop every other frame first to fragment the recycled list
+            let mut index = 0;
+            frames.retain(|_| {
+                index += 1;
+                index % 2 == 0
+            });
+            test_assert!(report().free == before.free - 32);
+        }
+        let after = report();
+        test_assert!(after.free == before.free, "Frames leaked");
+
+        Ok("passed")
+    });
+    test!(test_frame_alloc_contiguous, {
+        let mut allocator = StackFrameAllocator::new();
+        allocator.init(PhysPageNum(10), PhysPageNum(20));
+        test_assert!(allocator.alloc_contiguous(0).is_none());
+        test_assert!(allocator.alloc_contiguous(3) == Some(PhysPageNum(10)));
+        test_assert!(allocator.current == 13);
+
+        // a scattered recycled list is left for single frames
+        for _ in 0..4 {
+            allocator.alloc();
+        }
+        for ppn in [10, 12, 14, 15] {
+            allocator.dealloc(PhysPageNum(ppn));
+        }
+        test_assert!(allocator.alloc_contiguous(3) == Some(PhysPageNum(17)));
+        test_assert!(allocator.recycled.len() == 4);
+
+        // a recycled run long enough is reused
+        test_assert!(allocator.alloc_contiguous(2) == Some(PhysPageNum(14)));
+        test_assert!(allocator.recycled.len() == 2);
+
+        // fails cleanly when no run is long enough
+        test_assert!(allocator.alloc_contiguous(3).is_none());
+        test_assert!(allocator.report().free == 2);
+
+        Ok("passed")
+    });
+
+    test!(test_frame_dealloc_contiguous, {
+        let before = report();
+        let first = alloc_contiguous(4).expect("No space");
+        test_assert!(report().free == before.free - 4);
+        dealloc_contiguous(first, 4);
+        test_assert!(report().free == before.free, "Frames leaked");
+
+        // the freed run serves the next contiguous request
+        let again = alloc_contiguous(4).expect("No space");
+        test_assert!(again == first);
+        dealloc_contiguous(again, 4);
+
         Ok("passed")
     });
 }
@@ -1,20 +1,28 @@
 //! Implementation of [`MapArea`] and [`MemorySet`].
 
 use super::{
-    frame_allocator, PTEFlags, PageTable, PageTableEntry, PhysAddr, PhysPageNum, StepByOne,
-    VPNRange, VirtAddr, VirtPageNum,
+    address::VA_WIDTH_SV39, frame_allocator, PTEFlags, PageTable, PageTableEntry, PhysAddr,
+    PhysPageNum, StepByOne, VPNRange, VirtAddr, VirtPageNum,
 };
 use crate::{
     config::MMIO,
-    config::{MEMORY_END, PAGE_SIZE, TRAMPOLINE},
+    config::{MEMORY_END, PAGE_SIZE, TICK_PAGE, TRAMPOLINE, TRAP_CONTEXT_BASE},
     sync::UPIntrFreeCell,
+    timer,
 };
 use alloc::{sync::Arc, vec::Vec};
 use bitflags::bitflags;
-use core::arch::asm;
+use core::{
+    arch::asm,
+    mem::{align_of, size_of},
+};
 use lazy_static::lazy_static;
 use log::{info, trace};
 use riscv::register::satp;
+use xmas_elf::{
+    header::{Class, Data},
+    program::{ProgramHeader64, Type},
+};
 
 extern "C" {
     fn stext();
@@ -138,8 +146,19 @@ impl MapArea {
 
     /// Copies data into the virtual pages managed by this `MapArea`, assuming the area is framed.
     /// data: start-aligned but maybe with shorter length, assume that all frames were cleared before.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` does not fit in the pages of the area, rather than spilling into
+    /// whatever is mapped after it.
     pub fn copy_data(&mut self, page_table: &PageTable, data: &[u8]) {
         assert_eq!(self.map_type, MapType::Framed);
+        let capacity = (self.vpn_range.end().0 - self.vpn_range.start().0) * PAGE_SIZE;
+        assert!(
+            data.len() <= capacity,
+            "{} bytes of data overrun an area of {capacity} bytes",
+            data.len()
+        );
 
         if data.is_empty() {
             return;
@@ -169,6 +188,7 @@ impl Clone for MemorySet {
         let mut memory_set = Self::new_bare();
         // map trampoline
         memory_set.map_trampoline();
+        memory_set.map_tick_page();
         // copy data sections/trap_context/user_stack
         for area in &self.areas {
             let new_area = area.clone();
@@ -223,17 +243,34 @@ impl MemorySet {
         self.areas.push(map_area);
     }
 
-    /// Assume that no conflicts
+    /// Whether any existing [`MapArea`] shares a page with `[start_va, end_va)`.
+    pub fn has_conflict(&self, start_va: VirtAddr, end_va: VirtAddr) -> bool {
+        let range = VPNRange::new(start_va.as_vpn_by_floor(), end_va.as_vpn_by_ceil());
+        self.areas
+            .iter()
+            .any(|area| area.vpn_range.overlaps(&range))
+    }
+
+    /// Insert a framed area covering `[start_va, end_va)`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` without mapping anything if the range conflicts with an
+    /// existing area.
     pub fn insert_framed_area(
         &mut self,
         start_va: VirtAddr,
         end_va: VirtAddr,
         permission: MapPermission,
-    ) {
+    ) -> Result<(), &'static str> {
+        if self.has_conflict(start_va, end_va) {
+            return Err("range overlaps an existing area");
+        }
         self.push(
             MapArea::new(start_va, end_va, MapType::Framed, permission),
             None,
         );
+        Ok(())
     }
 
     /// Remove [`MapArea`] that starts with `start_vpn`
@@ -249,6 +286,26 @@ impl MemorySet {
         }
     }
 
+    /// Finds the [`MapArea`] whose page range contains `va`.
+    ///
+    /// Returns `None` when `va` falls into a gap between areas, which a fault
+    /// handler treats as a genuine segmentation fault.
+    pub fn find_area_mut(&mut self, va: VirtAddr) -> Option<&mut MapArea> {
+        let vpn = va.as_vpn_by_floor();
+        self.areas
+            .iter_mut()
+            .find(|area| area.vpn_range.contains(vpn))
+    }
+
+    /// Number of pages backed by frames of their own, which are all allocated up front
+    pub fn framed_pages(&self) -> usize {
+        self.areas
+            .iter()
+            .filter(|area| area.map_type == MapType::Framed)
+            .map(|area| area.vpn_range.end().0 - area.vpn_range.start().0)
+            .sum()
+    }
+
     /// Remove all [`MapArea`]
     pub fn recycle_data_pages(&mut self) {
         self.areas.clear();
@@ -263,6 +320,15 @@ impl MemorySet {
         );
     }
 
+    /// Map the shared tick page, user programs may read it but never write it.
+    fn map_tick_page(&mut self) {
+        self.page_table.map(
+            VirtAddr::from(TICK_PAGE).into(),
+            timer::tick_page(),
+            PTEFlags::U | PTEFlags::R,
+        );
+    }
+
     /// Without kernel stacks.
     pub fn new_kernel() -> Self {
         let mut memory_set = Self::new_bare();
@@ -350,10 +416,14 @@ impl MemorySet {
 
     /// Include sections in elf and trampoline and `TrapContext` and user stack.
     /// Returns `user_sp` and entry point.
+    ///
+    /// `elf_data` is trusted here, images coming from user space should be
+    /// checked with [`validate_elf`] first.
     pub fn from_elf(elf_data: &[u8]) -> (Self, usize, usize) {
         let mut memory_set = Self::new_bare();
 
         memory_set.map_trampoline();
+        memory_set.map_tick_page();
 
         let elf = xmas_elf::ElfFile::new(elf_data).unwrap();
         let elf_header = elf.header;
@@ -367,6 +437,10 @@ impl MemorySet {
         for i in 0..ph_count {
             let ph = elf.program_header(i).unwrap();
             if ph.get_type().unwrap() == xmas_elf::program::Type::Load {
+                assert!(
+                    ph.file_size() <= ph.mem_size(),
+                    "load segment file size exceeds memory size"
+                );
                 let start_va: VirtAddr = (ph.virtual_addr() as usize).into();
                 let end_va: VirtAddr = ((ph.virtual_addr() + ph.mem_size()) as usize).into();
 
@@ -403,6 +477,91 @@ impl MemorySet {
     }
 }
 
+/// Check that `elf_data` is an ELF image [`MemorySet::from_elf`] can load.
+///
+/// Verifies the magic, that the image is a little-endian 64-bit ELF, that the
+/// program header table lies within `elf_data`, and that every load segment
+/// has its data inside the image and its addresses inside user space, clear of
+/// the tick, trampoline and trap context pages.
+///
+/// # Returns
+///
+/// * `Ok(())` if the image can be loaded.
+/// * `Err(reason)` describing the first problem found.
+pub fn validate_elf(elf_data: &[u8]) -> Result<(), &'static str> {
+    // headers are read in place, so the image must be aligned for them
+    if elf_data.as_ptr() as usize & (align_of::<u64>() - 1) != 0 {
+        return Err("elf image is misaligned");
+    }
+
+    // checks the magic and that the file header fits
+    let elf = xmas_elf::ElfFile::new(elf_data)?;
+    let elf_header = elf.header;
+    if elf_header.pt1.class() != Class::SixtyFour {
+        return Err("elf is not 64-bit");
+    }
+    if elf_header.pt1.data() != Data::LittleEndian {
+        return Err("elf is not little-endian");
+    }
+
+    let ph_count = elf_header.pt2.ph_count();
+    let ph_offset = elf_header.pt2.ph_offset();
+    let ph_entry_size = u64::from(elf_header.pt2.ph_entry_size());
+    if ph_count == 0 {
+        return Err("elf has no program headers");
+    }
+    if ph_entry_size < size_of::<ProgramHeader64>() as u64 {
+        return Err("program header entry is too small");
+    }
+    if ph_offset & (align_of::<ProgramHeader64>() as u64 - 1) != 0 {
+        return Err("program header table is misaligned");
+    }
+    let ph_end = ph_entry_size
+        .checked_mul(u64::from(ph_count))
+        .and_then(|size| size.checked_add(ph_offset));
+    if ph_end.is_none_or(|end| end > elf_data.len() as u64) {
+        return Err("program header table out of range");
+    }
+
+    // user space is the lower half of the SV39 address space
+    let user_space_end = 1u64 << (VA_WIDTH_SV39 - 1);
+    // pages mapped by the kernel into every user space, compared as page numbers
+    // since addresses are truncated to SV39 width when mapped
+    let reserved_first = VirtAddr::from(TRAP_CONTEXT_BASE).as_vpn_by_floor();
+    let reserved_last = VirtAddr::from(TRAMPOLINE).as_vpn_by_floor();
+    let tick_vpn = VirtAddr::from(TICK_PAGE).as_vpn_by_floor();
+    for i in 0..ph_count {
+        let ph = elf.program_header(i)?;
+        if ph.get_type()? != Type::Load {
+            continue;
+        }
+
+        let file_end = ph.offset().checked_add(ph.file_size());
+        if file_end.is_none_or(|end| end > elf_data.len() as u64) {
+            return Err("load segment data out of range");
+        }
+        if ph.file_size() > ph.mem_size() {
+            return Err("load segment file size exceeds memory size");
+        }
+        let Some(end_va) = ph.virtual_addr().checked_add(ph.mem_size()) else {
+            return Err("load segment address overflows");
+        };
+        let start_vpn = VirtAddr::from(ph.virtual_addr() as usize).as_vpn_by_floor();
+        let end_vpn = VirtAddr::from(end_va as usize).as_vpn_by_ceil();
+        if start_vpn <= reserved_last && reserved_first < end_vpn {
+            return Err("load segment overlaps trampoline or trap context");
+        }
+        if start_vpn <= tick_vpn && tick_vpn < end_vpn {
+            return Err("load segment overlaps tick page");
+        }
+        if end_va > user_space_end {
+            return Err("load segment outside user space");
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -446,6 +605,35 @@ mod test {
         Ok("passed")
     });
 
+    test!(test_copy_data_fills_area, {
+        let mut memory_set = MemorySet::new_bare();
+        let next = MapArea::new(
+            VirtPageNum(2).into(),
+            VirtPageNum(3).into(),
+            MapType::Framed,
+            MapPermission::R,
+        );
+        memory_set.push(next, None);
+        // exactly as much data as the area holds, with another area right behind it
+        let data = [u8::MAX; 2 * PAGE_SIZE];
+        let area = MapArea::new(
+            VirtPageNum(0).into(),
+            VirtPageNum(2).into(),
+            MapType::Framed,
+            MapPermission::R,
+        );
+        memory_set.push(area, Some(&data));
+
+        for vpn in 0..2 {
+            let ppn = memory_set.translate(VirtPageNum(vpn)).unwrap().ppn();
+            test_assert!(ppn.as_mut_bytes_array().iter().all(|&byte| byte == u8::MAX));
+        }
+        let ppn = memory_set.translate(VirtPageNum(2)).unwrap().ppn();
+        test_assert!(ppn.as_mut_bytes_array().iter().all(|&byte| byte == 0));
+
+        Ok("passed")
+    });
+
     test!(test_memory_set_clone, {
         let mut memory_set = MemorySet::new_bare();
         let data = [u8::MAX; PAGE_SIZE];
@@ -474,4 +662,216 @@ mod test {
 
         Ok("passed")
     });
+
+    test!(test_find_area_mut, {
+        let mut memory_set = MemorySet::new_bare();
+        memory_set
+            .insert_framed_area(
+                VirtPageNum(0).into(),
+                VirtPageNum(2).into(),
+                MapPermission::R,
+            )
+            .unwrap();
+        memory_set
+            .insert_framed_area(
+                VirtPageNum(4).into(),
+                VirtPageNum(5).into(),
+                MapPermission::W,
+            )
+            .unwrap();
+
+        let inside = VirtAddr::from(VirtPageNum(1)).0 + 8;
+        let area = memory_set.find_area_mut(inside.into());
+        test_assert!(area.is_some_and(|area| area.vpn_range.start() == VirtPageNum(0)));
+        let area = memory_set.find_area_mut(VirtPageNum(4).into());
+        test_assert!(area.is_some_and(|area| area.map_perm == MapPermission::W));
+        test_assert!(memory_set.find_area_mut(VirtPageNum(2).into()).is_none());
+        test_assert!(memory_set.find_area_mut(VirtPageNum(5).into()).is_none());
+
+        Ok("passed")
+    });
+
+    test!(test_vpn_range_overlaps, {
+        let range = VPNRange::new(VirtPageNum(2), VirtPageNum(4));
+        test_assert!(!range.contains(VirtPageNum(1)));
+        test_assert!(range.contains(VirtPageNum(2)) && range.contains(VirtPageNum(3)));
+        test_assert!(!range.contains(VirtPageNum(4)));
+
+        test_assert!(range.overlaps(&VPNRange::new(VirtPageNum(3), VirtPageNum(6))));
+        test_assert!(range.overlaps(&VPNRange::new(VirtPageNum(0), VirtPageNum(8))));
+        test_assert!(!range.overlaps(&VPNRange::new(VirtPageNum(0), VirtPageNum(2))));
+        test_assert!(!range.overlaps(&VPNRange::new(VirtPageNum(4), VirtPageNum(5))));
+        test_assert!(!range.overlaps(&VPNRange::new(VirtPageNum(3), VirtPageNum(3))));
+
+        Ok("passed")
+    });
+
+    test!(test_insert_framed_area_conflict, {
+        let mut memory_set = MemorySet::new_bare();
+        let start = VirtAddr::from(VirtPageNum(2));
+        let end = VirtAddr::from(VirtPageNum(4));
+        test_assert!(memory_set
+            .insert_framed_area(start, end, MapPermission::R)
+            .is_ok());
+
+        // unaligned ends still claim their whole page
+        let inside = VirtAddr::from(end.0 - 1);
+        test_assert!(memory_set.has_conflict(inside, VirtPageNum(6).into()));
+        test_assert!(memory_set
+            .insert_framed_area(
+                VirtPageNum(1).into(),
+                (start.0 + 1).into(),
+                MapPermission::R
+            )
+            .is_err());
+        test_assert!(memory_set.translate(VirtPageNum(1)).is_none());
+
+        // touching ranges are fine on either side
+        test_assert!(!memory_set.has_conflict(VirtPageNum(0).into(), start));
+        test_assert!(memory_set
+            .insert_framed_area(end, VirtPageNum(5).into(), MapPermission::R)
+            .is_ok());
+        test_assert!(memory_set
+            .insert_framed_area(VirtPageNum(1).into(), start, MapPermission::R)
+            .is_ok());
+
+        Ok("passed")
+    });
+
+    /// A minimal RISC-V ELF image with a single 8-byte load segment.
+    #[repr(C, align(8))]
+    struct ElfImage([u8; 128]);
+
+    impl ElfImage {
+        fn new() -> Self {
+            let mut image = Self([0; 128]);
+            image.0[..4].copy_from_slice(&[0x7f, b'E', b'L', b'F']);
+            image.0[4] = 2; // 64-bit
+            image.0[5] = 1; // little-endian
+            image.0[6] = 1; // version
+            image.put(16, &2u16.to_le_bytes()); // executable
+            image.put(18, &0xf3u16.to_le_bytes()); // RISC-V
+            image.put(20, &1u32.to_le_bytes());
+            image.put(24, &0x1000u64.to_le_bytes()); // entry
+            image.put(32, &64u64.to_le_bytes()); // program header offset
+            image.put(52, &64u16.to_le_bytes());
+            image.put(54, &56u16.to_le_bytes()); // program header entry size
+            image.put(56, &1u16.to_le_bytes()); // program header count
+
+            image.put(64, &1u32.to_le_bytes()); // load
+            image.put(68, &5u32.to_le_bytes()); // R | X
+            image.put(72, &120u64.to_le_bytes()); // offset
+            image.put(80, &0x1000u64.to_le_bytes()); // virtual address
+            image.put(96, &8u64.to_le_bytes()); // file size
+            image.put(104, &8u64.to_le_bytes()); // memory size
+            image
+        }
+
+        fn put(&mut self, offset: usize, bytes: &[u8]) {
+            self.0[offset..offset + bytes.len()].copy_from_slice(bytes);
+        }
+    }
+
+    test!(test_validate_elf_valid, {
+        test_assert!(validate_elf(&ElfImage::new().0).is_ok());
+        Ok("passed")
+    });
+
+    test!(test_validate_elf_truncated, {
+        let image = ElfImage::new();
+        for len in 0..image.0.len() {
+            test_assert!(validate_elf(&image.0[..len]).is_err());
+        }
+        Ok("passed")
+    });
+
+    test!(test_validate_elf_malformed, {
+        let mut image = ElfImage::new();
+        image.0[4] = 1;
+        test_assert!(validate_elf(&image.0).is_err(), "32-bit elf accepted");
+
+        let mut image = ElfImage::new();
+        image.0[5] = 2;
+        test_assert!(validate_elf(&image.0).is_err(), "big-endian elf accepted");
+
+        let mut image = ElfImage::new();
+        image.put(56, &u16::MAX.to_le_bytes());
+        test_assert!(
+            validate_elf(&image.0).is_err(),
+            "oversized ph table accepted"
+        );
+
+        let mut image = ElfImage::new();
+        image.put(64, &0x1234u32.to_le_bytes());
+        test_assert!(validate_elf(&image.0).is_err(), "invalid ph type accepted");
+
+        let mut image = ElfImage::new();
+        image.put(72, &u64::MAX.to_le_bytes());
+        test_assert!(
+            validate_elf(&image.0).is_err(),
+            "segment offset overflow accepted"
+        );
+
+        let mut image = ElfImage::new();
+        image.put(80, &(u64::MAX - 4).to_le_bytes());
+        test_assert!(
+            validate_elf(&image.0).is_err(),
+            "segment address overflow accepted"
+        );
+
+        let mut image = ElfImage::new();
+        image.put(80, &(TRAMPOLINE as u64).to_le_bytes());
+        test_assert!(
+            validate_elf(&image.0).is_err(),
+            "segment over trampoline accepted"
+        );
+
+        // aliases the trampoline and trap context once truncated to SV39 width
+        let mut image = ElfImage::new();
+        image.put(80, &0x7f_ffff_e000u64.to_le_bytes());
+        test_assert!(
+            validate_elf(&image.0).is_err(),
+            "segment over trap context accepted"
+        );
+
+        let mut image = ElfImage::new();
+        image.put(80, &(TICK_PAGE as u64 + 0x800).to_le_bytes());
+        test_assert!(
+            validate_elf(&image.0).is_err(),
+            "segment over tick page accepted"
+        );
+
+        let mut image = ElfImage::new();
+        image.put(104, &4u64.to_le_bytes());
+        test_assert!(
+            validate_elf(&image.0).is_err(),
+            "file size > memory size accepted"
+        );
+
+        Ok("passed")
+    });
+
+    test!(test_validate_elf_garbage, {
+        // feed pseudo-random images, mostly checking that nothing panics
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        for round in 0..256 {
+            let mut image = ElfImage::new();
+            // keep the magic and most header bytes on even rounds to reach deeper checks
+            let mangle_all = round & 1 == 1;
+            let start = if mangle_all { 0 } else { 4 };
+            for byte in &mut image.0[start..] {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                if mangle_all || seed.trailing_zeros() >= 3 {
+                    *byte = seed as u8;
+                }
+            }
+            let result = validate_elf(&image.0);
+            if mangle_all {
+                test_assert!(result.is_err(), "random garbage accepted as elf");
+            }
+        }
+        Ok("passed")
+    });
 }
@@ -20,27 +20,80 @@ pub use frame_allocator::FrameTracker;
 pub use memory_set::{kernel_token, MapArea, MapPermission, MapType, MemorySet, KERNEL_SPACE};
 pub use page_table::{PTEFlags, PageTable, PageTableEntry};
 
+use crate::config::PAGE_SIZE;
 use address::VPNRange;
 use alloc::{string::String, vec::Vec};
+use log::info;
 
 /// Initialize heap allocator, frame allocator, kernel space.
 pub fn init() {
     heap_allocator::init();
     frame_allocator::init();
+    info!("[kernel] {:?}", frame_allocator::report());
     KERNEL_SPACE.exclusive_access().activate();
 }
 
-/// translate a pointer to a mutable u8 Vec through page table
-pub fn translated_byte_buffer(token: usize, ptr: *const u8, len: usize) -> Vec<&'static mut [u8]> {
+/// A user pointer that does not lead to mapped user memory, reported as `-14` (`EFAULT`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BadAddress;
+
+/// Why a string could not be copied in from user space
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BadString {
+    /// It runs into memory that is not mapped for the user, reported as `-14`
+    Address,
+    /// No terminator within the allowed length, reported as `-1`
+    TooLong,
+}
+
+impl From<BadAddress> for BadString {
+    fn from(_: BadAddress) -> Self {
+        Self::Address
+    }
+}
+
+impl BadString {
+    /// The value a syscall returns for this error
+    pub fn code(self) -> isize {
+        match self {
+            Self::Address => -14,
+            Self::TooLong => -1,
+        }
+    }
+}
+
+/// Translate the user page holding `va`, which has to be mapped accessible to the user with `flags`
+fn translate_user_page(
+    page_table: &PageTable,
+    va: usize,
+    flags: PTEFlags,
+) -> Result<PhysPageNum, BadAddress> {
+    // addresses beyond SV39 would otherwise wrap around into the mapped range
+    if usize::from(VirtAddr::from(va)) != va {
+        return Err(BadAddress);
+    }
+    page_table
+        .translate(VirtAddr::from(va).as_vpn_by_floor())
+        .filter(|pte| pte.is_valid() && pte.flags().contains(flags | PTEFlags::U))
+        .map(PageTableEntry::ppn)
+        .ok_or(BadAddress)
+}
+
+/// Translate a user buffer of `len` bytes at `ptr` into the slices of the pages holding it
+pub fn translated_byte_buffer(
+    token: usize,
+    ptr: *const u8,
+    len: usize,
+) -> Result<Vec<&'static mut [u8]>, BadAddress> {
     let page_table = PageTable::from_token(token);
     let mut start = ptr as usize;
-    let end = start + len;
+    let end = start.checked_add(len).ok_or(BadAddress)?;
     let mut v = Vec::new();
 
     while start < end {
         let start_va = VirtAddr::from(start);
         let mut vpn = start_va.as_vpn_by_floor();
-        let ppn = page_table.translate(vpn).unwrap().ppn();
+        let ppn = translate_user_page(&page_table, start, PTEFlags::empty())?;
         vpn.step();
         let mut end_va: VirtAddr = vpn.into();
         end_va = end_va.min(VirtAddr::from(end));
@@ -54,47 +107,56 @@ pub fn translated_byte_buffer(token: usize, ptr: *const u8, len: usize) -> Vec<&
         start = end_va.into();
     }
 
-    v
+    Ok(v)
 }
 
 /// Load a string from other address spaces into kernel space without an end `\0`.
-pub fn translated_str(token: usize, ptr: *const u8) -> String {
+///
+/// At most `max_len` bytes including the `\0` are scanned, a page at a time, so the scan
+/// never goes past the first page that is not mapped.
+pub fn translated_str(token: usize, ptr: *const u8, max_len: usize) -> Result<String, BadString> {
     let page_table = PageTable::from_token(token);
     let mut string = String::new();
     let mut va = ptr as usize;
+    let mut scanned = 0;
     loop {
-        let ch: u8 = *(page_table
-            .translate_va(VirtAddr::from(va))
-            .unwrap()
-            .as_mut_ref());
-        if ch == 0 {
-            break;
+        let ppn = translate_user_page(&page_table, va, PTEFlags::empty())?;
+        let offset = VirtAddr::from(va).page_offset();
+        let room = max_len - scanned;
+        let page = &ppn.as_mut_bytes_array()[offset..PAGE_SIZE.min(offset + room)];
+        if let Some(len) = page.iter().position(|&ch| ch == 0) {
+            string.extend(page[..len].iter().map(|&ch| ch as char));
+            return Ok(string);
+        }
+        string.extend(page.iter().map(|&ch| ch as char));
+        scanned += page.len();
+        if scanned >= max_len {
+            return Err(BadString::TooLong);
         }
-        string.push(ch as char);
-        va += 1;
+        va = va.checked_add(page.len()).ok_or(BadAddress)?;
     }
-    string
 }
 
-pub fn translated_ref<T>(token: usize, ptr: *const T) -> &'static T {
-    let page_table = PageTable::from_token(token);
-    let va = ptr as usize;
-    page_table
-        .translate_va(VirtAddr::from(va))
-        .unwrap()
-        .as_ref()
+/// Translate the physical address of a `T` at `va`, which has to be aligned and within one page
+fn translated_object<T>(token: usize, va: usize, flags: PTEFlags) -> Result<PhysAddr, BadAddress> {
+    let offset = VirtAddr::from(va).page_offset();
+    if !va.is_multiple_of(core::mem::align_of::<T>())
+        || offset + core::mem::size_of::<T>() > PAGE_SIZE
+    {
+        return Err(BadAddress);
+    }
+    let ppn = translate_user_page(&PageTable::from_token(token), va, flags)?;
+    Ok(PhysAddr::from(usize::from(PhysAddr::from(ppn)) + offset))
+}
+
+///translate a generic through page table and return a reference
+pub fn translated_ref<T>(token: usize, ptr: *const T) -> Result<&'static T, BadAddress> {
+    translated_object::<T>(token, ptr as usize, PTEFlags::empty()).map(|pa| pa.as_ref())
 }
 
 ///translate a generic through page table and return a mutable reference
-pub fn translated_mut_ref<T>(token: usize, ptr: *mut T) -> &'static mut T {
-    //println!("into translated_refmut!");
-    let page_table = PageTable::from_token(token);
-    let va = ptr as usize;
-    //println!("translated_refmut: before translate_va");
-    page_table
-        .translate_va(VirtAddr::from(va))
-        .unwrap()
-        .as_mut_ref()
+pub fn translated_mut_ref<T>(token: usize, ptr: *mut T) -> Result<&'static mut T, BadAddress> {
+    translated_object::<T>(token, ptr as usize, PTEFlags::W).map(|pa| pa.as_mut_ref())
 }
 
 /// Array of u8 slice that user communicate with os
@@ -109,6 +171,18 @@ impl UserBuffer {
         Self { buffers }
     }
 
+    /// Wrap a kernel buffer so it can be passed where a [`UserBuffer`] is expected
+    ///
+    /// # Safety
+    ///
+    /// `buf` must outlive the returned [`UserBuffer`].
+    pub unsafe fn from_kernel(buf: &mut [u8]) -> Self {
+        Self::new(alloc::vec![core::slice::from_raw_parts_mut(
+            buf.as_mut_ptr(),
+            buf.len()
+        )])
+    }
+
     /// Length of [`UserBuffer`]
     pub fn len(&self) -> usize {
         self.buffers.iter().map(|b| b.len()).sum()
@@ -11,6 +11,12 @@ pub trait Mutex: Sync + Send {
     fn lock(&self);
     /// Unlocks the mutex, allowing other threads to acquire it.
     fn unlock(&self);
+    /// Whether the current thread holds the mutex, always `false` for mutexes that do not
+    /// track their owner
+    #[cfg(feature = "mutex_debug")]
+    fn held_by_current(&self) -> bool {
+        false
+    }
 }
 
 /// A spinning mutex implementation.
@@ -57,6 +63,9 @@ pub struct Blocking {
 pub struct BlockingInner {
     locked: bool,
     wait_queue: VecDeque<Arc<TaskControlBlock>>,
+    /// Thread id of the holder, `None` while unlocked
+    #[cfg(feature = "mutex_debug")]
+    owner: Option<usize>,
 }
 
 impl Blocking {
@@ -67,6 +76,8 @@ impl Blocking {
                 UPIntrFreeCell::new(BlockingInner {
                     locked: false,
                     wait_queue: VecDeque::new(),
+                    #[cfg(feature = "mutex_debug")]
+                    owner: None,
                 })
             },
         }
@@ -83,6 +94,10 @@ impl Mutex for Blocking {
             block_current_and_run_next();
         } else {
             mutex_inner.locked = true;
+            #[cfg(feature = "mutex_debug")]
+            {
+                mutex_inner.owner = Some(tid_of(&current_tcb().unwrap()));
+            }
         }
     }
 
@@ -91,9 +106,29 @@ impl Mutex for Blocking {
         let mut mutex_inner = self.inner.exclusive_access();
         assert!(mutex_inner.locked);
         if let Some(waking_task) = mutex_inner.wait_queue.pop_front() {
+            // the mutex passes straight to the woken task
+            #[cfg(feature = "mutex_debug")]
+            {
+                mutex_inner.owner = Some(tid_of(&waking_task));
+            }
             manager::wakeup(waking_task);
         } else {
             mutex_inner.locked = false;
+            #[cfg(feature = "mutex_debug")]
+            {
+                mutex_inner.owner = None;
+            }
         }
     }
+
+    #[cfg(feature = "mutex_debug")]
+    fn held_by_current(&self) -> bool {
+        let owner = self.inner.exclusive_access().owner;
+        owner.is_some_and(|owner| owner == tid_of(&current_tcb().unwrap()))
+    }
+}
+
+#[cfg(feature = "mutex_debug")]
+fn tid_of(task: &TaskControlBlock) -> usize {
+    task.inner_exclusive_access().res.as_ref().unwrap().tid
 }
@@ -44,4 +44,20 @@ impl Semaphore {
             block_current_and_run_next();
         }
     }
+
+    /// Take a resource if one is free, never blocking and never waking a waiter
+    pub fn try_down(&self) -> bool {
+        let mut inner = self.inner.exclusive_access();
+        if inner.count > 0 {
+            inner.count -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The number of free resources, `0` while tasks are waiting
+    pub fn available(&self) -> usize {
+        usize::try_from(self.inner.exclusive_access().count).unwrap_or(0)
+    }
 }
@@ -1,5 +1,5 @@
 use core::{
-    cell::{RefCell, RefMut, UnsafeCell},
+    cell::{Ref, RefCell, RefMut, UnsafeCell},
     ops::{Deref, DerefMut},
 };
 
@@ -85,6 +85,14 @@ impl<T> UPIntrFreeCell<T> {
         UPIntrRefMut(Some(self.inner.borrow_mut()))
     }
 
+    /// Borrow the data unless it is mutably borrowed, leaving interrupts as they are
+    ///
+    /// For the panic handler, which may have interrupted a holder of the cell and must
+    /// not panic again on it.
+    pub fn try_peek(&self) -> Option<Ref<'_, T>> {
+        self.inner.try_borrow().ok()
+    }
+
     #[allow(unused)]
     pub fn exclusive_session<F, V>(&self, f: F) -> V
     where
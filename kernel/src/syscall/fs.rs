@@ -1,16 +1,37 @@
 //! File System System Calls
 
 use crate::{
-    fs::{get_full_path, inode, open_file, pipe, OpenFlags, Stat},
-    mm::{translated_byte_buffer, translated_mut_ref, translated_str, UserBuffer},
-    task::{current_pcb, current_user_token},
+    config::PATH_MAX,
+    fs::{
+        create_error_code,
+        eventfd::EventFd,
+        get_full_path, inode,
+        memfd::MemFd,
+        open_file, pipe,
+        proc::{is_generated, open_proc_file},
+        File, OpenFlags, PollEvents, PollFd, Stat, Statfs, Stdout,
+    },
+    mm::{
+        translated_byte_buffer, translated_mut_ref, translated_ref, translated_str, BadAddress,
+        BadString, UserBuffer,
+    },
+    task::{
+        block_current_and_run_next, current_pcb, current_tcb, current_user_token,
+        unmasked_signal_pending_of_current, SignalFlags,
+    },
+    timer,
 };
+use alloc::{string::String, sync::Arc, vec, vec::Vec};
 use core::ptr::slice_from_raw_parts;
-use easy_fs::DIRENT_SIZE;
+use easy_fs::{
+    validate_name, CreateError, DirEntry, Inode, NameError, XattrError, BLOCK_SIZE, DIRENT_SIZE,
+    XATTR_VALUE_MAX,
+};
 
 /// Retrieves the current working directory of the calling process.
 ///
 /// This function copies the current working directory into a user-provided buffer, up to the specified `len`.
+/// Nothing is copied if the buffer is too small, the caller can retry with the reported length.
 ///
 /// # Arguments
 ///
@@ -20,19 +41,25 @@ use easy_fs::DIRENT_SIZE;
 /// # Returns
 ///
 /// * The length of the directory path if successful.
-/// * `-1` if the buffer is too small.
+/// * The negated length of the directory path if the buffer is too small.
+/// * `-14` if `buf` is not a valid user pointer.
 pub fn sys_getcwd(buf: *const u8, len: usize) -> isize {
     let token = current_user_token();
     let process = current_pcb();
     let process_inner = process.inner_exclusive_access();
 
-    let mut user_buffer = UserBuffer::new(translated_byte_buffer(token, buf, len));
     let cwd = process_inner.cwd.as_bytes();
 
     if cwd.len() > len {
-        return -1;
+        return -(cwd.len() as isize);
     }
 
+    let Ok(buffers) = translated_byte_buffer(token, buf, len) else {
+        return -14;
+    };
+
+    let mut user_buffer = UserBuffer::new(buffers);
+
     user_buffer
         .iter_mut()
         .zip(cwd)
@@ -41,6 +68,58 @@ pub fn sys_getcwd(buf: *const u8, len: usize) -> isize {
     cwd.len() as isize
 }
 
+/// Resolves `path` to its canonical absolute form and copies it into a user-provided buffer.
+///
+/// Relative paths are resolved against the current working directory, and `.`, `..` and
+/// repeated separators are removed the same way `open` resolves them. The file system has
+/// neither symbolic links nor mount points yet, so the normalized path is already canonical.
+///
+/// # Arguments
+///
+/// * `path` - A pointer to the path to resolve.
+/// * `buf` - A pointer to the buffer where the resolved path should be copied.
+/// * `len` - The maximum number of bytes to copy into the buffer.
+///
+/// # Returns
+///
+/// * The length of the resolved path if successful.
+/// * `-1` if the path does not exist.
+/// * `-2` if the buffer is too small.
+/// * `-1` if `path` is longer than [`PATH_MAX`].
+/// * `-14` if `path` or `buf` is not a valid user pointer.
+pub fn sys_realpath(path: *const u8, buf: *const u8, len: usize) -> isize {
+    let token = current_user_token();
+    let process = current_pcb();
+    let process_inner = process.inner_exclusive_access();
+
+    let path = match translated_str(token, path, PATH_MAX) {
+        Ok(path) => path,
+        Err(err) => return err.code(),
+    };
+    let path = get_full_path(&process_inner.cwd, &path);
+
+    drop(process_inner);
+
+    if inode::find(&path).is_none() {
+        return -1;
+    }
+    if path.len() > len {
+        return -2;
+    }
+
+    let Ok(buffers) = translated_byte_buffer(token, buf, len) else {
+        return -14;
+    };
+
+    let mut user_buffer = UserBuffer::new(buffers);
+    user_buffer
+        .iter_mut()
+        .zip(path.as_bytes())
+        .for_each(|(p, &c)| unsafe { *p = c });
+
+    path.len() as isize
+}
+
 /// Duplicates an open file descriptor.
 ///
 /// Returns a new file descriptor that refers to the same file as the original file descriptor `fd`.
@@ -55,16 +134,11 @@ pub fn sys_getcwd(buf: *const u8, len: usize) -> isize {
 /// * `-1` if the original file descriptor is invalid.
 pub fn sys_dup(fd: usize) -> isize {
     let process = current_pcb();
-    let mut process_inner = process.inner_exclusive_access();
+    let process_inner = process.inner_exclusive_access();
 
     match process_inner.fd_table.get(fd) {
-        Some(Some(file)) => {
-            let new_file = file.clone();
-            let new_fd = process_inner.alloc_fd();
-            process_inner.fd_table[new_fd] = Some(new_file);
-            new_fd as isize
-        }
-        _ => -1,
+        Some(file) => process_inner.fd_table.insert(file, false) as isize,
+        None => -1,
     }
 }
 
@@ -83,23 +157,13 @@ pub fn sys_dup(fd: usize) -> isize {
 /// * `-1` if either `old_fd` or `new_fd` is invalid.
 pub fn sys_dup2(old_fd: usize, new_fd: usize) -> isize {
     let process = current_pcb();
-    let mut process_inner = process.inner_exclusive_access();
-
-    let fd_table = &mut process_inner.fd_table;
-    if old_fd >= fd_table.len() {
-        return -1;
-    }
-    if new_fd >= fd_table.len() {
-        fd_table.resize(new_fd + 1, None);
-    }
+    let fd_table = process.inner_exclusive_access().fd_table.clone();
 
-    if let Some(file) = &fd_table[old_fd] {
-        fd_table[new_fd] = Some(file.clone());
-    } else {
-        fd_table[new_fd] = None;
+    // the replaced file may flush its buffered writes as it is dropped, with the table released
+    match fd_table.duplicate_to(old_fd, new_fd) {
+        Ok(_replaced) => 0,
+        Err(()) => -1,
     }
-
-    0
 }
 
 /// Changes the current working directory of the calling process.
@@ -113,12 +177,17 @@ pub fn sys_dup2(old_fd: usize, new_fd: usize) -> isize {
 /// * `0` if successful.
 /// * `-1` if no such file.
 /// * `-2` if is not a directory.
+/// * `-1` if `path` is longer than [`PATH_MAX`].
+/// * `-14` if `path` is not a valid user pointer.
 pub fn sys_chdir(path: *const u8) -> isize {
     let token = current_user_token();
     let process = current_pcb();
     let process_inner = process.inner_exclusive_access();
 
-    let path = translated_str(token, path);
+    let path = match translated_str(token, path, PATH_MAX) {
+        Ok(path) => path,
+        Err(err) => return err.code(),
+    };
     let path = get_full_path(&process_inner.cwd, &path);
 
     drop(process_inner);
@@ -136,36 +205,162 @@ pub fn sys_chdir(path: *const u8) -> isize {
     }
 }
 
+/// Changes the current working directory of the calling process to an open directory.
+///
+/// The directory may have been opened with `O_PATH`.
+///
+/// # Arguments
+///
+/// * `fd` - The file descriptor of the new working directory.
+///
+/// # Returns
+///
+/// * `0` if successful.
+/// * `-1` if the file descriptor is invalid or the directory has been removed.
+/// * `-2` if is not a directory.
+pub fn sys_fchdir(fd: usize) -> isize {
+    let process = current_pcb();
+    let process_inner = process.inner_exclusive_access();
+
+    let Some(file) = process_inner.fd_table.get(fd) else {
+        return -1;
+    };
+    drop(process_inner);
+
+    let Some(dir) = file.as_os_inode().map(inode::OSInode::inode) else {
+        return -2; // not dir
+    };
+    if !dir.is_dir() {
+        return -2; // not dir
+    }
+    let Some(path) = inode::path_of(&dir) else {
+        return -1;
+    };
+    process.inner_exclusive_access().cwd = path;
+    0
+}
+
+/// Resolves relative paths against the current working directory in the `*at` calls
+const AT_FDCWD: isize = -100;
+
+/// Resolves `path` into its parent directory and final component.
+///
+/// Absolute paths ignore `dirfd`, relative ones are resolved against the directory open
+/// as `dirfd`, or against the current working directory if it is `AT_FDCWD`. Returns
+/// `None` if `dirfd` is not an open directory, a directory on the way does not exist, or
+/// the final component is empty, `.` or `..`.
+fn resolve_at(dirfd: isize, path: &str) -> Option<(Arc<Inode>, String)> {
+    let process = current_pcb();
+    let process_inner = process.inner_exclusive_access();
+
+    let (parent, target) = if path.starts_with('/') || dirfd == AT_FDCWD {
+        let path = get_full_path(&process_inner.cwd, path);
+        drop(process_inner);
+        let (parent_path, target) = path.rsplit_once('/')?;
+        (inode::find(parent_path)?, String::from(target))
+    } else {
+        let dir = usize::try_from(dirfd)
+            .ok()
+            .and_then(|fd| process_inner.fd_table.get(fd))?;
+        drop(process_inner);
+        let dir = dir.as_os_inode()?.inode();
+        if !dir.is_dir() {
+            return None;
+        }
+        let (parent_path, target) = path.rsplit_once('/').unwrap_or(("", path));
+        let parent = parent_path.split('/').try_fold(dir, |node, name| {
+            if name.is_empty() {
+                Some(node)
+            } else {
+                node.find(name)
+            }
+        })?;
+        (parent, String::from(target))
+    };
+    if matches!(target.as_str(), "" | "." | "..") {
+        return None;
+    }
+    Some((parent, target))
+}
+
+/// Whether `name` is too long for a directory entry
+///
+/// [`resolve_at`] and the path walk already leave no empty name and no `/`, so this is the
+/// only way such a name fails [`validate_name`].
+fn name_too_long(name: &str) -> bool {
+    matches!(validate_name(name), Err(NameError::TooLong(_)))
+}
+
+/// Reads the target of a symbolic link into a user-provided buffer.
+///
+/// The path is resolved the same way as in the other `*at` calls. The file system has no
+/// symbolic links yet, so any path that exists is reported as not being one.
+///
+/// # Arguments
+///
+/// * `dirfd` - The directory a relative `path` is resolved against, or `AT_FDCWD`.
+/// * `path` - A pointer to the path of the link.
+/// * `buf` - A pointer to the buffer where the target should be copied, without a null terminator.
+/// * `len` - The maximum number of bytes to copy into the buffer.
+///
+/// # Returns
+///
+/// * The length of the target copied if successful.
+/// * `-1` if the path does not exist.
+/// * `-2` if the path is not a symbolic link.
+/// * `-1` if `path` is longer than [`PATH_MAX`].
+/// * `-14` if `path` is not a valid user pointer.
+pub fn sys_readlinkat(dirfd: isize, path: *const u8, _buf: *mut u8, _len: usize) -> isize {
+    let token = current_user_token();
+    let path = match translated_str(token, path, PATH_MAX) {
+        Ok(path) => path,
+        Err(err) => return err.code(),
+    };
+
+    let Some((parent_inode, target)) = resolve_at(dirfd, &path) else {
+        return -1;
+    };
+    if parent_inode.find(&target).is_none() {
+        return -1;
+    }
+    -2
+}
+
 /// Creates a new directory at the specified path.
 ///
 /// # Arguments
 ///
+/// * `dirfd` - The directory a relative `path` is resolved against, or `AT_FDCWD`.
 /// * `path` - A pointer to the path where the directory will be created.
 ///
 /// # Returns
 ///
 /// * `0` on successful creation.
 /// * `-1` if the parent directory does not exist or cannot be accessed.
-/// * `-2` if the directory cannot be created (e.g., due to permissions or if the directory already exists).
-pub fn sys_mkdir(path: *const u8) -> isize {
+/// * `-2` if an entry named as the directory already exists, kept from before
+///   [`create_error_code`] and its `-17`.
+/// * `-1` if `path` is longer than [`PATH_MAX`].
+/// * `-14` if `path` is not a valid user pointer.
+/// * `-36` if the last component of `path` is longer than [`easy_fs::NAME_LENGTH_LIMIT`].
+/// * The [`create_error_code`] of any other reason the directory could not be created.
+pub fn sys_mkdirat(dirfd: isize, path: *const u8) -> isize {
     let token = current_user_token();
-    let process = current_pcb();
-    let process_inner = process.inner_exclusive_access();
-
-    let path = translated_str(token, path);
-    let path = get_full_path(&process_inner.cwd, &path);
-
-    drop(process_inner);
+    let path = match translated_str(token, path, PATH_MAX) {
+        Ok(path) => path,
+        Err(err) => return err.code(),
+    };
 
-    let (parent_path, target) = path
-        .rsplit_once('/')
-        .expect("Invalid path: the path must contain a '/'.");
-    match inode::find(parent_path) {
-        Some(parent_inode) => match parent_inode.create_dir(target) {
-            Some(_cur_inode) => 0,
-            None => -2,
-        },
-        None => -1,
+    let Some((parent_inode, target)) = resolve_at(dirfd, &path) else {
+        return -1;
+    };
+    if name_too_long(&target) {
+        return -36;
+    }
+    match parent_inode.try_create_dir(&target) {
+        Ok(_cur_inode) => 0,
+        // an existing entry has always been -2 here, unlike the -17 of `open_file`
+        Err(CreateError::Exists) => -2,
+        Err(err) => create_error_code(err),
     }
 }
 
@@ -175,6 +370,7 @@ const AT_REMOVEDIR: u32 = 1;
 ///
 /// # Arguments
 ///
+/// * `dirfd` - The directory a relative `path` is resolved against, or `AT_FDCWD`.
 /// * `path` - A pointer to the path of the file or directory to delete.
 /// * `flags` - Modification flags (e.g., `AT_REMOVEDIR` to specify directory removal).
 ///
@@ -184,44 +380,266 @@ const AT_REMOVEDIR: u32 = 1;
 /// * `-1` if the path does not exist.
 /// * `-2` if the type does not match (e.g., trying to delete a directory without `AT_REMOVEDIR`).
 /// * `-3` if the directory is not empty.
-pub fn sys_unlink(path: *const u8, flags: u32) -> isize {
+/// * `-1` if `path` is longer than [`PATH_MAX`].
+/// * `-14` if `path` is not a valid user pointer.
+pub fn sys_unlinkat(dirfd: isize, path: *const u8, flags: u32) -> isize {
+    let token = current_user_token();
+    let path = match translated_str(token, path, PATH_MAX) {
+        Ok(path) => path,
+        Err(err) => return err.code(),
+    };
+
+    let Some((parent_inode, target)) = resolve_at(dirfd, &path) else {
+        return -1;
+    };
+    match parent_inode.find(&target) {
+        Some(inode) => {
+            let remove_dir = flags & AT_REMOVEDIR == AT_REMOVEDIR;
+            if !remove_dir && !inode.is_dir() {
+                inode::discard_write_buffer(inode.inode_id());
+                inode.clear();
+                parent_inode.delete(&target);
+                return 0;
+            }
+            if remove_dir && inode.is_dir() {
+                if inode.is_empty_dir() {
+                    inode.clear();
+                    parent_inode.delete(&target);
+                    return 0;
+                }
+                return -3; // not empty
+            }
+            -2 // type not matched
+        }
+        None => -1,
+    }
+}
+
+/// Moves a file or directory to a new path, possibly in another directory.
+///
+/// Working directories are kept as paths, so a process inside a moved directory has to
+/// change its directory again.
+///
+/// # Arguments
+///
+/// * `olddirfd` - The directory a relative `oldpath` is resolved against, or `AT_FDCWD`.
+/// * `oldpath` - A pointer to the path of the file or directory to move.
+/// * `newdirfd` - The directory a relative `newpath` is resolved against, or `AT_FDCWD`.
+/// * `newpath` - A pointer to the new path.
+///
+/// # Returns
+///
+/// * `0` on success.
+/// * `-1` if `oldpath` or the parent of `newpath` does not exist.
+/// * `-2` if `newpath` already exists.
+/// * `-3` if a directory would be moved into itself or one of its subdirectories.
+/// * `-1` if either path is longer than [`PATH_MAX`].
+/// * `-14` if `oldpath` or `newpath` is not a valid user pointer.
+/// * `-36` if the last component of `newpath` is longer than [`easy_fs::NAME_LENGTH_LIMIT`].
+pub fn sys_renameat(
+    olddirfd: isize,
+    oldpath: *const u8,
+    newdirfd: isize,
+    newpath: *const u8,
+) -> isize {
+    sys_renameat2(olddirfd, oldpath, newdirfd, newpath, 0)
+}
+
+/// Fail rather than move onto an existing `newpath`
+const RENAME_NOREPLACE: u32 = 1;
+/// Swap `oldpath` and `newpath`, both of which must exist
+const RENAME_EXCHANGE: u32 = 2;
+
+/// Moves a file or directory like [`sys_renameat`], or swaps two of them.
+///
+/// With `RENAME_EXCHANGE` both entries keep their names and trade inodes at once, so
+/// neither path is ever missing, the way to replace a file by a prepared copy.
+///
+/// # Arguments
+///
+/// * `olddirfd` - The directory a relative `oldpath` is resolved against, or `AT_FDCWD`.
+/// * `oldpath` - A pointer to the path of the file or directory to move.
+/// * `newdirfd` - The directory a relative `newpath` is resolved against, or `AT_FDCWD`.
+/// * `newpath` - A pointer to the new path.
+/// * `flags` - `0`, `RENAME_NOREPLACE` or `RENAME_EXCHANGE`.
+///
+/// # Returns
+///
+/// * `0` on success.
+/// * `-1` if `oldpath` or the parent of `newpath` does not exist, if the parent of `newpath`
+///   has no room for another entry, or with `RENAME_EXCHANGE`, if `newpath` does not exist
+///   or either path ends in `.` or `..`.
+/// * `-2` if `newpath` already exists.
+/// * `-3` if a directory would be moved into itself or one of its subdirectories.
+/// * `-1` if either path is longer than [`PATH_MAX`].
+/// * `-14` if `oldpath` or `newpath` is not a valid user pointer.
+/// * `-17` if `newpath` already exists with `RENAME_NOREPLACE`.
+/// * `-22` if `flags` has unknown bits or both flags set.
+/// * `-36` if the last component of `newpath` is longer than [`easy_fs::NAME_LENGTH_LIMIT`].
+pub fn sys_renameat2(
+    olddirfd: isize,
+    oldpath: *const u8,
+    newdirfd: isize,
+    newpath: *const u8,
+    flags: u32,
+) -> isize {
+    if flags & !(RENAME_NOREPLACE | RENAME_EXCHANGE) != 0
+        || flags == RENAME_NOREPLACE | RENAME_EXCHANGE
+    {
+        return -22;
+    }
+    let token = current_user_token();
+    let oldpath = match translated_str(token, oldpath, PATH_MAX) {
+        Ok(oldpath) => oldpath,
+        Err(err) => return err.code(),
+    };
+    let newpath = match translated_str(token, newpath, PATH_MAX) {
+        Ok(newpath) => newpath,
+        Err(err) => return err.code(),
+    };
+
+    let (Some((old_parent, old_target)), Some((new_parent, new_target))) = (
+        resolve_at(olddirfd, &oldpath),
+        resolve_at(newdirfd, &newpath),
+    ) else {
+        return -1;
+    };
+    let Some(inode) = old_parent.find(&old_target) else {
+        return -1;
+    };
+    if name_too_long(&new_target) {
+        return -36;
+    }
+    if flags & RENAME_EXCHANGE != 0 {
+        let Some(other) = new_parent.find(&new_target) else {
+            return -1;
+        };
+        // each side must stay off the way from its new parent to the root
+        if (inode.is_dir() && inode.is_ancestor_of(&new_parent))
+            || (other.is_dir() && other.is_ancestor_of(&old_parent))
+        {
+            return -3;
+        }
+        return if old_parent.exchange(&old_target, &new_parent, &new_target) {
+            0
+        } else {
+            -1
+        };
+    }
+    if new_parent.find(&new_target).is_some() {
+        return if flags & RENAME_NOREPLACE != 0 {
+            -17
+        } else {
+            -2
+        };
+    }
+
+    // the moved directory must not be on the way from the new parent to the root
+    if inode.is_dir() && inode.is_ancestor_of(&new_parent) {
+        return -3;
+    }
+
+    if old_parent.rename(&old_target, &new_parent, &new_target) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// Truncates or extends the file at the specified path to exactly `len` bytes.
+///
+/// Shrinking frees the blocks past the new end, extending fills the new bytes with zeros.
+///
+/// # Arguments
+///
+/// * `path` - A pointer to the path of the file.
+/// * `len` - The new length of the file in bytes.
+///
+/// # Returns
+///
+/// * `0` on success.
+/// * `-1` if the path does not exist or `len` is too large.
+/// * `-2` if the path is a directory.
+/// * `-5` if the block device failed.
+/// * `-28` if the file system has no room to extend the file.
+/// * `-1` if `path` is longer than [`PATH_MAX`].
+/// * `-14` if `path` is not a valid user pointer.
+pub fn sys_truncate(path: *const u8, len: usize) -> isize {
     let token = current_user_token();
     let process = current_pcb();
     let process_inner = process.inner_exclusive_access();
 
-    let path = translated_str(token, path);
+    let path = match translated_str(token, path, PATH_MAX) {
+        Ok(path) => path,
+        Err(err) => return err.code(),
+    };
     let path = get_full_path(&process_inner.cwd, &path);
 
     drop(process_inner);
 
-    let (parent_path, target) = path
-        .rsplit_once('/')
-        .expect("Invalid path: the path must contain a '/'.");
-    match inode::find(parent_path) {
-        Some(parent_inode) => match parent_inode.find(target) {
-            Some(inode) => {
-                let remove_dir = flags & AT_REMOVEDIR == AT_REMOVEDIR;
-                if !remove_dir && !inode.is_dir() {
-                    inode.clear();
-                    parent_inode.delete(target);
-                    return 0;
-                }
-                if remove_dir && inode.is_dir() {
-                    if inode.file_size() as usize == DIRENT_SIZE * 2 {
-                        inode.clear();
-                        parent_inode.delete(target);
-                        return 0;
-                    }
-                    return -3; // not empty
-                }
-                -2 // type not matched
-            }
-            None => -1,
-        },
+    match inode::find(&path) {
+        Some(inode) => truncate_inode(&inode, len),
         None => -1,
     }
 }
 
+/// Resizes an inode for `truncate` and `ftruncate`, returning their result
+fn truncate_inode(inode: &Inode, len: usize) -> isize {
+    let Ok(len) = u32::try_from(len) else {
+        return -1;
+    };
+    if inode.is_dir() {
+        return -2;
+    }
+    if inode::flush_write_buffer(inode.inode_id()).is_err() {
+        return -5;
+    }
+    if inode.set_len(len) {
+        0
+    } else {
+        -28
+    }
+}
+
+/// Truncates or extends an open file to exactly `len` bytes.
+///
+/// Regular files behave as with `truncate`, in-memory files grow with zeros as well.
+///
+/// # Arguments
+///
+/// * `fd` - The file descriptor of the file, which must be open for writing.
+/// * `len` - The new length of the file in bytes.
+///
+/// # Returns
+///
+/// * `0` on success.
+/// * `-1` if the file descriptor is invalid or not open for writing, if the file cannot
+///   be resized, or if `len` is too large.
+/// * `-2` if the file is a directory.
+/// * `-5` if the block device failed.
+/// * `-28` if the file system has no room to extend the file.
+pub fn sys_ftruncate(fd: usize, len: usize) -> isize {
+    let process = current_pcb();
+    let process_inner = process.inner_exclusive_access();
+
+    let Some(file) = process_inner.fd_table.get(fd) else {
+        return -1;
+    };
+    drop(process_inner);
+
+    if !file.is_writable() {
+        return -1;
+    }
+    if let Some(os_inode) = file.as_os_inode() {
+        return truncate_inode(&os_inode.inode(), len);
+    }
+    if file.set_len(len) {
+        0
+    } else {
+        -1
+    }
+}
+
 /// Opens or creates a file or directory with specified flags.
 ///
 /// # Arguments
@@ -233,24 +651,58 @@ pub fn sys_unlink(path: *const u8, flags: u32) -> isize {
 ///
 /// * A file descriptor on success.
 /// * `-1` on failure.
+/// * `-1` if `path` is longer than [`PATH_MAX`].
+/// * `-14` if `path` is not a valid user pointer.
+/// * `-28` if no inode or data block is left to create the file.
+/// * `-31` if the directory to create the file in already holds [`easy_fs::DIRENT_LIMIT`]
+///   entries.
+/// * `-36` if the file would be created under a name longer than [`easy_fs::NAME_LENGTH_LIMIT`].
+///
+/// With [`OpenFlags::PATH`] the file is neither read nor written through the descriptor,
+/// which only serves `fstat`, `fchdir` and the `*at` calls. Other flags but `CLOEXEC` are
+/// then ignored, and the file is not required to be readable.
+///
+/// [`OpenFlags::NOFOLLOW`] is accepted, but the file system has no symbolic links yet for
+/// it to refuse.
 pub fn sys_open(path: *const u8, flags: u32) -> isize {
     let token = current_user_token();
     let process = current_pcb();
     let process_inner = process.inner_exclusive_access();
 
-    let path = translated_str(token, path);
+    let path = match translated_str(token, path, PATH_MAX) {
+        Ok(path) => path,
+        Err(err) => return err.code(),
+    };
     let path = get_full_path(&process_inner.cwd, &path);
 
     drop(process_inner);
 
-    if let Some(inode) = open_file(path.as_str(), OpenFlags::from_bits(flags).unwrap()) {
-        let mut process_inner = process.inner_exclusive_access();
-        let fd = process_inner.alloc_fd();
-        process_inner.fd_table[fd] = Some(inode);
-        fd as isize
-    } else {
-        -1
+    let flags = OpenFlags::from_bits(flags).unwrap();
+    let creating = flags.contains(OpenFlags::CREATE) && !flags.contains(OpenFlags::PATH);
+    if creating && path.rsplit('/').next().is_some_and(name_too_long) {
+        return -36;
     }
+    // with `O_PATH` the placeholder inode of a process file is opened rather than its data
+    let proc_file = if flags.contains(OpenFlags::PATH) {
+        None
+    } else {
+        open_proc_file(&path)
+    };
+    let file: Arc<dyn File + Send + Sync> = match proc_file {
+        Some(file) => file,
+        // descriptors listed under `/proc/<pid>/fd` are never created on disk
+        None if is_generated(&path) => return -1,
+        None => match open_file(path.as_str(), flags) {
+            Ok(inode) => inode,
+            Err(code) => return code,
+        },
+    };
+    file.set_status_flags(flags);
+    let process_inner = process.inner_exclusive_access();
+    let fd = process_inner
+        .fd_table
+        .insert(file, flags.contains(OpenFlags::CLOEXEC));
+    fd as isize
 }
 
 /// Closes an open file descriptor.
@@ -265,16 +717,48 @@ pub fn sys_open(path: *const u8, flags: u32) -> isize {
 /// * `-1` if the file descriptor is invalid.
 pub fn sys_close(fd: usize) -> isize {
     let process = current_pcb();
-    let mut process_inner = process.inner_exclusive_access();
+    let fd_table = process.inner_exclusive_access().fd_table.clone();
+
+    // the file may flush its buffered writes as it is dropped, with the process released
+    match fd_table.remove(fd) {
+        Some(_) => 0,
+        None => -1,
+    }
+}
 
-    if fd >= process_inner.fd_table.len() {
+/// Mark the descriptors close-on-exec instead of closing them
+const CLOSE_RANGE_CLOEXEC: u32 = 4;
+
+/// Closes every open file descriptor from `first` to `last`, both included.
+///
+/// Descriptors in the range that are not open are skipped, and `last` may lie past the end
+/// of the table, `u32::MAX` reaching to its end whatever its size.
+///
+/// # Arguments
+///
+/// * `first` - The lowest descriptor to close.
+/// * `last` - The highest descriptor to close.
+/// * `flags` - `CLOSE_RANGE_CLOEXEC` to mark the descriptors close-on-exec rather than
+///   close them, or `0`.
+///
+/// # Returns
+///
+/// * `0` on success.
+/// * `-1` if `first` is greater than `last` or `flags` holds an unknown flag.
+pub fn sys_close_range(first: usize, last: usize, flags: u32) -> isize {
+    if first > last || flags & !CLOSE_RANGE_CLOEXEC != 0 {
         return -1;
     }
+    let process = current_pcb();
+    let fd_table = process.inner_exclusive_access().fd_table.clone();
 
-    match process_inner.fd_table[fd].take() {
-        Some(_) => 0,
-        None => -1,
+    if flags & CLOSE_RANGE_CLOEXEC != 0 {
+        fd_table.set_cloexec_range(first, last);
+    } else {
+        // as for `close`, the files are dropped with the process released
+        drop(fd_table.remove_range(first, last));
     }
+    0
 }
 
 /// Reads data from an open file descriptor into a buffer.
@@ -289,63 +773,419 @@ pub fn sys_close(fd: usize) -> isize {
 ///
 /// * The number of bytes read on success.
 /// * `-1` on failure or if the file descriptor is invalid.
+/// * `-5` if the block device failed.
+/// * `-14` if `buf` is not a valid user pointer.
 pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> isize {
     let token = current_user_token();
     let process = current_pcb();
     let process_inner = process.inner_exclusive_access();
 
-    if fd >= process_inner.fd_table.len() {
-        return -1;
-    }
-
-    if let Some(file) = &process_inner.fd_table[fd] {
-        let file = file.clone();
+    if let Some(file) = process_inner.fd_table.get(fd) {
         if !file.is_readable() {
             return -1;
         }
         // release current task TCB manually to avoid multi-borrow
         drop(process_inner);
-        file.read(UserBuffer::new(translated_byte_buffer(token, buf, len))) as isize
+        let Ok(buffers) = translated_byte_buffer(token, buf, len) else {
+            return -14;
+        };
+        let read_size = file.read(UserBuffer::new(buffers));
+        if file.take_io_error() {
+            return -5;
+        }
+        read_size as isize
     } else {
         -1
     }
 }
 
-/// Writes data to an open file descriptor from a buffer.
+const SEEK_SET: usize = 0;
+const SEEK_CUR: usize = 1;
+const SEEK_END: usize = 2;
+
+/// Moves the offset of an open file.
+///
+/// The offset may go past the end of the file, writing there leaves a gap of zeros.
 ///
 /// # Arguments
 ///
-/// * `fd` - The file descriptor to write to.
-/// * `buf` - A pointer to the buffer containing the data to write.
-/// * `len` - The number of bytes to write.
+/// * `fd` - The file descriptor of the file.
+/// * `offset` - The new offset, relative to where `whence` says.
+/// * `whence` - `SEEK_SET` for the start of the file, `SEEK_CUR` for the current offset,
+///   or `SEEK_END` for the end of the file.
 ///
 /// # Returns
 ///
-/// * The number of bytes written on success,
-/// * `-1` on failure or if the file descriptor is invalid.
-pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
-    let token = current_user_token();
+/// * The new offset from the start of the file on success.
+/// * `-1` if the file descriptor is invalid, `whence` is unknown or the new offset would
+///   be negative.
+/// * `-29` if the file is a pipe or terminal, which has no offset.
+pub fn sys_lseek(fd: usize, offset: isize, whence: usize) -> isize {
     let process = current_pcb();
     let process_inner = process.inner_exclusive_access();
 
-    if fd >= process_inner.fd_table.len() {
+    let Some(file) = process_inner.fd_table.get(fd) else {
         return -1;
-    }
+    };
+    drop(process_inner);
 
-    if let Some(file) = &process_inner.fd_table[fd] {
-        if !file.is_writable() {
-            return -1;
-        }
-        let file = file.clone();
-        // release current task TCB manually to avoid multi-borrow
-        drop(process_inner);
-        file.write(UserBuffer::new(translated_byte_buffer(token, buf, len))) as isize
-    } else {
-        -1
+    if !file.is_seekable() {
+        return -29;
     }
+    let base = match whence {
+        SEEK_SET => 0,
+        SEEK_CUR => file.offset(),
+        SEEK_END => file.file_size() as usize,
+        _ => return -1,
+    };
+    let Some(new_offset) = base
+        .checked_add_signed(offset)
+        .filter(|&new_offset| isize::try_from(new_offset).is_ok())
+    else {
+        return -1;
+    };
+    file.set_offset(new_offset);
+    new_offset as isize
 }
 
-/// Retrieves file status information, writing it to a specified buffer.
+/// Reads the entries of an open directory into a buffer.
+///
+/// Entries are copied whole, [`DIRENT_SIZE`] bytes each, and every call continues after
+/// the last entry the previous one copied. Since entries are taken in the order of
+/// their inode numbers rather than where they are stored, creating or removing entries
+/// in between never makes the remaining ones be skipped or copied twice. Directories the
+/// kernel generates, such as `/proc/<pid>/fd`, list what they hold at the time of the call.
+///
+/// # Arguments
+///
+/// * `fd` - The file descriptor of the directory.
+/// * `buf` - A pointer to the buffer where the entries will be stored.
+/// * `len` - The size of the buffer in bytes.
+///
+/// # Returns
+///
+/// * The number of bytes copied, `0` once every entry has been copied.
+/// * `-1` if the file descriptor is invalid, not a directory or opened with `O_PATH`, or
+///   if `len` cannot hold a single entry.
+/// * `-14` if `buf` is not a valid user pointer.
+pub fn sys_getdents(fd: usize, buf: *const u8, len: usize) -> isize {
+    let token = current_user_token();
+    let process = current_pcb();
+    let process_inner = process.inner_exclusive_access();
+
+    let Some(file) = process_inner.fd_table.get(fd) else {
+        return -1;
+    };
+    drop(process_inner);
+    if !file.is_readable() {
+        return -1;
+    }
+
+    let count = len / DIRENT_SIZE;
+    if count == 0 {
+        return -1;
+    }
+    // checked before taking entries so that none are lost to a bad pointer
+    let Ok(buffers) = translated_byte_buffer(token, buf, count * DIRENT_SIZE) else {
+        return -14;
+    };
+    let entries = match file.as_os_inode() {
+        Some(inode) => inode.read_dirents(count),
+        None => file.read_dirents(count),
+    };
+    let Some(entries) = entries else {
+        return -1;
+    };
+
+    // the file system lock is not held while user memory is written
+    UserBuffer::new(buffers)
+        .iter_mut()
+        .zip(entries.iter().flat_map(DirEntry::as_bytes))
+        .for_each(|(p, &c)| unsafe { *p = c });
+    (entries.len() * DIRENT_SIZE) as isize
+}
+
+/// Writes data to an open file descriptor from a buffer.
+///
+/// # Arguments
+///
+/// * `fd` - The file descriptor to write to.
+/// * `buf` - A pointer to the buffer containing the data to write.
+/// * `len` - The number of bytes to write.
+///
+/// # Returns
+///
+/// * The number of bytes written on success, fewer than `len` if the file system filled up.
+/// * `-1` on failure or if the file descriptor is invalid.
+/// * `-5` if the block device failed.
+/// * `-14` if `buf` is not a valid user pointer.
+/// * `-28` if the file system is full and nothing could be written.
+pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
+    let token = current_user_token();
+    let process = current_pcb();
+    let process_inner = process.inner_exclusive_access();
+
+    if let Some(file) = process_inner.fd_table.get(fd) {
+        if !file.is_writable() {
+            return -1;
+        }
+        // release current task TCB manually to avoid multi-borrow
+        drop(process_inner);
+        let Ok(buffers) = translated_byte_buffer(token, buf, len) else {
+            return -14;
+        };
+        let write_size = file.write(UserBuffer::new(buffers));
+        if file.take_io_error() {
+            return -5;
+        }
+        // a short write reports the bytes that fit, only one writing nothing fails
+        if file.take_no_space() && write_size == 0 {
+            return -28;
+        }
+        write_size as isize
+    } else {
+        -1
+    }
+}
+
+/// Copies data from one open file to another inside the kernel.
+///
+/// If `offset` is null, reading starts at the offset of `in_fd` and advances it. Otherwise
+/// reading starts at `*offset`, which is advanced instead while the offset of `in_fd` is
+/// left alone. The output is written at the offset of `out_fd`, or at its end if it was
+/// opened with `O_APPEND`. Between two regular files the data moves from inode to inode,
+/// other files go through a kernel buffer.
+///
+/// # Arguments
+///
+/// * `out_fd` - The file descriptor to write to.
+/// * `in_fd` - The file descriptor to read from.
+/// * `offset` - A pointer to the offset to read from, or null.
+/// * `count` - The maximum number of bytes to copy.
+///
+/// # Returns
+///
+/// * The number of bytes copied, fewer than `count` if the end of `in_fd` was reached.
+/// * `-1` if a file descriptor is invalid or has the wrong access mode, or if `offset` is
+///   given for a file that is not a regular file.
+/// * `-5` if the block device failed.
+/// * `-14` if `offset` is not a valid user pointer.
+/// * `-28` if the file system is full and nothing could be copied.
+pub fn sys_sendfile(out_fd: usize, in_fd: usize, offset: *mut usize, count: usize) -> isize {
+    let token = current_user_token();
+    let process = current_pcb();
+    let process_inner = process.inner_exclusive_access();
+
+    let (Some(in_file), Some(out_file)) = (
+        process_inner.fd_table.get(in_fd),
+        process_inner.fd_table.get(out_fd),
+    ) else {
+        return -1;
+    };
+    drop(process_inner);
+
+    if !in_file.is_readable() || !out_file.is_writable() {
+        return -1;
+    }
+    if !offset.is_null() && in_file.as_os_inode().is_none() {
+        return -1;
+    }
+
+    let start = if offset.is_null() {
+        in_file.offset()
+    } else {
+        // checked up front, the offset is written back after copying
+        let Ok(offset) = translated_mut_ref(token, offset) else {
+            return -14;
+        };
+        *offset
+    };
+    let copied = if let (Some(src), Some(dst)) = (in_file.as_os_inode(), out_file.as_os_inode()) {
+        src.copy_to(start, dst, count)
+    } else {
+        let original = in_file.offset();
+        in_file.set_offset(start);
+        let copied = copy_through_buffer(&in_file, &out_file, count);
+        in_file.set_offset(original);
+        copied
+    };
+
+    // report errors of both ends
+    let in_error = in_file.take_io_error();
+    if out_file.take_io_error() || in_error {
+        return -5;
+    }
+    if out_file.take_no_space() && copied == 0 {
+        return -28;
+    }
+    if offset.is_null() {
+        in_file.set_offset(start + copied);
+    } else {
+        *translated_mut_ref(token, offset).unwrap() = start + copied;
+    }
+    copied as isize
+}
+
+/// Copy up to `count` bytes from the offset of `in_file` to `out_file` through a kernel buffer
+fn copy_through_buffer(
+    in_file: &Arc<dyn File + Send + Sync>,
+    out_file: &Arc<dyn File + Send + Sync>,
+    count: usize,
+) -> usize {
+    let mut buffer = vec![0u8; inode::COPY_CHUNK_SIZE.min(count)];
+    let mut copied = 0;
+    while copied < count {
+        let len = (count - copied).min(inode::COPY_CHUNK_SIZE);
+        let read_size = in_file.read(unsafe { UserBuffer::from_kernel(&mut buffer[..len]) });
+        if read_size == 0 {
+            break;
+        }
+        let write_size =
+            out_file.write(unsafe { UserBuffer::from_kernel(&mut buffer[..read_size]) });
+        copied += write_size;
+        if write_size < read_size {
+            break;
+        }
+    }
+    copied
+}
+
+/// Copies a range of one regular file to another, or to elsewhere in the same file.
+///
+/// Each offset pointer works like the one of [`sys_sendfile`]: if null, the offset of the
+/// file is used and advanced, otherwise `*off_in` or `*off_out` is used and advanced
+/// instead. The data moves from inode to inode without going through user memory.
+///
+/// # Arguments
+///
+/// * `fd_in` - The file descriptor to read from.
+/// * `off_in` - A pointer to the offset to read from, or null.
+/// * `fd_out` - The file descriptor to write to.
+/// * `off_out` - A pointer to the offset to write to, or null.
+/// * `len` - The maximum number of bytes to copy.
+///
+/// # Returns
+///
+/// * The number of bytes copied, fewer than `len` if the end of `fd_in` was reached.
+/// * `-1` if a file descriptor is invalid, has the wrong access mode or is not a regular
+///   file, or if `fd_out` was opened with `O_APPEND`.
+/// * `-5` if the block device failed.
+/// * `-14` if `off_in` or `off_out` is not a valid user pointer.
+/// * `-22` if both ranges are in the same file and overlap.
+/// * `-28` if the file system is full and nothing could be copied.
+pub fn sys_copy_file_range(
+    fd_in: usize,
+    off_in: *mut usize,
+    fd_out: usize,
+    off_out: *mut usize,
+    len: usize,
+) -> isize {
+    let token = current_user_token();
+    let process = current_pcb();
+    let process_inner = process.inner_exclusive_access();
+    let (Some(in_file), Some(out_file)) = (
+        process_inner.fd_table.get(fd_in),
+        process_inner.fd_table.get(fd_out),
+    ) else {
+        return -1;
+    };
+    drop(process_inner);
+
+    let (Some(src), Some(dst)) = (in_file.as_os_inode(), out_file.as_os_inode()) else {
+        return -1;
+    };
+    if !in_file.is_readable()
+        || !out_file.is_writable()
+        || out_file.status_flags().contains(OpenFlags::APPEND)
+    {
+        return -1;
+    }
+
+    // both are checked up front, the offsets are written back after copying
+    let read_offset = |file: &Arc<dyn File + Send + Sync>, offset: *mut usize| {
+        if offset.is_null() {
+            Ok(file.offset())
+        } else {
+            translated_mut_ref(token, offset).map(|offset| *offset)
+        }
+    };
+    let (Ok(start_in), Ok(start_out)) = (
+        read_offset(&in_file, off_in),
+        read_offset(&out_file, off_out),
+    ) else {
+        return -14;
+    };
+    if src.inode().inode_id() == dst.inode().inode_id()
+        && start_in < start_out.saturating_add(len)
+        && start_out < start_in.saturating_add(len)
+    {
+        return -22;
+    }
+
+    let copied = src.copy_range(start_in, dst, Some(start_out), len);
+    let in_error = in_file.take_io_error();
+    if out_file.take_io_error() || in_error {
+        return -5;
+    }
+    if out_file.take_no_space() && copied == 0 {
+        return -28;
+    }
+    for (file, offset, start) in [
+        (&in_file, off_in, start_in),
+        (&out_file, off_out, start_out),
+    ] {
+        if offset.is_null() {
+            file.set_offset(start + copied);
+        } else {
+            *translated_mut_ref(token, offset).unwrap() = start + copied;
+        }
+    }
+    copied as isize
+}
+
+/// Copies data from one pipe to another without consuming it.
+///
+/// The bytes copied are still read from `fd_in` afterwards. Only what is already waiting
+/// in `fd_in` and fits in `fd_out` is copied, waiting for the first byte and for room
+/// unless the respective descriptor is non-blocking.
+///
+/// # Arguments
+///
+/// * `fd_in` - The read end of the pipe to copy from.
+/// * `fd_out` - The write end of the pipe to copy to.
+/// * `len` - The maximum number of bytes to copy.
+///
+/// # Returns
+///
+/// * The number of bytes copied, `0` if every write end of `fd_in` is closed and nothing
+///   is left in it, or if `len` is `0`.
+/// * `-1` if a file descriptor is invalid or not the right end of a pipe, or if both refer
+///   to the same pipe.
+/// * `-2` if nothing can be copied without waiting on a non-blocking descriptor.
+pub fn sys_tee(fd_in: usize, fd_out: usize, len: usize) -> isize {
+    let process = current_pcb();
+    let process_inner = process.inner_exclusive_access();
+    let (Some(in_file), Some(out_file)) = (
+        process_inner.fd_table.get(fd_in),
+        process_inner.fd_table.get(fd_out),
+    ) else {
+        return -1;
+    };
+    drop(process_inner);
+
+    let (Some(input), Some(output)) = (in_file.as_pipe(), out_file.as_pipe()) else {
+        return -1;
+    };
+    if !input.is_readable() || !output.is_writable() || input.same_pipe(output) {
+        return -1;
+    }
+    if len == 0 {
+        return 0;
+    }
+    input.tee(output, len).map_or(-2, |copied| copied as isize)
+}
+
+/// Retrieves file status information, writing it to a specified buffer.
 ///
 /// # Arguments
 ///
@@ -356,22 +1196,20 @@ pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
 ///
 /// * `0` on success.
 /// * `-1` if the file descriptor is invalid.
+/// * `-14` if `stat` is not a valid user pointer.
 pub fn sys_fstat(fd: usize, stat: *mut u8) -> isize {
     let token = current_user_token();
     let process = current_pcb();
     let process_inner = process.inner_exclusive_access();
 
-    let mut user_buffer = UserBuffer::new(translated_byte_buffer(
-        token,
-        stat,
-        core::mem::size_of::<Stat>(),
-    ));
+    let Ok(buffers) = translated_byte_buffer(token, stat, core::mem::size_of::<Stat>()) else {
+        return -14;
+    };
+    let mut user_buffer = UserBuffer::new(buffers);
 
-    let fd_table = &process_inner.fd_table;
-    if fd >= fd_table.len() || fd_table[fd].is_none() {
+    let Some(file) = process_inner.fd_table.get(fd) else {
         return -1;
-    }
-    let file = fd_table[fd].clone().unwrap();
+    };
     drop(process_inner);
 
     let stat = Stat::from(file);
@@ -388,6 +1226,406 @@ pub fn sys_fstat(fd: usize, stat: *mut u8) -> isize {
     0
 }
 
+/// Copies the usage figures of the file system holding `inode` to the `Statfs` at `buf`
+fn copy_statfs(token: usize, inode: &Inode, buf: *mut u8) -> isize {
+    let stats = Statfs::from(&*inode.fs().lock());
+    let size = core::mem::size_of::<Statfs>();
+    let bytes = unsafe { core::slice::from_raw_parts((&raw const stats).cast::<u8>(), size) };
+    let Ok(buffers) = translated_byte_buffer(token, buf, size) else {
+        return -14;
+    };
+    let mut user_buffer = UserBuffer::new(buffers);
+    for (p, &b) in user_buffer.iter_mut().zip(bytes) {
+        unsafe {
+            *p = b;
+        }
+    }
+    0
+}
+
+/// Reports the usage of the file system holding the file at the specified path.
+///
+/// # Arguments
+///
+/// * `path` - A pointer to the path of any file or directory on the file system.
+/// * `buf` - A pointer to the `Statfs` to fill.
+///
+/// # Returns
+///
+/// * `0` on success.
+/// * `-1` if the path does not exist or is longer than [`PATH_MAX`].
+/// * `-14` if `path` or `buf` is not a valid user pointer.
+pub fn sys_statfs(path: *const u8, buf: *mut u8) -> isize {
+    let token = current_user_token();
+    let process = current_pcb();
+    let process_inner = process.inner_exclusive_access();
+
+    let path = match translated_str(token, path, PATH_MAX) {
+        Ok(path) => path,
+        Err(err) => return err.code(),
+    };
+    let path = get_full_path(&process_inner.cwd, &path);
+
+    drop(process_inner);
+
+    match inode::find(&path) {
+        Some(inode) => copy_statfs(token, &inode, buf),
+        None => -1,
+    }
+}
+
+/// Reports the usage of the file system holding an open file.
+///
+/// Works the same for files and directories. Pipes and the other files the kernel
+/// makes up live on no file system.
+///
+/// # Arguments
+///
+/// * `fd` - The file descriptor of the file.
+/// * `buf` - A pointer to the `Statfs` to fill.
+///
+/// # Returns
+///
+/// * `0` on success.
+/// * `-1` if the file descriptor is invalid or not open on a file system.
+/// * `-14` if `buf` is not a valid user pointer.
+pub fn sys_fstatfs(fd: usize, buf: *mut u8) -> isize {
+    let token = current_user_token();
+    let process = current_pcb();
+    let process_inner = process.inner_exclusive_access();
+
+    let Some(file) = process_inner.fd_table.get(fd) else {
+        return -1;
+    };
+    drop(process_inner);
+
+    match file.as_os_inode() {
+        Some(os_inode) => copy_statfs(token, &os_inode.inode(), buf),
+        None => -1,
+    }
+}
+
+/// Tells whether an open file descriptor refers to a terminal.
+///
+/// # Arguments
+///
+/// * `fd` - The file descriptor to check.
+///
+/// # Returns
+///
+/// * `1` if the file is the console.
+/// * `0` if it is a regular file, a pipe or any other file.
+/// * `-1` if the file descriptor is invalid.
+pub fn sys_isatty(fd: usize) -> isize {
+    let process = current_pcb();
+    let process_inner = process.inner_exclusive_access();
+
+    let Some(file) = process_inner.fd_table.get(fd) else {
+        return -1;
+    };
+    isize::from(file.is_tty())
+}
+
+/// Points stdout and stderr of the calling process at an open file, or back at the console.
+///
+/// Children forked afterwards inherit the redirection like any other descriptor, so the
+/// daemon can send the output of the shell and everything it starts to a log file. The
+/// console cannot be opened by path, `-1` is the way back to it.
+///
+/// # Arguments
+///
+/// * `fd` - A writable file descriptor, or `-1` for the console.
+///
+/// # Returns
+///
+/// * `0` on success.
+/// * `-1` if `fd` is invalid or not writable.
+pub fn sys_redirect_output(fd: isize) -> isize {
+    let process = current_pcb();
+    let fd_table = process.inner_exclusive_access().fd_table.clone();
+
+    let output: Arc<dyn File + Send + Sync> = if fd == -1 {
+        Arc::new(Stdout)
+    } else {
+        match usize::try_from(fd).ok().and_then(|fd| fd_table.get(fd)) {
+            Some(file) if file.is_writable() => file,
+            _ => return -1,
+        }
+    };
+    // the replaced files are dropped here, outside the table
+    let _replaced = [1, 2].map(|fd| fd_table.set(fd, output.clone()));
+    0
+}
+
+/// Writes the buffered data of an open file back to the device.
+///
+/// # Arguments
+///
+/// * `fd` - The file descriptor of the file.
+///
+/// # Returns
+///
+/// * `0` on success.
+/// * `-1` if the file descriptor is invalid.
+/// * `-5` if the block device failed.
+pub fn sys_fsync(fd: usize) -> isize {
+    let process = current_pcb();
+    let process_inner = process.inner_exclusive_access();
+
+    let Some(file) = process_inner.fd_table.get(fd) else {
+        return -1;
+    };
+    drop(process_inner);
+
+    file.sync();
+    if file.take_io_error() {
+        return -5;
+    }
+    0
+}
+
+/// Writes the buffered data of an open file back to the device, with only the metadata
+/// needed to read it back.
+///
+/// Inodes keep no timestamps, only a size and a block map, which change only as the file
+/// grows. Data written within the file goes straight to its blocks, so what is left to
+/// write back is buffered appends, and the size they grew the file to has to go with them
+/// for the data to be reachable. That leaves nothing `fsync` would write and this would
+/// not, and nothing at all for a file without buffered appends.
+///
+/// # Arguments
+///
+/// * `fd` - The file descriptor of the file.
+///
+/// # Returns
+///
+/// * `0` on success.
+/// * `-1` if the file descriptor is invalid.
+/// * `-5` if the block device failed.
+pub fn sys_fdatasync(fd: usize) -> isize {
+    sys_fsync(fd)
+}
+
+/// Wait for earlier writeback of the range and report its errors, before writing
+const SYNC_FILE_RANGE_WAIT_BEFORE: u32 = 1;
+/// Start writing back the range
+const SYNC_FILE_RANGE_WRITE: u32 = 2;
+/// Wait for the writeback of the range and report its errors
+const SYNC_FILE_RANGE_WAIT_AFTER: u32 = 4;
+
+/// Writes the buffered data of part of an open file back to the device.
+///
+/// The range is widened to whole blocks, and a `len` of `0` extends it to the end of the
+/// file. Without `SYNC_FILE_RANGE_WRITE` nothing is written. Writeback completes before
+/// the call returns, but an error is only reported when asked to wait; otherwise it is kept
+/// for the next wait or `fsync`. Only data is written, the file is not otherwise read or
+/// touched, so no access time would be updated. Files other than regular files have
+/// nothing to write back.
+///
+/// # Arguments
+///
+/// * `fd` - The file descriptor of the file.
+/// * `offset` - The first byte of the range.
+/// * `len` - The length of the range in bytes, or `0` for the rest of the file.
+/// * `flags` - A combination of `SYNC_FILE_RANGE_WAIT_BEFORE`, `SYNC_FILE_RANGE_WRITE` and
+///   `SYNC_FILE_RANGE_WAIT_AFTER`.
+///
+/// # Returns
+///
+/// * `0` on success.
+/// * `-1` if the file descriptor is invalid, `flags` holds an unknown flag, or the range
+///   overflows.
+/// * `-5` if the block device failed and a wait was asked for.
+pub fn sys_sync_file_range(fd: usize, offset: usize, len: usize, flags: u32) -> isize {
+    if flags & !(SYNC_FILE_RANGE_WAIT_BEFORE | SYNC_FILE_RANGE_WRITE | SYNC_FILE_RANGE_WAIT_AFTER)
+        != 0
+    {
+        return -1;
+    }
+    let end = if len == 0 {
+        usize::MAX
+    } else {
+        match offset.checked_add(len) {
+            Some(end) => end,
+            None => return -1,
+        }
+    };
+
+    let process = current_pcb();
+    let process_inner = process.inner_exclusive_access();
+    let Some(file) = process_inner.fd_table.get(fd) else {
+        return -1;
+    };
+    drop(process_inner);
+    let Some(inode) = file.as_os_inode() else {
+        return 0;
+    };
+
+    if flags & SYNC_FILE_RANGE_WAIT_BEFORE != 0 && file.take_io_error() {
+        return -5;
+    }
+    if flags & SYNC_FILE_RANGE_WRITE != 0 {
+        inode.sync_range(offset, end);
+    }
+    if flags & SYNC_FILE_RANGE_WAIT_AFTER != 0 && file.take_io_error() {
+        return -5;
+    }
+    0
+}
+
+/// Reports which blocks of an open file are in the block cache.
+///
+/// Fills one byte per block of `[offset, offset + len)`, `1` if the block is cached and `0`
+/// if it is not or lies past the end of the file. Nothing is loaded into the cache to find
+/// out, so asking does not change the answer, but it is only a snapshot: blocks may be
+/// loaded or evicted as soon as the call returns. Appends still waiting in the write buffer
+/// have not reached the cache yet.
+///
+/// # Arguments
+///
+/// * `fd` - The file descriptor of the file.
+/// * `offset` - The first byte of the range, a multiple of the block size.
+/// * `len` - The length of the range in bytes, rounded up to whole blocks.
+/// * `vec` - A pointer to a buffer of one byte per block of the range.
+///
+/// # Returns
+///
+/// * `0` on success.
+/// * `-1` if the file descriptor is invalid or not open on a file system, `offset` is not
+///   block-aligned, or the range is too large.
+/// * `-5` if the block device failed.
+/// * `-14` if `vec` is not a valid user pointer.
+pub fn sys_fincore(fd: usize, offset: usize, len: usize, vec: *mut u8) -> isize {
+    let token = current_user_token();
+    if offset % BLOCK_SIZE != 0 {
+        return -1;
+    }
+    let (Ok(first), Ok(count)) = (
+        u32::try_from(offset / BLOCK_SIZE),
+        u32::try_from(len.div_ceil(BLOCK_SIZE)),
+    ) else {
+        return -1;
+    };
+
+    let process = current_pcb();
+    let process_inner = process.inner_exclusive_access();
+    let Some(file) = process_inner.fd_table.get(fd) else {
+        return -1;
+    };
+    drop(process_inner);
+    let Some(inode) = file.as_os_inode().map(inode::OSInode::inode) else {
+        return -1;
+    };
+
+    let Ok(buffers) = translated_byte_buffer(token, vec, count as usize) else {
+        return -14;
+    };
+    let Ok(cached) = inode.cached_blocks(first, count) else {
+        return -5;
+    };
+    let mut user_buffer = UserBuffer::new(buffers);
+    for (p, cached) in user_buffer.iter_mut().zip(cached) {
+        unsafe {
+            *p = u8::from(cached);
+        }
+    }
+    0
+}
+
+/// How often a waiting poll checks its files again
+const POLL_INTERVAL_MS: usize = 10;
+
+/// Waits until one of a set of file descriptors is ready, with signals in `sigmask` blocked.
+///
+/// The signal mask is installed for the duration of the wait and the previous mask is restored
+/// before returning, however the wait ends. A pending signal that is not blocked by `sigmask`
+/// interrupts the wait. Without `sigmask`, signals ignored by default such as `SIGCHLD` are
+/// blocked as well, so only callers asking for them are interrupted.
+///
+/// # Arguments
+///
+/// * `fds` - A pointer to an array of `PollFd`, whose `revents` are filled in.
+/// * `nfds` - The number of entries in `fds`.
+/// * `timeout` - The maximum time to wait in milliseconds, or a negative number to wait forever.
+/// * `sigmask` - A pointer to the signals to block while waiting, or null to keep the current mask.
+///
+/// # Returns
+///
+/// * The number of file descriptors with events on success.
+/// * `0` if the timeout expired first.
+/// * `-1` if `sigmask` holds an unknown signal.
+/// * `-2` if the wait was interrupted by a signal.
+/// * `-14` if `fds` or `sigmask` is not a valid user pointer.
+pub fn sys_ppoll(fds: *mut PollFd, nfds: usize, timeout: isize, sigmask: *const u32) -> isize {
+    let token = current_user_token();
+    let process = current_pcb();
+    let deadline = usize::try_from(timeout)
+        .ok()
+        .map(|ms| timer::get_time_ms() + ms);
+
+    let mut process_inner = process.inner_exclusive_access();
+    let old_mask = process_inner.signal_mask;
+    if sigmask.is_null() {
+        process_inner.signal_mask |= SignalFlags::IGNORED_BY_DEFAULT;
+    } else {
+        let Ok(&bits) = translated_ref(token, sigmask) else {
+            return -14;
+        };
+        let Some(mask) = SignalFlags::from_bits(bits) else {
+            return -1;
+        };
+        process_inner.signal_mask = mask;
+    }
+    drop(process_inner);
+
+    let result = loop {
+        let Ok(ready) = poll_fds(token, fds, nfds) else {
+            break -14;
+        };
+        if ready > 0 {
+            break ready as isize;
+        }
+        if unmasked_signal_pending_of_current() {
+            break -2;
+        }
+        let now = timer::get_time_ms();
+        if deadline.is_some_and(|deadline| now >= deadline) {
+            break 0;
+        }
+        let wake_ms = deadline.map_or(now + POLL_INTERVAL_MS, |deadline| {
+            deadline.min(now + POLL_INTERVAL_MS)
+        });
+        timer::add_timer(wake_ms, current_tcb().unwrap());
+        block_current_and_run_next();
+    };
+
+    process.inner_exclusive_access().signal_mask = old_mask;
+    result
+}
+
+/// Fills in the `revents` of each `PollFd`, returning how many have events.
+fn poll_fds(token: usize, fds: *mut PollFd, nfds: usize) -> Result<usize, BadAddress> {
+    let process = current_pcb();
+    let mut ready = 0;
+    for i in 0..nfds {
+        let poll_fd = translated_mut_ref(token, fds.wrapping_add(i))?;
+        let file = usize::try_from(poll_fd.fd).ok().and_then(|fd| {
+            let process_inner = process.inner_exclusive_access();
+            process_inner.fd_table.get(fd)
+        });
+        poll_fd.revents = match (poll_fd.fd, file) {
+            // negative descriptors are skipped
+            (fd, _) if fd < 0 => PollEvents::empty(),
+            (_, Some(file)) => file.poll(poll_fd.events),
+            (_, None) => PollEvents::NVAL,
+        };
+        if !poll_fd.revents.is_empty() {
+            ready += 1;
+        }
+    }
+    Ok(ready)
+}
+
 /// Creates a pipe, a unidirectional data channel, and returns file descriptors for the read and write ends.
 ///
 /// # Arguments
@@ -397,21 +1635,293 @@ pub fn sys_fstat(fd: usize, stat: *mut u8) -> isize {
 /// # Returns
 ///
 /// * `0` on success.
+/// * `-14` if `pipe` is not a valid user pointer.
 pub fn sys_pipe(pipe: *mut usize) -> isize {
     let token = current_user_token();
+    let (Ok(read_end), Ok(write_end)) = (
+        translated_mut_ref(token, pipe),
+        translated_mut_ref(token, pipe.wrapping_add(1)),
+    ) else {
+        return -14;
+    };
     let process = current_pcb();
-    let mut process_inner = process.inner_exclusive_access();
+    let process_inner = process.inner_exclusive_access();
 
     let (pipe_read, pipe_write) = pipe::make();
 
-    let read_fd = process_inner.alloc_fd();
-    process_inner.fd_table[read_fd] = Some(pipe_read);
+    let read_fd = process_inner.fd_table.insert(pipe_read, false);
+    let write_fd = process_inner.fd_table.insert(pipe_write, false);
 
-    let write_fd = process_inner.alloc_fd();
-    process_inner.fd_table[write_fd] = Some(pipe_write);
-
-    *translated_mut_ref(token, pipe) = read_fd;
-    *translated_mut_ref(token, unsafe { pipe.add(1) }) = write_fd;
+    *read_end = read_fd;
+    *write_end = write_fd;
 
     0
 }
+
+/// Creates an event counter for signaling between threads and processes.
+///
+/// Reading the returned file descriptor yields the counter as 8 bytes and resets it,
+/// blocking while it is zero. Writing 8 bytes adds to the counter and wakes a reader.
+///
+/// # Arguments
+///
+/// * `initval` - The initial value of the counter.
+///
+/// # Returns
+///
+/// * A file descriptor on success.
+pub fn sys_eventfd(initval: u64) -> isize {
+    let process = current_pcb();
+    let process_inner = process.inner_exclusive_access();
+
+    let fd = process_inner
+        .fd_table
+        .insert(Arc::new(EventFd::new(initval)), false);
+
+    fd as isize
+}
+
+const MFD_CLOEXEC: u32 = 1;
+
+/// Longest name of an in-memory file
+const MFD_NAME_MAX: usize = 249;
+
+/// Creates an anonymous file that lives in memory, and returns a file descriptor for it.
+///
+/// The file starts empty and grows as it is written or resized with `ftruncate`. It has
+/// no path, every descriptor referring to it is duplicated from this one, and its memory
+/// is freed once the last of them is closed.
+///
+/// # Arguments
+///
+/// * `name` - A pointer to the null-terminated name of the file, which is only checked.
+/// * `flags` - `MFD_CLOEXEC` to close the file descriptor on `exec`.
+///
+/// # Returns
+///
+/// * A file descriptor on success.
+/// * `-1` if `flags` holds an unknown flag or `name` is longer than 249 bytes.
+/// * `-14` if `name` is not a valid user pointer.
+pub fn sys_memfd_create(name: *const u8, flags: u32) -> isize {
+    let token = current_user_token();
+    if flags & !MFD_CLOEXEC != 0 {
+        return -1;
+    }
+    if let Err(err) = translated_str(token, name, MFD_NAME_MAX + 1) {
+        return err.code();
+    }
+
+    let process = current_pcb();
+    let process_inner = process.inner_exclusive_access();
+    let fd = process_inner
+        .fd_table
+        .insert(Arc::new(MemFd::new()), flags & MFD_CLOEXEC != 0);
+    fd as isize
+}
+
+const F_GETFD: usize = 1;
+const F_SETFD: usize = 2;
+const F_GETFL: usize = 3;
+const F_SETFL: usize = 4;
+const FD_CLOEXEC: usize = 1;
+
+/// Queries or changes the flags of an open file descriptor.
+///
+/// # Arguments
+///
+/// * `fd` - The file descriptor to operate on.
+/// * `cmd` - The operation:
+///   * `F_GETFD` returns the descriptor flags, i.e. `FD_CLOEXEC` if it is closed on `exec`.
+///   * `F_SETFD` sets the descriptor flags to `arg`.
+///   * `F_GETFL` returns the access mode and status flags of the open file.
+///   * `F_SETFL` sets the status flags `APPEND` and `NONBLOCK` from `arg`, other bits are ignored.
+/// * `arg` - The argument of `F_SETFD` and `F_SETFL`.
+///
+/// # Returns
+///
+/// * The requested flags for `F_GETFD` and `F_GETFL`, `0` for `F_SETFD` and `F_SETFL`.
+/// * `-1` if the file descriptor is invalid or `cmd` is unknown.
+pub fn sys_fcntl(fd: usize, cmd: usize, arg: usize) -> isize {
+    let process = current_pcb();
+    let process_inner = process.inner_exclusive_access();
+
+    let Some(file) = process_inner.fd_table.get(fd) else {
+        return -1;
+    };
+
+    match cmd {
+        F_GETFD => {
+            if process_inner.fd_table.is_cloexec(fd) {
+                FD_CLOEXEC as isize
+            } else {
+                0
+            }
+        }
+        F_SETFD => {
+            process_inner
+                .fd_table
+                .set_cloexec(fd, arg & FD_CLOEXEC != 0);
+            0
+        }
+        F_GETFL => {
+            drop(process_inner);
+            file.status_flags().bits() as isize
+        }
+        F_SETFL => {
+            drop(process_inner);
+            file.set_status_flags(OpenFlags::from_bits_truncate(arg as u32) & OpenFlags::SETTABLE);
+            0
+        }
+        _ => -1,
+    }
+}
+
+/// The value an extended attribute syscall returns for `err`
+fn xattr_error_code(err: XattrError) -> isize {
+    match err {
+        XattrError::ValueTooLong => -7,
+        XattrError::InvalidName => -22,
+        XattrError::NoSpace => -28,
+        XattrError::NotFound => -61,
+        XattrError::Unsupported => -95,
+    }
+}
+
+/// Resolve `path` against the current directory for the extended attribute syscalls
+fn xattr_inode(token: usize, path: *const u8) -> Result<Arc<Inode>, isize> {
+    let path = translated_str(token, path, PATH_MAX).map_err(BadString::code)?;
+    let path = get_full_path(&current_pcb().inner_exclusive_access().cwd, &path);
+    inode::find(&path).ok_or(-1)
+}
+
+/// Copy `data` to the user buffer `buf` of `size` bytes, or only report its length if
+/// `size` is `0`, for `getxattr` and `listxattr`
+fn copy_xattr_out(token: usize, data: &[u8], buf: *mut u8, size: usize) -> isize {
+    if size == 0 {
+        return data.len() as isize;
+    }
+    if data.len() > size {
+        return -34;
+    }
+    let Ok(buffers) = translated_byte_buffer(token, buf, data.len()) else {
+        return -14;
+    };
+    UserBuffer::new(buffers)
+        .iter_mut()
+        .zip(data)
+        .for_each(|(p, &c)| unsafe { *p = c });
+    data.len() as isize
+}
+
+/// Sets an extended attribute of the file or directory at the specified path.
+///
+/// An attribute of the same name is replaced. Names take at most [`easy_fs::XATTR_NAME_MAX`] bytes
+/// and values at most [`XATTR_VALUE_MAX`], and all the attributes of a file have to fit in
+/// one block.
+///
+/// # Arguments
+///
+/// * `path` - A pointer to the path of the file.
+/// * `name` - A pointer to the null-terminated name of the attribute.
+/// * `value` - A pointer to the value.
+/// * `size` - The length of the value in bytes.
+///
+/// # Returns
+///
+/// * `0` on success.
+/// * `-1` if the path does not exist, or if `path` or `name` is too long.
+/// * `-7` if the value is longer than [`XATTR_VALUE_MAX`].
+/// * `-22` if the name is empty or longer than [`easy_fs::XATTR_NAME_MAX`].
+/// * `-28` if the attributes of the file or the file system have no room left.
+/// * `-95` if the file system was created without extended attributes.
+/// * `-14` if a pointer is not a valid user pointer.
+pub fn sys_setxattr(path: *const u8, name: *const u8, value: *const u8, size: usize) -> isize {
+    let token = current_user_token();
+    let inode = match xattr_inode(token, path) {
+        Ok(inode) => inode,
+        Err(err) => return err,
+    };
+    let name = match translated_str(token, name, PATH_MAX) {
+        Ok(name) => name,
+        Err(err) => return err.code(),
+    };
+    if size > XATTR_VALUE_MAX {
+        return xattr_error_code(XattrError::ValueTooLong);
+    }
+    let Ok(buffers) = translated_byte_buffer(token, value, size) else {
+        return -14;
+    };
+    let value: Vec<u8> = buffers.iter().flat_map(|buf| buf.iter().copied()).collect();
+    match inode.set_xattr(&name, &value) {
+        Ok(()) => 0,
+        Err(err) => xattr_error_code(err),
+    }
+}
+
+/// Gets an extended attribute of the file or directory at the specified path.
+///
+/// # Arguments
+///
+/// * `path` - A pointer to the path of the file.
+/// * `name` - A pointer to the null-terminated name of the attribute.
+/// * `value` - A pointer to the buffer where the value should be copied.
+/// * `size` - The size of the buffer, or `0` to only query the length of the value.
+///
+/// # Returns
+///
+/// * The length of the value.
+/// * `-1` if the path does not exist, or if `path` or `name` is too long.
+/// * `-34` if the value does not fit in the buffer.
+/// * `-61` if the file has no attribute of that name.
+/// * `-95` if the file system was created without extended attributes.
+/// * `-14` if a pointer is not a valid user pointer.
+pub fn sys_getxattr(path: *const u8, name: *const u8, value: *mut u8, size: usize) -> isize {
+    let token = current_user_token();
+    let inode = match xattr_inode(token, path) {
+        Ok(inode) => inode,
+        Err(err) => return err,
+    };
+    let name = match translated_str(token, name, PATH_MAX) {
+        Ok(name) => name,
+        Err(err) => return err.code(),
+    };
+    match inode.get_xattr(&name) {
+        Ok(data) => copy_xattr_out(token, &data, value, size),
+        Err(err) => xattr_error_code(err),
+    }
+}
+
+/// Lists the names of the extended attributes of the file or directory at the specified path.
+///
+/// The names are copied one after another, each followed by a null byte.
+///
+/// # Arguments
+///
+/// * `path` - A pointer to the path of the file.
+/// * `list` - A pointer to the buffer where the names should be copied.
+/// * `size` - The size of the buffer, or `0` to only query the length of the list.
+///
+/// # Returns
+///
+/// * The length of the list.
+/// * `-1` if the path does not exist or `path` is too long.
+/// * `-34` if the list does not fit in the buffer.
+/// * `-95` if the file system was created without extended attributes.
+/// * `-14` if a pointer is not a valid user pointer.
+pub fn sys_listxattr(path: *const u8, list: *mut u8, size: usize) -> isize {
+    let token = current_user_token();
+    let inode = match xattr_inode(token, path) {
+        Ok(inode) => inode,
+        Err(err) => return err,
+    };
+    match inode.list_xattrs() {
+        Ok(names) => {
+            let data: Vec<u8> = names
+                .iter()
+                .flat_map(|name| name.bytes().chain([0]))
+                .collect();
+            copy_xattr_out(token, &data, list, size)
+        }
+        Err(err) => xattr_error_code(err),
+    }
+}
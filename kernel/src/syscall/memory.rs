@@ -0,0 +1,63 @@
+//! Memory Management System Calls
+
+use crate::{
+    config::PAGE_SIZE,
+    mm::{address::VA_WIDTH_SV39, PTEFlags, StepByOne, VirtAddr},
+    task::current_pcb,
+};
+
+/// Expect access in the near future, pages may be read ahead
+const MADV_WILLNEED: usize = 3;
+/// Do not expect access in the near future, pages may be reclaimed
+const MADV_DONTNEED: usize = 4;
+
+/// Gives the kernel advice about the use of the pages in `[addr, addr + len)`.
+///
+/// `len` is rounded up to a whole number of pages. Only pages backed by a file can be read
+/// ahead or dropped and brought back later, but every user mapping is private memory for now:
+/// `MADV_WILLNEED` is accepted as a hint with nothing to prefetch, and `MADV_DONTNEED` is
+/// refused since dropping the frames would lose their contents.
+///
+/// # Arguments
+///
+/// * `addr` - The page-aligned start of the range.
+/// * `len` - The length of the range in bytes.
+/// * `advice` - `MADV_WILLNEED` or `MADV_DONTNEED`.
+///
+/// # Returns
+///
+/// * `0` on success.
+/// * `-1` if `addr` is not page-aligned, the advice is unknown, or part of the range is not
+///   mapped in user space.
+/// * `-2` if `MADV_DONTNEED` is given for a range that is not backed by a file.
+pub fn sys_madvise(addr: usize, len: usize, advice: usize) -> isize {
+    if addr & (PAGE_SIZE - 1) != 0 || !matches!(advice, MADV_WILLNEED | MADV_DONTNEED) {
+        return -1;
+    }
+    let end = addr
+        .checked_add(len)
+        .and_then(|end| end.checked_add(PAGE_SIZE - 1))
+        .map(|end| end & !(PAGE_SIZE - 1));
+    // user space is the lower half of the address space
+    if end.is_none_or(|end| end > 1 << (VA_WIDTH_SV39 - 1)) {
+        return -1;
+    }
+    let end_vpn = VirtAddr::from(end.unwrap()).as_vpn_by_floor();
+
+    let process = current_pcb();
+    let process_inner = process.inner_exclusive_access();
+
+    let mut vpn = VirtAddr::from(addr).as_vpn_by_floor();
+    while vpn < end_vpn {
+        match process_inner.memory_set.translate(vpn) {
+            Some(pte) if pte.is_valid() && pte.flags().contains(PTEFlags::U) => {}
+            _ => return -1,
+        }
+        vpn.step();
+    }
+
+    match advice {
+        MADV_DONTNEED if len > 0 => -2,
+        _ => 0,
+    }
+}
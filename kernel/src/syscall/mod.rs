@@ -1,38 +1,87 @@
 //! Implementation of syscalls
 
+const SYSCALL_SETXATTR: usize = 5;
+const SYSCALL_GETXATTR: usize = 8;
+const SYSCALL_LISTXATTR: usize = 11;
 const SYSCALL_GETCWD: usize = 17;
+const SYSCALL_EVENTFD: usize = 19;
 const SYSCALL_DUP: usize = 23;
 const SYSCALL_DUP2: usize = 24;
-const SYSCALL_MKDIR: usize = 34;
-const SYSCALL_UNLINK: usize = 35;
+const SYSCALL_FCNTL: usize = 25;
+const SYSCALL_MKDIRAT: usize = 34;
+const SYSCALL_UNLINKAT: usize = 35;
+const SYSCALL_RENAMEAT: usize = 38;
+const SYSCALL_STATFS: usize = 43;
+const SYSCALL_FSTATFS: usize = 44;
+const SYSCALL_TRUNCATE: usize = 45;
+const SYSCALL_FTRUNCATE: usize = 46;
 const SYSCALL_CHDIR: usize = 49;
+const SYSCALL_FCHDIR: usize = 50;
 const SYSCALL_OPEN: usize = 56;
 const SYSCALL_CLOSE: usize = 57;
 const SYSCALL_PIPE: usize = 59;
+const SYSCALL_GETDENTS: usize = 61;
+const SYSCALL_LSEEK: usize = 62;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
+const SYSCALL_SENDFILE: usize = 71;
+const SYSCALL_PPOLL: usize = 73;
+const SYSCALL_TEE: usize = 77;
+const SYSCALL_READLINKAT: usize = 78;
 const SYSCALL_FSTAT: usize = 80;
+const SYSCALL_FSYNC: usize = 82;
+const SYSCALL_FDATASYNC: usize = 83;
+const SYSCALL_SYNC_FILE_RANGE: usize = 84;
 const SYSCALL_EXIT: usize = 93;
 const SYSCALL_SLEEP: usize = 101;
+const SYSCALL_SCHED_SETAFFINITY: usize = 122;
+const SYSCALL_SCHED_GETAFFINITY: usize = 123;
 const SYSCALL_YIELD: usize = 124;
 const SYSCALL_KILL: usize = 129;
+const SYSCALL_TGKILL: usize = 131;
+const SYSCALL_SIGSUSPEND: usize = 133;
+const SYSCALL_SIGPENDING: usize = 136;
+const SYSCALL_SETPGID: usize = 154;
+const SYSCALL_GETPGID: usize = 155;
+const SYSCALL_PRCTL: usize = 167;
 const SYSCALL_GET_TIME: usize = 169;
 const SYSCALL_GETPID: usize = 172;
+const SYSCALL_SYSINFO: usize = 179;
 const SYSCALL_FORK: usize = 220;
 const SYSCALL_EXEC: usize = 221;
+const SYSCALL_MADVISE: usize = 233;
 const SYSCALL_WAITPID: usize = 260;
+const SYSCALL_RENAMEAT2: usize = 276;
+const SYSCALL_MEMFD_CREATE: usize = 279;
+const SYSCALL_COPY_FILE_RANGE: usize = 285;
+const SYSCALL_SPAWN: usize = 400;
+const SYSCALL_PIDFD_SEND_SIGNAL: usize = 424;
+const SYSCALL_PIDFD_OPEN: usize = 434;
+const SYSCALL_CLOSE_RANGE: usize = 436;
 const SYSCALL_THREAD_CREATE: usize = 1000;
 const SYSCALL_GETTID: usize = 1001;
 const SYSCALL_WAITTID: usize = 1002;
+const SYSCALL_CLONE: usize = 1003;
+const SYSCALL_VFORK: usize = 1004;
+const SYSCALL_WAIT4: usize = 1005;
 const SYSCALL_MUTEX_CREATE: usize = 1010;
 const SYSCALL_MUTEX_LOCK: usize = 1011;
 const SYSCALL_MUTEX_UNLOCK: usize = 1012;
 const SYSCALL_SEMAPHORE_CREATE: usize = 1020;
 const SYSCALL_SEMAPHORE_UP: usize = 1021;
 const SYSCALL_SEMAPHORE_DOWN: usize = 1022;
+const SYSCALL_SEMAPHORE_TRY_DOWN: usize = 1023;
+const SYSCALL_SEMAPHORE_AVAILABLE: usize = 1024;
 const SYSCALL_CONDVAR_CREATE: usize = 1030;
 const SYSCALL_CONDVAR_SIGNAL: usize = 1031;
 const SYSCALL_CONDVAR_WAIT: usize = 1032;
+const SYSCALL_REALPATH: usize = 1040;
+const SYSCALL_ISATTY: usize = 1041;
+const SYSCALL_FINCORE: usize = 1042;
+const SYSCALL_REDIRECT_OUTPUT: usize = 1043;
+const SYSCALL_PROCESS_INFO: usize = 1050;
+const SYSCALL_TCSETPGRP: usize = 1060;
+const SYSCALL_TCGETPGRP: usize = 1061;
 const SYSCALL_FRAMEBUFFER: usize = 2000;
 const SYSCALL_FRAMEBUFFER_FLUSH: usize = 2001;
 const SYSCALL_EVENT_GET: usize = 3000;
@@ -41,61 +90,165 @@ const SYSCALL_KEY_PRESSED: usize = 3001;
 mod fs;
 mod gui;
 mod input;
+mod memory;
 mod process;
 mod sync;
 mod thread;
 
+use crate::fs::PollFd;
 use fs::{
-    sys_chdir, sys_close, sys_dup, sys_dup2, sys_fstat, sys_getcwd, sys_mkdir, sys_open, sys_pipe,
-    sys_read, sys_unlink, sys_write,
+    sys_chdir, sys_close, sys_close_range, sys_copy_file_range, sys_dup, sys_dup2, sys_eventfd,
+    sys_fchdir, sys_fcntl, sys_fdatasync, sys_fincore, sys_fstat, sys_fstatfs, sys_fsync,
+    sys_ftruncate, sys_getcwd, sys_getdents, sys_getxattr, sys_isatty, sys_listxattr, sys_lseek,
+    sys_memfd_create, sys_mkdirat, sys_open, sys_pipe, sys_ppoll, sys_read, sys_readlinkat,
+    sys_realpath, sys_redirect_output, sys_renameat, sys_renameat2, sys_sendfile, sys_setxattr,
+    sys_statfs, sys_sync_file_range, sys_tee, sys_truncate, sys_unlinkat, sys_write,
 };
 use gui::{sys_framebuffer, sys_framebuffer_flush};
 use input::{sys_event_get, sys_key_pressed};
+use memory::sys_madvise;
 use process::{
-    sys_exec, sys_exit, sys_fork, sys_get_time, sys_getpid, sys_kill, sys_waitpid, sys_yield,
+    sys_clone, sys_exec, sys_exit, sys_fork, sys_get_time, sys_getpgid, sys_getpid, sys_kill,
+    sys_pidfd_open, sys_pidfd_send_signal, sys_prctl, sys_process_info, sys_setpgid,
+    sys_sigpending, sys_sigsuspend, sys_spawn, sys_sysinfo, sys_tcgetpgrp, sys_tcsetpgrp,
+    sys_tgkill, sys_vfork, sys_wait4, sys_waitpid, sys_yield, Rusage,
 };
 use sync::{
     sys_condvar_create, sys_condvar_signal, sys_condvar_wait, sys_mutex_create, sys_mutex_lock,
-    sys_mutex_unlock, sys_semaphore_create, sys_semaphore_down, sys_semaphore_up, sys_sleep,
+    sys_mutex_unlock, sys_semaphore_available, sys_semaphore_create, sys_semaphore_down,
+    sys_semaphore_try_down, sys_semaphore_up, sys_sleep,
+};
+use thread::{
+    sys_gettid, sys_sched_getaffinity, sys_sched_setaffinity, sys_thread_create, sys_waittid,
 };
-use thread::{sys_gettid, sys_thread_create, sys_waittid};
 
 /// handle syscall exception with `syscall_id` and other arguments
-pub fn syscall(syscall_id: usize, args: [usize; 3]) -> isize {
+pub fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
     match syscall_id {
+        SYSCALL_SETXATTR => sys_setxattr(
+            args[0] as *const u8,
+            args[1] as *const u8,
+            args[2] as *const u8,
+            args[3],
+        ),
+        SYSCALL_GETXATTR => sys_getxattr(
+            args[0] as *const u8,
+            args[1] as *const u8,
+            args[2] as *mut u8,
+            args[3],
+        ),
+        SYSCALL_LISTXATTR => sys_listxattr(args[0] as *const u8, args[1] as *mut u8, args[2]),
         SYSCALL_GETCWD => sys_getcwd(args[0] as *const u8, args[1]),
+        SYSCALL_EVENTFD => sys_eventfd(args[0] as u64),
         SYSCALL_DUP => sys_dup(args[0]),
         SYSCALL_DUP2 => sys_dup2(args[0], args[1]),
-        SYSCALL_MKDIR => sys_mkdir(args[0] as *const u8),
-        SYSCALL_UNLINK => sys_unlink(args[0] as *const u8, args[1] as u32),
+        SYSCALL_FCNTL => sys_fcntl(args[0], args[1], args[2]),
+        SYSCALL_MKDIRAT => sys_mkdirat(args[0] as isize, args[1] as *const u8),
+        SYSCALL_UNLINKAT => sys_unlinkat(args[0] as isize, args[1] as *const u8, args[2] as u32),
+        SYSCALL_RENAMEAT => sys_renameat(
+            args[0] as isize,
+            args[1] as *const u8,
+            args[2] as isize,
+            args[3] as *const u8,
+        ),
+        SYSCALL_RENAMEAT2 => sys_renameat2(
+            args[0] as isize,
+            args[1] as *const u8,
+            args[2] as isize,
+            args[3] as *const u8,
+            args[4] as u32,
+        ),
+        SYSCALL_STATFS => sys_statfs(args[0] as *const u8, args[1] as *mut u8),
+        SYSCALL_FSTATFS => sys_fstatfs(args[0], args[1] as *mut u8),
+        SYSCALL_TRUNCATE => sys_truncate(args[0] as *const u8, args[1]),
+        SYSCALL_FTRUNCATE => sys_ftruncate(args[0], args[1]),
         SYSCALL_CHDIR => sys_chdir(args[0] as *const u8),
+        SYSCALL_FCHDIR => sys_fchdir(args[0]),
         SYSCALL_OPEN => sys_open(args[0] as *const u8, args[1] as u32),
         SYSCALL_CLOSE => sys_close(args[0]),
         SYSCALL_PIPE => sys_pipe(args[0] as *mut usize),
+        SYSCALL_GETDENTS => sys_getdents(args[0], args[1] as *const u8, args[2]),
+        SYSCALL_LSEEK => sys_lseek(args[0], args[1] as isize, args[2]),
         SYSCALL_READ => sys_read(args[0], args[1] as *const u8, args[2]),
         SYSCALL_WRITE => sys_write(args[0], args[1] as *const u8, args[2]),
+        SYSCALL_SENDFILE => sys_sendfile(args[0], args[1], args[2] as *mut usize, args[3]),
+        SYSCALL_COPY_FILE_RANGE => sys_copy_file_range(
+            args[0],
+            args[1] as *mut usize,
+            args[2],
+            args[3] as *mut usize,
+            args[4],
+        ),
+        SYSCALL_TEE => sys_tee(args[0], args[1], args[2]),
+        SYSCALL_PPOLL => sys_ppoll(
+            args[0] as *mut PollFd,
+            args[1],
+            args[2] as isize,
+            args[3] as *const u32,
+        ),
+        SYSCALL_READLINKAT => sys_readlinkat(
+            args[0] as isize,
+            args[1] as *const u8,
+            args[2] as *mut u8,
+            args[3],
+        ),
         SYSCALL_FSTAT => sys_fstat(args[0], args[1] as *mut u8),
+        SYSCALL_FSYNC => sys_fsync(args[0]),
+        SYSCALL_FDATASYNC => sys_fdatasync(args[0]),
+        SYSCALL_SYNC_FILE_RANGE => sys_sync_file_range(args[0], args[1], args[2], args[3] as u32),
         SYSCALL_EXIT => sys_exit(args[0] as i32),
         SYSCALL_SLEEP => sys_sleep(args[0]),
+        SYSCALL_SCHED_SETAFFINITY => sys_sched_setaffinity(args[0]),
+        SYSCALL_SCHED_GETAFFINITY => sys_sched_getaffinity(),
         SYSCALL_YIELD => sys_yield(),
         SYSCALL_KILL => sys_kill(args[0], args[1] as u32),
+        SYSCALL_TGKILL => sys_tgkill(args[0], args[1], args[2] as u32),
+        SYSCALL_SIGSUSPEND => sys_sigsuspend(args[0] as *const u32),
+        SYSCALL_SIGPENDING => sys_sigpending(args[0] as *mut u32),
+        SYSCALL_SETPGID => sys_setpgid(args[0], args[1]),
+        SYSCALL_GETPGID => sys_getpgid(args[0]),
+        SYSCALL_PRCTL => sys_prctl(args[0], args[1]),
         SYSCALL_GET_TIME => sys_get_time(),
         SYSCALL_GETPID => sys_getpid(),
+        SYSCALL_SYSINFO => sys_sysinfo(args[0] as *mut u8),
         SYSCALL_FORK => sys_fork(),
         SYSCALL_EXEC => sys_exec(args[0] as *const u8, args[1] as *const usize),
+        SYSCALL_MADVISE => sys_madvise(args[0], args[1], args[2]),
         SYSCALL_WAITPID => sys_waitpid(args[0] as isize, args[1] as *mut i32),
+        SYSCALL_MEMFD_CREATE => sys_memfd_create(args[0] as *const u8, args[1] as u32),
+        SYSCALL_SPAWN => sys_spawn(args[0] as *const u8, args[1] as *const usize),
+        SYSCALL_PIDFD_SEND_SIGNAL => sys_pidfd_send_signal(args[0], args[1] as u32),
+        SYSCALL_PIDFD_OPEN => sys_pidfd_open(args[0]),
+        SYSCALL_CLOSE_RANGE => sys_close_range(args[0], args[1], args[2] as u32),
         SYSCALL_THREAD_CREATE => sys_thread_create(args[0], args[1]),
         SYSCALL_GETTID => sys_gettid(),
         SYSCALL_WAITTID => sys_waittid(args[0]) as isize,
+        SYSCALL_CLONE => sys_clone(args[0], args[1]),
+        SYSCALL_VFORK => sys_vfork(),
+        SYSCALL_WAIT4 => sys_wait4(
+            args[0] as isize,
+            args[1] as *mut i32,
+            args[2] as u32,
+            args[3] as *mut Rusage,
+        ),
         SYSCALL_MUTEX_CREATE => sys_mutex_create(args[0] == 1),
         SYSCALL_MUTEX_LOCK => sys_mutex_lock(args[0]),
         SYSCALL_MUTEX_UNLOCK => sys_mutex_unlock(args[0]),
         SYSCALL_SEMAPHORE_CREATE => sys_semaphore_create(args[0]),
         SYSCALL_SEMAPHORE_UP => sys_semaphore_up(args[0]),
         SYSCALL_SEMAPHORE_DOWN => sys_semaphore_down(args[0]),
+        SYSCALL_SEMAPHORE_TRY_DOWN => sys_semaphore_try_down(args[0]),
+        SYSCALL_SEMAPHORE_AVAILABLE => sys_semaphore_available(args[0]),
         SYSCALL_CONDVAR_CREATE => sys_condvar_create(),
         SYSCALL_CONDVAR_SIGNAL => sys_condvar_signal(args[0]),
         SYSCALL_CONDVAR_WAIT => sys_condvar_wait(args[0], args[1]),
+        SYSCALL_REALPATH => sys_realpath(args[0] as *const u8, args[1] as *const u8, args[2]),
+        SYSCALL_ISATTY => sys_isatty(args[0]),
+        SYSCALL_FINCORE => sys_fincore(args[0], args[1], args[2], args[3] as *mut u8),
+        SYSCALL_REDIRECT_OUTPUT => sys_redirect_output(args[0] as isize),
+        SYSCALL_PROCESS_INFO => sys_process_info(args[0] as *mut u8, args[1]),
+        SYSCALL_TCSETPGRP => sys_tcsetpgrp(args[0]),
+        SYSCALL_TCGETPGRP => sys_tcgetpgrp(),
         SYSCALL_FRAMEBUFFER => sys_framebuffer(),
         SYSCALL_FRAMEBUFFER_FLUSH => sys_framebuffer_flush(),
         SYSCALL_EVENT_GET => sys_event_get(),
@@ -1,16 +1,29 @@
 //! Process Management System Calls
 
-use alloc::{sync::Arc, vec::Vec};
-use log::trace;
+use alloc::{string::String, sync::Arc, vec::Vec};
+use easy_fs::BLOCK_SIZE;
+use log::{trace, warn};
 
+use super::thread::spawn_thread;
 use crate::{
-    fs::{get_full_path, open_file, OpenFlags},
-    mm::{translated_mut_ref, translated_ref, translated_str},
+    config::{PAGE_SIZE, PATH_MAX, USER_STACK_SIZE},
+    fs::{get_full_path, inode, inode::ROOT_INODE, open_file, pidfd::PidFd, OpenFlags},
+    mm::{
+        frame_allocator, memory_set::validate_elf, translated_byte_buffer, translated_mut_ref,
+        translated_ref, translated_str, UserBuffer,
+    },
     task::{
-        current_pcb, current_user_token, exit_current_and_run_next, pid2process,
-        suspend_current_and_run_next, SignalFlags,
+        block_current, block_current_and_run_next, consume_ignored_thread_signals_of_current,
+        current_pcb, current_tcb, current_trap_cx, current_user_token, exit_current_and_run_next,
+        manager::{
+            self, foreground_pgid, pgid_exists, process_count, process_infos, set_foreground_pgid,
+            ProcessInfo, PROCESS_NAME_LEN,
+        },
+        pcb::ProcessControlBlock,
+        pending_signals_of_current, pid2process, schedule, suspend_current_and_run_next,
+        unmasked_signal_pending_of_current, CloneFlags, SignalFlags,
     },
-    timer::get_time_ms,
+    timer::{self, get_time_ms},
 };
 
 /// Exits the current task and submits an exit code.
@@ -57,6 +70,86 @@ pub fn sys_getpid() -> isize {
     current_pcb().pid() as isize
 }
 
+/// Moves a process into a process group.
+///
+/// # Arguments
+///
+/// * `pid` - The process to move, the current process or one of its children, `0` for the
+///   current process.
+/// * `pgid` - The group to join, `0` to lead a new group whose ID is `pid`.
+///
+/// # Returns
+///
+/// * `0` on success.
+/// * `-1` if `pid` is neither the current process nor a child, or if `pgid` is another
+///   process's ID and no such group exists.
+#[allow(clippy::similar_names)]
+pub fn sys_setpgid(pid: usize, pgid: usize) -> isize {
+    let current = current_pcb();
+    let target = if pid == 0 || pid == current.pid() {
+        current
+    } else {
+        let inner = current.inner_exclusive_access();
+        let Some(child) = inner.children.iter().find(|child| child.pid() == pid) else {
+            return -1;
+        };
+        child.clone()
+    };
+    let pgid = if pgid == 0 { target.pid() } else { pgid };
+    if pgid != target.pid() && !pgid_exists(pgid) {
+        return -1;
+    }
+    target.inner_exclusive_access().pgid = pgid;
+    0
+}
+
+/// Retrieves the process group of a process.
+///
+/// # Arguments
+///
+/// * `pid` - The process to query, `0` for the current process.
+///
+/// # Returns
+///
+/// * The process group ID on success.
+/// * `-1` if the process does not exist.
+pub fn sys_getpgid(pid: usize) -> isize {
+    let process = if pid == 0 {
+        Some(current_pcb())
+    } else {
+        pid2process(pid)
+    };
+    process.map_or(-1, |process| process.inner_exclusive_access().pgid as isize)
+}
+
+/// Puts a process group in the foreground of the terminal, so it receives `SIGINT` on Ctrl-C.
+///
+/// # Arguments
+///
+/// * `pgid` - The group to put in the foreground, `0` for none, in which case Ctrl-C is read
+///   as ordinary input.
+///
+/// # Returns
+///
+/// * `0` on success.
+/// * `-1` if the process group does not exist.
+pub fn sys_tcsetpgrp(pgid: usize) -> isize {
+    if pgid != 0 && !pgid_exists(pgid) {
+        return -1;
+    }
+    set_foreground_pgid(pgid);
+    0
+}
+
+/// Retrieves the process group in the foreground of the terminal.
+///
+/// # Returns
+///
+/// The foreground process group ID, `0` if there is none.
+pub fn sys_tcgetpgrp() -> isize {
+    foreground_pgid() as isize
+}
+
 /// Creates a duplicate of the current process.
 ///
 /// # Returns
@@ -65,7 +158,7 @@ pub fn sys_getpid() -> isize {
 /// and `0` to the child process.
 pub fn sys_fork() -> isize {
     let current_process = current_pcb();
-    let new_process = current_process.fork();
+    let new_process = current_process.fork(CloneFlags::empty());
     let new_pid = new_process.pid();
     // modify trap context of new_task, because it returns immediately after switching
     let new_process_inner = new_process.inner_exclusive_access();
@@ -77,6 +170,95 @@ pub fn sys_fork() -> isize {
     new_pid as isize
 }
 
+/// Creates a child process that borrows the address space of the current process until it
+/// calls `exec` or exits.
+///
+/// This is the restricted `vfork` contract. Nothing is copied: the child runs on the memory
+/// and user stack of the caller, which stays suspended until the child calls [`sys_exec`] or
+/// exits. Until then the child may only do either of those. In particular it must not return
+/// from the function that called `vfork`, as that would pull the stack frame out from under
+/// the caller, and anything else it writes to memory is seen by the caller once resumed.
+///
+/// # Returns
+///
+/// * The PID of the child to the caller once it is resumed, and `0` to the child.
+/// * `-1` if the current process has more than one thread.
+pub fn sys_vfork() -> isize {
+    let process = current_pcb();
+    if process.inner_exclusive_access().thread_count() != 1 {
+        return -1;
+    }
+    let child = process.vfork(current_tcb().unwrap());
+    let new_pid = child.pid();
+    let task = child.inner_exclusive_access().task(0);
+    // the child returns from the same trap context, with 0
+    task.inner_exclusive_access().trap_cx().x[10] = 0;
+    // block before the child can run, it wakes us up again when it is done
+    let task_cx_ptr = block_current();
+    manager::add(task);
+    schedule(task_cx_ptr);
+    new_pid as isize
+}
+
+/// Creates a child of the current process, sharing the resources selected by `flags`.
+///
+/// Without `CLONE_VM` the child is a new process like with [`sys_fork`], and shares the
+/// file descriptor table with the caller if `CLONE_FILES` is given. With `CLONE_VM` the
+/// child is a new thread of the current process instead, which shares the file descriptor
+/// table and signal state as well, so `CLONE_FILES` and `CLONE_SIGHAND` must be given too.
+///
+/// The child continues after the call with the registers of the caller and a result of `0`.
+///
+/// # Arguments
+///
+/// * `flags` - A combination of `CLONE_VM`, `CLONE_FILES` and `CLONE_SIGHAND`.
+/// * `stack` - The stack pointer the child starts with, `0` to keep the caller's for a
+///   process, or to use the stack allocated for a thread.
+///
+/// # Returns
+///
+/// * The PID of the new process, or the TID of the new thread, to the caller, and `0` to the child.
+/// * `-1` if `flags` has unknown bits or an unsupported combination, or the process to be
+///   copied has more than one thread.
+pub fn sys_clone(flags: usize, stack: usize) -> isize {
+    let Some(flags) = CloneFlags::from_bits(flags) else {
+        return -1;
+    };
+    let parent_cx = *current_trap_cx();
+
+    if flags.contains(CloneFlags::VM) {
+        if !flags.contains(CloneFlags::FILES | CloneFlags::SIGHAND) {
+            return -1;
+        }
+        let tid = spawn_thread(|ustack_top, kstack_top| {
+            let mut trap_cx = parent_cx;
+            trap_cx.kernel_sp = kstack_top;
+            trap_cx.x[2] = if stack == 0 { ustack_top } else { stack };
+            trap_cx.x[10] = 0;
+            trap_cx
+        });
+        return tid as isize;
+    }
+
+    if flags.contains(CloneFlags::SIGHAND) {
+        return -1;
+    }
+    let current_process = current_pcb();
+    if current_process.inner_exclusive_access().thread_count() > 1 {
+        return -1;
+    }
+    let new_process = current_process.fork(flags);
+    let new_pid = new_process.pid();
+    let new_process_inner = new_process.inner_exclusive_access();
+    let task = new_process_inner.tasks[0].as_ref().unwrap();
+    let trap_cx = task.inner_exclusive_access().trap_cx();
+    trap_cx.x[10] = 0;
+    if stack != 0 {
+        trap_cx.x[2] = stack;
+    }
+    new_pid as isize
+}
+
 /// Replaces the current process's image with a new process image.
 ///
 /// This system call loads a new program into the current process's memory space
@@ -90,54 +272,98 @@ pub fn sys_fork() -> isize {
 /// # Returns
 ///
 /// * The number of arguments (`argc`) on success.
-/// * `-1` if the file cannot be opened.
+/// * `-1` if the file cannot be opened or is not a loadable ELF image.
+/// * `-2` if the path is a directory.
+/// * `-1` if `path` is longer than [`PATH_MAX`] or an argument does not fit the user stack.
+/// * `-14` if `path`, `args` or one of the arguments is not a valid user pointer.
+pub fn sys_exec(path: *const u8, args: *const usize) -> isize {
+    let (name, data, args) = match load_program(path, args) {
+        Ok(program) => program,
+        Err(code) => return code,
+    };
+    current_pcb().exec(&name, &data, &args);
+    // return argc because cx.x[10] will be covered with it later
+    args.len() as isize
+}
+
+/// Starts a program in a new child process, like [`sys_fork`] followed by [`sys_exec`].
+///
+/// The child's address space is built from the program directly instead of being copied
+/// from the caller first. It inherits the file descriptors that are not close-on-exec, and
+/// gets a `/proc/<pid>/cmdline` of its own like after `exec`.
+///
+/// # Arguments
+///
+/// * `path` - A pointer to the null-terminated string representing the file path of the program.
+/// * `args` - A pointer to the array of arguments for the program.
+///
+/// # Returns
+///
+/// * The PID of the child on success.
+/// * The error codes of [`sys_exec`] otherwise, no child is created then.
+pub fn sys_spawn(path: *const u8, args: *const usize) -> isize {
+    let (name, data, args) = match load_program(path, args) {
+        Ok(program) => program,
+        Err(code) => return code,
+    };
+    current_pcb().spawn(&name, &data, &args).pid() as isize
+}
+
+/// Reads the program path and arguments of [`sys_exec`] and [`sys_spawn`] and loads the program.
+///
+/// Returns the program name, its ELF image and the arguments, or the code the call fails with.
 #[allow(clippy::similar_names)]
-pub fn sys_exec(path: *const u8, mut args: *const usize) -> isize {
+fn load_program(
+    path: *const u8,
+    mut args: *const usize,
+) -> Result<(String, Vec<u8>, Vec<String>), isize> {
     let token = current_user_token();
     let process = current_pcb();
     let process_inner = process.inner_exclusive_access();
 
-    let path = translated_str(token, path);
+    let path = translated_str(token, path, PATH_MAX).map_err(|err| err.code())?;
     let path = get_full_path(&process_inner.cwd, &path);
     drop(process_inner);
 
     let mut args_vec = Vec::new();
     loop {
-        let arg_str_ptr = *translated_ref(token, args);
+        let &arg_str_ptr = translated_ref(token, args).map_err(|_| -14)?;
         if arg_str_ptr == 0 {
             break;
         }
-        let arg_str = translated_str(token, arg_str_ptr as *const u8);
+        let arg_str = translated_str(token, arg_str_ptr as *const u8, USER_STACK_SIZE)
+            .map_err(|err| err.code())?;
         args_vec.push(arg_str);
         unsafe {
             args = args.add(1);
         }
     }
 
-    if let Some(app_inode) = open_file(path.as_str(), OpenFlags::RDONLY) {
-        let data = app_inode.read_all();
-        let argc = args_vec.len();
-        process.exec(data.as_slice(), &args_vec);
-        // return argc because cx.x[10] will be covered with it later
-        argc as isize
-    } else {
-        -1
+    // a directory would otherwise be read as dirents and fail as a malformed ELF
+    if inode::find(&path).is_some_and(|inode| inode.is_dir()) {
+        warn!("[kernel] Refused to exec '{}': is a directory", path);
+        return Err(-2);
     }
+
+    let app_inode = open_file(path.as_str(), OpenFlags::RDONLY)?;
+    let data = app_inode.read_all();
+    if let Err(reason) = validate_elf(data.as_slice()) {
+        warn!("[kernel] Refused to exec '{}': {}", path, reason);
+        return Err(-1);
+    }
+    let name = String::from(path.rsplit('/').next().unwrap_or_default());
+    Ok((name, data, args_vec))
 }
 
-/// Waits for a child process to change state.
+/// Removes an exited child from the children of the current process and hands it over.
 ///
-/// # Arguments
-///
-/// * `pid` - The PID of the child process. If `-1`, waits for any child process.
-/// * `exit_code_ptr` - A pointer to where the exit code of the child process will be stored.
-///
-/// # Returns
-///
-/// * The PID of the child process if it has exited.
-/// * `-1` if no matching child process exists.
-/// * `-2` if the child process is still running.
-pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
+/// `pid` selects the child, `-1` any child. `prepare` runs on the address space token once
+/// a zombie is found but before it is removed, so the child is left unreaped if it fails.
+/// The child is freed once the caller drops it, after reading what it needs.
+fn reap_child<T>(
+    pid: isize,
+    prepare: impl FnOnce(usize) -> Result<T, isize>,
+) -> Result<(T, Arc<ProcessControlBlock>), isize> {
     let process = current_pcb();
     // find a child process
 
@@ -148,7 +374,7 @@ pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
         .iter()
         .any(|p| pid == -1 || pid as usize == p.pid())
     {
-        return -1;
+        return Err(-1);
         // ---- release current PCB
     }
     let pair = inner.children.iter().enumerate().find(|(_, p)| {
@@ -156,22 +382,119 @@ pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
         p.inner_exclusive_access().is_zombie && (pid == -1 || pid as usize == p.pid())
         // ++++ release child PCB
     });
-    if let Some((idx, _)) = pair {
-        let child = inner.children.remove(idx);
-        // confirm that child will be deallocated after removing from children list
-        assert_eq!(Arc::strong_count(&child), 1);
-        let found_pid = child.pid();
-        // ++++ temporarily access child PCB exclusively
-        let exit_code = child.inner_exclusive_access().exit_code;
-        // ++++ release child PCB
-        *translated_mut_ref(inner.memory_set.token(), exit_code_ptr) = exit_code;
-        found_pid as isize
-    } else {
-        -2
-    }
+    let Some((idx, _)) = pair else {
+        return Err(-2);
+    };
+    // validate before reaping so a bad pointer leaves the zombie in place
+    let prepared = prepare(inner.memory_set.token())?;
+    let child = inner.children.remove(idx);
+    // confirm that child will be deallocated once the caller is done with it
+    assert_eq!(Arc::strong_count(&child), 1);
+    inner.settle_sigchld();
+    Ok((prepared, child))
     // ---- release current PCB lock automatically
 }
 
+/// Waits for a child process to change state.
+///
+/// # Arguments
+///
+/// * `pid` - The PID of the child process. If `-1`, waits for any child process.
+/// * `exit_code_ptr` - A pointer to where the exit code of the child process will be stored.
+///
+/// # Returns
+///
+/// * The PID of the child process if it has exited.
+/// * `-1` if no matching child process exists.
+/// * `-2` if the child process is still running.
+/// * `-14` if `exit_code_ptr` is not a valid user pointer, the child is left unreaped then.
+pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
+    let reaped = reap_child(pid, |token| {
+        translated_mut_ref(token, exit_code_ptr).map_err(|_| -14)
+    });
+    match reaped {
+        Ok((exit_code_slot, child)) => {
+            *exit_code_slot = child.inner_exclusive_access().exit_code;
+            child.pid() as isize
+        }
+        Err(code) => code,
+    }
+}
+
+/// Return at once if no child has exited yet, rather than reporting it as an error
+const WNOHANG: u32 = 1;
+
+/// Resources used by a reaped child, as reported to user space.
+///
+/// The layout is fixed, fields are only ever appended. Times are not accounted per task
+/// yet and always read zero.
+#[repr(C)]
+pub struct Rusage {
+    /// Microseconds spent running in user mode
+    pub utime_us: usize,
+    /// Microseconds spent in the kernel on behalf of the process
+    pub stime_us: usize,
+    /// Largest resident size of the address space, in KiB
+    pub maxrss: usize,
+}
+
+/// Waits for a child process to change state, reporting how it ended and the resources it used.
+///
+/// The status tells an exit from a termination by a signal: the low 7 bits hold the
+/// signal number, or are zero when the process exited, with its exit code in bits 8-15.
+///
+/// # Arguments
+///
+/// * `pid` - The PID of the child process. If `-1`, waits for any child process.
+/// * `status` - A pointer to where the status will be stored, or null.
+/// * `options` - `WNOHANG` to return `0` rather than `-2` while the child runs.
+/// * `rusage` - A pointer to where the [`Rusage`] of the child will be stored, or null.
+///
+/// # Returns
+///
+/// * The PID of the child process if it has exited.
+/// * `0` if the child process is still running and `options` holds `WNOHANG`.
+/// * `-1` if no matching child process exists or `options` holds an unknown flag.
+/// * `-2` if the child process is still running.
+/// * `-14` if `status` or `rusage` is not a valid user pointer, the child is left unreaped then.
+pub fn sys_wait4(pid: isize, status: *mut i32, options: u32, rusage: *mut Rusage) -> isize {
+    if options & !WNOHANG != 0 {
+        return -1;
+    }
+    let reaped = reap_child(pid, |token| {
+        let status = (!status.is_null())
+            .then(|| translated_mut_ref(token, status))
+            .transpose();
+        let rusage = (!rusage.is_null())
+            .then(|| translated_mut_ref(token, rusage))
+            .transpose();
+        match (status, rusage) {
+            (Ok(status), Ok(rusage)) => Ok((status, rusage)),
+            _ => Err(-14),
+        }
+    });
+    let ((status_slot, rusage_slot), child) = match reaped {
+        Ok(reaped) => reaped,
+        Err(-2) if options & WNOHANG != 0 => return 0,
+        Err(code) => return code,
+    };
+
+    // the child is freed below, everything is read from it first
+    let child_inner = child.inner_exclusive_access();
+    if let Some(status_slot) = status_slot {
+        *status_slot = child_inner.wait_status();
+    }
+    if let Some(rusage_slot) = rusage_slot {
+        *rusage_slot = Rusage {
+            utime_us: 0,
+            stime_us: 0,
+            maxrss: child_inner.peak_pages * PAGE_SIZE / 1024,
+        };
+    }
+    drop(child_inner);
+    child.pid() as isize
+}
+
 /// Sends a signal to a process.
 ///
 /// # Arguments
@@ -195,3 +518,334 @@ pub fn sys_kill(pid: usize, signal: u32) -> isize {
         -1
     }
 }
+
+/// Sends a signal to one thread of a process.
+///
+/// The signal is pending for that thread alone and is delivered on its way back to user space,
+/// blocked by the signal mask of the process like any other. A fatal one ends only the thread,
+/// or the whole process if it is the main thread. One ignored by default is discarded unless
+/// it is blocked, and taken as delivered once it no longer is.
+///
+/// # Arguments
+///
+/// * `pid` - The PID of the process the thread belongs to.
+/// * `tid` - The TID of the thread to signal.
+/// * `signal` - The signal to send.
+///
+/// # Returns
+///
+/// * `0` on successfully sending the signal.
+/// * `-1` if the specified process or thread does not exist or the signal is invalid.
+pub fn sys_tgkill(pid: usize, tid: usize, signal: u32) -> isize {
+    let Some(flag) = SignalFlags::from_bits(signal) else {
+        return -1;
+    };
+    let Some(process) = pid2process(pid) else {
+        return -1;
+    };
+    let process_inner = process.inner_exclusive_access();
+    let Some(task) = process_inner.tasks.get(tid).and_then(Option::clone) else {
+        return -1;
+    };
+    let mask = process_inner.signal_mask;
+    drop(process_inner);
+
+    let mut task_inner = task.inner_exclusive_access();
+    // an exited thread waiting to be reaped is gone as far as signals go
+    if task_inner.res.is_none() {
+        return -1;
+    }
+    task_inner.signals |= flag - (SignalFlags::IGNORED_BY_DEFAULT - mask);
+    0
+}
+
+/// Opens a file descriptor referring to a process.
+///
+/// Unlike its PID, the file descriptor keeps referring to the same process even after the
+/// PID is reused, and it becomes readable for `poll` once the process exits. It does not keep
+/// the process alive. The file descriptor is closed on `exec`.
+///
+/// # Arguments
+///
+/// * `pid` - The PID of the process.
+///
+/// # Returns
+///
+/// * A file descriptor on success.
+/// * `-3` if the specified process does not exist.
+pub fn sys_pidfd_open(pid: usize) -> isize {
+    let Some(target) = pid2process(pid) else {
+        return -3;
+    };
+    let pidfd = Arc::new(PidFd::new(&target));
+    drop(target);
+
+    let process = current_pcb();
+    let process_inner = process.inner_exclusive_access();
+    process_inner.fd_table.insert(pidfd, true) as isize
+}
+
+/// Sends a signal to the process referred to by a pidfd.
+///
+/// # Arguments
+///
+/// * `pidfd` - A file descriptor opened by [`sys_pidfd_open`].
+/// * `signal` - The signal to send.
+///
+/// # Returns
+///
+/// * `0` on successfully sending the signal.
+/// * `-1` if `pidfd` is not an open pidfd or the signal is invalid.
+/// * `-3` if the process has exited.
+pub fn sys_pidfd_send_signal(pidfd: usize, signal: u32) -> isize {
+    let Some(file) = current_pcb().inner_exclusive_access().fd_table.get(pidfd) else {
+        return -1;
+    };
+    let Some(pidfd) = file.as_pidfd() else {
+        return -1;
+    };
+    let Some(flag) = SignalFlags::from_bits(signal) else {
+        return -1;
+    };
+    let Some(process) = pidfd.process() else {
+        return -3;
+    };
+    process.inner_exclusive_access().signals |= flag;
+    0
+}
+
+/// How often a task suspended by [`sys_sigsuspend`] checks its signals again
+const SIGNAL_POLL_MS: usize = 10;
+
+/// Reports the signals that are pending but blocked by the signal mask.
+///
+/// # Arguments
+///
+/// * `set` - A pointer where the pending blocked signals will be stored.
+///
+/// # Returns
+///
+/// * `0` on success.
+/// * `-14` if `set` is not a valid user pointer.
+pub fn sys_sigpending(set: *mut u32) -> isize {
+    let Ok(set) = translated_mut_ref(current_user_token(), set) else {
+        return -14;
+    };
+    let mask = current_pcb().inner_exclusive_access().signal_mask;
+    *set = (pending_signals_of_current() & mask).bits();
+    0
+}
+
+/// Replaces the signal mask with `mask` and waits until a signal it does not block is pending.
+///
+/// The previous mask is restored before returning, so a signal it blocks stays pending and
+/// one it leaves unblocked is delivered on the way back to user space.
+///
+/// # Arguments
+///
+/// * `mask` - A pointer to the signals to block while waiting.
+///
+/// # Returns
+///
+/// * `-1` if `mask` holds an unknown signal.
+/// * `-2` once a signal has arrived, the only way the wait ends.
+/// * `-14` if `mask` is not a valid user pointer.
+pub fn sys_sigsuspend(mask: *const u32) -> isize {
+    let Ok(&bits) = translated_ref(current_user_token(), mask) else {
+        return -14;
+    };
+    let Some(mask) = SignalFlags::from_bits(bits) else {
+        return -1;
+    };
+    let process = current_pcb();
+    let old_mask = core::mem::replace(&mut process.inner_exclusive_access().signal_mask, mask);
+
+    while !unmasked_signal_pending_of_current() {
+        timer::add_timer(get_time_ms() + SIGNAL_POLL_MS, current_tcb().unwrap());
+        block_current_and_run_next();
+    }
+    // one sent to this thread alone that ended the wait is delivered now, the restored mask
+    // could block it again and have it end every later wait at once
+    consume_ignored_thread_signals_of_current(mask);
+
+    process.inner_exclusive_access().signal_mask = old_mask;
+    -2
+}
+
+/// Lists the live processes and the zombies not yet reaped by their parents.
+///
+/// The snapshot is taken at once and written as an array of `ProcessInfo` records ordered
+/// by PID, zombies carry the exit code their parent will collect.
+///
+/// # Arguments
+///
+/// * `buf` - A pointer to the array of records to fill.
+/// * `len` - The number of records the array can hold.
+///
+/// # Returns
+///
+/// * The number of records written on success.
+/// * The negated number of processes if the array is too small, nothing is written then.
+/// * `-14` if `buf` is not a valid user pointer.
+pub fn sys_process_info(buf: *mut u8, len: usize) -> isize {
+    let token = current_user_token();
+    let infos = process_infos();
+    if infos.len() > len {
+        return -(infos.len() as isize);
+    }
+
+    let size = infos.len() * core::mem::size_of::<ProcessInfo>();
+    let bytes = unsafe { core::slice::from_raw_parts(infos.as_ptr().cast::<u8>(), size) };
+    let Ok(buffers) = translated_byte_buffer(token, buf, size) else {
+        return -14;
+    };
+    let mut user_buffer = UserBuffer::new(buffers);
+    for (p, &b) in user_buffer.iter_mut().zip(bytes) {
+        unsafe {
+            *p = b;
+        }
+    }
+    infos.len() as isize
+}
+
+/// System-wide figures as reported to user space.
+///
+/// The layout is fixed, fields are only ever appended. Sizes are in bytes.
+#[repr(C)]
+pub struct SysInfo {
+    /// Milliseconds since boot
+    pub uptime_ms: usize,
+    /// Physical memory managed by the frame allocator
+    pub total_ram: usize,
+    /// Physical memory not allocated to any frame
+    pub free_ram: usize,
+    /// Number of live processes, zombies excluded
+    pub processes: usize,
+    /// Data area of the root file system
+    pub fs_total: usize,
+    /// Free space in the data area of the root file system
+    pub fs_free: usize,
+}
+
+/// Reports uptime, memory, process and file system usage in one call.
+///
+/// All figures are gathered back to back before any is copied out, so they
+/// describe the same moment.
+///
+/// # Arguments
+///
+/// * `buf` - A pointer to the `SysInfo` to fill.
+///
+/// # Returns
+///
+/// * `0` on success.
+/// * `-14` if `buf` is not a valid user pointer.
+pub fn sys_sysinfo(buf: *mut u8) -> isize {
+    let token = current_user_token();
+    let frames = frame_allocator::report();
+    let (fs_total, fs_free) = {
+        let fs = ROOT_INODE.fs();
+        let fs = fs.lock();
+        (
+            fs.data_area_blocks() as usize,
+            fs.free_data_blocks() as usize,
+        )
+    };
+    let info = SysInfo {
+        uptime_ms: get_time_ms(),
+        total_ram: frames.total * PAGE_SIZE,
+        free_ram: frames.free * PAGE_SIZE,
+        processes: process_count(),
+        fs_total: fs_total * BLOCK_SIZE,
+        fs_free: fs_free * BLOCK_SIZE,
+    };
+
+    let size = core::mem::size_of::<SysInfo>();
+    let bytes = unsafe { core::slice::from_raw_parts((&raw const info).cast::<u8>(), size) };
+    let Ok(buffers) = translated_byte_buffer(token, buf, size) else {
+        return -14;
+    };
+    let mut user_buffer = UserBuffer::new(buffers);
+    for (p, &b) in user_buffer.iter_mut().zip(bytes) {
+        unsafe {
+            *p = b;
+        }
+    }
+    0
+}
+
+/// Set the name of the process to the string `arg` points to
+const PR_SET_NAME: usize = 15;
+/// Copy the name of the process into the [`PROCESS_NAME_LEN`] bytes `arg` points to
+const PR_GET_NAME: usize = 16;
+/// Become the reaper of orphaned descendants if `arg` is nonzero, stop being one if zero
+const PR_SET_CHILD_SUBREAPER: usize = 36;
+/// Store whether the process is a child subreaper at the `i32` `arg` points to
+const PR_GET_CHILD_SUBREAPER: usize = 37;
+/// Give up gaining privileges for good, `arg` must be `1`
+const PR_SET_NO_NEW_PRIVS: usize = 38;
+/// Whether the process gave up gaining privileges
+const PR_GET_NO_NEW_PRIVS: usize = 39;
+
+/// Reads or changes a behavior of the current process.
+///
+/// Children keep the name and no-new-privileges but are never child subreapers themselves.
+/// A subreaper adopts the children of its exiting descendants instead of the daemon, and
+/// has to wait for them once they exit.
+///
+/// # Arguments
+///
+/// * `option` - Which behavior to read or change, one of the `PR_*` options.
+/// * `arg` - The value or pointer the option takes.
+///
+/// # Returns
+///
+/// * `0` on success, or the value read for [`PR_GET_NO_NEW_PRIVS`].
+/// * `-1` if `option` is unknown, if [`PR_SET_NO_NEW_PRIVS`] is given anything but `1`, or
+///   if the name does not fit in [`PROCESS_NAME_LEN`] bytes with its terminator.
+/// * `-14` if `arg` is not a valid user pointer for the options taking one.
+pub fn sys_prctl(option: usize, arg: usize) -> isize {
+    let token = current_user_token();
+    let process = current_pcb();
+    match option {
+        PR_SET_NAME => {
+            let name = match translated_str(token, arg as *const u8, PROCESS_NAME_LEN) {
+                Ok(name) => name,
+                Err(err) => return err.code(),
+            };
+            process.inner_exclusive_access().name = name;
+        }
+        PR_GET_NAME => {
+            let mut name = [0u8; PROCESS_NAME_LEN];
+            let inner = process.inner_exclusive_access();
+            let len = inner.name.len().min(PROCESS_NAME_LEN - 1);
+            name[..len].copy_from_slice(&inner.name.as_bytes()[..len]);
+            drop(inner);
+            let Ok(buffers) = translated_byte_buffer(token, arg as *const u8, PROCESS_NAME_LEN)
+            else {
+                return -14;
+            };
+            for (p, &b) in UserBuffer::new(buffers).iter_mut().zip(&name) {
+                unsafe {
+                    *p = b;
+                }
+            }
+        }
+        PR_SET_CHILD_SUBREAPER => process.inner_exclusive_access().child_subreaper = arg != 0,
+        PR_GET_CHILD_SUBREAPER => {
+            let Ok(value) = translated_mut_ref(token, arg as *mut i32) else {
+                return -14;
+            };
+            *value = i32::from(process.inner_exclusive_access().child_subreaper);
+        }
+        PR_SET_NO_NEW_PRIVS => {
+            if arg != 1 {
+                return -1;
+            }
+            process.inner_exclusive_access().no_new_privs = true;
+        }
+        PR_GET_NO_NEW_PRIVS => return isize::from(process.inner_exclusive_access().no_new_privs),
+        _ => return -1,
+    }
+    0
+}
@@ -81,6 +81,8 @@ pub fn sys_mutex_create(blocking: bool) -> isize {
 ///
 /// * `0` on successful lock operation.
 /// * `-1` if the mutex does not exist.
+/// * `-2` if the calling thread already holds the blocking mutex, which would never return.
+///   Only checked when the kernel is built with the `mutex_debug` feature.
 pub fn sys_mutex_lock(mutex_id: usize) -> isize {
     let process = current_pcb();
     let process_inner = process.inner_exclusive_access();
@@ -90,6 +92,11 @@ pub fn sys_mutex_lock(mutex_id: usize) -> isize {
             let mutex = Arc::clone(mutex);
             drop(process_inner);
             drop(process);
+            #[cfg(feature = "mutex_debug")]
+            if mutex.held_by_current() {
+                log::warn!("mutex {mutex_id} relocked by the thread holding it");
+                return -2;
+            }
             mutex.lock();
             0
         }
@@ -219,6 +226,58 @@ pub fn sys_semaphore_down(sem_id: usize) -> isize {
     }
 }
 
+/// Takes a resource from a specified semaphore if one is free, without blocking.
+///
+/// Unlike `sys_semaphore_down`, a failed attempt leaves the semaphore unchanged and
+/// never wakes a waiting task.
+///
+/// # Arguments
+///
+/// * `sem_id` - The identifier of the semaphore, which corresponds to its index in
+///     the current process's semaphore list.
+///
+/// # Returns
+///
+/// * `0` if a resource was taken.
+/// * `-1` if the semaphore does not exist.
+/// * `-2` if no resource is free.
+pub fn sys_semaphore_try_down(sem_id: usize) -> isize {
+    let process = current_pcb();
+    let process_inner = process.inner_exclusive_access();
+
+    match process_inner.semaphore_list.get(sem_id) {
+        Some(Some(semaphore)) => {
+            if semaphore.try_down() {
+                0
+            } else {
+                -2
+            }
+        }
+        _ => -1,
+    }
+}
+
+/// Queries the number of free resources of a specified semaphore.
+///
+/// # Arguments
+///
+/// * `sem_id` - The identifier of the semaphore, which corresponds to its index in
+///     the current process's semaphore list.
+///
+/// # Returns
+///
+/// * The number of free resources, `0` while tasks are waiting on the semaphore.
+/// * `-1` if the semaphore does not exist.
+pub fn sys_semaphore_available(sem_id: usize) -> isize {
+    let process = current_pcb();
+    let process_inner = process.inner_exclusive_access();
+
+    match process_inner.semaphore_list.get(sem_id) {
+        Some(Some(semaphore)) => semaphore.available() as isize,
+        _ => -1,
+    }
+}
+
 /// Creates a new condition variable.
 ///
 /// Adds a new condition variable to the current process's condition variable list.
@@ -1,6 +1,7 @@
 //! Thread Management System Calls
 
 use crate::{
+    config::ALL_HARTS,
     mm::kernel_token,
     task::{current_tcb, manager, tcb::TaskControlBlock},
     trap::{user_handler, Context},
@@ -22,6 +23,24 @@ use alloc::sync::Arc;
 ///
 /// * The Thread ID (TID) of the newly created thread on success.
 pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
+    spawn_thread(|ustack_top, kstack_top| {
+        let mut trap_cx = Context::app_init_context(
+            entry,
+            ustack_top,
+            kernel_token(),
+            kstack_top,
+            user_handler as usize,
+        );
+        trap_cx.x[10] = arg;
+        trap_cx
+    }) as isize
+}
+
+/// Adds a thread to the current process and returns its TID.
+///
+/// `init_cx` builds the trap context the thread starts from, given the top of the user
+/// stack allocated for it and the top of its kernel stack.
+pub fn spawn_thread(init_cx: impl FnOnce(usize, usize) -> Context) -> usize {
     let task = current_tcb().unwrap();
     let process = task.process.upgrade().unwrap();
 
@@ -35,6 +54,7 @@ pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
             .ustack_base,
         true,
     ));
+    new_task.inner_exclusive_access().affinity = task.inner_exclusive_access().affinity;
 
     // add new task to scheduler
     manager::add(new_task.clone());
@@ -48,16 +68,8 @@ pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
     tasks.resize_with(new_task_tid + 1, || None);
     tasks[new_task_tid] = Some(new_task.clone());
 
-    let new_task_trap_cx = new_task_inner.trap_cx();
-    *new_task_trap_cx = Context::app_init_context(
-        entry,
-        new_task_res.ustack_top(),
-        kernel_token(),
-        new_task.kstack.top(),
-        user_handler as usize,
-    );
-    new_task_trap_cx.x[10] = arg;
-    new_task_tid as isize
+    *new_task_inner.trap_cx() = init_cx(new_task_res.ustack_top(), new_task.kstack.top());
+    new_task_tid
 }
 
 /// Retrieves the Thread ID (TID) of the current thread.
@@ -121,3 +133,34 @@ pub fn sys_waittid(tid: usize) -> i32 {
         -2
     }
 }
+
+/// Restricts the current thread to the harts set in `mask`.
+///
+/// Bits for harts that do not exist are dropped. Without the `smp` feature
+/// only hart 0 exists, so the mask is validated and stored but changes nothing.
+///
+/// # Arguments
+///
+/// * `mask` - One bit per hart, bit 0 being hart 0.
+///
+/// # Returns
+///
+/// * `0` on success.
+/// * `-1` if `mask` names no existing hart.
+pub fn sys_sched_setaffinity(mask: usize) -> isize {
+    let mask = mask & ALL_HARTS;
+    if mask == 0 {
+        return -1;
+    }
+    current_tcb().unwrap().inner_exclusive_access().affinity = mask;
+    0
+}
+
+/// Retrieves the affinity mask of the current thread.
+///
+/// # Returns
+///
+/// The mask of harts the current thread may run on.
+pub fn sys_sched_getaffinity() -> isize {
+    current_tcb().unwrap().inner_exclusive_access().affinity as isize
+}
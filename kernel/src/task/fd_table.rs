@@ -0,0 +1,156 @@
+//! File descriptor tables, which processes cloned with [`super::CloneFlags::FILES`] share
+
+use alloc::{collections::BTreeSet, sync::Arc, vec::Vec};
+
+use crate::{fs::File, sync::UPIntrFreeCell};
+
+/// An open file as a file descriptor refers to it
+pub type FileRef = Arc<dyn File + Send + Sync>;
+
+/// The open files of one or more processes, indexed by file descriptor
+pub struct FdTable {
+    inner: UPIntrFreeCell<FdTableInner>,
+}
+
+#[derive(Clone)]
+struct FdTableInner {
+    files: Vec<Option<FileRef>>,
+    /// File descriptors closed on `exec`
+    cloexec: BTreeSet<usize>,
+}
+
+impl FdTable {
+    /// Create a table holding `files` at the descriptors of their positions
+    pub fn new(files: Vec<Option<FileRef>>) -> Self {
+        Self::from_inner(FdTableInner {
+            files,
+            cloexec: BTreeSet::new(),
+        })
+    }
+
+    fn from_inner(inner: FdTableInner) -> Self {
+        Self {
+            inner: unsafe { UPIntrFreeCell::new(inner) },
+        }
+    }
+
+    /// Copy the table for a process that does not share it, the files themselves are shared
+    pub fn duplicate(&self) -> Self {
+        Self::from_inner(self.inner.exclusive_access().clone())
+    }
+
+    /// The file open at `fd`
+    pub fn get(&self, fd: usize) -> Option<FileRef> {
+        self.inner
+            .exclusive_access()
+            .files
+            .get(fd)
+            .cloned()
+            .flatten()
+    }
+
+    /// The open descriptors, in increasing order
+    pub fn open_fds(&self) -> Vec<usize> {
+        let inner = self.inner.exclusive_access();
+        (0..inner.files.len())
+            .filter(|&fd| inner.files[fd].is_some())
+            .collect()
+    }
+
+    /// Open `file` at the lowest free descriptor and return it
+    pub fn insert(&self, file: FileRef, cloexec: bool) -> usize {
+        let mut inner = self.inner.exclusive_access();
+        let fd = if let Some(fd) = inner.files.iter().position(Option::is_none) {
+            fd
+        } else {
+            inner.files.push(None);
+            inner.files.len() - 1
+        };
+        inner.files[fd] = Some(file);
+        if cloexec {
+            inner.cloexec.insert(fd);
+        }
+        fd
+    }
+
+    /// Open `file` at `fd`, returning the file that was open there
+    pub fn set(&self, fd: usize, file: FileRef) -> Option<FileRef> {
+        let mut inner = self.inner.exclusive_access();
+        if fd >= inner.files.len() {
+            inner.files.resize(fd + 1, None);
+        }
+        inner.cloexec.remove(&fd);
+        inner.files[fd].replace(file)
+    }
+
+    /// Close `fd`, returning the file that was open there
+    pub fn remove(&self, fd: usize) -> Option<FileRef> {
+        let mut inner = self.inner.exclusive_access();
+        inner.cloexec.remove(&fd);
+        inner.files.get_mut(fd)?.take()
+    }
+
+    /// Close the open descriptors in `first..=last`, returning their files to be dropped
+    pub fn remove_range(&self, first: usize, last: usize) -> Vec<FileRef> {
+        let mut inner = self.inner.exclusive_access();
+        let inner = &mut *inner;
+        let end = inner.files.len().min(last.saturating_add(1));
+        inner.cloexec.retain(|&fd| fd < first || fd > last);
+        inner
+            .files
+            .get_mut(first..end)
+            .map_or_else(Vec::new, |files| {
+                files.iter_mut().filter_map(Option::take).collect()
+            })
+    }
+
+    /// Mark the open descriptors in `first..=last` close-on-exec
+    pub fn set_cloexec_range(&self, first: usize, last: usize) {
+        let mut inner = self.inner.exclusive_access();
+        let inner = &mut *inner;
+        let end = inner.files.len().min(last.saturating_add(1));
+        let open = (first..end).filter(|&fd| inner.files[fd].is_some());
+        inner.cloexec.extend(open);
+    }
+
+    /// Make `new_fd` refer to the file at `old_fd`, returning the file that was open at `new_fd`
+    ///
+    /// Fails if `old_fd` is out of range.
+    pub fn duplicate_to(&self, old_fd: usize, new_fd: usize) -> Result<Option<FileRef>, ()> {
+        let mut inner = self.inner.exclusive_access();
+        if old_fd >= inner.files.len() {
+            return Err(());
+        }
+        if new_fd >= inner.files.len() {
+            inner.files.resize(new_fd + 1, None);
+        }
+        let file = inner.files[old_fd].clone();
+        inner.cloexec.remove(&new_fd);
+        Ok(core::mem::replace(&mut inner.files[new_fd], file))
+    }
+
+    /// Whether `fd` is closed on `exec`
+    pub fn is_cloexec(&self, fd: usize) -> bool {
+        self.inner.exclusive_access().cloexec.contains(&fd)
+    }
+
+    /// Set whether `fd` is closed on `exec`
+    pub fn set_cloexec(&self, fd: usize, cloexec: bool) {
+        let mut inner = self.inner.exclusive_access();
+        if cloexec {
+            inner.cloexec.insert(fd);
+        } else {
+            inner.cloexec.remove(&fd);
+        }
+    }
+
+    /// Close the descriptors marked close-on-exec, returning their files to be dropped
+    pub fn take_cloexec(&self) -> Vec<FileRef> {
+        let mut inner = self.inner.exclusive_access();
+        let inner = &mut *inner;
+        core::mem::take(&mut inner.cloexec)
+            .into_iter()
+            .filter_map(|fd| inner.files[fd].take())
+            .collect()
+    }
+}
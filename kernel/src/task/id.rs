@@ -72,6 +72,11 @@ impl KernelStack {
         kernel_stack_top
     }
 
+    /// Lowest and highest address of the stack
+    pub fn bounds(&self) -> (usize, usize) {
+        kernel_stack_position(self.0)
+    }
+
     #[allow(unused)]
     /// Push a value on top of kernel stack
     pub fn push_on_top<T>(&self, value: T) -> *mut T
@@ -129,20 +134,27 @@ impl TaskUserRes {
         // alloc user stack
         let ustack_bottom = ustack_bottom_from_tid(self.ustack_base, self.tid);
         let ustack_top = ustack_bottom + USER_STACK_SIZE;
-        process_inner.memory_set.insert_framed_area(
-            ustack_bottom.into(),
-            ustack_top.into(),
-            MapPermission::R | MapPermission::W | MapPermission::U,
-        );
+        process_inner
+            .memory_set
+            .insert_framed_area(
+                ustack_bottom.into(),
+                ustack_top.into(),
+                MapPermission::R | MapPermission::W | MapPermission::U,
+            )
+            .expect("user stack overlaps another area");
 
         // alloc trap_cx
         let trap_cx_bottom = trap_cx_bottom_from_tid(self.tid);
         let trap_cx_top = trap_cx_bottom + PAGE_SIZE;
-        process_inner.memory_set.insert_framed_area(
-            trap_cx_bottom.into(),
-            trap_cx_top.into(),
-            MapPermission::R | MapPermission::W,
-        );
+        process_inner
+            .memory_set
+            .insert_framed_area(
+                trap_cx_bottom.into(),
+                trap_cx_top.into(),
+                MapPermission::R | MapPermission::W,
+            )
+            .expect("trap context overlaps another area");
+        process_inner.note_memory_usage();
     }
 
     fn dealloc_user_res(&self) {
@@ -206,11 +218,14 @@ pub fn pid_alloc() -> PidHandle {
 pub fn kstack_alloc() -> KernelStack {
     let kstack_id = KSTACK_ALLOCATOR.exclusive_access().alloc();
     let (kstack_bottom, kstack_top) = kernel_stack_position(kstack_id);
-    KERNEL_SPACE.exclusive_access().insert_framed_area(
-        kstack_bottom.into(),
-        kstack_top.into(),
-        MapPermission::R | MapPermission::W,
-    );
+    KERNEL_SPACE
+        .exclusive_access()
+        .insert_framed_area(
+            kstack_bottom.into(),
+            kstack_top.into(),
+            MapPermission::R | MapPermission::W,
+        )
+        .expect("kernel stack overlaps another area");
     KernelStack(kstack_id)
 }
 
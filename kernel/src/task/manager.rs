@@ -4,9 +4,11 @@ use crate::sync::UPIntrFreeCell;
 
 use alloc::collections::{BTreeMap, VecDeque};
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use lazy_static::lazy_static;
 
-use super::pcb::ProcessControlBlock;
+use super::pcb::{ProcessControlBlock, ProcessControlBlockInner};
+use super::signal::SignalFlags;
 use super::tcb::{Status, TaskControlBlock};
 
 /// A array of `TaskControlBlock` that is thread-safe
@@ -28,9 +30,20 @@ impl Manager {
     }
 
     /// Remove the first task and return it,or [`None`] if [`Manager`] is empty
+    #[cfg(not(feature = "smp"))]
     pub fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
         self.ready_queue.pop_front()
     }
+
+    /// Remove the first task allowed to run on `hart` and return it
+    #[cfg(feature = "smp")]
+    pub fn fetch_for(&mut self, hart: usize) -> Option<Arc<TaskControlBlock>> {
+        let index = self
+            .ready_queue
+            .iter()
+            .position(|task| task.inner_exclusive_access().affinity & (1 << hart) != 0)?;
+        self.ready_queue.remove(index)
+    }
 }
 
 lazy_static! {
@@ -38,6 +51,8 @@ lazy_static! {
         unsafe { UPIntrFreeCell::new(Manager::new()) };
     static ref PID2PCB: UPIntrFreeCell<BTreeMap<usize, Arc<ProcessControlBlock>>> =
         unsafe { UPIntrFreeCell::new(BTreeMap::new()) };
+    /// Process group in the foreground of the terminal, `0` if none
+    static ref FOREGROUND_PGID: UPIntrFreeCell<usize> = unsafe { UPIntrFreeCell::new(0) };
 }
 
 /// Add the thread to the ready queue
@@ -53,10 +68,19 @@ pub fn wakeup(task: Arc<TaskControlBlock>) {
 }
 
 /// Pop a task from the ready queue
+#[cfg(not(feature = "smp"))]
 pub fn fetch() -> Option<Arc<TaskControlBlock>> {
     TASK_MANAGER.exclusive_access().fetch()
 }
 
+/// Pop the first task from the ready queue that may run on this hart
+#[cfg(feature = "smp")]
+pub fn fetch() -> Option<Arc<TaskControlBlock>> {
+    TASK_MANAGER
+        .exclusive_access()
+        .fetch_for(super::processor::hart_id())
+}
+
 /// Query the PCB based on PID
 pub fn pid2process(pid: usize) -> Option<Arc<ProcessControlBlock>> {
     let map = PID2PCB.exclusive_access();
@@ -72,7 +96,136 @@ pub fn remove_from_pid2process(pid: usize) {
     );
 }
 
+/// Number of live processes
+pub fn process_count() -> usize {
+    PID2PCB.exclusive_access().len()
+}
+
 /// Add a pair of PID-PCB mappings
 pub fn insert_into_pid2process(pid: usize, process: Arc<ProcessControlBlock>) {
     PID2PCB.exclusive_access().insert(pid, process);
 }
+
+/// Whether any live process belongs to the process group `pgid`
+pub fn pgid_exists(pgid: usize) -> bool {
+    PID2PCB
+        .exclusive_access()
+        .values()
+        .any(|process| process.inner_exclusive_access().pgid == pgid)
+}
+
+/// Send `signal` to every live process in the process group `pgid`, returning how many got it
+pub fn signal_group(pgid: usize, signal: SignalFlags) -> usize {
+    let members: Vec<_> = PID2PCB
+        .exclusive_access()
+        .values()
+        .filter(|process| process.inner_exclusive_access().pgid == pgid)
+        .cloned()
+        .collect();
+    for process in &members {
+        process.inner_exclusive_access().signals |= signal;
+    }
+    members.len()
+}
+
+/// Process group that receives the signals typed at the terminal, `0` if none
+pub fn foreground_pgid() -> usize {
+    *FOREGROUND_PGID.exclusive_access()
+}
+
+/// Hand the terminal to the process group `pgid`, or to no group if `0`
+pub fn set_foreground_pgid(pgid: usize) {
+    *FOREGROUND_PGID.exclusive_access() = pgid;
+}
+
+/// Send `signal` to the foreground process group, returning `false` if there is none
+pub fn signal_foreground(signal: SignalFlags) -> bool {
+    match foreground_pgid() {
+        0 => false,
+        pgid => signal_group(pgid, signal) > 0,
+    }
+}
+
+/// Length of the program name kept in a [`ProcessInfo`], including the trailing NUL
+pub const PROCESS_NAME_LEN: usize = 32;
+
+/// Scheduling state of a process
+#[repr(u32)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ProcessState {
+    /// A thread is waiting to be scheduled
+    Ready = 0,
+    /// A thread is running
+    Running = 1,
+    /// All threads are blocked
+    Blocked = 2,
+    /// The process exited and waits to be reaped by its parent
+    Zombie = 3,
+}
+
+/// A process as reported to user space
+#[repr(C)]
+pub struct ProcessInfo {
+    pub pid: usize,
+    pub ppid: usize,
+    pub state: ProcessState,
+    /// Exit code of a zombie, `0` otherwise
+    pub exit_code: i32,
+    /// Program name, truncated and NUL-padded
+    pub name: [u8; PROCESS_NAME_LEN],
+}
+
+impl ProcessInfo {
+    fn new(pid: usize, inner: &ProcessControlBlockInner) -> Self {
+        let state = if inner.is_zombie {
+            ProcessState::Zombie
+        } else {
+            let statuses = inner
+                .tasks
+                .iter()
+                .flatten()
+                .map(|task| task.inner_exclusive_access().task_status);
+            statuses.fold(ProcessState::Blocked, |state, status| match status {
+                Status::Running => ProcessState::Running,
+                Status::Ready if state == ProcessState::Blocked => ProcessState::Ready,
+                _ => state,
+            })
+        };
+        let mut name = [0u8; PROCESS_NAME_LEN];
+        let len = inner.name.len().min(PROCESS_NAME_LEN - 1);
+        name[..len].copy_from_slice(&inner.name.as_bytes()[..len]);
+        Self {
+            pid,
+            ppid: inner
+                .parent
+                .as_ref()
+                .and_then(alloc::sync::Weak::upgrade)
+                .map_or(0, |parent| parent.pid()),
+            state,
+            exit_code: if inner.is_zombie { inner.exit_code } else { 0 },
+            name,
+        }
+    }
+}
+
+/// Snapshot of all live processes and their zombie children, ordered by PID
+///
+/// The PID map stays locked while the snapshot is taken, so no process can
+/// appear or exit halfway through.
+pub fn process_infos() -> Vec<ProcessInfo> {
+    let map = PID2PCB.exclusive_access();
+    let mut infos = Vec::new();
+    for (&pid, process) in map.iter() {
+        let inner = process.inner_exclusive_access();
+        infos.push(ProcessInfo::new(pid, &inner));
+        for child in &inner.children {
+            let child_inner = child.inner_exclusive_access();
+            if child_inner.is_zombie {
+                infos.push(ProcessInfo::new(child.pid(), &child_inner));
+            }
+        }
+    }
+    drop(map);
+    infos.sort_unstable_by_key(|info| info.pid);
+    infos
+}
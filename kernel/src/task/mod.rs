@@ -1,6 +1,7 @@
 //! # Task Management
 
 mod context;
+mod fd_table;
 mod id;
 pub mod manager;
 pub mod pcb;
@@ -10,20 +11,29 @@ mod switch;
 pub mod tcb;
 
 use crate::{
-    fs::{open_file, OpenFlags},
+    fs::{open_file, sync_all, OpenFlags},
     sbi::shutdown,
 };
-use alloc::{sync::Arc, vec::Vec};
+use alloc::{
+    sync::{Arc, Weak},
+    vec::Vec,
+};
 use lazy_static::lazy_static;
 use log::info;
 
 pub use context::Context;
+pub use fd_table::FdTable;
 pub use manager::{pid2process, remove_from_pid2process};
+pub use pcb::CloneFlags;
 pub use processor::{
     current_pcb, current_tcb, current_trap_cx, current_trap_cx_user_va, current_user_token,
-    run_tasks, schedule, take_current_tcb,
+    peek_current_tcb, run_tasks, schedule, take_current_tcb,
+};
+pub use signal::{
+    add_signal_to_current, check_signals_error_of_current, check_thread_signals_error_of_current,
+    consume_ignored_thread_signals_of_current, pending_signals_of_current,
+    unmasked_signal_pending_of_current, SignalFlags,
 };
-pub use signal::{add_signal_to_current, check_signals_error_of_current, SignalFlags};
 
 use id::TaskUserRes;
 use pcb::ProcessControlBlock;
@@ -34,7 +44,7 @@ lazy_static! {
     pub static ref DAEMON: Arc<ProcessControlBlock> = {
         let inode = open_file("/bin/daemon", OpenFlags::RDONLY).expect("Failed to open '/bin/daemon'.");
         let v = inode.read_all();
-        ProcessControlBlock::new(v.as_slice())
+        ProcessControlBlock::new("daemon", v.as_slice())
     };
 }
 
@@ -77,11 +87,53 @@ pub fn block_current_and_run_next() {
     schedule(task_cx_ptr);
 }
 
+/// Free the orphaned zombies [`DAEMON`] adopted, as the daemon only waits for its own children.
+///
+/// `exiting` is skipped, it is still running on the kernel stack of its main thread.
+fn reap_orphans(exiting: &Arc<ProcessControlBlock>) {
+    let mut daemon_inner = DAEMON.inner_exclusive_access();
+    let mut reaped = Vec::new();
+    let mut i = 0;
+    while i < daemon_inner.children.len() {
+        let child = &daemon_inner.children[i];
+        let child_inner = child.inner_exclusive_access();
+        let reapable = child_inner.orphaned && child_inner.is_zombie;
+        drop(child_inner);
+        // a zombie only referenced from here is not in use anywhere else
+        if reapable && !Arc::ptr_eq(child, exiting) && Arc::strong_count(child) == 1 {
+            reaped.push(daemon_inner.children.swap_remove(i));
+        } else {
+            i += 1;
+        }
+    }
+    daemon_inner.settle_sigchld();
+    // freeing the PCBs touches /proc and the kernel space, so release the daemon first
+    drop(daemon_inner);
+    drop(reaped);
+}
+
+/// The nearest of `process` and its ancestors still alive that asked to reap orphaned
+/// descendants, see [`pcb::ProcessControlBlockInner::child_subreaper`]
+fn subreaper_of(process: &Arc<ProcessControlBlock>) -> Option<Arc<ProcessControlBlock>> {
+    let mut ancestor = Some(process.clone());
+    while let Some(process) = ancestor {
+        let inner = process.inner_exclusive_access();
+        if inner.child_subreaper && !inner.is_zombie {
+            drop(inner);
+            return Some(process);
+        }
+        ancestor = inner.parent.as_ref().and_then(Weak::upgrade);
+    }
+    None
+}
+
 /// Exit the current 'Running' task and run the next task in task list.
 pub fn exit_current_and_run_next(exit_code: i32) {
     let task = take_current_tcb().unwrap();
-    let mut task_inner = task.inner_exclusive_access();
     let process = task.process.upgrade().unwrap();
+    // a vfork child gives the address space back before its user res are freed from it
+    process.release_vfork();
+    let mut task_inner = task.inner_exclusive_access();
     let tid = task_inner.res.as_ref().unwrap().tid;
 
     // record exit code and recycle task user res
@@ -102,6 +154,7 @@ pub fn exit_current_and_run_next(exit_code: i32) {
                 "[kernel] Idle process exit with exit_code {} ...",
                 exit_code
             );
+            sync_all();
             if exit_code != 0 {
                 shutdown(true)
             } else {
@@ -117,13 +170,22 @@ pub fn exit_current_and_run_next(exit_code: i32) {
         process_inner.is_zombie = true;
         // record exit code of main process
         process_inner.exit_code = exit_code;
+        let parent = process_inner.parent.as_ref().and_then(Weak::upgrade);
 
         {
-            // move all child processes under daemon process
-            let mut daemon_inner = DAEMON.inner_exclusive_access();
+            // move all child processes under the nearest subreaper, the daemon if there is none
+            let reaper = parent.as_ref().and_then(subreaper_of);
+            let orphaned = reaper.is_none();
+            let reaper = reaper.unwrap_or_else(|| DAEMON.clone());
+            let mut reaper_inner = reaper.inner_exclusive_access();
             for child in &process_inner.children {
-                child.inner_exclusive_access().parent = Some(Arc::downgrade(&DAEMON));
-                daemon_inner.children.push(child.clone());
+                let mut child_inner = child.inner_exclusive_access();
+                child_inner.parent = Some(Arc::downgrade(&reaper));
+                child_inner.orphaned = orphaned;
+                if child_inner.is_zombie && !orphaned {
+                    reaper_inner.signals |= SignalFlags::SIGCHLD;
+                }
+                reaper_inner.children.push(child.clone());
             }
         }
 
@@ -154,16 +216,48 @@ pub fn exit_current_and_run_next(exit_code: i32) {
         process_inner.children.clear();
         // deallocate other data in user space i.e. program code/data section
         process_inner.memory_set.recycle_data_pages();
-        // drop file descriptors
-        process_inner.fd_table.clear();
+        // file descriptors are dropped once the process is released, closing files may flush them
+        let fd_table = core::mem::replace(
+            &mut process_inner.fd_table,
+            Arc::new(FdTable::new(Vec::new())),
+        );
         // Remove all tasks except for the main thread itself.
         // This is because we are still using the kstack under the TCB
         // of the main thread. This TCB, including its kstack, will be
         // deallocated when the process is reaped via waitpid.
         process_inner.tasks.truncate(1);
+        drop(process_inner);
+        drop(fd_table);
+
+        // tell the parent a child is ready to be waited for
+        if let Some(parent) = parent {
+            parent.inner_exclusive_access().signals |= SignalFlags::SIGCHLD;
+        }
+        reap_orphans(&process);
     }
 
     drop(process);
     // we do not have to save task context
     schedule(core::ptr::from_mut(&mut Context::zero_init()));
 }
+
+/// Exit the current task on behalf of `signal`, recording it so a waiting parent can
+/// tell the process was killed rather than exited. The exit code is the negated signal
+/// number, as `waitpid` reports it.
+pub fn kill_current_and_run_next(signal: u32) {
+    current_pcb().inner_exclusive_access().term_signal = Some(signal);
+    exit_current_and_run_next(-(signal as i32));
+}
+
+/// Exit the current thread on behalf of a `signal` sent to it alone. Only if it is the main
+/// thread, which takes the others along, does the process count as killed.
+pub fn kill_current_thread_and_run_next(signal: u32) {
+    let task = current_tcb().unwrap();
+    let tid = task.inner_exclusive_access().res.as_ref().unwrap().tid;
+    drop(task);
+    if tid == 0 {
+        kill_current_and_run_next(signal);
+    } else {
+        exit_current_and_run_next(-(signal as i32));
+    }
+}
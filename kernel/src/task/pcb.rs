@@ -1,11 +1,12 @@
 use super::{
+    fd_table::FdTable,
     id::{pid_alloc, PidHandle, RecycleAllocator},
-    manager::{add, insert_into_pid2process},
+    manager::{add, insert_into_pid2process, wakeup},
     tcb::TaskControlBlock,
     SignalFlags,
 };
 use crate::{
-    fs::{inode, File, Stdin, Stdout, PROC_INODE},
+    fs::{inode, Stdin, Stdout, PROC_INODE},
     mm::{translated_mut_ref, MemorySet, KERNEL_SPACE},
     sync::{Condvar, Mutex, Semaphore, UPIntrFreeCell, UPIntrRefMut},
     trap::{user_handler, Context},
@@ -18,6 +19,20 @@ use alloc::{
     vec,
     vec::Vec,
 };
+use bitflags::bitflags;
+
+bitflags! {
+    /// Resources a child created by `clone` shares with its parent
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct CloneFlags: usize {
+        /// Share the address space, the child is a thread of the parent's process
+        const VM = 0x100;
+        /// Share the file descriptor table
+        const FILES = 0x400;
+        /// Share the signal state, only possible along with the address space
+        const SIGHAND = 0x800;
+    }
+}
 
 pub struct ProcessControlBlock {
     pub pid: PidHandle,
@@ -29,36 +44,47 @@ impl ProcessControlBlock {
         self.inner.exclusive_access()
     }
 
-    pub fn new(elf_data: &[u8]) -> Arc<Self> {
+    pub fn new(name: &str, elf_data: &[u8]) -> Arc<Self> {
         // memory_set with elf program headers/trampoline/trap context/user stack
         let (memory_set, ustack_base, entry_point) = MemorySet::from_elf(elf_data);
+        let peak_pages = memory_set.framed_pages();
 
-        // allocate a pid
+        // allocate a pid, the process leads a group of its own
         let pid = pid_alloc();
+        let leader = pid.0;
         let process = Arc::new(Self {
             pid,
             inner: unsafe {
                 UPIntrFreeCell::new(ProcessControlBlockInner {
+                    name: String::from(name),
+                    pgid: leader,
                     is_zombie: false,
                     memory_set,
                     parent: None,
+                    orphaned: false,
+                    child_subreaper: false,
+                    no_new_privs: false,
                     children: Vec::new(),
                     exit_code: 0,
                     cwd: String::from("/"),
-                    fd_table: vec![
+                    fd_table: Arc::new(FdTable::new(vec![
                         // 0 -> stdin
                         Some(Arc::new(Stdin)),
                         // 1 -> stdout
                         Some(Arc::new(Stdout)),
                         // 2 -> stderr
                         Some(Arc::new(Stdout)),
-                    ],
+                    ])),
                     signals: SignalFlags::empty(),
+                    signal_mask: SignalFlags::empty(),
                     tasks: Vec::new(),
                     task_res_allocator: RecycleAllocator::new(),
                     mutex_list: Vec::new(),
                     semaphore_list: Vec::new(),
                     condvar_list: Vec::new(),
+                    vfork_parent: None,
+                    peak_pages,
+                    term_signal: None,
                 })
             },
         });
@@ -96,16 +122,27 @@ impl ProcessControlBlock {
     }
 
     #[allow(clippy::similar_names)]
-    pub fn exec(self: &Arc<Self>, elf_data: &[u8], args: &[String]) {
+    pub fn exec(self: &Arc<Self>, name: &str, elf_data: &[u8], args: &[String]) {
         // only support processes with a single thread
         assert_eq!(self.inner_exclusive_access().thread_count(), 1);
+        // a vfork child gives the address space back, it is replaced below anyway
+        self.release_vfork();
 
         // memory_set with elf program headers/trampoline/trap context/user stack
         let (memory_set, ustack_base, entry_point) = MemorySet::from_elf(elf_data);
         let new_token = memory_set.token();
 
-        // substitute memory_set
-        self.inner_exclusive_access().memory_set = memory_set;
+        // substitute memory_set and close file descriptors marked close-on-exec
+        let mut process_inner = self.inner_exclusive_access();
+        process_inner.name = String::from(name);
+        process_inner.memory_set = memory_set;
+        // the processes sharing the table keep their descriptors, exec gets a table of its own
+        if Arc::strong_count(&process_inner.fd_table) > 1 {
+            process_inner.fd_table = Arc::new(process_inner.fd_table.duplicate());
+        }
+        let closed = process_inner.fd_table.take_cloexec();
+        drop(process_inner);
+        drop(closed);
         // then we alloc user resource for main thread again
         // since memory_set has been changed
         let task = self.inner_exclusive_access().task(0);
@@ -114,30 +151,10 @@ impl ProcessControlBlock {
         task_inner.res.as_mut().unwrap().alloc_user_res();
         task_inner.trap_cx_ppn = task_inner.res.as_mut().unwrap().trap_cx_ppn();
 
-        // push arguments on user stack
+        // push arguments on user stack, which was just mapped by from_elf
         let argc = args.len();
-        let mut user_sp = task_inner.res.as_mut().unwrap().ustack_top();
-        user_sp -= (argc + 1) * core::mem::size_of::<usize>();
-        let argv_base = user_sp;
-        let mut argv: Vec<_> = (0..=argc)
-            .map(|arg| {
-                translated_mut_ref(
-                    new_token,
-                    (argv_base + arg * core::mem::size_of::<usize>()) as *mut usize,
-                )
-            })
-            .collect();
-        *argv[argc] = 0;
-        for i in 0..argc {
-            user_sp -= args[i].len() + 1;
-            *argv[i] = user_sp;
-            let mut p = user_sp;
-            for c in args[i].as_bytes() {
-                *translated_mut_ref(new_token, p as *mut u8) = *c;
-                p += 1;
-            }
-            *translated_mut_ref(new_token, p as *mut u8) = 0;
-        }
+        let ustack_top = task_inner.res.as_mut().unwrap().ustack_top();
+        let (user_sp, argv_base) = push_args(new_token, ustack_top, args);
 
         // write cmdline
         *DEV_NON_BLOCKING_ACCESS.exclusive_access() = false;
@@ -164,37 +181,100 @@ impl ProcessControlBlock {
         *task_inner.trap_cx() = trap_cx;
     }
 
-    pub fn fork(self: &Arc<Self>) -> Arc<Self> {
+    /// Create a child process with a copy of the address space, sharing what `flags` select
+    ///
+    /// [`CloneFlags::VM`] and [`CloneFlags::SIGHAND`] are not handled here, sharing those
+    /// makes a thread of this process instead.
+    pub fn fork(self: &Arc<Self>, flags: CloneFlags) -> Arc<Self> {
+        // clone parent's memory_set completely including trampoline/ustacks/trap_cxs
+        let child = self.new_child(flags, |parent_inner| parent_inner.memory_set.clone());
+        // add this thread to scheduler
+        add(child.inner_exclusive_access().task(0));
+        child
+    }
+
+    /// Create a child process running in the address space of this one until it calls `exec`
+    /// or exits, for `sys_vfork`
+    ///
+    /// The address space is moved to the child rather than copied, so this process must not
+    /// run until [`Self::release_vfork`] moves it back and wakes up `task`, the calling
+    /// thread. The child resumes on the trap context page of `task`, whose contents are kept
+    /// aside to be restored then. Its main thread is left for the caller to schedule.
+    pub fn vfork(self: &Arc<Self>, task: Arc<TaskControlBlock>) -> Arc<Self> {
+        let trap_cx = *task.inner_exclusive_access().trap_cx();
+        let child = self.new_child(CloneFlags::empty(), |parent_inner| {
+            core::mem::replace(&mut parent_inner.memory_set, MemorySet::new_bare())
+        });
+        child.inner_exclusive_access().vfork_parent = Some(VforkParent { task, trap_cx });
+        child
+    }
+
+    /// Hand the address space borrowed by [`Self::vfork`] back to the parent and resume it
+    ///
+    /// Does nothing unless this process is a `vfork` child that has not released it yet.
+    pub fn release_vfork(&self) {
+        let mut inner = self.inner_exclusive_access();
+        let Some(VforkParent { task, trap_cx }) = inner.vfork_parent.take() else {
+            return;
+        };
+        let memory_set = core::mem::replace(&mut inner.memory_set, MemorySet::new_bare());
+        drop(inner);
+        let parent = task.process.upgrade().unwrap();
+        parent.inner_exclusive_access().memory_set = memory_set;
+        *task.inner_exclusive_access().trap_cx() = trap_cx;
+        wakeup(task);
+    }
+
+    /// Create a child process on the address space `memory_set` takes from this one, its main
+    /// thread is not scheduled yet
+    fn new_child(
+        self: &Arc<Self>,
+        flags: CloneFlags,
+        memory_set: impl FnOnce(&mut ProcessControlBlockInner) -> MemorySet,
+    ) -> Arc<Self> {
         let mut parent_inner = self.inner_exclusive_access();
         // only support processes with a single thread
         assert_eq!(parent_inner.thread_count(), 1);
 
-        // clone parent's memory_set completely including trampoline/ustacks/trap_cxs
-        let memory_set = parent_inner.memory_set.clone();
+        let memory_set = memory_set(&mut parent_inner);
+        let peak_pages = memory_set.framed_pages();
         // alloc a pid
         let pid = pid_alloc();
         let pid_str = pid.0.to_string();
-        // copy fd table
-        let new_fd_table = parent_inner.fd_table.clone();
+        // share or copy fd table
+        let new_fd_table = if flags.contains(CloneFlags::FILES) {
+            parent_inner.fd_table.clone()
+        } else {
+            Arc::new(parent_inner.fd_table.duplicate())
+        };
 
         // create child process PCB
         let child = Arc::new(Self {
             pid,
             inner: unsafe {
                 UPIntrFreeCell::new(ProcessControlBlockInner {
+                    name: parent_inner.name.clone(),
+                    pgid: parent_inner.pgid,
                     is_zombie: false,
                     memory_set,
                     parent: Some(Arc::downgrade(self)),
+                    orphaned: false,
+                    child_subreaper: false,
+                    no_new_privs: parent_inner.no_new_privs,
                     children: Vec::new(),
                     exit_code: 0,
                     cwd: parent_inner.cwd.clone(),
                     fd_table: new_fd_table,
                     signals: SignalFlags::empty(),
+                    signal_mask: parent_inner.signal_mask,
                     tasks: Vec::new(),
                     task_res_allocator: RecycleAllocator::new(),
                     mutex_list: Vec::new(),
                     semaphore_list: Vec::new(),
                     condvar_list: Vec::new(),
+                    vfork_parent: None,
+                    peak_pages,
+                    term_signal: None,
                 })
             },
         });
@@ -214,6 +294,9 @@ impl ProcessControlBlock {
             false,
         ));
 
+        task.inner_exclusive_access().affinity =
+            parent_inner.task(0).inner_exclusive_access().affinity;
+
         // attach task to child process
         let mut child_inner = child.inner_exclusive_access();
         child_inner.tasks.push(Some(task.clone()));
@@ -227,15 +310,15 @@ impl ProcessControlBlock {
 
         insert_into_pid2process(child.pid(), child.clone());
 
-        // add this thread to scheduler
-        add(task);
-
         // write proc info
         *DEV_NON_BLOCKING_ACCESS.exclusive_access() = false;
         let proc_inode = PROC_INODE
             .create_dir(&pid_str)
             .unwrap_or_else(|| panic!("Failed to create inode for '/proc/{}/'.", pid_str));
         proc_inode.set_default_dirent(PROC_INODE.inode_id());
+        proc_inode
+            .create_dir("fd")
+            .unwrap_or_else(|| panic!("Failed to create inode for '/proc/{}/fd/'.", pid_str));
         let cmdline_inode = proc_inode
             .create("cmdline")
             .unwrap_or_else(|| panic!("Failed to find inode for '/proc/{}/cmdline'.", pid_str));
@@ -248,22 +331,173 @@ impl ProcessControlBlock {
 
         child
     }
+
+    /// Create a child process running `elf_data`, without copying this process's address space
+    ///
+    /// The child inherits what [`Self::fork`] passes on, except for the file descriptors
+    /// marked close-on-exec, as if it had called `exec` right after forking.
+    pub fn spawn(self: &Arc<Self>, name: &str, elf_data: &[u8], args: &[String]) -> Arc<Self> {
+        // memory_set with elf program headers/trampoline, the user resources come with the thread
+        let (memory_set, ustack_base, entry_point) = MemorySet::from_elf(elf_data);
+        let peak_pages = memory_set.framed_pages();
+        let new_token = memory_set.token();
+        let pid = pid_alloc();
+        let pid_str = pid.0.to_string();
+
+        let mut parent_inner = self.inner_exclusive_access();
+        let fd_table = parent_inner.fd_table.duplicate();
+        let closed = fd_table.take_cloexec();
+        let child = Arc::new(Self {
+            pid,
+            inner: unsafe {
+                UPIntrFreeCell::new(ProcessControlBlockInner {
+                    name: String::from(name),
+                    pgid: parent_inner.pgid,
+                    is_zombie: false,
+                    memory_set,
+                    parent: Some(Arc::downgrade(self)),
+                    orphaned: false,
+                    child_subreaper: false,
+                    no_new_privs: parent_inner.no_new_privs,
+                    children: Vec::new(),
+                    exit_code: 0,
+                    cwd: parent_inner.cwd.clone(),
+                    fd_table: Arc::new(fd_table),
+                    signals: SignalFlags::empty(),
+                    signal_mask: parent_inner.signal_mask,
+                    tasks: Vec::new(),
+                    task_res_allocator: RecycleAllocator::new(),
+                    mutex_list: Vec::new(),
+                    semaphore_list: Vec::new(),
+                    condvar_list: Vec::new(),
+                    vfork_parent: None,
+                    peak_pages,
+                    term_signal: None,
+                })
+            },
+        });
+        parent_inner.children.push(child.clone());
+        let affinity = parent_inner.task(0).inner_exclusive_access().affinity;
+        drop(parent_inner);
+        drop(closed);
+
+        // create the main thread along with its user stack and trap_cx
+        let task = Arc::new(TaskControlBlock::new(&child, ustack_base, true));
+        let mut task_inner = task.inner_exclusive_access();
+        task_inner.affinity = affinity;
+        let ustack_top = task_inner.res.as_ref().unwrap().ustack_top();
+        let (user_sp, argv_base) = push_args(new_token, ustack_top, args);
+        let mut trap_cx = Context::app_init_context(
+            entry_point,
+            user_sp,
+            KERNEL_SPACE.exclusive_access().token(),
+            task.kstack.top(),
+            user_handler as usize,
+        );
+        trap_cx.x[10] = args.len();
+        trap_cx.x[11] = argv_base;
+        *task_inner.trap_cx() = trap_cx;
+        drop(task_inner);
+        child
+            .inner_exclusive_access()
+            .tasks
+            .push(Some(task.clone()));
+
+        // write proc info before the child can run
+        *DEV_NON_BLOCKING_ACCESS.exclusive_access() = false;
+        let proc_inode = PROC_INODE
+            .create_dir(&pid_str)
+            .unwrap_or_else(|| panic!("Failed to create inode for '/proc/{}/'.", pid_str));
+        proc_inode.set_default_dirent(PROC_INODE.inode_id());
+        proc_inode
+            .create_dir("fd")
+            .unwrap_or_else(|| panic!("Failed to create inode for '/proc/{}/fd/'.", pid_str));
+        let cmdline_inode = proc_inode
+            .create("cmdline")
+            .unwrap_or_else(|| panic!("Failed to find inode for '/proc/{}/cmdline'.", pid_str));
+        cmdline_inode.write_at(0, args.join(" ").as_bytes());
+        *DEV_NON_BLOCKING_ACCESS.exclusive_access() = true;
+
+        insert_into_pid2process(child.pid(), child.clone());
+        add(task);
+        child
+    }
+}
+
+/// Push `args` and a null-terminated `argv` array pointing at them below `user_sp`
+///
+/// Returns the new stack pointer and the address of `argv`.
+#[allow(clippy::similar_names)]
+fn push_args(token: usize, mut user_sp: usize, args: &[String]) -> (usize, usize) {
+    let argc = args.len();
+    user_sp -= (argc + 1) * core::mem::size_of::<usize>();
+    let argv_base = user_sp;
+    let mut argv: Vec<_> = (0..=argc)
+        .map(|arg| {
+            translated_mut_ref(
+                token,
+                (argv_base + arg * core::mem::size_of::<usize>()) as *mut usize,
+            )
+            .unwrap()
+        })
+        .collect();
+    *argv[argc] = 0;
+    for i in 0..argc {
+        user_sp -= args[i].len() + 1;
+        *argv[i] = user_sp;
+        let mut p = user_sp;
+        for c in args[i].as_bytes() {
+            *translated_mut_ref(token, p as *mut u8).unwrap() = *c;
+            p += 1;
+        }
+        *translated_mut_ref(token, p as *mut u8).unwrap() = 0;
+    }
+    (user_sp, argv_base)
 }
 
+#[allow(clippy::struct_excessive_bools)]
 pub struct ProcessControlBlockInner {
+    /// Name of the program the process runs
+    pub name: String,
+    /// Process group, which terminal signals are sent to as a whole
+    pub pgid: usize,
     pub is_zombie: bool,
     pub memory_set: MemorySet,
     pub parent: Option<Weak<ProcessControlBlock>>,
+    /// Whether the process was handed to [`super::DAEMON`] when its parent exited
+    pub orphaned: bool,
+    /// Whether orphaned descendants are handed to this process rather than the daemon,
+    /// not inherited by children
+    pub child_subreaper: bool,
+    /// Whether the process gave up gaining privileges, kept by children and across `exec`
+    pub no_new_privs: bool,
     pub children: Vec<Arc<ProcessControlBlock>>,
     pub exit_code: i32,
     pub cwd: String,
-    pub fd_table: Vec<Option<Arc<dyn File + Send + Sync>>>,
+    /// Open files, shared with the processes cloned with [`CloneFlags::FILES`]
+    pub fd_table: Arc<FdTable>,
     pub signals: SignalFlags,
+    /// Signals kept pending instead of being delivered
+    pub signal_mask: SignalFlags,
     pub tasks: Vec<Option<Arc<TaskControlBlock>>>,
     pub task_res_allocator: RecycleAllocator,
     pub mutex_list: Vec<Option<Arc<dyn Mutex>>>,
     pub semaphore_list: Vec<Option<Arc<Semaphore>>>,
     pub condvar_list: Vec<Option<Arc<Condvar>>>,
+    /// The parent whose address space the process runs in after `vfork`, until it is released
+    pub vfork_parent: Option<VforkParent>,
+    /// Most pages the address space has held, sampled whenever it grows
+    pub peak_pages: usize,
+    /// The signal that terminated the process, `None` while it runs or if it exited itself
+    pub term_signal: Option<u32>,
+}
+
+/// The suspended parent of a process created by [`ProcessControlBlock::vfork`]
+pub struct VforkParent {
+    /// The thread that called `vfork`, blocked until the address space is handed back
+    task: Arc<TaskControlBlock>,
+    /// Trap context of `task`, whose page the child runs on meanwhile
+    trap_cx: Context,
 }
 
 impl ProcessControlBlockInner {
@@ -275,15 +509,36 @@ impl ProcessControlBlockInner {
         self.task_res_allocator.dealloc(tid);
     }
 
-    pub fn alloc_fd(&mut self) -> usize {
-        if let Some(idx) = self.fd_table.iter().position(core::option::Option::is_none) {
-            idx
-        } else {
-            self.fd_table.push(None);
-            self.fd_table.len() - 1
+    /// Withdraw a pending `SIGCHLD` once no exited child is left to reap
+    ///
+    /// Child exits are coalesced into the one signal, so it stays pending until the last
+    /// zombie is gone rather than being consumed by the first wait.
+    pub fn settle_sigchld(&mut self) {
+        if !self
+            .children
+            .iter()
+            .any(|child| child.inner_exclusive_access().is_zombie)
+        {
+            self.signals.remove(SignalFlags::SIGCHLD);
         }
     }
 
+    /// Status reported to `wait4` once the process is a zombie
+    ///
+    /// The low 7 bits hold the signal that killed the process, or are zero if it exited,
+    /// with the low byte of its exit code in bits 8-15.
+    pub fn wait_status(&self) -> i32 {
+        match self.term_signal {
+            Some(signal) => (signal & 0x7f) as i32,
+            None => (self.exit_code & 0xff) << 8,
+        }
+    }
+
+    /// Raise [`Self::peak_pages`] to the current size of the address space
+    pub fn note_memory_usage(&mut self) {
+        self.peak_pages = self.peak_pages.max(self.memory_set.framed_pages());
+    }
+
     pub fn thread_count(&self) -> usize {
         self.tasks.len()
     }
@@ -298,6 +553,7 @@ impl Drop for ProcessControlBlock {
         *DEV_NON_BLOCKING_ACCESS.exclusive_access() = false;
         if let Some(proc_inode) = PROC_INODE.find(&self.pid.0.to_string()) {
             proc_inode.delete("cmdline");
+            proc_inode.delete("fd");
             PROC_INODE.delete(&self.pid.0.to_string());
         }
         *DEV_NON_BLOCKING_ACCESS.exclusive_access() = true;
@@ -53,6 +53,14 @@ pub fn current_tcb() -> Option<Arc<TaskControlBlock>> {
     PROCESSOR.exclusive_access().current()
 }
 
+/// Run `f` on the current thread without cloning it or waiting on the processor
+///
+/// For the panic handler, `None` if no thread is running or the processor is being changed.
+pub fn peek_current_tcb<V>(f: impl FnOnce(&TaskControlBlock) -> V) -> Option<V> {
+    let processor = PROCESSOR.try_peek()?;
+    processor.current.as_deref().map(f)
+}
+
 /// Current PCB
 pub fn current_pcb() -> Arc<ProcessControlBlock> {
     current_tcb().unwrap().process.upgrade().unwrap()
@@ -80,6 +88,14 @@ pub fn current_trap_cx_user_va() -> usize {
         .trap_cx_user_va()
 }
 
+/// Id of the hart running this code, as stashed in `tp` at boot
+#[cfg(feature = "smp")]
+pub fn hart_id() -> usize {
+    let id;
+    unsafe { core::arch::asm!("mv {}, tp", out(reg) id) };
+    id
+}
+
 /// The main part of process execution and scheduling.
 /// Loop [`manager::fetch`] to get the process that needs to run, and switch the process through
 /// `__switch`
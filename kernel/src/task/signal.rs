@@ -1,4 +1,4 @@
-use super::current_pcb;
+use super::{current_pcb, current_tcb};
 use bitflags::bitflags;
 
 bitflags! {
@@ -9,10 +9,17 @@ bitflags! {
         const SIGABRT   = 1 << 6;
         const SIGFPE    = 1 << 8;
         const SIGSEGV   = 1 << 11;
+        const SIGCHLD   = 1 << 17;
     }
 }
 
 impl SignalFlags {
+    /// Signals whose default action is to ignore them.
+    ///
+    /// They never terminate a process and only interrupt a wait whose caller passed a mask
+    /// leaving them unblocked.
+    pub const IGNORED_BY_DEFAULT: Self = Self::SIGCHLD;
+
     pub fn check_error(self) -> Option<(i32, &'static str)> {
         if self.contains(Self::SIGINT) {
             Some((-2, "Killed, SIGINT=2"))
@@ -33,7 +40,34 @@ impl SignalFlags {
 pub fn check_signals_error_of_current() -> Option<(i32, &'static str)> {
     let process = current_pcb();
     let process_inner = process.inner_exclusive_access();
-    process_inner.signals.check_error()
+    (process_inner.signals - process_inner.signal_mask).check_error()
+}
+
+/// Like [`check_signals_error_of_current`], for the signals sent to the current thread alone
+pub fn check_thread_signals_error_of_current() -> Option<(i32, &'static str)> {
+    let mask = current_pcb().inner_exclusive_access().signal_mask;
+    (current_tcb().unwrap().inner_exclusive_access().signals - mask).check_error()
+}
+
+/// Signals pending for the current thread, both its own and those of its process
+pub fn pending_signals_of_current() -> SignalFlags {
+    let thread_signals = current_tcb().unwrap().inner_exclusive_access().signals;
+    current_pcb().inner_exclusive_access().signals | thread_signals
+}
+
+/// Whether the current thread has a pending signal that is not masked
+pub fn unmasked_signal_pending_of_current() -> bool {
+    let mask = current_pcb().inner_exclusive_access().signal_mask;
+    !(pending_signals_of_current() - mask).is_empty()
+}
+
+/// Take the signals sent to the current thread alone that `mask` leaves unblocked and
+/// that are ignored by default, they are delivered by doing nothing
+pub fn consume_ignored_thread_signals_of_current(mask: SignalFlags) {
+    let task = current_tcb().unwrap();
+    let mut task_inner = task.inner_exclusive_access();
+    let delivered = (task_inner.signals - mask) & SignalFlags::IGNORED_BY_DEFAULT;
+    task_inner.signals -= delivered;
 }
 
 pub fn add_signal_to_current(signal: SignalFlags) {
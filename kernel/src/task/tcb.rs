@@ -2,13 +2,16 @@ use super::{
     context::Context,
     id::{kstack_alloc, KernelStack, TaskUserRes},
     pcb::ProcessControlBlock,
+    SignalFlags,
 };
 use crate::{
+    config::ALL_HARTS,
     mm::PhysPageNum,
     sync::{UPIntrFreeCell, UPIntrRefMut},
     trap,
 };
 use alloc::sync::{Arc, Weak};
+use core::cell::Ref;
 
 #[allow(clippy::module_name_repetitions)]
 pub struct TaskControlBlock {
@@ -37,6 +40,8 @@ impl TaskControlBlock {
                     task_cx: Context::leave_trap(kstack_top),
                     task_status: Status::Ready,
                     exit_code: None,
+                    affinity: ALL_HARTS,
+                    signals: SignalFlags::empty(),
                 })
             },
         }
@@ -46,6 +51,11 @@ impl TaskControlBlock {
         self.inner.exclusive_access()
     }
 
+    /// Inner state unless it is being changed, see [`UPIntrFreeCell::try_peek`]
+    pub fn try_inner_peek(&self) -> Option<Ref<'_, TaskControlBlockInner>> {
+        self.inner.try_peek()
+    }
+
     pub fn user_token(&self) -> usize {
         let process = self.process.upgrade().unwrap();
         let inner = process.inner_exclusive_access();
@@ -59,6 +69,10 @@ pub struct TaskControlBlockInner {
     pub task_cx: Context,
     pub task_status: Status,
     pub exit_code: Option<i32>,
+    /// Harts this task may be scheduled on, one bit per hart
+    pub affinity: usize,
+    /// Signals sent to this thread alone, pending alongside those of its process
+    pub signals: SignalFlags,
 }
 
 impl TaskControlBlockInner {
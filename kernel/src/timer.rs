@@ -1,8 +1,10 @@
 //! RISC-V timer-related functionality
 
 use core::cmp::Ordering;
+use core::sync::atomic::{self, AtomicUsize};
 
 use crate::config::CLOCK_FREQ;
+use crate::mm::{frame_allocator, FrameTracker, PhysPageNum};
 use crate::sbi::set_timer;
 use crate::sync::UPIntrFreeCell;
 use crate::task::{manager, tcb::TaskControlBlock};
@@ -24,11 +26,24 @@ pub fn get_time_ms() -> usize {
     time::read() / (CLOCK_FREQ / MSEC_PER_SEC)
 }
 
-/// set the next timer interrupt
+/// set the next timer interrupt, publishing the current time to the tick page
 pub fn set_next_trigger() {
+    tick_page()
+        .as_mut_ref::<AtomicUsize>()
+        .store(get_time_ms(), atomic::Ordering::Release);
     set_timer(get_time() + CLOCK_FREQ / TICKS_PER_SEC);
 }
 
+lazy_static! {
+    /// Frame behind [`crate::config::TICK_PAGE`], shared by every user space
+    static ref TICK_FRAME: FrameTracker = frame_allocator::alloc().unwrap();
+}
+
+/// The frame holding the time in milliseconds as of the last timer interrupt
+pub fn tick_page() -> PhysPageNum {
+    TICK_FRAME.ppn
+}
+
 #[allow(clippy::module_name_repetitions)]
 pub struct TimeCondVar {
     pub expire_ms: usize,
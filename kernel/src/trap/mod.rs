@@ -6,15 +6,21 @@ pub use context::Context;
 
 use crate::{
     config::TRAMPOLINE,
+    fs::{inode, klog},
     syscall::syscall,
     task::{
-        add_signal_to_current, check_signals_error_of_current, current_trap_cx,
-        current_trap_cx_user_va, current_user_token, exit_current_and_run_next,
-        suspend_current_and_run_next, SignalFlags,
+        add_signal_to_current, check_signals_error_of_current,
+        check_thread_signals_error_of_current, consume_ignored_thread_signals_of_current,
+        current_pcb, current_trap_cx, current_trap_cx_user_va, current_user_token,
+        kill_current_and_run_next, kill_current_thread_and_run_next, suspend_current_and_run_next,
+        SignalFlags,
     },
     timer,
 };
-use core::arch::{asm, global_asm};
+use core::{
+    arch::{asm, global_asm},
+    sync::atomic::{AtomicPtr, Ordering},
+};
 use log::debug;
 use riscv::register::{
     mtvec::TrapMode,
@@ -40,7 +46,10 @@ pub extern "C" fn user_handler() -> ! {
             enable_supervisor_interrupt();
 
             // get system call return value
-            let result = syscall(cx.x[17], [cx.x[10], cx.x[11], cx.x[12]]);
+            let result = syscall(
+                cx.x[17],
+                [cx.x[10], cx.x[11], cx.x[12], cx.x[13], cx.x[14], cx.x[15]],
+            );
             // cx is changed during sys_exec, so we have to call it again
             cx = current_trap_cx();
             cx.x[10] = result as usize;
@@ -53,10 +62,16 @@ pub extern "C" fn user_handler() -> ! {
             | Exception::LoadFault
             | Exception::LoadPageFault,
         ) => {
+            let mapped = current_pcb()
+                .inner_exclusive_access()
+                .memory_set
+                .find_area_mut(stval.into())
+                .is_some();
             debug!(
-                "[kernel] {:?} in application, bad addr = {:#x}, bad instruction = {:#x}, kernel killed it.",
+                "[kernel] {:?} in application, bad addr = {:#x} ({}), bad instruction = {:#x}, kernel killed it.",
                 scause.cause(),
                 stval,
+                if mapped { "mapped" } else { "unmapped" },
                 cx.sepc,
             );
             add_signal_to_current(SignalFlags::SIGSEGV);
@@ -82,10 +97,22 @@ pub extern "C" fn user_handler() -> ! {
         }
     }
 
+    // this task holds no file system lock here, so buffered log lines and flushes can be
+    // written out, unless a task blocked on the device holds the locks they need
+    klog::sync();
+    inode::flush_deferred();
+
     // check signals
     if let Some((errno, msg)) = check_signals_error_of_current() {
         debug!("[kernel] {}", msg);
-        exit_current_and_run_next(errno);
+        kill_current_and_run_next(errno.unsigned_abs());
+    } else if let Some((errno, msg)) = check_thread_signals_error_of_current() {
+        // sent to this thread alone, so it ends only this thread
+        debug!("[kernel] {}", msg);
+        kill_current_thread_and_run_next(errno.unsigned_abs());
+    } else {
+        let mask = current_pcb().inner_exclusive_access().signal_mask;
+        consume_ignored_thread_signals_of_current(mask);
     }
 
     leave()
@@ -119,8 +146,36 @@ pub extern "C" fn leave() -> ! {
     }
 }
 
+/// Registers `__alltraps_k` saves on the kernel stack, all but `sp` and `tp`
+#[repr(C)]
+pub struct KernelFrame {
+    /// general regs[0..31], `x[2]` and `x[4]` not saved
+    pub x: [usize; 32],
+    /// CSR sstatus
+    pub sstatus: usize,
+    /// CSR sepc
+    pub sepc: usize,
+}
+
+impl KernelFrame {
+    /// Stack pointer of the interrupted code, right above the frame
+    pub fn sp(&self) -> usize {
+        core::ptr::from_ref(self) as usize + core::mem::size_of::<Self>()
+    }
+}
+
+/// Frame of the kernel trap being handled, null outside of one
+static KERNEL_FRAME: AtomicPtr<KernelFrame> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Registers of the kernel code interrupted by the trap being handled, if any
+pub fn kernel_trap_frame() -> Option<&'static KernelFrame> {
+    unsafe { KERNEL_FRAME.load(Ordering::Acquire).as_ref() }
+}
+
 #[no_mangle]
-pub extern "C" fn kernel_handler() {
+pub extern "C" fn kernel_handler(frame: *mut KernelFrame) {
+    // kernel traps do not nest, interrupts stay masked until `__restore_k`
+    KERNEL_FRAME.store(frame, Ordering::Release);
     let scause = scause::read();
     let stval = stval::read();
     match scause.cause() {
@@ -139,6 +194,7 @@ pub extern "C" fn kernel_handler() {
             );
         }
     }
+    KERNEL_FRAME.store(core::ptr::null_mut(), Ordering::Release);
 }
 
 /// initialize CSR `stvec` as the entry of `__alltraps`
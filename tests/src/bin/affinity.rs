@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{
+    process::{fork, waitpid},
+    thread::{sched_getaffinity, sched_setaffinity},
+};
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    // every task starts on all harts, which includes hart 0
+    let initial = sched_getaffinity();
+    assert_ne!(initial & 1, 0);
+
+    // an empty mask, or one naming only missing harts, is rejected
+    assert_eq!(sched_setaffinity(0), -1);
+    assert_eq!(sched_setaffinity(1 << (usize::BITS - 1)), -1);
+    assert_eq!(sched_getaffinity(), initial);
+
+    assert_eq!(sched_setaffinity(usize::MAX), 0);
+    assert_eq!(sched_getaffinity(), initial);
+    assert_eq!(sched_setaffinity(1), 0);
+    assert_eq!(sched_getaffinity(), 1);
+
+    // the child inherits the mask
+    let pid = fork();
+    if pid == 0 {
+        return i32::from(sched_getaffinity() != 1);
+    }
+    let mut exit_code = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    0
+}
@@ -0,0 +1,76 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::{vec, vec::Vec};
+use user_lib::{
+    fs::{close, fstat, open, read, unlink, write, OpenFlags, Stat},
+    process::exit,
+    thread::{thread_create, waittid},
+};
+
+static TEST_FILE: &str = "/append_race_test";
+const THREAD_COUNT: usize = 4;
+const RECORDS: usize = 24;
+
+/// Every other record is small enough to be buffered, the rest go straight to the disk
+fn record_len(round: usize) -> usize {
+    if round % 2 == 0 {
+        100 + round
+    } else {
+        600 + round * 7
+    }
+}
+
+pub fn appender(id: usize) -> ! {
+    let fd = open(TEST_FILE, OpenFlags::WRONLY | OpenFlags::APPEND);
+    assert!(fd >= 0);
+    let fill = b'a' + id as u8;
+    for round in 0..RECORDS {
+        let record = vec![fill; record_len(round)];
+        assert_eq!(write(fd as usize, &record), record.len() as isize);
+    }
+    close(fd as usize);
+    exit(0)
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let fd = open(TEST_FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd >= 0);
+    close(fd as usize);
+
+    let tids: Vec<_> = (0..THREAD_COUNT)
+        .map(|id| thread_create(appender as usize, id))
+        .collect();
+    for tid in tids {
+        assert_eq!(waittid(tid as usize), 0);
+    }
+
+    // no append landed on another, so the file holds every byte written
+    let per_thread: usize = (0..RECORDS).map(record_len).sum();
+    let fd = open(TEST_FILE, OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    let mut stat = Stat::new();
+    assert_eq!(fstat(fd, &mut stat), 0);
+    assert_eq!(stat.size as usize, per_thread * THREAD_COUNT);
+    let mut counts = [0usize; THREAD_COUNT];
+    let mut buf = [0u8; 512];
+    loop {
+        let len = read(fd, &mut buf);
+        assert!(len >= 0);
+        if len == 0 {
+            break;
+        }
+        for &byte in &buf[..len as usize] {
+            counts[(byte - b'a') as usize] += 1;
+        }
+    }
+    close(fd);
+    assert!(counts.iter().all(|&count| count == per_thread));
+    assert_eq!(unlink(TEST_FILE, 0), 0);
+    0
+}
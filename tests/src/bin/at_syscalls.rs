@@ -0,0 +1,113 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::string::String;
+use user_lib::fs::{
+    chdir, close, getcwd, mkdir, mkdirat, open, read, readlinkat, rename, renameat, unlink,
+    unlinkat, write, OpenFlags, AT_FDCWD, AT_REMOVEDIR,
+};
+
+static DIR_A: &str = "/at_test_a";
+static DIR_B: &str = "/at_test_b";
+static CONTENT: &[u8] = b"moved along";
+
+fn exists(path: &str) -> bool {
+    let fd = open(path, OpenFlags::RDONLY);
+    if fd >= 0 {
+        close(fd as usize);
+    }
+    fd >= 0
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let mut cwd = String::new();
+    getcwd(&mut cwd);
+
+    assert_eq!(mkdir(DIR_A), 0);
+    assert_eq!(mkdir(DIR_B), 0);
+    let dir_a = open(DIR_A, OpenFlags::RDONLY);
+    assert!(dir_a >= 0);
+
+    // relative paths resolve against the directory, absolute ones ignore it
+    assert_eq!(mkdirat(dir_a, "sub"), 0);
+    assert_eq!(mkdirat(dir_a, "sub"), -2);
+    assert_eq!(mkdirat(dir_a, "sub/inner"), 0);
+    assert_eq!(mkdirat(42, "/at_test_a/abs"), 0);
+    assert!(exists("/at_test_a/sub/inner") && exists("/at_test_a/abs"));
+    assert_eq!(mkdirat(42, "nowhere"), -1);
+    assert_eq!(mkdirat(dir_a, "missing/nowhere"), -1);
+
+    let fd = open("/at_test_a/sub/file", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd >= 0);
+    assert_eq!(write(fd as usize, CONTENT), CONTENT.len() as isize);
+    close(fd as usize);
+    // a regular file is not a directory to resolve against
+    let file = open("/at_test_a/sub/file", OpenFlags::RDONLY);
+    assert_eq!(mkdirat(file, "nowhere"), -1);
+    close(file as usize);
+
+    // unlinkat keeps the semantics of unlink
+    assert_eq!(unlinkat(dir_a, "sub", 0), -2);
+    assert_eq!(unlinkat(dir_a, "sub", AT_REMOVEDIR), -3);
+    assert_eq!(unlinkat(dir_a, "sub/file", AT_REMOVEDIR), -2);
+    assert_eq!(unlinkat(dir_a, "abs", AT_REMOVEDIR), 0);
+    assert!(!exists("/at_test_a/abs"));
+
+    // renaming a file keeps its contents
+    assert_eq!(renameat(dir_a, "sub/file", dir_a, "moved"), 0);
+    assert!(!exists("/at_test_a/sub/file"));
+    let fd = open("/at_test_a/moved", OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let mut buffer = [0u8; 32];
+    assert_eq!(read(fd as usize, &mut buffer), CONTENT.len() as isize);
+    assert_eq!(&buffer[..CONTENT.len()], CONTENT);
+    close(fd as usize);
+    // there are no symbolic links, so nothing is refused for being one
+    assert_eq!(readlinkat(dir_a, "moved", &mut buffer), -2);
+    assert_eq!(readlinkat(dir_a, "missing", &mut buffer), -1);
+    let fd = open("/at_test_a/moved", OpenFlags::RDONLY | OpenFlags::NOFOLLOW);
+    assert!(fd >= 0);
+    close(fd as usize);
+    assert_eq!(rename("/at_test_a/missing", "/at_test_a/other"), -1);
+    assert_eq!(rename("/at_test_a/moved", "/at_test_a/sub"), -2);
+
+    // a directory cannot move into itself or below itself
+    assert_eq!(renameat(dir_a, "sub", dir_a, "sub/inner/sub"), -3);
+    assert_eq!(renameat(dir_a, "sub", dir_a, "sub/sub"), -3);
+
+    // moving a directory to another parent updates its `..`
+    let dir_b = open(DIR_B, OpenFlags::RDONLY);
+    assert_eq!(renameat(dir_a, "sub", dir_b, "sub"), 0);
+    assert!(!exists("/at_test_a/sub") && exists("/at_test_b/sub/inner"));
+    let sub = open("/at_test_b/sub", OpenFlags::RDONLY);
+    assert_eq!(mkdirat(sub, "../made"), 0);
+    assert!(exists("/at_test_b/made"));
+    close(sub as usize);
+
+    // `AT_FDCWD` resolves against the working directory
+    assert_eq!(chdir(DIR_A), 0);
+    assert_eq!(unlinkat(AT_FDCWD, "moved", 0), 0);
+    assert_eq!(chdir(&cwd), 0);
+    assert!(!exists("/at_test_a/moved"));
+
+    // a directory emptied again can be removed, one still holding an entry cannot
+    assert_eq!(mkdirat(dir_b, "made/a"), 0);
+    assert_eq!(mkdirat(dir_b, "made/b"), 0);
+    assert_eq!(unlinkat(dir_b, "made/a", AT_REMOVEDIR), 0);
+    assert_eq!(unlinkat(dir_b, "made", AT_REMOVEDIR), -3);
+    assert_eq!(unlinkat(dir_b, "made/b", AT_REMOVEDIR), 0);
+    assert_eq!(unlinkat(dir_b, "made", AT_REMOVEDIR), 0);
+
+    close(dir_a as usize);
+    close(dir_b as usize);
+    unlink("/at_test_b/sub/inner", AT_REMOVEDIR);
+    unlink("/at_test_b/sub", AT_REMOVEDIR);
+    unlink(DIR_B, AT_REMOVEDIR);
+    unlink(DIR_A, AT_REMOVEDIR);
+
+    0
+}
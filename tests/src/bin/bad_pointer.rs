@@ -0,0 +1,84 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{
+    fs::{close, open, unlink, OpenFlags},
+    process::{exit, fork, waitpid, yield_},
+    syscall::{sys_fstat, sys_open, sys_pipe, sys_read, sys_waitpid, sys_write},
+};
+
+const EFAULT: isize = -14;
+
+static TEST_FILE: &str = "bad_pointer_test";
+
+/// Never mapped, user programs are loaded well above it
+const UNMAPPED: usize = 0x10;
+/// Truncated by the 39-bit virtual address space
+const NON_CANONICAL: usize = 0xdead_beef_0000_0000;
+/// The trampoline, mapped but not accessible from user mode
+const TRAMPOLINE: usize = usize::MAX - 4096 + 1;
+
+/// Nonzero so it lands in `.rodata` rather than `.bss`
+static READ_ONLY: [u8; 64] = [0x5a; 64];
+
+fn bytes(addr: usize, len: usize) -> &'static mut [u8] {
+    unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, len) }
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let fd = open(TEST_FILE, OpenFlags::CREATE | OpenFlags::RDWR);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    let mut valid = [0u8; 16];
+
+    for addr in [UNMAPPED, NON_CANONICAL, TRAMPOLINE] {
+        assert_eq!(sys_write(fd, bytes(addr, 16)), EFAULT);
+        assert_eq!(sys_read(fd, bytes(addr, 16)), EFAULT);
+        assert_eq!(sys_fstat(fd, addr as *mut u8), EFAULT);
+        let path = unsafe { core::str::from_utf8_unchecked(bytes(addr, 1)) };
+        assert_eq!(sys_open(path, OpenFlags::RDONLY.bits()), EFAULT);
+        let fds = unsafe { core::slice::from_raw_parts_mut(addr as *mut usize, 2) };
+        assert_eq!(sys_pipe(fds), EFAULT);
+    }
+
+    // a buffer that starts out valid but runs off the end of the address space
+    assert_eq!(
+        sys_write(fd, bytes(valid.as_ptr() as usize, usize::MAX)),
+        EFAULT
+    );
+    // a misaligned stat
+    assert_eq!(sys_fstat(fd, valid.as_mut_ptr().wrapping_add(1)), EFAULT);
+    // read-only memory cannot receive data
+    assert_eq!(sys_fstat(fd, READ_ONLY.as_ptr().cast_mut()), EFAULT);
+
+    // the file descriptor is still usable afterwards
+    assert_eq!(sys_write(fd, &valid), 16);
+    close(fd);
+    let fd = open(TEST_FILE, OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    assert_eq!(sys_read(fd as usize, &mut valid), 16);
+    close(fd as usize);
+    assert_eq!(unlink(TEST_FILE, 0), 0);
+
+    // a bad exit code pointer leaves the zombie to be reaped again
+    let pid = fork();
+    if pid == 0 {
+        exit(7);
+    }
+    loop {
+        match sys_waitpid(pid, UNMAPPED as *mut i32) {
+            EFAULT => break,
+            -2 => {
+                yield_();
+            }
+            r => panic!("unexpected waitpid result {r}"),
+        }
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 7);
+    0
+}
@@ -0,0 +1,65 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::{format, string::String};
+use core::fmt::Write;
+use user_lib::fs::{close, open, unlink, BufReader, BufWriter, OpenFlags, BUF_SIZE};
+
+static TEST_FILE: &str = "buffered_io_test";
+const LINES: usize = 200;
+
+fn line(i: usize) -> String {
+    // every tenth line is longer than the buffer
+    let len = if i.is_multiple_of(10) {
+        BUF_SIZE + i
+    } else {
+        i % 37
+    };
+    format!("{i}:{}\n", "x".repeat(len))
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    // the writer is flushed when dropped
+    let fd = open(
+        TEST_FILE,
+        OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC,
+    );
+    assert!(fd >= 0);
+    {
+        let mut writer = BufWriter::new(fd as usize);
+        for i in 0..LINES {
+            write!(writer, "{}", line(i)).unwrap();
+        }
+        assert_eq!(writer.write(b"no newline"), 10);
+    }
+    close(fd as usize);
+
+    let fd = open(TEST_FILE, OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let mut reader = BufReader::new(fd as usize);
+    for i in 0..LINES {
+        let mut s = String::new();
+        let expected = line(i);
+        assert_eq!(reader.read_line(&mut s), expected.len() as isize);
+        assert_eq!(s, expected);
+    }
+    // the last line ends with the file, then end of file is reported again and again
+    let mut s = String::new();
+    assert_eq!(reader.read_line(&mut s), 10);
+    assert_eq!(s, "no newline");
+    assert_eq!(reader.read_line(&mut s), 0);
+    assert_eq!(reader.read_byte(), None);
+    assert_eq!(reader.read(&mut [0u8; 4]), 0);
+    close(fd as usize);
+
+    // a closed descriptor reports the error instead of end of file
+    let mut reader = BufReader::new(fd as usize);
+    assert!(reader.read_line(&mut s) < 0);
+
+    assert_eq!(unlink(TEST_FILE, 0), 0);
+    0
+}
@@ -0,0 +1,88 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{
+    fs::{close, fstat, open, unlink, OpenFlags, Stat},
+    process::{clone, clone_thread, exit, getpid, waitpid, CloneFlags},
+    syscall::sys_clone,
+    thread::{gettid, waittid},
+};
+
+static TEST_FILE: &str = "clone_test";
+
+/// Written by the thread, seen by the main thread through the shared address space
+static SHARED: AtomicUsize = AtomicUsize::new(0);
+static THREAD_PID: AtomicUsize = AtomicUsize::new(0);
+
+/// Stack for a thread, which grows down from its end
+static mut STACK: [u8; 4096] = [0; 4096];
+
+extern "C" fn thread_main(arg: usize) -> ! {
+    SHARED.store(arg, Ordering::SeqCst);
+    THREAD_PID.store(getpid() as usize, Ordering::SeqCst);
+    exit(gettid() as i32)
+}
+
+fn is_open(fd: usize) -> bool {
+    fstat(fd, &mut Stat::default()) == 0
+}
+
+/// Opens the test file in a child cloned with `flags`, returning the descriptor it got
+fn open_in_child(flags: CloneFlags) -> usize {
+    let pid = clone(flags);
+    assert!(pid >= 0);
+    if pid == 0 {
+        let fd = open(TEST_FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+        exit(fd as i32);
+    }
+    let mut exit_code = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert!(exit_code >= 0);
+    exit_code as usize
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    // a copied table leaves the parent's descriptors alone
+    let fd = open_in_child(CloneFlags::empty());
+    assert!(!is_open(fd));
+
+    // a shared one outlives the child, and closing in the child closes for the parent
+    let fd = open_in_child(CloneFlags::FILES);
+    assert!(is_open(fd));
+    let pid = clone(CloneFlags::FILES);
+    if pid == 0 {
+        exit(close(fd) as i32);
+    }
+    let mut exit_code = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert!(!is_open(fd));
+    assert_eq!(unlink(TEST_FILE, 0), 0);
+
+    // a thread shares the address space, on the stack it is given or one of its own
+    let stack_top = core::ptr::addr_of_mut!(STACK) as usize + 4096;
+    for (stack, arg) in [(stack_top, 42), (0, 7)] {
+        let tid = clone_thread(
+            stack,
+            thread_main as extern "C" fn(usize) -> ! as usize,
+            arg,
+        );
+        assert!(tid > 0);
+        assert_eq!(waittid(tid as usize), tid);
+        assert_eq!(SHARED.load(Ordering::SeqCst), arg);
+        assert_eq!(THREAD_PID.load(Ordering::SeqCst), getpid() as usize);
+    }
+
+    // a thread always shares files and signal state, and those need the address space
+    let vm = CloneFlags::VM.bits();
+    assert_eq!(sys_clone(vm, 0), -1);
+    assert_eq!(sys_clone(vm | CloneFlags::FILES.bits(), 0), -1);
+    assert_eq!(clone(CloneFlags::SIGHAND), -1);
+    assert_eq!(clone(CloneFlags::VM), -1);
+    assert_eq!(sys_clone(1, 0), -1);
+    0
+}
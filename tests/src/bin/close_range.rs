@@ -0,0 +1,39 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::fs::{close, close_range, dup, fcntl, CLOSE_RANGE_CLOEXEC, FD_CLOEXEC, F_GETFD};
+
+fn is_open(fd: usize) -> bool {
+    fcntl(fd, F_GETFD, 0) >= 0
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let fds: [usize; 6] = core::array::from_fn(|_| dup(0) as usize);
+    for window in fds.windows(2) {
+        assert_eq!(window[1], window[0] + 1);
+    }
+
+    // closed descriptors in the range are skipped
+    assert_eq!(close(fds[2]), 0);
+    assert_eq!(close_range(fds[1], fds[3], 0), 0);
+    assert!(is_open(fds[0]) && !is_open(fds[1]) && !is_open(fds[3]) && is_open(fds[4]));
+
+    // marking close-on-exec leaves the descriptors open
+    assert_eq!(close_range(fds[4], fds[5], CLOSE_RANGE_CLOEXEC), 0);
+    assert_eq!(fcntl(fds[4], F_GETFD, 0), FD_CLOEXEC as isize);
+    assert_eq!(fcntl(fds[5], F_GETFD, 0), FD_CLOEXEC as isize);
+    assert_eq!(fcntl(fds[0], F_GETFD, 0), 0);
+
+    // `u32::MAX` reaches to the end of the table, however far past it
+    assert_eq!(close_range(fds[0], u32::MAX as usize, 0), 0);
+    assert!(fds.iter().all(|&fd| !is_open(fd)));
+    assert_eq!(close_range(fds[0] + 100, fds[0] + 200, 0), 0);
+    assert!(is_open(0) && is_open(1));
+
+    assert_eq!(close_range(5, 4, 0), -1);
+    assert_eq!(close_range(fds[0], fds[0], 1), -1);
+    0
+}
@@ -0,0 +1,116 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::format;
+use user_lib::{
+    fs::{close, dup, open, read, unlink, write, OpenFlags},
+    process::{Command, Stdio},
+};
+
+static TEST_FILE: &str = "/command_test";
+
+/// Copies standard input to standard output in upper case
+fn upper() -> i32 {
+    let mut buf = [0u8; 64];
+    loop {
+        let len = read(0, &mut buf);
+        if len <= 0 {
+            return 0;
+        }
+        let data = &mut buf[..len as usize];
+        data.make_ascii_uppercase();
+        write(1, data);
+    }
+}
+
+/// The lowest free descriptor, which moves if one is leaked
+fn next_fd() -> isize {
+    let fd = dup(0);
+    close(fd as usize);
+    fd
+}
+
+#[no_mangle]
+pub extern "Rust" fn main(_argc: usize, argv: &[&str]) -> i32 {
+    match argv.get(1) {
+        Some(&"upper") => return upper(),
+        Some(&"exit") => return argv[2].parse().unwrap(),
+        // descriptors of the caller other than the standard streams are not passed on
+        Some(&"closed") => return i32::from(write(argv[2].parse().unwrap(), b"x") != -1),
+        _ => {}
+    }
+    let free = next_fd();
+
+    // the exit code is decoded from the wait status
+    let status = Command::new("/tests/command")
+        .args(&["exit", "3"])
+        .status()
+        .unwrap();
+    assert_eq!(status.code(), Some(3));
+    assert!(!status.success() && status.signal().is_none());
+
+    // both streams through pipes, the input ends when the child is waited for
+    let mut child = Command::new("/tests/command")
+        .arg("upper")
+        .stdin(Stdio::Piped)
+        .stdout(Stdio::Piped)
+        .spawn()
+        .unwrap();
+    let stdout = child.stdout.unwrap();
+    assert_eq!(write(child.stdin.unwrap(), b"hello"), 5);
+    assert!(child.wait().success());
+    assert!(child.stdin.is_none());
+    let mut buf = [0u8; 64];
+    assert_eq!(read(stdout, &mut buf), 5);
+    assert_eq!(&buf[..5], b"HELLO");
+    // only the child held the other end
+    assert_eq!(read(stdout, &mut buf), 0);
+    close(stdout);
+
+    // a descriptor of the caller, which stays open
+    let file = open(TEST_FILE, OpenFlags::CREATE | OpenFlags::RDWR);
+    assert!(file >= 0);
+    let file = file as usize;
+    let mut child = Command::new("/tests/command")
+        .arg("upper")
+        .stdin(Stdio::Piped)
+        .stdout(Stdio::Fd(file))
+        .spawn()
+        .unwrap();
+    assert!(child.stdout.is_none());
+    assert_eq!(write(child.stdin.unwrap(), b"to file"), 7);
+    assert!(child.wait().success());
+    let fd = open(TEST_FILE, OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    assert_eq!(read(fd as usize, &mut buf), 7);
+    assert_eq!(&buf[..7], b"TO FILE");
+    close(fd as usize);
+    close(file);
+    assert_eq!(unlink(TEST_FILE, 0), 0);
+
+    // only the standard streams are inherited
+    let kept = dup(0);
+    assert!(kept > 2);
+    let kept_fd = format!("{kept}");
+    let status = Command::new("/tests/command")
+        .args(&["closed", kept_fd.as_str()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    close(kept as usize);
+
+    // a program that cannot be started is reported, with or without redirections
+    assert_eq!(Command::new("/tests/missing").status(), Err(-1));
+    let missing = Command::new("/tests/missing")
+        .stdout(Stdio::Piped)
+        .spawn()
+        .map(|child| child.id());
+    assert_eq!(missing, Err(-1));
+
+    // no pipe end was left behind in the caller
+    assert_eq!(next_fd(), free);
+    0
+}
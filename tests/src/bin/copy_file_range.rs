@@ -0,0 +1,108 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::{vec, vec::Vec};
+use user_lib::fs::{
+    close, copy_file_range, fstat, open, pipe, read, unlink, write, OpenFlags, Stat,
+};
+
+static SRC_FILE: &str = "copy_file_range_src";
+static DST_FILE: &str = "copy_file_range_dst";
+/// Larger than the kernel's copy chunk, so the copy takes several rounds
+const SRC_SIZE: usize = 10000;
+
+fn offset_of(fd: usize) -> usize {
+    let mut stat = Stat::new();
+    assert_eq!(fstat(fd, &mut stat), 0);
+    stat.off
+}
+
+fn read_file(path: &str) -> Vec<u8> {
+    let fd = open(path, OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let mut data = vec![0u8; 2 * SRC_SIZE];
+    let len = read(fd as usize, &mut data);
+    assert!(len >= 0);
+    close(fd as usize);
+    data.truncate(len as usize);
+    data
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let content: Vec<u8> = (0..SRC_SIZE).map(|i| (i % 251) as u8).collect();
+    let src = open(SRC_FILE, OpenFlags::CREATE | OpenFlags::RDWR);
+    assert!(src >= 0);
+    let src = src as usize;
+    assert_eq!(write(src, &content), SRC_SIZE as isize);
+    let dst = open(DST_FILE, OpenFlags::CREATE | OpenFlags::RDWR);
+    assert!(dst >= 0);
+    let dst = dst as usize;
+
+    // explicit offsets are advanced, the offsets of both files left alone
+    let (mut off_in, mut off_out) = (100, 0);
+    assert_eq!(
+        copy_file_range(src, Some(&mut off_in), dst, Some(&mut off_out), 6000),
+        6000
+    );
+    assert_eq!((off_in, off_out), (6100, 6000));
+    assert_eq!((offset_of(src), offset_of(dst)), (SRC_SIZE, 0));
+    // without them the offsets of the files are used and advanced, the copy stopping
+    // at the end of the input
+    let mut off_in = 7000;
+    assert_eq!(
+        copy_file_range(src, Some(&mut off_in), dst, None, 5000),
+        3000
+    );
+    assert_eq!((off_in, offset_of(dst)), (SRC_SIZE, 3000));
+    assert_eq!(copy_file_range(src, None, dst, None, 100), 0);
+    let data = read_file(DST_FILE);
+    assert_eq!(data.len(), 6000);
+    assert_eq!(data[..3000], content[7000..]);
+    assert_eq!(data[3000..], content[3100..6100]);
+
+    // the copy is independent of the original once made
+    let mut off_out = 0;
+    assert_eq!(
+        copy_file_range(src, Some(&mut 0), dst, Some(&mut off_out), SRC_SIZE),
+        SRC_SIZE as isize
+    );
+    assert_eq!(write(dst, &[0xff; 100]), 100);
+    assert_eq!(read_file(SRC_FILE), content);
+    let data = read_file(DST_FILE);
+    assert!(data[3000..3100].iter().all(|&byte| byte == 0xff));
+    assert_eq!(data[3100..], content[3100..]);
+
+    // within one file the ranges must not overlap
+    assert_eq!(
+        copy_file_range(src, Some(&mut 0), src, Some(&mut 500), 1000),
+        -22
+    );
+    let mut off_out = SRC_SIZE;
+    assert_eq!(
+        copy_file_range(src, Some(&mut 0), src, Some(&mut off_out), 1000),
+        1000
+    );
+    assert_eq!(read_file(SRC_FILE)[SRC_SIZE..], content[..1000]);
+
+    // only regular files, written at an offset rather than appended to
+    let appending = open(DST_FILE, OpenFlags::WRONLY | OpenFlags::APPEND) as usize;
+    assert_eq!(copy_file_range(src, Some(&mut 0), appending, None, 10), -1);
+    close(appending);
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(copy_file_range(src, Some(&mut 0), pipe_fd[1], None, 10), -1);
+    assert_eq!(copy_file_range(pipe_fd[0], None, dst, None, 10), -1);
+    assert_eq!(copy_file_range(42, None, dst, None, 10), -1);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+
+    close(src);
+    close(dst);
+    unlink(SRC_FILE, 0);
+    unlink(DST_FILE, 0);
+    0
+}
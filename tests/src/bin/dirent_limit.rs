@@ -0,0 +1,47 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::format;
+use user_lib::fs::{close, mkdir, open, rename, unlink, OpenFlags, AT_REMOVEDIR};
+
+static TEST_DIR: &str = "/dirent_limit";
+/// Entries a directory holds at most, `.` and `..` included
+const DIRENT_LIMIT: usize = 1024;
+
+fn create(path: &str) -> isize {
+    let fd = open(path, OpenFlags::CREATE | OpenFlags::WRONLY);
+    if fd >= 0 {
+        close(fd as usize);
+        return 0;
+    }
+    fd
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    assert_eq!(mkdir(TEST_DIR), 0);
+    for i in 2..DIRENT_LIMIT {
+        assert_eq!(create(&format!("{TEST_DIR}/{i}")), 0);
+    }
+
+    // a full directory has its own error, and takes no entry whichever way it comes
+    assert_eq!(create("/dirent_limit/more"), -31);
+    assert_eq!(mkdir("/dirent_limit/more"), -31);
+    assert_eq!(create("/dirent_limit_outside"), 0);
+    assert_eq!(rename("/dirent_limit_outside", "/dirent_limit/outside"), -1);
+    assert_eq!(unlink("/dirent_limit_outside", 0), 0);
+    // an existing file is still opened, and a freed slot can be taken again
+    assert_eq!(create("/dirent_limit/2"), 0);
+    assert_eq!(unlink("/dirent_limit/2", 0), 0);
+    assert_eq!(mkdir("/dirent_limit/more"), 0);
+
+    assert_eq!(unlink("/dirent_limit/more", AT_REMOVEDIR), 0);
+    for i in 3..DIRENT_LIMIT {
+        assert_eq!(unlink(&format!("{TEST_DIR}/{i}"), 0), 0);
+    }
+    assert_eq!(unlink(TEST_DIR, AT_REMOVEDIR), 0);
+    0
+}
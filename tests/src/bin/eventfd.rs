@@ -0,0 +1,57 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{
+    fs::{close, eventfd, eventfd_read, eventfd_write, read},
+    process::exit,
+    thread::{thread_create, waittid},
+};
+
+const THREAD_NUM: usize = 3;
+
+/// Waits on the event in `arg`, then reports the value it took on the next fd.
+pub fn waiter(arg: usize) -> ! {
+    let value = eventfd_read(arg).unwrap();
+    assert_eq!(eventfd_write(arg + 1, value), 8);
+    exit(value as i32)
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    // the initial value is read back and the counter is reset
+    let fd = eventfd(5);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    assert_eq!(eventfd_write(fd, 2), 8);
+    assert_eq!(eventfd_read(fd), Some(7));
+
+    // only whole 8-byte counters are transferred
+    let mut short_buf = [0u8; 4];
+    assert_eq!(read(fd, &mut short_buf), 0);
+    close(fd);
+
+    // several threads blocked on one event, each write wakes exactly one of them
+    let event = eventfd(0) as usize;
+    let ack = eventfd(0) as usize;
+    assert_eq!(ack, event + 1);
+
+    let tids = [
+        thread_create(waiter as usize, event),
+        thread_create(waiter as usize, event),
+        thread_create(waiter as usize, event),
+    ];
+    for _ in 0..THREAD_NUM {
+        assert_eq!(eventfd_write(event, 1), 8);
+        assert_eq!(eventfd_read(ack), Some(1));
+    }
+    for tid in tids {
+        assert_eq!(waittid(tid as usize), 1);
+    }
+
+    close(event);
+    close(ack);
+
+    0
+}
@@ -0,0 +1,90 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::{vec, vec::Vec};
+use user_lib::{
+    fs::{close, open, read, unlink, write, OpenFlags},
+    process::exec,
+};
+
+static TEST_FILE: &str = "exec_invalid_test";
+
+fn read_file(path: &str) -> Vec<u8> {
+    let fd = open(path, OpenFlags::RDONLY);
+    assert!(fd >= 0, "Open executable failed!");
+    let fd = fd as usize;
+
+    let mut data = Vec::new();
+    let mut buf = vec![0u8; 512];
+    loop {
+        let len = read(fd, &mut buf);
+        assert!(len >= 0);
+        if len == 0 {
+            break;
+        }
+        data.extend_from_slice(&buf[..len as usize]);
+    }
+    close(fd);
+
+    data
+}
+
+fn write_file(path: &str, data: &[u8]) {
+    let fd = open(
+        path,
+        OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC,
+    );
+    assert!(fd >= 0, "Open test file failed!");
+    let fd = fd as usize;
+    assert_eq!(write(fd, data), data.len() as isize);
+    close(fd);
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    // if any of these images were loaded, the test would exit with the panic code
+    let elf = read_file("/tests/panic");
+
+    // truncated copies of a real executable
+    for len in [0, 4, 16, 63, 64, 100, 200, 1024] {
+        write_file(TEST_FILE, &elf[..len]);
+        assert_eq!(exec(TEST_FILE, &[TEST_FILE]), -1);
+    }
+
+    // a real executable with garbage behind the magic
+    let mut garbage = elf.clone();
+    let mut seed: u32 = 0x1234_5678;
+    for byte in &mut garbage[4..] {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        *byte = (seed >> 16) as u8;
+    }
+    write_file(TEST_FILE, &garbage);
+    assert_eq!(exec(TEST_FILE, &[TEST_FILE]), -1);
+
+    // a load segment with more data in the file than memory to hold it
+    let mut oversized = elf.clone();
+    let ph_offset = u64::from_le_bytes(elf[32..40].try_into().unwrap()) as usize;
+    let ph_entry_size = usize::from(u16::from_le_bytes([elf[54], elf[55]]));
+    let ph_count = usize::from(u16::from_le_bytes([elf[56], elf[57]]));
+    let load = (0..ph_count)
+        .map(|i| ph_offset + i * ph_entry_size)
+        .filter(|&ph| elf[ph..ph + 4] == 1u32.to_le_bytes())
+        .max_by_key(|&ph| u64::from_le_bytes(elf[ph + 32..ph + 40].try_into().unwrap()))
+        .unwrap();
+    let file_size = u64::from_le_bytes(elf[load + 32..load + 40].try_into().unwrap());
+    assert!(file_size > 1);
+    oversized[load + 40..load + 48].copy_from_slice(&(file_size / 2).to_le_bytes());
+    write_file(TEST_FILE, &oversized);
+    assert_eq!(exec(TEST_FILE, &[TEST_FILE]), -1);
+
+    unlink(TEST_FILE, 0);
+
+    // directories are refused before they are read
+    assert_eq!(exec("/", &["/"]), -2);
+    assert_eq!(exec("/bin", &["/bin"]), -2);
+
+    0
+}
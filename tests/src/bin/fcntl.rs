@@ -0,0 +1,76 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::fs::{
+    close, dup, fcntl, fstat, open, pipe, read, unlink, write, OpenFlags, Stat, FD_CLOEXEC,
+    F_GETFD, F_GETFL, F_SETFD, F_SETFL,
+};
+
+static TEST_FILE: &str = "fcntl_test";
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let fd = open(
+        TEST_FILE,
+        OpenFlags::CREATE | OpenFlags::RDWR | OpenFlags::CLOEXEC,
+    );
+    assert!(fd >= 0, "Open test file failed!");
+    let fd = fd as usize;
+
+    // descriptor flags round-trip, and are not shared with duplicates
+    assert_eq!(fcntl(fd, F_GETFD, 0), FD_CLOEXEC as isize);
+    let dup_fd = dup(fd) as usize;
+    assert_eq!(fcntl(dup_fd, F_GETFD, 0), 0);
+    assert_eq!(fcntl(fd, F_SETFD, 0), 0);
+    assert_eq!(fcntl(fd, F_GETFD, 0), 0);
+    assert_eq!(fcntl(fd, F_SETFD, FD_CLOEXEC), 0);
+    assert_eq!(fcntl(fd, F_GETFD, 0), FD_CLOEXEC as isize);
+
+    // status flags round-trip, the access mode cannot change after open
+    assert_eq!(fcntl(fd, F_GETFL, 0), OpenFlags::RDWR.bits() as isize);
+    let new_flags = OpenFlags::WRONLY | OpenFlags::APPEND | OpenFlags::NONBLOCK;
+    assert_eq!(fcntl(fd, F_SETFL, new_flags.bits() as usize), 0);
+    let expected_flags = OpenFlags::RDWR | OpenFlags::APPEND | OpenFlags::NONBLOCK;
+    assert_eq!(fcntl(fd, F_GETFL, 0), expected_flags.bits() as isize);
+    // duplicates share the open file and so its status flags
+    assert_eq!(fcntl(dup_fd, F_GETFL, 0), expected_flags.bits() as isize);
+
+    // appending writes land at the end whatever the offset
+    assert_eq!(write(fd, b"abc"), 3);
+    let other_fd = open(TEST_FILE, OpenFlags::WRONLY);
+    assert!(other_fd >= 0);
+    assert_eq!(write(other_fd as usize, b"12345"), 5);
+    close(other_fd as usize);
+    assert_eq!(write(dup_fd, b"def"), 3);
+    let mut stat = Stat::new();
+    assert_eq!(fstat(fd, &mut stat), 0);
+    assert_eq!(stat.size, 8);
+
+    // invalid commands and descriptors
+    assert_eq!(fcntl(fd, 0xdead, 0), -1);
+    assert_eq!(fcntl(usize::MAX, F_GETFL, 0), -1);
+    close(dup_fd);
+    assert_eq!(fcntl(dup_fd, F_GETFD, 0), -1);
+    close(fd);
+    unlink(TEST_FILE, 0);
+
+    // a non-blocking pipe returns instead of waiting for data
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(
+        fcntl(pipe_fd[0], F_GETFL, 0),
+        OpenFlags::RDONLY.bits() as isize
+    );
+    assert_eq!(
+        fcntl(pipe_fd[0], F_SETFL, OpenFlags::NONBLOCK.bits() as usize),
+        0
+    );
+    let mut buf = [0u8; 4];
+    assert_eq!(read(pipe_fd[0], &mut buf), 0);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+
+    0
+}
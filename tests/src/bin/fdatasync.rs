@@ -0,0 +1,68 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::{string::String, vec};
+use user_lib::fs::{
+    close, fdatasync, fsync, lseek, open, read, unlink, write, OpenFlags, SEEK_SET,
+};
+
+static TEST_FILE: &str = "/fdatasync_test";
+
+/// Bytes written to the block device so far, from `/proc/interrupts`
+fn block_written() -> usize {
+    let fd = open("/proc/interrupts", OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let mut buf = vec![0u8; 1024];
+    let len = read(fd as usize, &mut buf);
+    close(fd as usize);
+    buf.truncate(len as usize);
+    let table = String::from_utf8(buf).unwrap();
+    let row = table
+        .lines()
+        .find(|line| line.starts_with("block"))
+        .unwrap();
+    row.split_whitespace().nth(3).unwrap().parse().unwrap()
+}
+
+/// Bytes `sync` writes to the block device
+fn written_by(sync: fn(usize) -> isize, fd: usize) -> usize {
+    let before = block_written();
+    assert_eq!(sync(fd), 0);
+    block_written() - before
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let fd = open(TEST_FILE, OpenFlags::CREATE | OpenFlags::RDWR);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+
+    // a clean file has nothing to write back
+    assert_eq!(written_by(fdatasync, fd), 0);
+
+    // buffered appends grow the file, so their size goes out along with the data
+    assert_eq!(write(fd, &[b'a'; 100]), 100);
+    assert!(written_by(fdatasync, fd) > 0);
+    assert_eq!(written_by(fdatasync, fd), 0);
+    // exactly as much as a full fsync writes for an append of the same shape
+    assert_eq!(write(fd, &[b'b'; 100]), 100);
+    let full = written_by(fsync, fd);
+    assert_eq!(write(fd, &[b'c'; 100]), 100);
+    assert_eq!(written_by(fdatasync, fd), full);
+
+    // writes within the file reach the disk at once and leave the size alone
+    assert_eq!(lseek(fd, 0, SEEK_SET), 0);
+    let before = block_written();
+    assert_eq!(write(fd, &[b'd'; 10]), 10);
+    assert!(block_written() > before);
+    assert_eq!(written_by(fdatasync, fd), 0);
+    assert_eq!(written_by(fsync, fd), 0);
+
+    assert_eq!(fdatasync(99), -1);
+    close(fd);
+    assert_eq!(unlink(TEST_FILE, 0), 0);
+    0
+}
@@ -0,0 +1,63 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::fs::{
+    close, fincore, lseek, open, pipe, read, unlink, write, OpenFlags, BLOCK_SIZE, SEEK_SET,
+};
+
+static TEST_FILE: &str = "/fincore_test_file";
+static CHURN_FILE: &str = "/fincore_churn_file";
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let fd = open(TEST_FILE, OpenFlags::CREATE | OpenFlags::RDWR);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    let block = [b'x'; BLOCK_SIZE];
+    let data = [b'x'; 4 * BLOCK_SIZE];
+    assert_eq!(write(fd, &data), data.len() as isize);
+
+    // blocks just written are cached, and asking again gives the same answer
+    let mut vec = [0u8; 4];
+    assert_eq!(fincore(fd, 0, &mut vec), 0);
+    assert_eq!(vec, [1; 4]);
+    assert_eq!(fincore(fd, 0, &mut vec), 0);
+    assert_eq!(vec, [1; 4]);
+    // blocks past the end never are
+    let mut vec = [9u8; 2];
+    assert_eq!(fincore(fd, 3 * BLOCK_SIZE, &mut vec), 0);
+    assert_eq!(vec, [1, 0]);
+
+    // push the file out with more blocks than the cache holds, then read one back in
+    let churn = open(CHURN_FILE, OpenFlags::CREATE | OpenFlags::RDWR);
+    assert!(churn >= 0);
+    let churn = churn as usize;
+    for _ in 0..64 {
+        assert_eq!(write(churn, &block), BLOCK_SIZE as isize);
+    }
+    close(churn);
+    let mut buf = [0u8; BLOCK_SIZE];
+    assert_eq!(
+        lseek(fd, 2 * BLOCK_SIZE as isize, SEEK_SET),
+        2 * BLOCK_SIZE as isize
+    );
+    assert_eq!(read(fd, &mut buf), BLOCK_SIZE as isize);
+    let mut vec = [0u8; 4];
+    assert_eq!(fincore(fd, 0, &mut vec), 0);
+    assert_eq!(vec[2], 1);
+
+    assert_eq!(fincore(fd, 1, &mut vec), -1);
+    assert_eq!(fincore(99, 0, &mut vec), -1);
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(fincore(pipe_fd[0], 0, &mut vec), -1);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+
+    close(fd);
+    assert_eq!(unlink(TEST_FILE, 0), 0);
+    assert_eq!(unlink(CHURN_FILE, 0), 0);
+    0
+}
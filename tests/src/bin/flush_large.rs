@@ -0,0 +1,52 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    fs::{close, fsync, open, read, unlink, write, OpenFlags},
+    process::get_time,
+};
+
+const BUFFER_SIZE: usize = 4096;
+/// Size of the file in KiB, well beyond what the block cache holds
+const SIZE_KB: usize = 512;
+
+static TEST_FILE: &str = "flush_large_test";
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let mut buffer = [0u8; BUFFER_SIZE];
+
+    let fd = open(TEST_FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd >= 0, "Open test file failed!");
+    let fd = fd as usize;
+
+    // the file outgrows the block cache, so its dirty blocks are written back in batches
+    let start = get_time();
+    for chunk in 0..SIZE_KB * 1024 / BUFFER_SIZE {
+        buffer.fill(chunk as u8);
+        assert_eq!(write(fd, &buffer), BUFFER_SIZE as isize);
+    }
+    assert_eq!(fsync(fd), 0);
+    let time_ms = (get_time() - start).max(1) as usize;
+    close(fd);
+    println!(
+        "{}KiB flushed, time cost = {}ms, flush speed = {}KiB/s",
+        SIZE_KB,
+        time_ms,
+        SIZE_KB * 1000 / time_ms
+    );
+
+    let fd = open(TEST_FILE, OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    for chunk in 0..SIZE_KB * 1024 / BUFFER_SIZE {
+        assert_eq!(read(fd, &mut buffer), BUFFER_SIZE as isize);
+        assert!(buffer.iter().all(|&byte| byte == chunk as u8));
+    }
+    close(fd);
+    assert_eq!(unlink(TEST_FILE, 0), 0);
+    0
+}
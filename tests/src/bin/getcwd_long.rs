@@ -0,0 +1,54 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::{format, string::String, vec::Vec};
+use user_lib::{
+    fs::{chdir, getcwd, mkdir, unlink, AT_REMOVEDIR},
+    syscall::sys_getcwd,
+};
+
+static TEST_DIR: &str = "/getcwd_long_test_directory";
+const DEPTH: usize = 8;
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let mut cwd = String::new();
+    getcwd(&mut cwd);
+
+    // a working directory longer than the wrapper's initial buffer
+    let mut dirs = Vec::new();
+    let mut path = String::from(TEST_DIR);
+    assert_eq!(mkdir(&path), 0);
+    dirs.push(path.clone());
+    for _ in 1..DEPTH {
+        path = format!("{path}{TEST_DIR}");
+        assert_eq!(mkdir(&path), 0);
+        dirs.push(path.clone());
+    }
+    assert!(path.len() > 128);
+    assert_eq!(chdir(&path), 0);
+
+    let mut long_cwd = String::new();
+    assert_eq!(getcwd(&mut long_cwd), path.len() as isize);
+    assert_eq!(long_cwd, path);
+
+    // a short buffer reports the needed length and is left untouched
+    let mut buffer = [0xffu8; 64];
+    assert_eq!(sys_getcwd(&mut buffer[..32]), -(path.len() as isize));
+    assert!(buffer.iter().all(|&b| b == 0xff));
+
+    // an exactly sized buffer succeeds
+    let mut buffer = alloc::vec![0u8; path.len()];
+    assert_eq!(sys_getcwd(&mut buffer), path.len() as isize);
+    assert_eq!(buffer, path.as_bytes());
+
+    assert_eq!(chdir(&cwd), 0);
+    for dir in dirs.iter().rev() {
+        assert_eq!(unlink(dir, AT_REMOVEDIR), 0);
+    }
+
+    0
+}
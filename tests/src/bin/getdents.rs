@@ -0,0 +1,96 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::{format, string::String, vec::Vec};
+use user_lib::fs::{
+    close, getdents, mkdir, open, unlink, Dirent, OpenFlags, AT_REMOVEDIR, DIRENT_SIZE,
+};
+
+static TEST_DIR: &str = "/getdents_test_dir";
+const FILE_COUNT: usize = 24;
+/// Entries taken by each call, small so deletions land between calls
+const BATCH: usize = 3;
+
+fn file_path(i: usize) -> String {
+    format!("{TEST_DIR}/f{i:02}")
+}
+
+fn read_batch(fd: usize, names: &mut Vec<String>) -> usize {
+    let mut buf = [0u8; BATCH * DIRENT_SIZE];
+    let len = getdents(fd, &mut buf);
+    assert!(len >= 0 && (len as usize).is_multiple_of(DIRENT_SIZE));
+    for entry in buf[..len as usize].chunks_exact(DIRENT_SIZE) {
+        let dirent = unsafe { entry.as_ptr().cast::<Dirent>().read_unaligned() };
+        names.push(String::from(dirent.name()));
+    }
+    len as usize / DIRENT_SIZE
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    assert_eq!(mkdir(TEST_DIR), 0);
+    for i in 0..FILE_COUNT {
+        let fd = open(&file_path(i), OpenFlags::CREATE | OpenFlags::WRONLY);
+        assert!(fd >= 0);
+        close(fd as usize);
+    }
+
+    // a listing in one go reports every entry once
+    let fd = open(TEST_DIR, OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    let mut names = Vec::new();
+    while read_batch(fd, &mut names) > 0 {}
+    assert_eq!(names.len(), FILE_COUNT + 2);
+    assert_eq!(names[..2], [".", ".."]);
+    close(fd);
+
+    // delete entries, both reported and not yet reported, between calls; removing one
+    // moves the last entry of the directory into its slot
+    let fd = open(TEST_DIR, OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    let mut names = Vec::new();
+    let mut deleted = [false; FILE_COUNT];
+    let mut next = 0;
+    while read_batch(fd, &mut names) > 0 {
+        for _ in 0..2 {
+            if next < FILE_COUNT {
+                assert_eq!(unlink(&file_path(next), 0), 0);
+                deleted[next] = true;
+                next += 3;
+            }
+        }
+    }
+    // nothing is left to report after the end
+    assert_eq!(read_batch(fd, &mut names), 0);
+    close(fd);
+
+    for (i, name) in names.iter().enumerate() {
+        assert!(!names[..i].contains(name), "{name} reported twice");
+    }
+    for i in (0..FILE_COUNT).filter(|&i| !deleted[i]) {
+        let name = format!("f{i:02}");
+        assert!(names.contains(&name), "surviving {name} not reported");
+    }
+
+    // bad arguments
+    let fd = open(&file_path(1), OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let mut buf = [0u8; DIRENT_SIZE];
+    assert_eq!(getdents(fd as usize, &mut buf), -1);
+    close(fd as usize);
+    let fd = open(TEST_DIR, OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    assert_eq!(getdents(fd as usize, &mut buf[..DIRENT_SIZE - 1]), -1);
+    close(fd as usize);
+
+    for i in (0..FILE_COUNT).filter(|&i| !deleted[i]) {
+        assert_eq!(unlink(&file_path(i), 0), 0);
+    }
+    assert_eq!(unlink(TEST_DIR, AT_REMOVEDIR), 0);
+    0
+}
@@ -0,0 +1,45 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{
+    fs::{close, dup, dup2, isatty, open, pipe, unlink, OpenFlags},
+    process::{exit, fork, waitpid},
+};
+
+static TEST_FILE: &str = "isatty_test";
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    // the standard streams start out on the console
+    assert!(isatty(0) && isatty(1) && isatty(2));
+    let fd = dup(1);
+    assert!(fd >= 0 && isatty(fd as usize));
+    close(fd as usize);
+    assert!(!isatty(fd as usize));
+    assert!(!isatty(1000));
+
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert!(!isatty(pipe_fd[0]) && !isatty(pipe_fd[1]));
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+
+    // a stdout redirected to a file is no terminal
+    let pid = fork();
+    if pid == 0 {
+        let fd = open(TEST_FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+        assert!(fd >= 0);
+        assert!(!isatty(fd as usize));
+        assert_eq!(dup2(fd as usize, 1), 0);
+        close(fd as usize);
+        exit(i32::from(isatty(1)));
+    }
+    let mut exit_code = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert_eq!(unlink(TEST_FILE, 0), 0);
+    assert!(isatty(1));
+    0
+}
@@ -0,0 +1,40 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::process::{madvise, MADV_DONTNEED, MADV_WILLNEED};
+
+const PAGE_SIZE: usize = 0x1000;
+
+#[repr(C, align(4096))]
+struct Pages([u8; PAGE_SIZE * 2]);
+
+static mut PAGES: Pages = Pages([1; PAGE_SIZE * 2]);
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let addr = core::ptr::addr_of!(PAGES) as usize;
+
+    // read-ahead is a harmless hint, the length is rounded up to whole pages
+    assert_eq!(madvise(addr, PAGE_SIZE * 2, MADV_WILLNEED), 0);
+    assert_eq!(madvise(addr, 1, MADV_WILLNEED), 0);
+    assert_eq!(madvise(addr, 0, MADV_WILLNEED), 0);
+
+    // private memory cannot be dropped without losing its contents
+    assert_eq!(madvise(addr, PAGE_SIZE, MADV_DONTNEED), -2);
+    assert!(unsafe {
+        (*core::ptr::addr_of!(PAGES))
+            .0
+            .iter()
+            .all(|&byte| byte == 1)
+    });
+
+    // misaligned, unmapped and overflowing ranges, unknown advice
+    assert_eq!(madvise(addr + 1, PAGE_SIZE, MADV_WILLNEED), -1);
+    assert_eq!(madvise(0, PAGE_SIZE, MADV_WILLNEED), -1);
+    assert_eq!(madvise(addr, usize::MAX - addr, MADV_WILLNEED), -1);
+    assert_eq!(madvise(addr, PAGE_SIZE, 0xdead), -1);
+
+    0
+}
@@ -0,0 +1,99 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{
+    fs::{
+        close, dup, fstat, ftruncate, lseek, memfd_create, pipe, read, write, Stat, StatMode,
+        MFD_CLOEXEC, SEEK_CUR, SEEK_END, SEEK_SET,
+    },
+    process::{exit, fork, sysinfo, waitpid},
+};
+
+const PAGE_SIZE: usize = 4096;
+
+fn size_of(fd: usize) -> usize {
+    let mut stat = Stat::new();
+    assert_eq!(fstat(fd, &mut stat), 0);
+    assert!(stat.mode == StatMode::REG);
+    stat.size as usize
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    assert_eq!(memfd_create("bad", 1 << 4), -1);
+    let fd = memfd_create("scratch", MFD_CLOEXEC);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    assert_eq!(size_of(fd), 0);
+
+    // read, write and seek like a regular file
+    assert_eq!(write(fd, b"hello"), 5);
+    assert_eq!(lseek(fd, 0, SEEK_CUR), 5);
+    assert_eq!(lseek(fd, 0, SEEK_SET), 0);
+    let mut buffer = [0u8; 16];
+    assert_eq!(read(fd, &mut buffer), 5);
+    assert_eq!(&buffer[..5], b"hello");
+    assert_eq!(read(fd, &mut buffer), 0);
+    assert_eq!(lseek(fd, -6, SEEK_CUR), -1);
+    assert_eq!(lseek(fd, 0, 3), -1);
+
+    // writing past the end grows the file, leaving a gap of zeros
+    let end = 2 * PAGE_SIZE + 100;
+    assert_eq!(lseek(fd, end as isize, SEEK_SET), end as isize);
+    assert_eq!(write(fd, b"end"), 3);
+    assert_eq!(size_of(fd), end + 3);
+    assert_eq!(lseek(fd, -3, SEEK_END), end as isize);
+    assert_eq!(read(fd, &mut buffer), 3);
+    assert_eq!(&buffer[..3], b"end");
+    assert_eq!(lseek(fd, 5, SEEK_SET), 5);
+    let mut gap = [0xffu8; 2 * PAGE_SIZE];
+    assert_eq!(read(fd, &mut gap), gap.len() as isize);
+    assert!(gap.iter().all(|&byte| byte == 0));
+
+    // truncated bytes read back as zeros once the file grows again
+    assert_eq!(ftruncate(fd, 3), 0);
+    assert_eq!(size_of(fd), 3);
+    assert_eq!(ftruncate(fd, 5), 0);
+    assert_eq!(lseek(fd, 0, SEEK_SET), 0);
+    assert_eq!(read(fd, &mut buffer), 5);
+    assert_eq!(&buffer[..5], b"hel\0\0");
+
+    // a forked child shares the data and the offset
+    assert_eq!(lseek(fd, 0, SEEK_SET), 0);
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(write(fd, b"child"), 5);
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert_eq!(lseek(fd, 0, SEEK_CUR), 5);
+    assert_eq!(lseek(fd, 0, SEEK_SET), 0);
+    assert_eq!(read(fd, &mut buffer), 5);
+    assert_eq!(&buffer[..5], b"child");
+    close(fd);
+
+    // pipes have no offset
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(lseek(pipe_fd[0], 0, SEEK_SET), -29);
+    assert_eq!(ftruncate(pipe_fd[1], 0), -1);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+
+    // the memory is freed with the last file descriptor
+    let before = sysinfo().free_ram;
+    let fd = memfd_create("large", 0) as usize;
+    assert_eq!(ftruncate(fd, 64 * PAGE_SIZE), 0);
+    assert!(sysinfo().free_ram <= before - 64 * PAGE_SIZE);
+    let copy = dup(fd) as usize;
+    close(fd);
+    assert!(sysinfo().free_ram <= before - 64 * PAGE_SIZE);
+    close(copy);
+    assert_eq!(sysinfo().free_ram, before);
+
+    0
+}
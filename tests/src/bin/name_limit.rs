@@ -0,0 +1,47 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::{format, string::String};
+use user_lib::fs::{
+    close, mkdir, open, rename, unlink, OpenFlags, AT_REMOVEDIR, NAME_LENGTH_LIMIT,
+};
+
+fn path(len: usize) -> String {
+    format!("/{}", "n".repeat(len))
+}
+
+fn exists(path: &str) -> bool {
+    let fd = open(path, OpenFlags::RDONLY);
+    if fd >= 0 {
+        close(fd as usize);
+    }
+    fd >= 0
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let longest = path(NAME_LENGTH_LIMIT);
+    let too_long = path(NAME_LENGTH_LIMIT + 1);
+
+    // a name exactly at the limit is accepted
+    let fd = open(&longest, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd >= 0);
+    close(fd as usize);
+    assert!(exists(&longest));
+
+    // one byte more is refused, whichever call would create it
+    assert_eq!(open(&too_long, OpenFlags::CREATE | OpenFlags::WRONLY), -36);
+    assert_eq!(mkdir(&too_long), -36);
+    assert_eq!(rename(&longest, &too_long), -36);
+    // and nothing was left behind
+    assert!(!exists(&too_long));
+    assert!(exists(&longest));
+
+    assert_eq!(unlink(&longest, 0), 0);
+    assert_eq!(mkdir(&longest), 0);
+    assert_eq!(unlink(&longest, AT_REMOVEDIR), 0);
+    0
+}
@@ -0,0 +1,89 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::string::String;
+use user_lib::fs::{
+    chdir, close, fchdir, fstat, getcwd, getdents, mkdir, mkdirat, open, read, unlink, write,
+    OpenFlags, Stat, StatMode, AT_REMOVEDIR,
+};
+
+static DIR: &str = "/open_path_test";
+static FILE: &str = "/open_path_test/file";
+static CONTENT: &[u8] = b"left alone";
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let mut cwd = String::new();
+    getcwd(&mut cwd);
+
+    assert_eq!(mkdir(DIR), 0);
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd >= 0);
+    assert_eq!(write(fd as usize, CONTENT), CONTENT.len() as isize);
+    close(fd as usize);
+
+    // a file is referred to but neither read nor written, and never truncated
+    let file = open(FILE, OpenFlags::PATH | OpenFlags::RDWR | OpenFlags::TRUNC);
+    assert!(file >= 0);
+    let file = file as usize;
+    let mut buffer = [0u8; 32];
+    assert_eq!(read(file, &mut buffer), -1);
+    assert_eq!(write(file, CONTENT), -1);
+    let mut stat = Stat::new();
+    assert_eq!(fstat(file, &mut stat), 0);
+    assert!(stat.mode == StatMode::REG);
+    assert_eq!(stat.size, CONTENT.len() as u32);
+    assert_eq!(fchdir(file), -2);
+    close(file);
+
+    // nor is it created
+    assert_eq!(
+        open(
+            "/open_path_test/missing",
+            OpenFlags::PATH | OpenFlags::CREATE
+        ),
+        -1
+    );
+
+    // a directory serves wherever a directory descriptor is expected
+    let dir = open(DIR, OpenFlags::PATH);
+    assert!(dir >= 0);
+    let dir = dir as usize;
+    assert_eq!(read(dir, &mut buffer), -1);
+    assert_eq!(getdents(dir, &mut buffer), -1);
+    assert_eq!(fstat(dir, &mut stat), 0);
+    assert!(stat.mode == StatMode::DIR);
+    assert_eq!(mkdirat(dir as isize, "sub"), 0);
+
+    assert_eq!(fchdir(dir), 0);
+    let mut now = String::new();
+    getcwd(&mut now);
+    assert_eq!(now, DIR);
+    let fd = open("file", OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    assert_eq!(read(fd as usize, &mut buffer), CONTENT.len() as isize);
+    assert_eq!(&buffer[..CONTENT.len()], CONTENT);
+    close(fd as usize);
+
+    let sub = open("sub", OpenFlags::PATH);
+    assert_eq!(fchdir(sub as usize), 0);
+    getcwd(&mut now);
+    assert_eq!(now, "/open_path_test/sub");
+    close(sub as usize);
+
+    // a removed directory has no path to change to
+    assert_eq!(chdir(&cwd), 0);
+    let sub = open("/open_path_test/sub", OpenFlags::PATH);
+    assert_eq!(unlink("/open_path_test/sub", AT_REMOVEDIR), 0);
+    assert_eq!(fchdir(sub as usize), -1);
+    close(sub as usize);
+
+    close(dir);
+    unlink(FILE, 0);
+    unlink(DIR, AT_REMOVEDIR);
+
+    0
+}
@@ -0,0 +1,49 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::process::{exit, fork, process_info, waitpid, yield_, ProcessState};
+
+const ROUNDS: usize = 1000;
+/// Zombies allowed to linger, an orphan that exits last waits for the next exit
+const SLACK: usize = 8;
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let before = process_info().len();
+    let mut max_pid = 0;
+
+    for _ in 0..ROUNDS {
+        let pid = fork();
+        if pid == 0 {
+            // leave a grandchild behind that outlives us
+            if fork() == 0 {
+                yield_();
+                exit(0);
+            }
+            exit(0);
+        }
+        max_pid = max_pid.max(pid as usize);
+        let mut exit_code = -1;
+        assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+        assert_eq!(exit_code, 0);
+    }
+
+    // let the last orphans finish
+    for _ in 0..16 {
+        yield_();
+    }
+
+    // pids are recycled instead of leaking one per round
+    assert!(max_pid < ROUNDS / 2, "pids leaked up to {max_pid}");
+    let infos = process_info();
+    let zombies = infos
+        .iter()
+        .filter(|info| info.state == ProcessState::Zombie)
+        .count();
+    assert!(zombies <= SLACK, "{zombies} zombies left");
+    assert!(infos.len() <= before + SLACK);
+
+    0
+}
@@ -0,0 +1,67 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::string::String;
+use user_lib::{
+    fs::{chdir, close, open, unlink, OpenFlags, PATH_MAX},
+    process::{madvise, MADV_WILLNEED},
+    syscall::sys_open,
+};
+
+const PAGE_SIZE: usize = 4096;
+const EFAULT: isize = -14;
+
+static TEST_FILE: &str = "path_max_test";
+/// Static since it is as large as the whole user stack
+static UNTERMINATED: [u8; PATH_MAX * 2] = [b'/'; PATH_MAX * 2];
+
+/// Padding with repeated slashes resolves to the same file at any length
+fn padded_path(len: usize) -> String {
+    let mut path = "/".repeat(len - TEST_FILE.len());
+    path.push_str(TEST_FILE);
+    path
+}
+
+/// Opens the bytes at `addr` as a path without appending a terminator
+fn open_raw(addr: usize) -> isize {
+    let path = unsafe {
+        core::str::from_utf8_unchecked(core::slice::from_raw_parts(addr as *const u8, 1))
+    };
+    sys_open(path, OpenFlags::RDONLY.bits())
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let fd = open(TEST_FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd >= 0);
+    close(fd as usize);
+
+    // the terminator counts towards the limit
+    let fd = open(&padded_path(PATH_MAX - 1), OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    close(fd as usize);
+    assert_eq!(open(&padded_path(PATH_MAX), OpenFlags::RDONLY), -1);
+    assert_eq!(chdir(&padded_path(PATH_MAX * 2)), -1);
+
+    // an unterminated buffer is only scanned up to the limit
+    assert_eq!(open_raw(UNTERMINATED.as_ptr() as usize), -1);
+
+    // the page after the top of the stack is a guard page, end the stack without a terminator
+    let local = 0u8;
+    let mut page = core::ptr::from_ref(&local) as usize & !(PAGE_SIZE - 1);
+    while madvise(page + PAGE_SIZE, PAGE_SIZE, MADV_WILLNEED) == 0 {
+        page += PAGE_SIZE;
+    }
+    let stack_top = page + PAGE_SIZE;
+    // the top word is the terminating null of argv, which has been read already
+    for addr in stack_top - 8..stack_top {
+        unsafe { (addr as *mut u8).write_volatile(b'/') };
+    }
+    assert_eq!(open_raw(stack_top - 8), EFAULT);
+
+    assert_eq!(unlink(TEST_FILE, 0), 0);
+    0
+}
@@ -0,0 +1,60 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{
+    fs::{close, poll, PollEvents, PollFd},
+    process::{fork, waitpid},
+    signal::{pidfd_open, pidfd_send_signal, SignalFlags},
+    sync::sleep,
+};
+
+/// Forks a child that runs until it is signaled.
+fn spawn_idle() -> usize {
+    let pid = fork();
+    if pid == 0 {
+        loop {
+            sleep(10);
+        }
+    }
+    pid as usize
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let pid = spawn_idle();
+    let pidfd = pidfd_open(pid);
+    assert!(pidfd >= 0);
+    let pidfd = pidfd as usize;
+
+    // not readable while the process runs
+    let mut fds = [PollFd::new(pidfd, PollEvents::IN)];
+    assert_eq!(poll(&mut fds, 0), 0);
+
+    // the signal reaches the process, and poll waits for it to exit
+    assert_eq!(pidfd_send_signal(pidfd, SignalFlags::SIGINT.bits()), 0);
+    assert_eq!(poll(&mut fds, -1), 1);
+    assert!(fds[0].revents == PollEvents::IN);
+
+    // an exited process cannot be signaled, whether reaped or not
+    assert_eq!(pidfd_send_signal(pidfd, SignalFlags::SIGINT.bits()), -3);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), pid as isize);
+    assert_eq!(exit_code, -2);
+    assert_eq!(pidfd_send_signal(pidfd, SignalFlags::SIGINT.bits()), -3);
+    assert_eq!(poll(&mut fds, 0), 1);
+    assert_eq!(pidfd_open(pid), -3);
+
+    // only an open pidfd and a known signal are accepted
+    assert_eq!(close(pidfd), 0);
+    assert_eq!(pidfd_send_signal(pidfd, SignalFlags::SIGINT.bits()), -1);
+    assert_eq!(pidfd_send_signal(0, SignalFlags::SIGINT.bits()), -1);
+    let pid = spawn_idle();
+    let pidfd = pidfd_open(pid) as usize;
+    assert_eq!(pidfd_send_signal(pidfd, 1 << 30), -1);
+    assert_eq!(pidfd_send_signal(pidfd, SignalFlags::SIGINT.bits()), 0);
+    assert_eq!(waitpid(pid, &mut exit_code), pid as isize);
+    assert_eq!(close(pidfd), 0);
+    0
+}
@@ -0,0 +1,102 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{
+    fs::{close, eventfd, eventfd_write, pipe, poll, ppoll, read, write, PollEvents, PollFd},
+    process::{exit, fork, get_time, waitpid, waitpid_nb},
+    signal::{kill, SignalFlags},
+    sync::sleep,
+};
+
+/// Forks a child that waits on `fd` with `sigmask` blocked, and exits with `0` if it returns.
+fn poll_in_child(fd: usize, sigmask: SignalFlags) -> usize {
+    let pid = fork();
+    if pid == 0 {
+        let mut fds = [PollFd::new(fd, PollEvents::IN)];
+        ppoll(&mut fds, -1, sigmask);
+        exit(0);
+    }
+    pid as usize
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let (read_end, write_end) = (pipe_fd[0], pipe_fd[1]);
+
+    // nothing to read yet, the write end has room
+    let mut fds = [
+        PollFd::new(read_end, PollEvents::IN),
+        PollFd::new(write_end, PollEvents::OUT),
+    ];
+    assert_eq!(poll(&mut fds, 0), 1);
+    assert!(fds[0].revents.is_empty());
+    assert!(fds[1].revents == PollEvents::OUT);
+
+    // the timeout is honored
+    let start = get_time();
+    assert_eq!(poll(&mut fds[..1], 50), 0);
+    assert!(get_time() - start >= 50);
+
+    // data becomes readable
+    assert_eq!(write(write_end, b"x"), 1);
+    assert_eq!(poll(&mut fds[..1], -1), 1);
+    assert!(fds[0].revents == PollEvents::IN);
+    let mut buf = [0u8; 1];
+    assert_eq!(read(read_end, &mut buf), 1);
+
+    // closed and negative descriptors
+    let mut fds = [
+        PollFd::new(read_end, PollEvents::IN),
+        PollFd::new(100, PollEvents::IN),
+        PollFd {
+            fd: -1,
+            events: PollEvents::IN,
+            revents: PollEvents::empty(),
+        },
+    ];
+    assert_eq!(poll(&mut fds, 0), 1);
+    assert!(fds[1].revents == PollEvents::NVAL);
+    assert!(fds[2].revents.is_empty());
+
+    // event counters are readable once written
+    let event = eventfd(0) as usize;
+    let mut fds = [PollFd::new(event, PollEvents::IN | PollEvents::OUT)];
+    assert_eq!(poll(&mut fds, 0), 1);
+    assert!(fds[0].revents == PollEvents::OUT);
+    assert_eq!(eventfd_write(event, 1), 8);
+    assert_eq!(poll(&mut fds, 0), 1);
+    assert!(fds[0].revents == PollEvents::IN | PollEvents::OUT);
+    close(event);
+
+    // a blocked signal stays pending while waiting, and is delivered once the mask is restored
+    let pid = poll_in_child(read_end, SignalFlags::SIGINT);
+    sleep(20);
+    assert_eq!(kill(pid, SignalFlags::SIGINT.bits()), 0);
+    sleep(50);
+    let mut exit_code = 0;
+    assert_eq!(waitpid_nb(pid, &mut exit_code), -2);
+    assert_eq!(write(write_end, b"x"), 1);
+    assert_eq!(waitpid(pid, &mut exit_code), pid as isize);
+    assert_eq!(exit_code, -2);
+    assert_eq!(read(read_end, &mut buf), 1);
+
+    // a signal the mask does not block interrupts the wait
+    let pid = poll_in_child(read_end, SignalFlags::SIGABRT);
+    sleep(20);
+    assert_eq!(kill(pid, SignalFlags::SIGINT.bits()), 0);
+    assert_eq!(waitpid(pid, &mut exit_code), pid as isize);
+    assert_eq!(exit_code, -2);
+
+    // closing the write end hangs up the read end
+    close(write_end);
+    let mut fds = [PollFd::new(read_end, PollEvents::IN)];
+    assert_eq!(poll(&mut fds, -1), 1);
+    assert!(fds[0].revents == PollEvents::HUP);
+    close(read_end);
+
+    0
+}
@@ -0,0 +1,82 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{
+    fs::{close, pipe, read, write},
+    process::{
+        exit, fork, get_name, prctl, set_name, waitpid, PROCESS_NAME_LEN, PR_GET_CHILD_SUBREAPER,
+        PR_GET_NO_NEW_PRIVS, PR_SET_CHILD_SUBREAPER, PR_SET_NO_NEW_PRIVS,
+    },
+    sync::sleep,
+};
+
+fn is_subreaper() -> i32 {
+    let mut value = -1i32;
+    assert_eq!(prctl(PR_GET_CHILD_SUBREAPER, (&raw mut value) as usize), 0);
+    value
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    assert_eq!(prctl(0x1234, 0), -1);
+
+    // names
+    assert_eq!(set_name("renamed"), 0);
+    assert_eq!(get_name(), "renamed");
+    let long = [b'x'; PROCESS_NAME_LEN];
+    assert_eq!(set_name(core::str::from_utf8(&long).unwrap()), -1);
+    assert_eq!(get_name(), "renamed");
+
+    // no new privileges cannot be undone and children keep it
+    assert_eq!(prctl(PR_GET_NO_NEW_PRIVS, 0), 0);
+    assert_eq!(prctl(PR_SET_NO_NEW_PRIVS, 0), -1);
+    assert_eq!(prctl(PR_SET_NO_NEW_PRIVS, 1), 0);
+    assert_eq!(prctl(PR_GET_NO_NEW_PRIVS, 0), 1);
+
+    // a subreaper adopts the children of its exiting descendants, which it must reap
+    assert_eq!(is_subreaper(), 0);
+    assert_eq!(prctl(PR_SET_CHILD_SUBREAPER, 1), 0);
+    assert_eq!(is_subreaper(), 1);
+    let mut fds = [0usize; 2];
+    assert_eq!(pipe(&mut fds), 0);
+    let pid = fork();
+    if pid == 0 {
+        close(fds[0]);
+        assert_eq!(prctl(PR_GET_NO_NEW_PRIVS, 0), 1);
+        assert_eq!(is_subreaper(), 0);
+        assert_eq!(get_name(), "renamed");
+        // one grandchild is a zombie when its parent exits, the other still runs
+        let zombie = fork();
+        if zombie == 0 {
+            exit(41);
+        }
+        let running = fork();
+        if running == 0 {
+            sleep(50);
+            exit(42);
+        }
+        for pid in [zombie, running] {
+            assert_eq!(write(fds[1], &pid.to_ne_bytes()), 8);
+        }
+        sleep(10);
+        exit(0);
+    }
+    close(fds[1]);
+    let mut exit_code = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    for expected in [41, 42] {
+        let mut buf = [0u8; 8];
+        assert_eq!(read(fds[0], &mut buf), 8);
+        let grandchild = isize::from_ne_bytes(buf);
+        assert_eq!(waitpid(grandchild as usize, &mut exit_code), grandchild);
+        assert_eq!(exit_code, expected);
+    }
+    close(fds[0]);
+
+    assert_eq!(prctl(PR_SET_CHILD_SUBREAPER, 0), 0);
+    assert_eq!(is_subreaper(), 0);
+    0
+}
@@ -0,0 +1,108 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::{format, string::String, vec::Vec};
+use user_lib::{
+    fs::{
+        close, fstat, getdents, open, pipe, read, unlink, write, Dirent, OpenFlags, Stat, StatMode,
+        DIRENT_SIZE,
+    },
+    process::getpid,
+};
+
+static TEST_FILE: &str = "/proc_fd_test_file";
+
+/// Lists the descriptors under `/proc/<pid>/fd`, a few entries per call
+fn list_fds(dir: &str) -> Vec<usize> {
+    let fd = open(dir, OpenFlags::RDONLY);
+    assert!(fd >= 0, "Open {dir} failed!");
+    let fd = fd as usize;
+    let mut stat = Stat::new();
+    assert_eq!(fstat(fd, &mut stat), 0);
+    assert!(stat.mode == StatMode::DIR);
+
+    let mut fds = Vec::new();
+    let mut buf = [0u8; 2 * DIRENT_SIZE];
+    loop {
+        let len = getdents(fd, &mut buf);
+        assert!(len >= 0);
+        if len == 0 {
+            break;
+        }
+        for entry in buf[..len as usize].chunks_exact(DIRENT_SIZE) {
+            let dirent = unsafe { entry.as_ptr().cast::<Dirent>().read_unaligned() };
+            fds.push(dirent.name().parse().unwrap());
+        }
+    }
+    close(fd);
+    fds
+}
+
+/// Reads what the entry of `fd` names
+fn target(dir: &str, fd: usize) -> Option<String> {
+    let entry = open(&format!("{dir}/{fd}"), OpenFlags::RDONLY);
+    if entry < 0 {
+        return None;
+    }
+    let entry = entry as usize;
+    let mut buf = [0u8; 128];
+    let len = read(entry, &mut buf);
+    assert!(len > 0);
+    // the entries cannot be written to
+    assert_eq!(write(entry, b"x"), -1);
+    close(entry);
+    let text = core::str::from_utf8(&buf[..len as usize]).unwrap();
+    Some(String::from(text.strip_suffix('\n').unwrap()))
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let dir = format!("/proc/{}/fd", getpid());
+
+    let file = open(TEST_FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(file >= 0);
+    let file = file as usize;
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+
+    let fds = list_fds(&dir);
+    for fd in [0, 1, 2, file, pipe_fd[0], pipe_fd[1]] {
+        assert!(fds.contains(&fd), "fd {fd} is not listed");
+    }
+    assert_eq!(target(&dir, file).as_deref(), Some(TEST_FILE));
+    let read_end = target(&dir, pipe_fd[0]).unwrap();
+    assert!(read_end.starts_with("pipe:["));
+    // both ends name the same pipe
+    assert_eq!(target(&dir, pipe_fd[1]), Some(read_end));
+
+    // the listing follows the descriptor table as it changes
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+    let fds = list_fds(&dir);
+    assert!(!fds.contains(&pipe_fd[0]) && !fds.contains(&pipe_fd[1]));
+    assert_eq!(target(&dir, pipe_fd[0]), None);
+
+    // a descriptor closed while the directory is being read is simply not listed
+    let listing = open(&dir, OpenFlags::RDONLY);
+    assert!(listing >= 0);
+    let listing = listing as usize;
+    let mut buf = [0u8; DIRENT_SIZE];
+    assert_eq!(getdents(listing, &mut buf), DIRENT_SIZE as isize);
+    close(file);
+    let mut remaining = Vec::new();
+    while getdents(listing, &mut buf) > 0 {
+        let dirent = unsafe { buf.as_ptr().cast::<Dirent>().read_unaligned() };
+        remaining.push(dirent.name().parse::<usize>().unwrap());
+    }
+    assert!(!remaining.contains(&file));
+    close(listing);
+
+    // nothing can be created in the directory
+    assert!(open(&format!("{dir}/99"), OpenFlags::CREATE | OpenFlags::WRONLY) < 0);
+
+    assert_eq!(unlink(TEST_FILE, 0), 0);
+    0
+}
@@ -0,0 +1,63 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::{string::String, vec, vec::Vec};
+use user_lib::fs::{close, fstat, open, read, write, OpenFlags, Stat, StatMode};
+
+/// Reads `/proc/interrupts` and returns the counters of `device`.
+fn device_stats(device: &str) -> Vec<usize> {
+    let fd = open("/proc/interrupts", OpenFlags::RDONLY);
+    assert!(fd >= 0, "Open /proc/interrupts failed!");
+    let fd = fd as usize;
+
+    let mut stat = Stat::new();
+    assert_eq!(fstat(fd, &mut stat), 0);
+    assert!(stat.mode == StatMode::REG);
+    let mut buf = vec![0u8; stat.size as usize];
+    assert_eq!(read(fd, &mut buf), stat.size as isize);
+    // the snapshot ends where the file does
+    assert_eq!(read(fd, &mut buf), 0);
+    // and it cannot be written to
+    assert_eq!(write(fd, b"0"), -1);
+    close(fd);
+
+    let table = String::from_utf8(buf).unwrap();
+    let mut lines = table.lines();
+    let header: Vec<&str> = lines.next().unwrap().split_whitespace().collect();
+    assert_eq!(
+        header,
+        ["DEVICE", "IRQS", "READ", "WRITTEN", "QUEUED", "DROPPED"]
+    );
+    let row = lines
+        .find(|line| line.split_whitespace().next() == Some(device))
+        .unwrap();
+    row.split_whitespace()
+        .skip(1)
+        .map(|count| count.parse().unwrap())
+        .collect()
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    for device in ["uart", "block", "keyboard", "mouse"] {
+        assert_eq!(device_stats(device).len(), 5);
+    }
+
+    // the counters only grow as the devices are used
+    let uart = device_stats("uart");
+    let block = device_stats("block");
+    println!("proc_interrupts: console output is counted");
+    let fd = open("/tests/proc_interrupts", OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    close(fd as usize);
+    let new_uart = device_stats("uart");
+    let new_block = device_stats("block");
+    assert!(new_uart[2] > uart[2]);
+    assert!(new_block[1] >= block[1] && new_block[1] > 0);
+
+    0
+}
@@ -0,0 +1,56 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{
+    fs::{close, pipe, read, write},
+    process::{exit, fork, getpgid, getpid, setpgid, tcgetpgrp, tcsetpgrp, waitpid},
+};
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let pid = getpid() as usize;
+    let pgid = getpgid(0);
+    assert!(pgid > 0);
+    assert_eq!(getpgid(pid), pgid);
+
+    // the child starts in our group and stays alive until told to exit
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let child = fork();
+    if child == 0 {
+        close(pipe_fd[1]);
+        let mut buf = [0u8; 1];
+        read(pipe_fd[0], &mut buf);
+        exit(i32::from(getpgid(0) == getpid()));
+    }
+    close(pipe_fd[0]);
+    let child = child as usize;
+    assert_eq!(getpgid(child), pgid);
+
+    // only ourselves and our children can be moved, and only into existing groups
+    assert_eq!(setpgid(100_000, 0), -1);
+    assert_eq!(setpgid(child, 100_000), -1);
+    assert_eq!(setpgid(child, 0), 0);
+    assert_eq!(getpgid(child), child as isize);
+    // leaving for an existing group and coming back
+    assert_eq!(setpgid(child, pgid as usize), 0);
+    assert_eq!(getpgid(child), pgid);
+    assert_eq!(setpgid(child, child), 0);
+
+    // the terminal can be handed to an existing group and taken back
+    let foreground = tcgetpgrp();
+    assert_eq!(tcsetpgrp(100_000), -1);
+    assert_eq!(tcsetpgrp(child), 0);
+    assert_eq!(tcgetpgrp(), child as isize);
+    assert_eq!(tcsetpgrp(foreground as usize), 0);
+
+    write(pipe_fd[1], b"x");
+    close(pipe_fd[1]);
+    let mut exit_code = -1;
+    assert_eq!(waitpid(child, &mut exit_code), child as isize);
+    assert_eq!(exit_code, 1);
+    assert_eq!(getpgid(child), -1);
+    0
+}
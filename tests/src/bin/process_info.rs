@@ -0,0 +1,56 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{
+    process::{exit, fork, getpid, process_info, waitpid, ProcessState},
+    sync::sleep,
+    syscall::sys_process_info,
+};
+
+const CHILD_EXIT_CODE: i32 = 7;
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        exit(CHILD_EXIT_CODE);
+    }
+    // let the child run to its exit
+    sleep(10);
+
+    let infos = process_info();
+    assert!(infos.windows(2).all(|w| w[0].pid < w[1].pid));
+
+    let me = infos
+        .iter()
+        .find(|info| info.pid == getpid() as usize)
+        .expect("Current process missing!");
+    assert_eq!(me.state, ProcessState::Running);
+    assert_eq!(me.name(), "process_info");
+    assert!(infos.iter().any(|info| info.pid == me.ppid));
+
+    // a zombie is listed with its exit code until it is reaped
+    let child = infos
+        .iter()
+        .find(|info| info.pid == pid as usize)
+        .expect("Zombie child missing!");
+    assert_eq!(child.state, ProcessState::Zombie);
+    assert_eq!(child.exit_code, CHILD_EXIT_CODE);
+    assert_eq!(child.ppid, me.pid);
+    assert_eq!(child.name(), "process_info");
+
+    // a short array reports the number of processes
+    assert_eq!(
+        sys_process_info(core::ptr::null_mut(), 0),
+        -(infos.len() as isize)
+    );
+
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, CHILD_EXIT_CODE);
+    assert!(process_info().iter().all(|info| info.pid != pid as usize));
+
+    0
+}
@@ -0,0 +1,59 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::vec::Vec;
+use user_lib::fs::{close, lseek, open, read, unlink, write, OpenFlags, SEEK_SET};
+
+static TEST_FILE: &str = "/read_eof_test";
+const SIZE: usize = 1000;
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let data: Vec<u8> = (0..SIZE).map(|i| (i % 251) as u8).collect();
+    let fd = open(TEST_FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd >= 0);
+    assert_eq!(write(fd as usize, &data), SIZE as isize);
+    close(fd as usize);
+
+    // reading until `0` copies the whole file and ends there, as `cat` does
+    let fd = open(TEST_FILE, OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    let mut copy = Vec::new();
+    let mut buf = [0u8; 64];
+    loop {
+        let len = read(fd, &mut buf);
+        assert!(len >= 0);
+        if len == 0 {
+            break;
+        }
+        copy.extend_from_slice(&buf[..len as usize]);
+    }
+    assert!(copy == data);
+    // the end stays the end, nothing waits for more data
+    assert_eq!(read(fd, &mut buf), 0);
+    assert_eq!(read(fd, &mut buf), 0);
+
+    // a read running into the end returns what was left
+    assert_eq!(
+        lseek(fd, (SIZE - 10) as isize, SEEK_SET),
+        (SIZE - 10) as isize
+    );
+    assert_eq!(read(fd, &mut buf), 10);
+    assert_eq!(buf[..10], data[SIZE - 10..]);
+    // past the end, however far, there is nothing to read
+    assert_eq!(
+        lseek(fd, (SIZE + 10) as isize, SEEK_SET),
+        (SIZE + 10) as isize
+    );
+    assert_eq!(read(fd, &mut buf), 0);
+    assert_eq!(lseek(fd, isize::MAX, SEEK_SET), isize::MAX);
+    assert_eq!(read(fd, &mut buf), 0);
+
+    close(fd);
+    assert_eq!(unlink(TEST_FILE, 0), 0);
+    0
+}
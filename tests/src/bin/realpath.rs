@@ -0,0 +1,57 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::string::String;
+use user_lib::{
+    fs::{chdir, close, getcwd, mkdir, open, realpath, unlink, OpenFlags, AT_REMOVEDIR},
+    syscall::sys_realpath,
+};
+
+static TEST_DIR: &str = "/realpath_test_dir";
+static TEST_FILE: &str = "/realpath_test_dir/file";
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let mut cwd = String::new();
+    getcwd(&mut cwd);
+
+    assert_eq!(mkdir(TEST_DIR), 0);
+    let fd = open(TEST_FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd >= 0, "Open test file failed!");
+    close(fd as usize);
+
+    // absolute paths are normalized
+    let mut path = String::new();
+    let len = realpath(
+        "//realpath_test_dir/./../realpath_test_dir//file",
+        &mut path,
+    );
+    assert_eq!(len, TEST_FILE.len() as isize);
+    assert_eq!(path, TEST_FILE);
+    assert_eq!(realpath("/", &mut path), 1);
+    assert_eq!(path, "/");
+
+    // relative paths resolve against the working directory
+    assert_eq!(chdir(TEST_DIR), 0);
+    assert_eq!(realpath("file", &mut path), TEST_FILE.len() as isize);
+    assert_eq!(path, TEST_FILE);
+    assert_eq!(realpath("..", &mut path), 1);
+    assert_eq!(path, "/");
+    assert_eq!(realpath(".", &mut path), TEST_DIR.len() as isize);
+    assert_eq!(path, TEST_DIR);
+
+    // missing paths and short buffers
+    assert_eq!(realpath("nonexistent", &mut path), -1);
+    assert_eq!(path, TEST_DIR);
+    let mut short_buf = [0u8; 4];
+    assert_eq!(sys_realpath("file\0", &mut short_buf), -2);
+
+    assert_eq!(chdir(&cwd), 0);
+    unlink(TEST_FILE, 0);
+    unlink(TEST_DIR, AT_REMOVEDIR);
+
+    0
+}
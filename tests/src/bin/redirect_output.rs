@@ -0,0 +1,48 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec;
+use user_lib::{
+    fs::{close, isatty, open, read, redirect_output, unlink, write, OpenFlags},
+    process::{exit, fork, waitpid},
+};
+
+static TEST_FILE: &str = "/redirect_output_test";
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let fd = open(TEST_FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd >= 0);
+    let reader = open(TEST_FILE, OpenFlags::RDONLY);
+    assert!(reader >= 0);
+    assert_eq!(redirect_output(42), -1);
+    assert_eq!(redirect_output(reader), -1);
+
+    // both descriptors follow, and so does a child forked afterwards
+    assert_eq!(redirect_output(fd), 0);
+    close(fd as usize);
+    println!("parent");
+    assert_eq!(write(2, b"error\n"), 6);
+    let pid = fork();
+    if pid == 0 {
+        println!("child");
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert!(!isatty(1) && !isatty(2));
+
+    assert_eq!(redirect_output(-1), 0);
+    assert!(isatty(1) && isatty(2));
+    let mut buf = vec![0u8; 64];
+    let len = read(reader as usize, &mut buf);
+    assert_eq!(&buf[..len as usize], b"parent\nerror\nchild\n");
+    close(reader as usize);
+    assert_eq!(unlink(TEST_FILE, 0), 0);
+    0
+}
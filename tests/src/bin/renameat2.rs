@@ -0,0 +1,106 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::fs::{
+    close, mkdir, open, read, renameat2, unlink, write, OpenFlags, AT_FDCWD, AT_REMOVEDIR,
+    RENAME_EXCHANGE, RENAME_NOREPLACE,
+};
+
+static DIR_A: &str = "/renameat2_a";
+static DIR_B: &str = "/renameat2_b";
+
+fn create(path: &str, content: &[u8]) {
+    let fd = open(path, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd >= 0);
+    assert_eq!(write(fd as usize, content), content.len() as isize);
+    close(fd as usize);
+}
+
+/// The contents of the file at `path`, at most 16 bytes of them
+fn contents(path: &str, buf: &mut [u8; 16]) -> usize {
+    let fd = open(path, OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let len = read(fd as usize, buf);
+    close(fd as usize);
+    len as usize
+}
+
+fn rename2(oldpath: &str, newpath: &str, flags: u32) -> isize {
+    renameat2(AT_FDCWD, oldpath, AT_FDCWD, newpath, flags)
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let mut buf = [0u8; 16];
+    assert_eq!(mkdir(DIR_A), 0);
+    assert_eq!(mkdir(DIR_B), 0);
+    create("/renameat2_a/old", b"old");
+    create("/renameat2_a/new", b"new");
+    create("/renameat2_b/far", b"far");
+
+    assert_eq!(rename2("/renameat2_a/old", "/renameat2_a/new", 4), -22);
+    let both = RENAME_NOREPLACE | RENAME_EXCHANGE;
+    assert_eq!(rename2("/renameat2_a/old", "/renameat2_a/new", both), -22);
+
+    // refusing to replace has its own error, and moves to free names as usual
+    assert_eq!(
+        rename2("/renameat2_a/old", "/renameat2_a/new", RENAME_NOREPLACE),
+        -17
+    );
+    assert_eq!(
+        rename2("/renameat2_a/old", "/renameat2_b/far", RENAME_NOREPLACE),
+        -17
+    );
+    assert_eq!(rename2("/renameat2_a/old", "/renameat2_a/new", 0), -2);
+    assert_eq!(
+        rename2("/renameat2_a/old", "/renameat2_a/moved", RENAME_NOREPLACE),
+        0
+    );
+    assert_eq!(
+        rename2("/renameat2_a/moved", "/renameat2_b/old", RENAME_NOREPLACE),
+        0
+    );
+    assert_eq!(contents("/renameat2_b/old", &mut buf), 3);
+    assert_eq!(&buf[..3], b"old");
+
+    // exchanging within one directory swaps the contents behind the names
+    assert_eq!(
+        rename2("/renameat2_b/old", "/renameat2_b/far", RENAME_EXCHANGE),
+        0
+    );
+    assert_eq!(contents("/renameat2_b/old", &mut buf), 3);
+    assert_eq!(&buf[..3], b"far");
+    assert_eq!(contents("/renameat2_b/far", &mut buf), 3);
+    assert_eq!(&buf[..3], b"old");
+    // and across directories too
+    assert_eq!(
+        rename2("/renameat2_a/new", "/renameat2_b/far", RENAME_EXCHANGE),
+        0
+    );
+    assert_eq!(contents("/renameat2_a/new", &mut buf), 3);
+    assert_eq!(&buf[..3], b"old");
+    assert_eq!(contents("/renameat2_b/far", &mut buf), 3);
+    assert_eq!(&buf[..3], b"new");
+    // both sides have to exist
+    assert_eq!(
+        rename2("/renameat2_a/new", "/renameat2_b/missing", RENAME_EXCHANGE),
+        -1
+    );
+
+    // a swapped directory takes its contents along, but never goes below itself
+    assert_eq!(rename2(DIR_A, "/renameat2_b/far", RENAME_EXCHANGE), 0);
+    assert_eq!(contents("/renameat2_b/far/new", &mut buf), 3);
+    assert_eq!(contents(DIR_A, &mut buf), 3);
+    assert_eq!(&buf[..3], b"new");
+    assert_eq!(rename2(DIR_B, "/renameat2_b/far/new", RENAME_EXCHANGE), -3);
+    assert_eq!(rename2("/renameat2_b/far/new", DIR_B, RENAME_EXCHANGE), -3);
+
+    assert_eq!(unlink("/renameat2_b/far/new", 0), 0);
+    assert_eq!(unlink("/renameat2_b/far", AT_REMOVEDIR), 0);
+    assert_eq!(unlink("/renameat2_b/old", 0), 0);
+    assert_eq!(unlink(DIR_B, AT_REMOVEDIR), 0);
+    assert_eq!(unlink(DIR_A, 0), 0);
+    0
+}
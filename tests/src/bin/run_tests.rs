@@ -15,6 +15,9 @@ static TESTS: &[(&str, &[&str], i32)] = &[
         0,
     ),
     ("condsync_condvar", &["condsync_condvar"], 0),
+    ("semaphore_pool", &["semaphore_pool"], 0),
+    ("eventfd", &["eventfd"], 0),
+    ("fcntl", &["fcntl"], 0),
     ("file", &["file"], 0),
     ("fork", &["fork"], 0),
     ("fork_sleep", &["fork_sleep"], 0),
@@ -29,9 +32,59 @@ static TESTS: &[(&str, &[&str], i32)] = &[
     ("stack_overflow", &["stack_overflow"], -11),
     ("store_fault", &["store_fault"], -11),
     ("exit", &["exit"], 0),
+    ("exec_invalid", &["exec_invalid"], 0),
     ("huge_write", &["huge_write"], 0),
+    ("madvise", &["madvise"], 0),
+    ("process_info", &["process_info"], 0),
     ("pipe", &["pipe"], 0),
     ("pipe_large", &["pipe_large"], 0),
+    ("ppoll", &["ppoll"], 0),
+    ("proc_interrupts", &["proc_interrupts"], 0),
+    ("proc_fd", &["proc_fd"], 0),
+    ("realpath", &["realpath"], 0),
+    ("at_syscalls", &["at_syscalls"], 0),
+    ("affinity", &["affinity"], 0),
+    ("orphan_reaping", &["orphan_reaping"], 0),
+    ("sysinfo", &["sysinfo"], 0),
+    ("buffered_io", &["buffered_io"], 0),
+    ("isatty", &["isatty"], 0),
+    ("sigchld", &["sigchld"], 0),
+    ("sigsuspend", &["sigsuspend"], 0),
+    ("tgkill", &["tgkill"], 0),
+    ("spawn", &["spawn"], 0),
+    ("command", &["command"], 0),
+    ("close_range", &["close_range"], 0),
+    ("vfork", &["vfork"], 0),
+    ("pidfd", &["pidfd"], 0),
+    ("wait4", &["wait4"], 0),
+    ("process_group", &["process_group"], 0),
+    ("bad_pointer", &["bad_pointer"], 0),
+    ("path_max", &["path_max"], 0),
+    ("write_on_exit", &["write_on_exit"], 0),
+    ("clone", &["clone"], 0),
+    ("tick_page", &["tick_page"], 0),
+    ("getdents", &["getdents"], 0),
+    ("flush_large", &["flush_large"], 0),
+    ("prctl", &["prctl"], 0),
+    ("open_path", &["open_path"], 0),
+    ("memfd", &["memfd"], 0),
+    ("getcwd_long", &["getcwd_long"], 0),
+    ("small_append", &["small_append"], 0),
+    ("sync_file_range", &["sync_file_range"], 0),
+    ("fdatasync", &["fdatasync"], 0),
+    ("sendfile", &["sendfile"], 0),
+    ("tee", &["tee"], 0),
+    ("truncate", &["truncate"], 0),
+    ("xattr", &["xattr"], 0),
+    ("statfs", &["statfs"], 0),
+    ("fincore", &["fincore"], 0),
+    ("append_race", &["append_race"], 0),
+    ("renameat2", &["renameat2"], 0),
+    ("copy_file_range", &["copy_file_range"], 0),
+    ("redirect_output", &["redirect_output"], 0),
+    ("name_limit", &["name_limit"], 0),
+    ("dirent_limit", &["dirent_limit"], 0),
+    ("read_eof", &["read_eof"], 0),
     (
         "process_timeout",
         &["process_timeout", "2000", "/tests/loop_infinity"],
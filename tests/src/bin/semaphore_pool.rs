@@ -0,0 +1,90 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+extern crate alloc;
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use user_lib::{
+    process::{exit, yield_},
+    sync::{
+        mutex_blocking_create, mutex_lock, mutex_unlock, semaphore_available, semaphore_create,
+        semaphore_down, semaphore_try_down, semaphore_up,
+    },
+    thread::{thread_create, waittid},
+};
+
+const QUEUE_SIZE: usize = 4;
+const JOB_COUNT: usize = 100;
+const WORKER_COUNT: usize = 3;
+
+const JOBS_ID: usize = 0;
+const SLOTS_ID: usize = 1;
+const MUTEX_ID: usize = 0;
+
+/// A bounded job queue, `JOBS_ID` counts queued jobs and `SLOTS_ID` free slots
+static QUEUE: [AtomicUsize; QUEUE_SIZE] = [const { AtomicUsize::new(0) }; QUEUE_SIZE];
+static HEAD: AtomicUsize = AtomicUsize::new(0);
+static DONE: AtomicBool = AtomicBool::new(false);
+static SUM: AtomicUsize = AtomicUsize::new(0);
+static TAKEN: [AtomicUsize; WORKER_COUNT] = [const { AtomicUsize::new(0) }; WORKER_COUNT];
+
+fn worker(id: usize) -> ! {
+    loop {
+        if semaphore_try_down(JOBS_ID) {
+            mutex_lock(MUTEX_ID);
+            let head = HEAD.fetch_add(1, Ordering::Relaxed);
+            let job = QUEUE[head % QUEUE_SIZE].load(Ordering::Relaxed);
+            mutex_unlock(MUTEX_ID);
+            semaphore_up(SLOTS_ID);
+
+            SUM.fetch_add(job, Ordering::Relaxed);
+            TAKEN[id].fetch_add(1, Ordering::Relaxed);
+        } else if DONE.load(Ordering::Acquire) {
+            break;
+        } else {
+            yield_();
+        }
+    }
+    exit(0)
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    assert_eq!(semaphore_create(0) as usize, JOBS_ID);
+    assert_eq!(semaphore_create(QUEUE_SIZE) as usize, SLOTS_ID);
+    assert_eq!(mutex_blocking_create() as usize, MUTEX_ID);
+
+    // an empty semaphore is left untouched
+    assert!(!semaphore_try_down(JOBS_ID));
+    assert_eq!(semaphore_available(JOBS_ID), 0);
+    assert_eq!(semaphore_available(SLOTS_ID), QUEUE_SIZE as isize);
+    assert_eq!(semaphore_available(42), -1);
+
+    let threads: [isize; WORKER_COUNT] =
+        core::array::from_fn(|id| thread_create(worker as usize, id));
+
+    for (tail, job) in (1..=JOB_COUNT).enumerate() {
+        semaphore_down(SLOTS_ID);
+        QUEUE[tail % QUEUE_SIZE].store(job, Ordering::Relaxed);
+        semaphore_up(JOBS_ID);
+        assert!(semaphore_available(JOBS_ID) <= QUEUE_SIZE as isize);
+    }
+    DONE.store(true, Ordering::Release);
+
+    for thread in threads {
+        waittid(thread as usize);
+    }
+
+    assert_eq!(SUM.load(Ordering::Relaxed), JOB_COUNT * (JOB_COUNT + 1) / 2);
+    let taken: usize = TAKEN.iter().map(|t| t.load(Ordering::Relaxed)).sum();
+    assert_eq!(taken, JOB_COUNT);
+    assert_eq!(semaphore_available(JOBS_ID), 0);
+    assert_eq!(semaphore_available(SLOTS_ID), QUEUE_SIZE as isize);
+
+    println!("semaphore_pool passed!");
+    0
+}
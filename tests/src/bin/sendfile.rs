@@ -0,0 +1,91 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::{vec, vec::Vec};
+use user_lib::{
+    fs::{close, fstat, open, pipe, read, sendfile, unlink, write, OpenFlags, Stat},
+    syscall::sys_sendfile,
+};
+
+static SRC_FILE: &str = "sendfile_test_src";
+static DST_FILE: &str = "sendfile_test_dst";
+/// Larger than the kernel's copy chunk, so the copy takes several rounds
+const SRC_SIZE: usize = 10000;
+
+fn offset_of(fd: usize) -> usize {
+    let mut stat = Stat::new();
+    assert_eq!(fstat(fd, &mut stat), 0);
+    stat.off
+}
+
+fn read_file(path: &str) -> Vec<u8> {
+    let fd = open(path, OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let mut data = vec![0u8; 2 * SRC_SIZE];
+    let len = read(fd as usize, &mut data);
+    assert!(len >= 0);
+    close(fd as usize);
+    data.truncate(len as usize);
+    data
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let content: Vec<u8> = (0..SRC_SIZE).map(|i| (i % 251) as u8).collect();
+    let fd = open(SRC_FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd >= 0);
+    assert_eq!(write(fd as usize, &content), SRC_SIZE as isize);
+    close(fd as usize);
+
+    let src = open(SRC_FILE, OpenFlags::RDONLY) as usize;
+    let dst = open(DST_FILE, OpenFlags::CREATE | OpenFlags::WRONLY) as usize;
+
+    // without an offset the copy uses and advances the offset of the input,
+    // stopping at its end
+    assert_eq!(sendfile(dst, src, None, 2 * SRC_SIZE), SRC_SIZE as isize);
+    assert_eq!(offset_of(src), SRC_SIZE);
+    assert_eq!(sendfile(dst, src, None, 100), 0);
+    close(dst);
+    assert_eq!(read_file(DST_FILE), content);
+
+    // an explicit offset is advanced instead, a copy near the end is partial
+    let dst = open(DST_FILE, OpenFlags::WRONLY | OpenFlags::APPEND) as usize;
+    let mut offset = SRC_SIZE - 1000;
+    assert_eq!(sendfile(dst, src, Some(&mut offset), 5000), 1000);
+    assert_eq!(offset, SRC_SIZE);
+    assert_eq!(offset_of(src), SRC_SIZE);
+    close(dst);
+    // the output was opened with `APPEND`, so the copy went to its end
+    let data = read_file(DST_FILE);
+    assert_eq!(data.len(), SRC_SIZE + 1000);
+    assert_eq!(data[SRC_SIZE..], content[SRC_SIZE - 1000..]);
+
+    // non-regular files go through a kernel buffer
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let mut offset = 10;
+    assert_eq!(sendfile(pipe_fd[1], src, Some(&mut offset), 100), 100);
+    assert_eq!(offset, 110);
+    let mut buffer = [0u8; 100];
+    assert_eq!(read(pipe_fd[0], &mut buffer), 100);
+    assert_eq!(buffer[..], content[10..110]);
+
+    // a pipe has no offset to read from, and the ends must have the right access mode
+    assert_eq!(
+        sys_sendfile(pipe_fd[1], pipe_fd[0], core::ptr::from_mut(&mut offset), 10),
+        -1
+    );
+    assert_eq!(sendfile(src, src, None, 10), -1);
+    assert_eq!(sendfile(pipe_fd[1], 42, None, 10), -1);
+
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+    close(src);
+    unlink(SRC_FILE, 0);
+    unlink(DST_FILE, 0);
+
+    0
+}
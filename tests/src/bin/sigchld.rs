@@ -0,0 +1,69 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{
+    fs::{pipe, poll, ppoll, PollEvents, PollFd},
+    process::{exit, fork, waitpid_nb},
+    signal::SignalFlags,
+    sync::sleep,
+};
+
+const CHILDREN: usize = 3;
+
+fn spawn(delay_ms: usize) -> usize {
+    let pid = fork();
+    if pid == 0 {
+        sleep(delay_ms);
+        exit(7);
+    }
+    pid as usize
+}
+
+/// Reaps every exited child without blocking, returning how many there were
+fn reap_all() -> usize {
+    let mut reaped = 0;
+    let mut exit_code = 0;
+    while waitpid_nb(usize::MAX, &mut exit_code) > 0 {
+        assert_eq!(exit_code, 7);
+        reaped += 1;
+    }
+    reaped
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    // a pipe nobody writes to, so only a signal ends the wait
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let mut fds = [PollFd::new(pipe_fd[0], PollEvents::IN)];
+
+    // SIGCHLD is ignored by default, the parent survives its children and a plain poll is
+    // not interrupted
+    for _ in 0..CHILDREN {
+        spawn(0);
+    }
+    sleep(50);
+    assert_eq!(poll(&mut fds, 20), 0);
+
+    // the exits are coalesced into one pending signal, yet every child is reaped
+    assert_eq!(ppoll(&mut fds, -1, SignalFlags::empty()), -2);
+    assert_eq!(reap_all(), CHILDREN);
+    // nothing is left to wait for, so the signal is no longer pending
+    assert_eq!(ppoll(&mut fds, 0, SignalFlags::empty()), 0);
+
+    // a reaper waiting for the signal wakes up when a child exits
+    let pid = spawn(30);
+    assert_eq!(ppoll(&mut fds, -1, SignalFlags::empty()), -2);
+    let mut exit_code = 0;
+    assert_eq!(waitpid_nb(pid, &mut exit_code), pid as isize);
+    assert_eq!(ppoll(&mut fds, 0, SignalFlags::empty()), 0);
+
+    // blocking the signal keeps the wait going
+    spawn(0);
+    sleep(20);
+    assert_eq!(ppoll(&mut fds, 20, SignalFlags::SIGCHLD), 0);
+    assert_eq!(reap_all(), 1);
+    0
+}
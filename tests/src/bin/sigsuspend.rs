@@ -0,0 +1,79 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{
+    process::{exit, fork, getpid, waitpid, waitpid_nb},
+    signal::{kill, sigpending, sigsuspend, SignalFlags},
+    sync::sleep,
+    syscall::sys_sigsuspend,
+    thread::thread_create,
+};
+
+fn spawn(delay_ms: usize) -> usize {
+    let pid = fork();
+    if pid == 0 {
+        sleep(delay_ms);
+        exit(7);
+    }
+    pid as usize
+}
+
+fn reap(pid: usize) {
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), pid as isize);
+    assert_eq!(exit_code, 7);
+}
+
+/// Waits with `SIGCHLD` blocked, only `SIGINT` can end it
+fn suspended() -> ! {
+    sigsuspend(SignalFlags::SIGCHLD);
+    exit(0)
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    assert!(sigpending().is_empty());
+    let unknown = 1 << 30;
+    assert_eq!(sys_sigsuspend(core::ptr::from_ref(&unknown)), -1);
+
+    // a pending signal that is not blocked ends the wait at once, and is not reported
+    let pid = spawn(0);
+    sleep(20);
+    assert!(sigpending().is_empty());
+    assert_eq!(sigsuspend(SignalFlags::empty()), -2);
+    reap(pid);
+
+    // a child exiting later wakes the wait up
+    let pid = spawn(30);
+    assert_eq!(sigsuspend(SignalFlags::SIGINT), -2);
+    let mut exit_code = 0;
+    assert_eq!(waitpid_nb(pid, &mut exit_code), pid as isize);
+
+    // the mask is restored afterwards, a child inheriting it is not shielded from SIGINT
+    let pid = fork();
+    if pid == 0 {
+        kill(getpid() as usize, SignalFlags::SIGINT.bits());
+        exit(0);
+    }
+    waitpid(pid as usize, &mut exit_code);
+    assert_eq!(exit_code, -2);
+
+    // a thread suspended with SIGCHLD blocked leaves it pending, until SIGINT ends the process
+    let pid = fork();
+    if pid == 0 {
+        thread_create(suspended as usize, 0);
+        sleep(20);
+        spawn(0);
+        sleep(30);
+        if sigpending() != SignalFlags::SIGCHLD {
+            exit(1);
+        }
+        kill(getpid() as usize, SignalFlags::SIGINT.bits());
+        exit(0);
+    }
+    waitpid(pid as usize, &mut exit_code);
+    assert_eq!(exit_code, -2);
+    0
+}
@@ -0,0 +1,90 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::{format, string::String, vec, vec::Vec};
+use user_lib::{
+    fs::{close, fstat, fsync, open, read, unlink, write, OpenFlags, Stat},
+    process::{exit, fork, waitpid},
+};
+
+static TEST_FILE: &str = "small_append_test";
+const LINES: usize = 200;
+
+fn file_size(fd: usize) -> usize {
+    let mut stat = Stat::new();
+    assert_eq!(fstat(fd, &mut stat), 0);
+    stat.size as usize
+}
+
+fn read_file(path: &str) -> Vec<u8> {
+    let fd = open(path, OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let mut data = vec![0u8; 4096];
+    let len = read(fd as usize, &mut data);
+    assert!(len >= 0);
+    close(fd as usize);
+    data.truncate(len as usize);
+    data
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let fd = open(TEST_FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd >= 0, "Open test file failed!");
+    let fd = fd as usize;
+    let reader = open(TEST_FILE, OpenFlags::RDONLY);
+    assert!(reader >= 0);
+    let reader = reader as usize;
+
+    // every small append is visible to another reader of the file at once
+    let mut expected = String::new();
+    let mut buf = [0u8; 16];
+    for i in 0..LINES {
+        let line = format!("log {i:03}\n");
+        assert_eq!(write(fd, line.as_bytes()), line.len() as isize);
+        expected.push_str(&line);
+        assert_eq!(file_size(fd), expected.len());
+        assert_eq!(read(reader, &mut buf), line.len() as isize);
+        assert_eq!(&buf[..line.len()], line.as_bytes());
+    }
+    assert_eq!(read_file(TEST_FILE), expected.as_bytes());
+
+    // a write elsewhere in the file is ordered after the buffered appends
+    assert_eq!(write(fd, b"tail"), 4);
+    expected.push_str("tail");
+    let other_fd = open(TEST_FILE, OpenFlags::WRONLY);
+    assert!(other_fd >= 0);
+    assert_eq!(write(other_fd as usize, b"LOG"), 3);
+    close(other_fd as usize);
+    expected.replace_range(..3, "LOG");
+    assert_eq!(read_file(TEST_FILE), expected.as_bytes());
+
+    assert_eq!(write(fd, b"!"), 1);
+    expected.push('!');
+    assert_eq!(fsync(fd), 0);
+    assert_eq!(fsync(usize::MAX), -1);
+    close(reader);
+    close(fd);
+    assert_eq!(read_file(TEST_FILE), expected.as_bytes());
+
+    // appends are not lost when a process exits without closing the file
+    let pid = fork();
+    if pid == 0 {
+        let fd = open(TEST_FILE, OpenFlags::WRONLY | OpenFlags::APPEND);
+        assert!(fd >= 0);
+        assert_eq!(write(fd as usize, b"child"), 5);
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    expected.push_str("child");
+    assert_eq!(read_file(TEST_FILE), expected.as_bytes());
+
+    unlink(TEST_FILE, 0);
+
+    0
+}
@@ -0,0 +1,60 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::format;
+use user_lib::{
+    fs::{close, fcntl, open, pipe, read, write, OpenFlags, FD_CLOEXEC, F_SETFD},
+    process::{spawn, waitpid},
+};
+
+/// Runs in the spawned child, which is handed one descriptor to keep and one closed on exec
+fn child(kept: usize, cloexec: usize) -> i32 {
+    if write(cloexec, b"leaked") != -1 {
+        return 1;
+    }
+    if write(kept, b"inherited") != 9 {
+        return 2;
+    }
+    0
+}
+
+#[no_mangle]
+pub extern "Rust" fn main(_argc: usize, argv: &[&str]) -> i32 {
+    if argv.get(1) == Some(&"child") {
+        return child(argv[2].parse().unwrap(), argv[3].parse().unwrap());
+    }
+
+    // nothing is created for a program that cannot be loaded
+    assert_eq!(spawn("/tests/missing", &["missing"]), -1);
+    assert_eq!(spawn("/tests", &["tests"]), -2);
+
+    let mut kept = [0usize; 2];
+    let mut cloexec = [0usize; 2];
+    assert_eq!(pipe(&mut kept), 0);
+    assert_eq!(pipe(&mut cloexec), 0);
+    assert_eq!(fcntl(cloexec[1], F_SETFD, FD_CLOEXEC), 0);
+
+    let kept_fd = format!("{}", kept[1]);
+    let cloexec_fd = format!("{}", cloexec[1]);
+    let args = ["spawn", "child", kept_fd.as_str(), cloexec_fd.as_str()];
+    let pid = spawn("/tests/spawn", &args);
+    assert!(pid > 0);
+
+    // the child is not reaped yet, so its cmdline stays readable
+    let fd = open(&format!("/proc/{pid}/cmdline"), OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let mut buffer = [0u8; 64];
+    let len = read(fd as usize, &mut buffer);
+    close(fd as usize);
+    assert_eq!(&buffer[..len as usize], args.join(" ").as_bytes());
+
+    let mut exit_code = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert_eq!(read(kept[0], &mut buffer), 9);
+    assert_eq!(&buffer[..9], b"inherited");
+    0
+}
@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::fs::{close, fstatfs, fsync, open, pipe, statfs, unlink, write, OpenFlags, Statfs};
+
+static TEST_FILE: &str = "/statfs_test_file";
+
+fn stats_of(fd: usize) -> Statfs {
+    let mut stats = Statfs::default();
+    assert_eq!(fstatfs(fd, &mut stats), 0);
+    stats
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let mut before = Statfs::default();
+    assert_eq!(statfs("/", &mut before), 0);
+    assert_eq!(before.block_size, 512);
+    assert_eq!(before.name_max, 27);
+    assert!(before.free_blocks <= before.blocks);
+    assert!(before.free_files < before.files);
+
+    // a directory descriptor reports the same file system as its path
+    let root = open("/", OpenFlags::RDONLY);
+    assert!(root >= 0);
+    assert_eq!(stats_of(root as usize), before);
+    close(root as usize);
+
+    let fd = open(TEST_FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    assert_eq!(write(fd, &[b'x'; 4 * 512]), 4 * 512);
+    assert_eq!(fsync(fd), 0);
+    let used = stats_of(fd);
+    assert_eq!(used.free_files, before.free_files - 1);
+    assert!(used.free_blocks <= before.free_blocks - 4);
+    let mut by_path = Statfs::default();
+    assert_eq!(statfs(TEST_FILE, &mut by_path), 0);
+    assert_eq!(by_path, used);
+    close(fd);
+
+    // pipes live on no file system
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let mut stats = Statfs::default();
+    assert_eq!(fstatfs(pipe_fd[0], &mut stats), -1);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+    assert_eq!(fstatfs(99, &mut stats), -1);
+    assert_eq!(statfs("/statfs_missing", &mut stats), -1);
+
+    assert_eq!(unlink(TEST_FILE, 0), 0);
+    assert_eq!(statfs("/", &mut stats), 0);
+    assert_eq!(stats.free_files, before.free_files);
+    0
+}
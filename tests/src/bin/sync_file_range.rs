@@ -0,0 +1,71 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::{string::String, vec};
+use user_lib::fs::{
+    close, open, pipe, read, sync_file_range, unlink, write, OpenFlags, SYNC_FILE_RANGE_WAIT_AFTER,
+    SYNC_FILE_RANGE_WAIT_BEFORE, SYNC_FILE_RANGE_WRITE,
+};
+
+static TEST_FILE: &str = "/sync_file_range_test";
+
+/// Bytes written to the block device so far, from `/proc/interrupts`
+fn block_written() -> usize {
+    let fd = open("/proc/interrupts", OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let mut buf = vec![0u8; 1024];
+    let len = read(fd as usize, &mut buf);
+    close(fd as usize);
+    buf.truncate(len as usize);
+    let table = String::from_utf8(buf).unwrap();
+    let row = table
+        .lines()
+        .find(|line| line.starts_with("block"))
+        .unwrap();
+    row.split_whitespace().nth(3).unwrap().parse().unwrap()
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let fd = open(TEST_FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    let sync_wait = SYNC_FILE_RANGE_WRITE | SYNC_FILE_RANGE_WAIT_AFTER;
+
+    // a small append stays buffered until its block is written back
+    assert_eq!(write(fd, &[b'x'; 100]), 100);
+    let written = block_written();
+    // waiting alone writes nothing
+    assert_eq!(
+        sync_file_range(
+            fd,
+            0,
+            0,
+            SYNC_FILE_RANGE_WAIT_BEFORE | SYNC_FILE_RANGE_WAIT_AFTER
+        ),
+        0
+    );
+    // neither does a range past the buffered data, even once rounded out to blocks
+    assert_eq!(sync_file_range(fd, 600, 100, sync_wait), 0);
+    assert_eq!(block_written(), written);
+    // a range within the same block as the data does
+    assert_eq!(sync_file_range(fd, 200, 1, SYNC_FILE_RANGE_WRITE), 0);
+    assert!(block_written() > written);
+
+    assert_eq!(sync_file_range(fd, 0, 0, 8), -1);
+    assert_eq!(sync_file_range(fd, usize::MAX, 2, sync_wait), -1);
+    assert_eq!(sync_file_range(99, 0, 0, sync_wait), -1);
+    // other files have nothing to write back
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(sync_file_range(pipe_fd[1], 0, 0, sync_wait), 0);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+
+    close(fd);
+    assert_eq!(unlink(TEST_FILE, 0), 0);
+    0
+}
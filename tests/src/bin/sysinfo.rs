@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::vec;
+use user_lib::{
+    fs::{close, open, pipe, read, unlink, write, OpenFlags},
+    process::{exit, fork, sysinfo, waitpid},
+};
+
+static TEST_FILE: &str = "sysinfo_test";
+const FILE_SIZE: usize = 16 * 512;
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let before = sysinfo();
+    assert!(before.uptime_ms > 0);
+    assert!(before.free_ram > 0 && before.free_ram <= before.total_ram);
+    assert!(before.processes > 0);
+    assert!(before.fs_free > 0 && before.fs_free <= before.fs_total);
+
+    // a child blocked on a pipe counts as a live process
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let pid = fork();
+    if pid == 0 {
+        close(pipe_fd[1]);
+        let mut buf = [0u8; 1];
+        read(pipe_fd[0], &mut buf);
+        exit(0);
+    }
+    close(pipe_fd[0]);
+    assert_eq!(sysinfo().processes, before.processes + 1);
+    write(pipe_fd[1], b"x");
+    close(pipe_fd[1]);
+    let mut exit_code = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(sysinfo().processes, before.processes);
+
+    // writing a file takes disk space, removing it gives it back
+    let fd = open(TEST_FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd >= 0);
+    assert_eq!(
+        write(fd as usize, &vec![0x5a; FILE_SIZE]),
+        FILE_SIZE as isize
+    );
+    close(fd as usize);
+    let written = sysinfo();
+    assert!(written.fs_free + FILE_SIZE <= before.fs_free);
+    assert_eq!(written.fs_total, before.fs_total);
+    assert_eq!(unlink(TEST_FILE, 0), 0);
+    assert!(sysinfo().fs_free >= written.fs_free + FILE_SIZE);
+
+    assert!(sysinfo().uptime_ms >= before.uptime_ms);
+    0
+}
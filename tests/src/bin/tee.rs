@@ -0,0 +1,64 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::fs::{close, fcntl, pipe, read, tee, write, OpenFlags, F_SETFL};
+
+fn read_str(fd: usize, buf: &mut [u8]) -> &str {
+    let len = read(fd, buf);
+    assert!(len >= 0);
+    core::str::from_utf8(&buf[..len as usize]).unwrap()
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let mut input = [0usize; 2];
+    let mut output = [0usize; 2];
+    assert_eq!(pipe(&mut input), 0);
+    assert_eq!(pipe(&mut output), 0);
+    let mut buf = [0u8; 64];
+
+    // only the data waiting in the input is copied, and it stays there
+    assert_eq!(write(input[1], b"hello"), 5);
+    assert_eq!(tee(input[0], output[1], 64), 5);
+    assert_eq!(read_str(output[0], &mut buf), "hello");
+    assert_eq!(read_str(input[0], &mut buf), "hello");
+
+    // no more than asked for
+    assert_eq!(write(input[1], b"abcdef"), 6);
+    assert_eq!(tee(input[0], output[1], 3), 3);
+    assert_eq!(read_str(output[0], &mut buf), "abc");
+    assert_eq!(read_str(input[0], &mut buf), "abcdef");
+    assert_eq!(tee(input[0], output[1], 0), 0);
+
+    // only the read end of one pipe to the write end of another
+    assert_eq!(tee(input[0], input[1], 1), -1);
+    assert_eq!(tee(input[1], output[1], 1), -1);
+    assert_eq!(tee(input[0], output[0], 1), -1);
+    assert_eq!(tee(0, output[1], 1), -1);
+
+    // non-blocking descriptors do not wait for data or room
+    assert_eq!(
+        fcntl(input[0], F_SETFL, OpenFlags::NONBLOCK.bits() as usize),
+        0
+    );
+    assert_eq!(tee(input[0], output[1], 1), -2);
+    assert_eq!(
+        fcntl(output[1], F_SETFL, OpenFlags::NONBLOCK.bits() as usize),
+        0
+    );
+    while write(output[1], &buf[..16]) > 0 {}
+    assert_eq!(write(input[1], b"x"), 1);
+    assert_eq!(tee(input[0], output[1], 1), -2);
+    assert_eq!(read_str(input[0], &mut buf), "x");
+
+    // an empty input without writers is at its end
+    close(input[1]);
+    assert_eq!(tee(input[0], output[1], 1), 0);
+
+    close(input[0]);
+    close(output[0]);
+    close(output[1]);
+    0
+}
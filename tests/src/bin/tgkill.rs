@@ -0,0 +1,90 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{
+    process::{exit, fork, getpid, waitpid, waitpid_nb, yield_},
+    signal::{sigpending, sigsuspend, tgkill, SignalFlags},
+    sync::sleep,
+    thread::{gettid, thread_create, waittid},
+};
+
+pub fn spinner(_arg: usize) -> ! {
+    loop {
+        yield_();
+    }
+}
+
+/// Waits with the signals in `mask` blocked, only another signal can end it
+pub fn suspended(mask: usize) -> ! {
+    sigsuspend(SignalFlags::from_bits_truncate(mask as i32));
+    exit(0)
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let pid = getpid() as usize;
+    let abort = SignalFlags::SIGABRT.bits();
+    let tid = thread_create(spinner as usize, 0) as usize;
+    assert_eq!(tgkill(pid, tid + 1, abort), -1);
+    assert_eq!(tgkill(pid, tid, 1 << 30), -1);
+    assert_eq!(tgkill(0xdead, tid, abort), -1);
+
+    // a fatal signal ends the thread it was sent to and no other
+    assert_eq!(tgkill(pid, tid, abort), 0);
+    assert_eq!(waittid(tid), -6);
+    assert_eq!(tgkill(pid, tid, abort), -1);
+    // one ignored by default is dropped
+    assert_eq!(
+        tgkill(pid, gettid() as usize, SignalFlags::SIGCHLD.bits()),
+        0
+    );
+    assert!(sigpending().is_empty());
+
+    // the mask of the process blocks it too, and the main thread takes the process along
+    let child = fork();
+    if child == 0 {
+        let pid = getpid() as usize;
+        let tid = thread_create(suspended as usize, SignalFlags::SIGINT.bits() as usize) as usize;
+        sleep(20);
+        assert_eq!(tgkill(pid, 0, SignalFlags::SIGINT.bits()), 0);
+        if sigpending() != SignalFlags::SIGINT {
+            exit(1);
+        }
+        // restoring the mask once woken up delivers the signal of the main thread
+        assert_eq!(tgkill(pid, tid, abort), 0);
+        waittid(tid);
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(child as usize, &mut exit_code), child);
+    assert_eq!(exit_code, -2);
+
+    // one ignored by default but blocked stays pending, and is taken once delivered
+    let child = fork();
+    if child == 0 {
+        let pid = getpid() as usize;
+        let chld = SignalFlags::SIGCHLD.bits();
+        let tid = thread_create(suspended as usize, chld as usize) as usize;
+        sleep(20);
+        assert_eq!(tgkill(pid, gettid() as usize, chld), 0);
+        assert!(sigpending() == SignalFlags::SIGCHLD);
+        assert_eq!(tgkill(pid, tid, abort), 0);
+        assert_eq!(waittid(tid), -6);
+        // so each wait lasts until a child exits rather than ending at once
+        for _ in 0..2 {
+            let grandchild = fork();
+            if grandchild == 0 {
+                sleep(30);
+                exit(7);
+            }
+            assert_eq!(sigsuspend(SignalFlags::empty()), -2);
+            assert_eq!(waitpid_nb(grandchild as usize, &mut exit_code), grandchild);
+        }
+        exit(0);
+    }
+    assert_eq!(waitpid(child as usize, &mut exit_code), child);
+    assert_eq!(exit_code, 0);
+    0
+}
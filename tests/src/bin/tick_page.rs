@@ -0,0 +1,49 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::process::{exit, fork, get_time, get_time_cached, waitpid, TICK_PAGE};
+
+/// Timer interrupts may be held off for a while, allow a few missed ticks
+const SLACK_MS: isize = 50;
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    // never ahead of the timer, and at most a little behind it
+    let before = get_time();
+    let cached = get_time_cached();
+    assert!(cached > 0);
+    assert!(cached + SLACK_MS >= before);
+    assert!(cached <= get_time());
+
+    // advances on its own while spinning without syscalls
+    let mut spins: usize = 0;
+    while get_time_cached() == cached {
+        spins += 1;
+        if spins.is_multiple_of(1 << 16) {
+            assert!(get_time() - before < 1000, "tick page never updated");
+        }
+    }
+    let mut last = get_time_cached();
+    assert!(last > cached);
+    for _ in 0..1 << 16 {
+        let now = get_time_cached();
+        assert!(now >= last);
+        last = now;
+    }
+
+    // writes to the page are store faults
+    let pid = fork();
+    if pid == 0 {
+        unsafe {
+            (TICK_PAGE as *mut usize).write_volatile(0);
+        }
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, -11);
+    assert!(get_time_cached() >= last);
+    0
+}
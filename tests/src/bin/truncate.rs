@@ -0,0 +1,59 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::vec;
+use user_lib::fs::{
+    close, fstat, mkdir, open, read, truncate, unlink, write, OpenFlags, Stat, AT_REMOVEDIR,
+};
+
+static TEST_FILE: &str = "truncate_test";
+static TEST_DIR: &str = "truncate_test_dir";
+static STR: &str = "Hello, world!";
+
+fn file_size(path: &str) -> u32 {
+    let fd = open(path, OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let mut stat = Stat::new();
+    assert_eq!(fstat(fd as usize, &mut stat), 0);
+    close(fd as usize);
+    stat.size
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let fd = open(TEST_FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd >= 0, "Open test file failed!");
+    assert_eq!(write(fd as usize, STR.as_bytes()), STR.len() as isize);
+    close(fd as usize);
+
+    // shrink, then grow again past a block boundary
+    assert_eq!(truncate(TEST_FILE, 5), 0);
+    assert_eq!(file_size(TEST_FILE), 5);
+    assert_eq!(truncate(TEST_FILE, 1000), 0);
+    assert_eq!(file_size(TEST_FILE), 1000);
+
+    // the kept bytes survive and the grown region is zero-filled
+    let fd = open(TEST_FILE, OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let mut buf = vec![0xffu8; 1000];
+    assert_eq!(read(fd as usize, &mut buf), 1000);
+    close(fd as usize);
+    assert_eq!(&buf[..5], &STR.as_bytes()[..5]);
+    assert!(buf[5..].iter().all(|&byte| byte == 0));
+
+    // relative paths resolve against the working directory like open
+    assert_eq!(truncate("./truncate_test", 0), 0);
+    assert_eq!(file_size(TEST_FILE), 0);
+
+    assert_eq!(truncate("truncate_test_nonexistent", 0), -1);
+    assert_eq!(mkdir(TEST_DIR), 0);
+    assert_eq!(truncate(TEST_DIR, 0), -2);
+
+    unlink(TEST_DIR, AT_REMOVEDIR);
+    unlink(TEST_FILE, 0);
+
+    0
+}
@@ -0,0 +1,53 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::process::{exec, exit, vfork, waitpid};
+
+/// Written by the children, which run in this process's memory
+static SHARED: AtomicUsize = AtomicUsize::new(0);
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    let before = 42;
+
+    // the child exits before the parent goes on, having written to the shared memory
+    let pid = vfork();
+    if pid == 0 {
+        SHARED.store(1, Ordering::Relaxed);
+        exit(7);
+    }
+    assert!(pid > 0);
+    assert_eq!(SHARED.load(Ordering::Relaxed), 1);
+    assert_eq!(before, 42);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 7);
+
+    // the parent resumes once the child has exec'd into a program of its own
+    let pid = vfork();
+    if pid == 0 {
+        SHARED.store(2, Ordering::Relaxed);
+        exec(
+            "/tests/cmdline_args",
+            &["cmdline_args", "welcome", "to", "the", "wired", "world"],
+        );
+        exit(-1);
+    }
+    assert!(pid > 0);
+    assert_eq!(SHARED.load(Ordering::Relaxed), 2);
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    // a failed exec leaves the child in the parent's memory, it can still exit
+    let pid = vfork();
+    if pid == 0 {
+        let failed = exec("/tests/missing", &["missing"]);
+        exit(failed as i32);
+    }
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, -1);
+    0
+}
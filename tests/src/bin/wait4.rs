@@ -0,0 +1,103 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::vec::Vec;
+use user_lib::{
+    process::{
+        exit, fork, getpid, wait4, waitpid, wexitstatus, wifexited, wifsignaled, wtermsig, Rusage,
+        WNOHANG,
+    },
+    signal::{kill, SignalFlags},
+    sync::sleep,
+    thread::{thread_create, waittid},
+};
+
+fn idle() -> ! {
+    sleep(20);
+    exit(0)
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    // an exit is reported with its code, along with the memory the child used
+    let pid = fork();
+    if pid == 0 {
+        exit(3);
+    }
+    let mut status = 0;
+    let mut rusage = Rusage::default();
+    assert_eq!(wait4(pid, Some(&mut status), 0, Some(&mut rusage)), pid);
+    assert!(wifexited(status) && !wifsignaled(status));
+    assert_eq!(wexitstatus(status), 3);
+    assert!(rusage.maxrss > 0);
+    assert_eq!((rusage.utime_us, rusage.stime_us), (0, 0));
+
+    // a negative exit code is still an exit, not a signal
+    let pid = fork();
+    if pid == 0 {
+        exit(-2);
+    }
+    assert_eq!(wait4(pid, Some(&mut status), 0, None), pid);
+    assert!(wifexited(status));
+    assert_eq!(wexitstatus(status), 0xfe);
+
+    // a signal is reported as such, while waitpid keeps the plain exit code
+    let pid = fork();
+    if pid == 0 {
+        kill(getpid() as usize, SignalFlags::SIGINT.bits());
+        loop {
+            sleep(10);
+        }
+    }
+    assert_eq!(wait4(pid, Some(&mut status), 0, None), pid);
+    assert!(wifsignaled(status));
+    assert_eq!(wtermsig(status), 2);
+    // a panic aborts, as would a fault
+    let pid = fork();
+    if pid == 0 {
+        panic!("aborting on purpose");
+    }
+    assert_eq!(wait4(pid, Some(&mut status), 0, None), pid);
+    assert!(wifsignaled(status) && !wifexited(status));
+    assert_eq!(wtermsig(status), 6);
+    let pid = fork();
+    if pid == 0 {
+        kill(getpid() as usize, SignalFlags::SIGINT.bits());
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, -2);
+
+    // the peak is kept after the thread stacks are given back
+    let pid = fork();
+    if pid == 0 {
+        let tids: Vec<_> = (0..4).map(|_| thread_create(idle as usize, 0)).collect();
+        for tid in tids {
+            waittid(tid as usize);
+        }
+        exit(0);
+    }
+    let mut threaded = Rusage::default();
+    assert_eq!(wait4(pid, None, 0, Some(&mut threaded)), pid);
+    assert!(threaded.maxrss >= rusage.maxrss + 4 * 8);
+
+    // WNOHANG returns while the child runs
+    let pid = fork();
+    if pid == 0 {
+        loop {
+            sleep(10);
+        }
+    }
+    assert_eq!(wait4(pid, None, WNOHANG, None), 0);
+    kill(pid as usize, SignalFlags::SIGINT.bits());
+    assert_eq!(wait4(pid, None, 0, None), pid);
+
+    // nothing left to wait for, and unknown options are refused
+    assert_eq!(wait4(-1, None, WNOHANG, None), -1);
+    assert_eq!(wait4(-1, None, 1 << 8, None), -1);
+    0
+}
@@ -0,0 +1,44 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{
+    fs::{close, open, read, unlink, write, OpenFlags},
+    process::{exit, fork, waitpid},
+};
+
+static TEST_FILE: &str = "write_on_exit_test";
+
+/// Appends `data` in a child that exits with the file still open and never syncs
+fn append_and_exit(data: &[u8]) {
+    let pid = fork();
+    if pid == 0 {
+        let fd = open(
+            TEST_FILE,
+            OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::APPEND,
+        );
+        assert!(fd >= 0);
+        assert_eq!(write(fd as usize, data), data.len() as isize);
+        exit(0);
+    }
+    let mut exit_code = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    // small enough to stay in the write-back buffer until the file is closed
+    append_and_exit(b"first ");
+    append_and_exit(b"second");
+
+    let fd = open(TEST_FILE, OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let mut buf = [0u8; 32];
+    let len = read(fd as usize, &mut buf);
+    assert_eq!(&buf[..len as usize], b"first second");
+    close(fd as usize);
+    assert_eq!(unlink(TEST_FILE, 0), 0);
+    0
+}
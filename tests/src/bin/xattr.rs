@@ -0,0 +1,47 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::fs::{close, getxattr, listxattr, open, setxattr, unlink, OpenFlags};
+
+static TEST_FILE: &str = "/xattr_test_file";
+
+fn create() {
+    let fd = open(TEST_FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd >= 0);
+    close(fd as usize);
+}
+
+#[no_mangle]
+pub extern "Rust" fn main() -> i32 {
+    create();
+    let mut buf = [0u8; 64];
+    assert_eq!(listxattr(TEST_FILE, &mut buf), 0);
+    assert_eq!(getxattr(TEST_FILE, "mime", &mut buf), -61);
+
+    assert_eq!(setxattr(TEST_FILE, "mime", b"text/plain"), 0);
+    assert_eq!(setxattr(TEST_FILE, "user.tag", b"red"), 0);
+    assert_eq!(setxattr(TEST_FILE, "mime", b"text/html"), 0);
+
+    // an empty buffer only asks for the length
+    assert_eq!(getxattr(TEST_FILE, "mime", &mut []), 9);
+    assert_eq!(getxattr(TEST_FILE, "mime", &mut buf[..4]), -34);
+    assert_eq!(getxattr(TEST_FILE, "mime", &mut buf), 9);
+    assert_eq!(&buf[..9], b"text/html");
+    assert_eq!(listxattr(TEST_FILE, &mut []), 14);
+    assert_eq!(listxattr(TEST_FILE, &mut buf), 14);
+    assert_eq!(&buf[..14], b"mime\0user.tag\0");
+
+    // names and values are bounded
+    assert_eq!(setxattr(TEST_FILE, "", b"x"), -22);
+    assert_eq!(setxattr(TEST_FILE, "big", &[0u8; 129]), -7);
+    assert_eq!(setxattr("/xattr_missing", "mime", b"x"), -1);
+
+    // a new file on the same path starts without attributes
+    assert_eq!(unlink(TEST_FILE, 0), 0);
+    create();
+    assert_eq!(listxattr(TEST_FILE, &mut buf), 0);
+    assert_eq!(unlink(TEST_FILE, 0), 0);
+    0
+}
@@ -2,12 +2,21 @@ use alloc::{
     format,
     string::{String, ToString},
     vec,
+    vec::Vec,
 };
 use bitflags::bitflags;
 
-use crate::syscall::{
-    sys_chdir, sys_close, sys_dup, sys_dup2, sys_fstat, sys_getcwd, sys_mkdir, sys_open, sys_pipe,
-    sys_read, sys_unlink, sys_write,
+use crate::{
+    signal::SignalFlags,
+    syscall::{
+        sys_chdir, sys_close, sys_close_range, sys_copy_file_range, sys_dup, sys_dup2, sys_eventfd,
+        sys_fchdir, sys_fcntl, sys_fdatasync, sys_fincore, sys_fstat, sys_fstatfs, sys_fsync,
+        sys_ftruncate, sys_getcwd, sys_getdents, sys_getxattr, sys_isatty, sys_listxattr,
+        sys_lseek, sys_memfd_create, sys_mkdirat, sys_open, sys_pipe, sys_ppoll, sys_read,
+        sys_readlinkat, sys_realpath, sys_redirect_output, sys_renameat, sys_renameat2,
+        sys_sendfile, sys_setxattr, sys_statfs, sys_sync_file_range, sys_tee, sys_truncate,
+        sys_unlinkat, sys_write,
+    },
 };
 
 bitflags! {
@@ -17,6 +26,11 @@ bitflags! {
         const RDWR = 1 << 1;
         const CREATE = 1 << 9;
         const TRUNC = 1 << 10;
+        const APPEND = 1 << 11;
+        const NONBLOCK = 1 << 12;
+        const NOFOLLOW = 1 << 17;
+        const CLOEXEC = 1 << 19;
+        const PATH = 1 << 21;
     }
 }
 
@@ -24,6 +38,7 @@ bitflags! {
     #[derive(PartialEq, Eq, Default)]
     pub struct StatMode: u32 {
         const NULL = 0;
+        const CHR = 0o020_000;
         const DIR = 0o040_000;
         const REG = 0o100_000;
         const LNK = 0o120_000;
@@ -52,18 +67,115 @@ impl Stat {
     }
 }
 
+/// Usage figures of a file system, sizes in blocks
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Statfs {
+    pub block_size: usize,
+    pub blocks: usize,
+    pub free_blocks: usize,
+    /// Inodes the file system has room for
+    pub files: usize,
+    pub free_files: usize,
+    /// Longest name a directory entry can hold
+    pub name_max: usize,
+}
+
+bitflags! {
+    #[derive(Clone, Copy, PartialEq, Eq, Default)]
+    pub struct PollEvents: u16 {
+        const IN = 1;
+        const OUT = 1 << 2;
+        const ERR = 1 << 3;
+        const HUP = 1 << 4;
+        const NVAL = 1 << 5;
+    }
+}
+
+#[repr(C)]
+pub struct PollFd {
+    pub fd: i32,
+    pub events: PollEvents,
+    pub revents: PollEvents,
+}
+
+impl PollFd {
+    pub fn new(fd: usize, events: PollEvents) -> Self {
+        Self {
+            fd: fd as i32,
+            events,
+            revents: PollEvents::empty(),
+        }
+    }
+}
+
 pub const NAME_LENGTH_LIMIT: usize = 27;
+/// Longest path the kernel accepts, counting its terminating `\0`
+pub const PATH_MAX: usize = 4096;
+/// Size of a file system block, the unit [`fincore`] reports in
+pub const BLOCK_SIZE: usize = 512;
+
+/// Kind of a directory entry that was not recorded, the image predates entry types
+pub const DT_UNKNOWN: u8 = 0;
+/// Directory entry of a regular file
+pub const DT_REG: u8 = 1;
+/// Directory entry of a directory
+pub const DT_DIR: u8 = 2;
 
 #[repr(C)]
 pub struct Dirent {
-    pub name: [u8; NAME_LENGTH_LIMIT + 1],
+    /// NUL-terminated unless it is [`NAME_LENGTH_LIMIT`] bytes long
+    pub name: [u8; NAME_LENGTH_LIMIT],
+    /// One of [`DT_UNKNOWN`], [`DT_REG`] or [`DT_DIR`]
+    pub file_type: u8,
     pub inode_number: u32,
 }
 
+impl Dirent {
+    /// The name of the entry, `?` if it is not valid UTF-8
+    pub fn name(&self) -> &str {
+        let len = self
+            .name
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(self.name.len());
+        core::str::from_utf8(&self.name[..len]).unwrap_or("?")
+    }
+}
+
 pub const DIRENT_SIZE: usize = core::mem::size_of::<Dirent>();
 
+/// Resolves relative paths against the current working directory in the `*at` calls
+pub const AT_FDCWD: isize = -100;
 pub const AT_REMOVEDIR: u32 = 1;
 
+/// Makes [`renameat2`] fail with `-17` rather than move onto an existing path
+pub const RENAME_NOREPLACE: u32 = 1;
+/// Makes [`renameat2`] swap two existing paths
+pub const RENAME_EXCHANGE: u32 = 2;
+
+pub const F_GETFD: usize = 1;
+pub const F_SETFD: usize = 2;
+pub const F_GETFL: usize = 3;
+pub const F_SETFL: usize = 4;
+pub const FD_CLOEXEC: usize = 1;
+/// Mark the descriptors of [`close_range`] close-on-exec instead of closing them
+pub const CLOSE_RANGE_CLOEXEC: u32 = 4;
+
+pub const SEEK_SET: usize = 0;
+pub const SEEK_CUR: usize = 1;
+pub const SEEK_END: usize = 2;
+
+/// Reports errors of earlier writeback of the range before writing it
+pub const SYNC_FILE_RANGE_WAIT_BEFORE: u32 = 1;
+/// Writes back the range
+pub const SYNC_FILE_RANGE_WRITE: u32 = 2;
+/// Reports errors of the writeback of the range
+pub const SYNC_FILE_RANGE_WAIT_AFTER: u32 = 4;
+
+/// Closes the file descriptor of an in-memory file on `exec`
+pub const MFD_CLOEXEC: u32 = 1;
+
 /// Gets the current working directory and stores it in the provided string buffer.
 ///
 /// # Panics
@@ -71,7 +183,12 @@ pub const AT_REMOVEDIR: u32 = 1;
 /// Panics if the current working directory contains invalid UTF-8 sequences.
 pub fn getcwd(s: &mut String) -> isize {
     let mut buffer = vec![0u8; 128];
-    let len = sys_getcwd(&mut buffer);
+    let mut len = sys_getcwd(&mut buffer);
+    // the kernel reports the length it needs when the buffer is too small
+    while len < 0 {
+        buffer.resize(len.unsigned_abs(), 0);
+        len = sys_getcwd(&mut buffer);
+    }
     *s = core::str::from_utf8(&buffer[0..len as usize])
         .unwrap()
         .to_string();
@@ -87,13 +204,133 @@ pub fn dup2(old_fd: usize, new_fd: usize) -> isize {
 }
 
 pub fn mkdir(path: &str) -> isize {
+    mkdirat(AT_FDCWD, path)
+}
+
+pub fn mkdirat(dirfd: isize, path: &str) -> isize {
     let path = format!("{path}\0");
-    sys_mkdir(&path)
+    sys_mkdirat(dirfd, &path)
 }
 
 pub fn unlink(path: &str, flags: u32) -> isize {
+    unlinkat(AT_FDCWD, path, flags)
+}
+
+pub fn unlinkat(dirfd: isize, path: &str, flags: u32) -> isize {
+    let path = format!("{path}\0");
+    sys_unlinkat(dirfd, &path, flags)
+}
+
+pub fn rename(oldpath: &str, newpath: &str) -> isize {
+    renameat(AT_FDCWD, oldpath, AT_FDCWD, newpath)
+}
+
+/// Moves `oldpath` to `newpath`, relative paths are resolved against the directory
+/// file descriptors or the working directory for [`AT_FDCWD`].
+pub fn renameat(olddirfd: isize, oldpath: &str, newdirfd: isize, newpath: &str) -> isize {
+    let oldpath = format!("{oldpath}\0");
+    let newpath = format!("{newpath}\0");
+    sys_renameat(olddirfd, &oldpath, newdirfd, &newpath)
+}
+
+/// Like [`renameat`], `flags` may refuse to replace `newpath` or swap the two paths.
+pub fn renameat2(
+    olddirfd: isize,
+    oldpath: &str,
+    newdirfd: isize,
+    newpath: &str,
+    flags: u32,
+) -> isize {
+    let oldpath = format!("{oldpath}\0");
+    let newpath = format!("{newpath}\0");
+    sys_renameat2(olddirfd, &oldpath, newdirfd, &newpath, flags)
+}
+
+/// Copies the target of the symbolic link at `path` into `buf` and returns its length.
+///
+/// Returns `-1` if `path` does not exist and `-2` if it is not a symbolic link.
+pub fn readlinkat(dirfd: isize, path: &str, buf: &mut [u8]) -> isize {
+    let path = format!("{path}\0");
+    sys_readlinkat(dirfd, &path, buf)
+}
+
+/// Fills `stats` with the usage of the file system holding `path`.
+pub fn statfs(path: &str, stats: &mut Statfs) -> isize {
+    let path = format!("{path}\0");
+    sys_statfs(&path, core::ptr::from_mut(stats).cast())
+}
+
+/// Fills `stats` with the usage of the file system holding the file open as `fd`.
+pub fn fstatfs(fd: usize, stats: &mut Statfs) -> isize {
+    sys_fstatfs(fd, core::ptr::from_mut(stats).cast())
+}
+
+/// Truncates or zero-extends the file at `path` to `len` bytes.
+pub fn truncate(path: &str, len: usize) -> isize {
     let path = format!("{path}\0");
-    sys_unlink(&path, flags)
+    sys_truncate(&path, len)
+}
+
+/// Sets the extended attribute `name` of the file at `path` to `value`.
+///
+/// Returns `-28` if the attributes of the file would outgrow their block, and `-95` if the
+/// file system has no extended attributes.
+pub fn setxattr(path: &str, name: &str, value: &[u8]) -> isize {
+    let path = format!("{path}\0");
+    let name = format!("{name}\0");
+    sys_setxattr(&path, &name, value)
+}
+
+/// Copies the value of the extended attribute `name` of the file at `path` into `value`
+/// and returns its length, or only returns the length if `value` is empty.
+///
+/// Returns `-34` if `value` is too small and `-61` if there is no such attribute.
+pub fn getxattr(path: &str, name: &str, value: &mut [u8]) -> isize {
+    let path = format!("{path}\0");
+    let name = format!("{name}\0");
+    sys_getxattr(&path, &name, value)
+}
+
+/// Copies the names of the extended attributes of the file at `path` into `list`, each
+/// followed by a null byte, and returns their total length, or only returns the length if
+/// `list` is empty.
+pub fn listxattr(path: &str, list: &mut [u8]) -> isize {
+    let path = format!("{path}\0");
+    sys_listxattr(&path, list)
+}
+
+pub fn ftruncate(fd: usize, len: usize) -> isize {
+    sys_ftruncate(fd, len)
+}
+
+/// Moves the offset of a file, returning the new offset from its start.
+pub fn lseek(fd: usize, offset: isize, whence: usize) -> isize {
+    sys_lseek(fd, offset, whence)
+}
+
+/// Creates an anonymous file in memory, freed once its last file descriptor is closed.
+pub fn memfd_create(name: &str, flags: u32) -> isize {
+    let name = format!("{name}\0");
+    sys_memfd_create(&name, flags)
+}
+
+/// Resolves `path` to its canonical absolute form and stores it in `s`.
+///
+/// Returns the length of the resolved path, or a negative error code leaving `s` untouched.
+///
+/// # Panics
+///
+/// Panics if the resolved path contains invalid UTF-8 sequences.
+pub fn realpath(path: &str, s: &mut String) -> isize {
+    let path = format!("{path}\0");
+    let mut buffer = vec![0u8; 128];
+    let len = sys_realpath(&path, &mut buffer);
+    if len >= 0 {
+        *s = core::str::from_utf8(&buffer[0..len as usize])
+            .unwrap()
+            .to_string();
+    }
+    len
 }
 
 pub fn chdir(path: &str) -> isize {
@@ -101,24 +338,68 @@ pub fn chdir(path: &str) -> isize {
     sys_chdir(&path)
 }
 
+pub fn fchdir(fd: usize) -> isize {
+    sys_fchdir(fd)
+}
+
 #[allow(clippy::needless_pass_by_value)]
 pub fn open(path: &str, flags: OpenFlags) -> isize {
     let path = format!("{path}\0");
     sys_open(&path, flags.bits())
 }
 
+/// Queries or changes the flags of `fd`, `cmd` is one of `F_GETFD`, `F_SETFD`, `F_GETFL` and `F_SETFL`.
+pub fn fcntl(fd: usize, cmd: usize, arg: usize) -> isize {
+    sys_fcntl(fd, cmd, arg)
+}
+
 pub fn close(fd: usize) -> isize {
     sys_close(fd)
 }
 
+/// Closes the open descriptors from `first` to `last`, both included, skipping closed ones.
+///
+/// A `last` of `u32::MAX` reaches to the end of the table. With [`CLOSE_RANGE_CLOEXEC`] the
+/// descriptors are marked close-on-exec instead.
+pub fn close_range(first: usize, last: usize, flags: u32) -> isize {
+    sys_close_range(first, last, flags)
+}
+
 pub fn pipe(pipe_fd: &mut [usize]) -> isize {
     sys_pipe(pipe_fd)
 }
 
+/// Creates an event counter starting at `initval` and returns its file descriptor.
+///
+/// Read and write it 8 bytes at a time, see [`eventfd_read`] and [`eventfd_write`].
+pub fn eventfd(initval: u64) -> isize {
+    sys_eventfd(initval)
+}
+
+/// Takes the value of an event counter, blocking while it is zero.
+pub fn eventfd_read(fd: usize) -> Option<u64> {
+    let mut buf = [0u8; 8];
+    (sys_read(fd, &mut buf) == 8).then(|| u64::from_ne_bytes(buf))
+}
+
+/// Adds `value` to an event counter.
+pub fn eventfd_write(fd: usize, value: u64) -> isize {
+    sys_write(fd, &value.to_ne_bytes())
+}
+
 pub fn read(fd: usize, buf: &mut [u8]) -> isize {
     sys_read(fd, buf)
 }
 
+/// Reads the entries of a directory, continuing after those read by the last call.
+///
+/// Fills `buf` with whole [`Dirent`]s and returns the number of bytes read, `0` once
+/// every entry has been read. Entries created or removed in between do not make the
+/// others be skipped or read twice.
+pub fn getdents(fd: usize, buf: &mut [u8]) -> isize {
+    sys_getdents(fd, buf)
+}
+
 pub fn write(fd: usize, buf: &[u8]) -> isize {
     sys_write(fd, buf)
 }
@@ -126,3 +407,253 @@ pub fn write(fd: usize, buf: &[u8]) -> isize {
 pub fn fstat(fd: usize, stat: &mut Stat) -> isize {
     sys_fstat(fd, core::ptr::from_mut(stat).cast())
 }
+
+/// Copies up to `count` bytes from `in_fd` to `out_fd` inside the kernel.
+///
+/// Reads at `offset` and advances it if given, otherwise at the offset of `in_fd`.
+pub fn sendfile(out_fd: usize, in_fd: usize, offset: Option<&mut usize>, count: usize) -> isize {
+    let offset = offset.map_or(core::ptr::null_mut(), core::ptr::from_mut);
+    sys_sendfile(out_fd, in_fd, offset, count)
+}
+
+/// Copies up to `len` bytes between two regular files inside the kernel.
+///
+/// Each side uses and advances its offset if given, otherwise the offset of its file.
+pub fn copy_file_range(
+    fd_in: usize,
+    off_in: Option<&mut usize>,
+    fd_out: usize,
+    off_out: Option<&mut usize>,
+    len: usize,
+) -> isize {
+    let off_in = off_in.map_or(core::ptr::null_mut(), core::ptr::from_mut);
+    let off_out = off_out.map_or(core::ptr::null_mut(), core::ptr::from_mut);
+    sys_copy_file_range(fd_in, off_in, fd_out, off_out, len)
+}
+
+/// Copies up to `len` bytes from the pipe `fd_in` to the pipe `fd_out`, leaving them to be
+/// read from `fd_in` as well.
+///
+/// Returns `-2` if nothing can be copied without waiting on a non-blocking descriptor.
+pub fn tee(fd_in: usize, fd_out: usize, len: usize) -> isize {
+    sys_tee(fd_in, fd_out, len)
+}
+
+/// Points stdout and stderr at `fd`, or back at the console for `-1`, for this process
+/// and the children it forks afterwards.
+pub fn redirect_output(fd: isize) -> isize {
+    sys_redirect_output(fd)
+}
+
+/// Whether `fd` refers to the console, as opposed to a regular file or pipe.
+pub fn isatty(fd: usize) -> bool {
+    sys_isatty(fd) == 1
+}
+
+/// Writes the buffered data of `fd` back to the device.
+pub fn fsync(fd: usize) -> isize {
+    sys_fsync(fd)
+}
+
+/// Writes the buffered data of `fd` back to the device, with only the metadata needed to
+/// read it back.
+pub fn fdatasync(fd: usize) -> isize {
+    sys_fdatasync(fd)
+}
+
+/// Writes the buffered data of `len` bytes of `fd` from `offset` back to the device, or of
+/// the rest of the file if `len` is `0`.
+///
+/// `flags` combines the `SYNC_FILE_RANGE_*` flags, errors are only reported when waiting.
+pub fn sync_file_range(fd: usize, offset: usize, len: usize, flags: u32) -> isize {
+    sys_sync_file_range(fd, offset, len, flags)
+}
+
+/// Fills `vec` with one byte per block of `fd` from block-aligned `offset` on, `1` if the
+/// block is in the kernel's block cache.
+///
+/// A snapshot, taken without loading anything into the cache.
+pub fn fincore(fd: usize, offset: usize, vec: &mut [u8]) -> isize {
+    sys_fincore(fd, offset, vec.len() * BLOCK_SIZE, vec.as_mut_ptr())
+}
+
+/// Waits up to `timeout` milliseconds (forever if negative) until one of `fds` is ready.
+pub fn poll(fds: &mut [PollFd], timeout: isize) -> isize {
+    sys_ppoll(
+        fds.as_mut_ptr().cast(),
+        fds.len(),
+        timeout,
+        core::ptr::null(),
+    )
+}
+
+/// Like [`poll`], with the signals in `sigmask` blocked while waiting.
+///
+/// Returns `-2` if a signal not in `sigmask` interrupted the wait.
+#[allow(clippy::needless_pass_by_value)]
+pub fn ppoll(fds: &mut [PollFd], timeout: isize, sigmask: SignalFlags) -> isize {
+    let sigmask = sigmask.bits();
+    sys_ppoll(
+        fds.as_mut_ptr().cast(),
+        fds.len(),
+        timeout,
+        core::ptr::from_ref(&sigmask),
+    )
+}
+
+/// Size of the buffer of a [`BufReader`] or [`BufWriter`]
+pub const BUF_SIZE: usize = 512;
+
+/// Reads from a file descriptor through a buffer, one syscall per [`BUF_SIZE`] bytes
+pub struct BufReader {
+    fd: usize,
+    buf: Vec<u8>,
+    pos: usize,
+    filled: usize,
+}
+
+impl BufReader {
+    pub fn new(fd: usize) -> Self {
+        Self {
+            fd,
+            buf: vec![0u8; BUF_SIZE],
+            pos: 0,
+            filled: 0,
+        }
+    }
+
+    /// The bytes read from the file but not yet consumed
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..self.filled]
+    }
+
+    /// Refills the buffer once it is used up.
+    ///
+    /// Returns the number of buffered bytes, `0` at end of file, or the error of `read`.
+    fn fill_buf(&mut self) -> isize {
+        if self.pos == self.filled {
+            let len = sys_read(self.fd, &mut self.buf);
+            if len < 0 {
+                return len;
+            }
+            self.pos = 0;
+            self.filled = len as usize;
+        }
+        (self.filled - self.pos) as isize
+    }
+
+    /// Reads into `buf`, bypassing the buffer for reads at least as large as it.
+    pub fn read(&mut self, buf: &mut [u8]) -> isize {
+        if self.pos == self.filled && buf.len() >= self.buf.len() {
+            return sys_read(self.fd, buf);
+        }
+        let available = self.fill_buf();
+        if available <= 0 {
+            return available;
+        }
+        let len = buf.len().min(available as usize);
+        buf[..len].copy_from_slice(&self.buf[self.pos..self.pos + len]);
+        self.pos += len;
+        len as isize
+    }
+
+    /// Reads a single byte, `None` at end of file or on error
+    pub fn read_byte(&mut self) -> Option<u8> {
+        if self.fill_buf() <= 0 {
+            return None;
+        }
+        self.pos += 1;
+        Some(self.buf[self.pos - 1])
+    }
+
+    /// Appends a line to `line`, including its `\n` unless the file ends first.
+    ///
+    /// Invalid UTF-8 is replaced. Returns the number of bytes read, `0` at end of file, or
+    /// the error of `read` if nothing was read before it.
+    pub fn read_line(&mut self, line: &mut String) -> isize {
+        let mut bytes = Vec::new();
+        loop {
+            let available = self.fill_buf();
+            if available < 0 && bytes.is_empty() {
+                return available;
+            }
+            if available <= 0 {
+                break;
+            }
+            let buffered = &self.buf[self.pos..self.filled];
+            if let Some(i) = buffered.iter().position(|&c| c == b'\n') {
+                bytes.extend_from_slice(&buffered[..=i]);
+                self.pos += i + 1;
+                break;
+            }
+            bytes.extend_from_slice(buffered);
+            self.pos = self.filled;
+        }
+        line.push_str(&String::from_utf8_lossy(&bytes));
+        bytes.len() as isize
+    }
+}
+
+/// Writes to a file descriptor through a buffer, which is flushed when full or dropped
+pub struct BufWriter {
+    fd: usize,
+    buf: Vec<u8>,
+}
+
+impl BufWriter {
+    pub fn new(fd: usize) -> Self {
+        Self {
+            fd,
+            buf: Vec::with_capacity(BUF_SIZE),
+        }
+    }
+
+    /// Buffers `data`, writing straight through if it is at least as large as the buffer.
+    ///
+    /// Returns `data.len()`, or the error of `write` if flushing failed.
+    pub fn write(&mut self, data: &[u8]) -> isize {
+        if self.buf.len() + data.len() > self.buf.capacity() {
+            let ret = self.flush();
+            if ret < 0 {
+                return ret;
+            }
+        }
+        if data.len() >= self.buf.capacity() {
+            return sys_write(self.fd, data);
+        }
+        self.buf.extend_from_slice(data);
+        data.len() as isize
+    }
+
+    /// Writes out the buffered data.
+    ///
+    /// Returns `0`, or the error of `write`, in which case the unwritten data is kept.
+    pub fn flush(&mut self) -> isize {
+        let mut written = 0;
+        while written < self.buf.len() {
+            let len = sys_write(self.fd, &self.buf[written..]);
+            if len <= 0 {
+                self.buf.drain(..written);
+                return len.min(-1);
+            }
+            written += len as usize;
+        }
+        self.buf.clear();
+        0
+    }
+}
+
+impl core::fmt::Write for BufWriter {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        if self.write(s.as_bytes()) < 0 {
+            return Err(core::fmt::Error);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for BufWriter {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
@@ -1,7 +1,97 @@
-use crate::syscall::{
-    sys_exec, sys_exit, sys_fork, sys_get_time, sys_getpid, sys_waitpid, sys_yield,
+use crate::{
+    fs::{
+        close, close_range, dup2, fcntl, pipe, read, write, CLOSE_RANGE_CLOEXEC, FD_CLOEXEC,
+        F_SETFD,
+    },
+    syscall::{
+        sys_clone, sys_clone_entry, sys_exec, sys_exit, sys_fork, sys_get_time, sys_getpgid,
+        sys_getpid, sys_madvise, sys_prctl, sys_process_info, sys_setpgid, sys_spawn, sys_sysinfo,
+        sys_tcgetpgrp, sys_tcsetpgrp, sys_vfork, sys_wait4, sys_waitpid, sys_yield,
+    },
 };
-use alloc::{format, string::String, vec::Vec};
+use alloc::{format, string::String, vec, vec::Vec};
+use bitflags::bitflags;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+bitflags! {
+    /// Resources a child created by [`clone`] shares with its parent
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct CloneFlags: usize {
+        /// Share the address space, the child is a thread, see [`clone_thread`]
+        const VM = 0x100;
+        /// Share the file descriptor table
+        const FILES = 0x400;
+        /// Share the signal state, only possible along with the address space
+        const SIGHAND = 0x800;
+    }
+}
+
+/// Expect access to the pages in the near future
+pub const MADV_WILLNEED: usize = 3;
+/// Do not expect access to the pages in the near future
+pub const MADV_DONTNEED: usize = 4;
+
+/// Read-only page the kernel publishes the time in milliseconds to on every timer tick
+pub const TICK_PAGE: usize = 0x10000 - 4096;
+
+/// Length of the program name in a [`ProcessInfo`], including the trailing NUL
+pub const PROCESS_NAME_LEN: usize = 32;
+
+/// [`prctl`] option setting the name of the process, see [`set_name`]
+pub const PR_SET_NAME: usize = 15;
+/// [`prctl`] option reading the name of the process, see [`get_name`]
+pub const PR_GET_NAME: usize = 16;
+/// [`prctl`] option making the process adopt orphaned descendants if `arg` is nonzero
+pub const PR_SET_CHILD_SUBREAPER: usize = 36;
+/// [`prctl`] option storing whether the process is a child subreaper at the `i32` `arg` points to
+pub const PR_GET_CHILD_SUBREAPER: usize = 37;
+/// [`prctl`] option giving up gaining privileges for good, `arg` must be `1`
+pub const PR_SET_NO_NEW_PRIVS: usize = 38;
+/// [`prctl`] option returning whether the process gave up gaining privileges
+pub const PR_GET_NO_NEW_PRIVS: usize = 39;
+
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProcessState {
+    Ready = 0,
+    Running = 1,
+    Blocked = 2,
+    Zombie = 3,
+}
+
+#[repr(C)]
+pub struct ProcessInfo {
+    pub pid: usize,
+    pub ppid: usize,
+    pub state: ProcessState,
+    /// Exit code of a zombie, `0` otherwise
+    pub exit_code: i32,
+    pub name: [u8; PROCESS_NAME_LEN],
+}
+
+impl ProcessInfo {
+    pub fn name(&self) -> &str {
+        let len = self
+            .name
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(self.name.len());
+        core::str::from_utf8(&self.name[..len]).unwrap_or("?")
+    }
+}
+
+/// System-wide figures, sizes in bytes
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SysInfo {
+    pub uptime_ms: usize,
+    pub total_ram: usize,
+    pub free_ram: usize,
+    /// Live processes, unreaped zombies excluded
+    pub processes: usize,
+    pub fs_total: usize,
+    pub fs_free: usize,
+}
 
 pub fn exit(exit_code: i32) -> ! {
     sys_exit(exit_code)
@@ -15,25 +105,112 @@ pub fn get_time() -> isize {
     sys_get_time()
 }
 
+/// Time in milliseconds read from [`TICK_PAGE`] without trapping into the kernel
+///
+/// Lags [`get_time`] by up to one timer tick, which is 10ms. Falls back to
+/// [`get_time`] if the kernel has not published the time yet.
+pub fn get_time_cached() -> isize {
+    let tick = unsafe { &*(TICK_PAGE as *const AtomicUsize) };
+    match tick.load(Ordering::Acquire) {
+        0 => get_time(),
+        ms => ms as isize,
+    }
+}
+
 pub fn getpid() -> isize {
     sys_getpid()
 }
 
+/// Moves `pid` (`0` for the caller) into the group `pgid` (`0` for a new group led by `pid`)
+#[allow(clippy::similar_names)]
+pub fn setpgid(pid: usize, pgid: usize) -> isize {
+    sys_setpgid(pid, pgid)
+}
+
+/// Process group of `pid`, `0` for the caller
+pub fn getpgid(pid: usize) -> isize {
+    sys_getpgid(pid)
+}
+
+/// Gives the terminal to the group `pgid`, so Ctrl-C interrupts it, or to no group if `0`
+pub fn tcsetpgrp(pgid: usize) -> isize {
+    sys_tcsetpgrp(pgid)
+}
+
+/// Process group in the foreground of the terminal, `0` if none
+pub fn tcgetpgrp() -> isize {
+    sys_tcgetpgrp()
+}
+
 pub fn fork() -> isize {
     sys_fork()
 }
 
+/// Creates a child process that runs in the caller's memory until it calls [`exec`] or
+/// [`exit`], the caller is suspended until then.
+///
+/// Returns the PID of the child to the caller and `0` to the child, like [`fork`], or `-1`
+/// if the process has more than one thread.
+///
+/// This follows the restricted `vfork` contract, as nothing is copied for the child:
+///
+/// * The child may only call [`exec`] or [`exit`], after computing their arguments.
+/// * It must not return from the function that called `vfork`, which still has to return
+///   in the caller.
+/// * Whatever else it writes to memory, the caller sees once resumed.
+#[inline(always)]
+#[allow(clippy::inline_always)]
+pub fn vfork() -> isize {
+    sys_vfork()
+}
+
+/// Creates a child process sharing the resources selected by `flags`.
+///
+/// Returns the PID of the child to the caller and `0` to the child, like [`fork`].
+/// [`CloneFlags::VM`] is refused with `-1`, use [`clone_thread`] for that.
+pub fn clone(flags: CloneFlags) -> isize {
+    if flags.contains(CloneFlags::VM) {
+        return -1;
+    }
+    sys_clone(flags.bits(), 0)
+}
+
+/// Creates a thread with `clone`, which starts at `entry` with `arg` on the stack ending at
+/// `stack_top`, or on a stack allocated for it if that is `0`.
+///
+/// Returns the TID of the thread, which has to exit rather than return from `entry`.
+pub fn clone_thread(stack_top: usize, entry: usize, arg: usize) -> isize {
+    let flags = CloneFlags::VM | CloneFlags::FILES | CloneFlags::SIGHAND;
+    sys_clone_entry(flags.bits(), stack_top, entry, arg)
+}
+
 pub fn exec<T: AsRef<str>>(path: &str, args: &[T]) -> isize {
     let path = format!("{path}\0");
-    let args: Vec<String> = args
-        .iter()
-        .map(|arg| format!("{}\0", arg.as_ref()))
-        .collect();
+    let args = c_args(args);
     let mut arg_ptrs: Vec<*const u8> = args.iter().map(|s| s.as_ptr()).collect();
     arg_ptrs.push(core::ptr::null());
     sys_exec(&path, &arg_ptrs)
 }
 
+/// Runs the program at `path` in a new child process, like [`fork`] followed by [`exec`]
+/// in the child, without copying the caller's address space.
+///
+/// Returns the PID of the child, or the error [`exec`] would have returned.
+pub fn spawn<T: AsRef<str>>(path: &str, args: &[T]) -> isize {
+    let path = format!("{path}\0");
+    let args = c_args(args);
+    let mut arg_ptrs: Vec<*const u8> = args.iter().map(|s| s.as_ptr()).collect();
+    arg_ptrs.push(core::ptr::null());
+    sys_spawn(&path, &arg_ptrs)
+}
+
+/// `args` as null-terminated strings
+fn c_args<T: AsRef<str>>(args: &[T]) -> Vec<String> {
+    args.iter()
+        .map(|arg| format!("{}\0", arg.as_ref()))
+        .collect()
+}
+
 pub fn wait(exit_code: &mut i32) -> isize {
     loop {
         match sys_waitpid(-1, core::ptr::from_mut(exit_code)) {
@@ -61,3 +238,338 @@ pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
 pub fn waitpid_nb(pid: usize, exit_code: &mut i32) -> isize {
     sys_waitpid(pid as isize, core::ptr::from_mut(exit_code))
 }
+
+/// Return `0` from [`wait4`] at once if no child has exited yet
+pub const WNOHANG: u32 = 1;
+
+/// Resources used by a child, filled in by [`wait4`]
+#[repr(C)]
+#[derive(Debug, Default)]
+pub struct Rusage {
+    /// Microseconds spent in user mode, always zero as it is not accounted yet
+    pub utime_us: usize,
+    /// Microseconds spent in the kernel, always zero as it is not accounted yet
+    pub stime_us: usize,
+    /// Largest resident size of the address space, in KiB
+    pub maxrss: usize,
+}
+
+/// Waits for the child `pid`, or any child if it is `-1`, and reaps it.
+///
+/// Unlike [`waitpid`], the status tells how the child ended, see [`wifexited`] and
+/// [`wifsignaled`]. Returns the PID of the child, `0` if `options` holds [`WNOHANG`] and
+/// it still runs, or `-1` if there is no such child.
+pub fn wait4(
+    pid: isize,
+    status: Option<&mut i32>,
+    options: u32,
+    rusage: Option<&mut Rusage>,
+) -> isize {
+    let status = status.map_or(core::ptr::null_mut(), core::ptr::from_mut);
+    let rusage = rusage.map_or(core::ptr::null_mut(), |rusage| {
+        core::ptr::from_mut(rusage).cast()
+    });
+    loop {
+        match sys_wait4(pid, status, options, rusage) {
+            -2 => {
+                let _ = yield_();
+            }
+            exit_pid => return exit_pid,
+        }
+    }
+}
+
+/// Whether a [`wait4`] status is that of a process that exited by itself
+pub fn wifexited(status: i32) -> bool {
+    status & 0x7f == 0
+}
+
+/// The exit code in a [`wait4`] status, if [`wifexited`]
+pub fn wexitstatus(status: i32) -> i32 {
+    (status >> 8) & 0xff
+}
+
+/// Whether a [`wait4`] status is that of a process terminated by a signal
+pub fn wifsignaled(status: i32) -> bool {
+    status & 0x7f != 0
+}
+
+/// The number of the signal in a [`wait4`] status, if [`wifsignaled`]
+pub fn wtermsig(status: i32) -> i32 {
+    status & 0x7f
+}
+
+/// Where a standard stream of a [`Command`] is connected
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stdio {
+    /// The stream of the caller
+    Inherit,
+    /// A new pipe, whose other end is left to the caller in the [`Child`]
+    Piped,
+    /// A descriptor of the caller, which stays open in the caller
+    Fd(usize),
+}
+
+/// A program to run in a child process, with its arguments and standard streams
+///
+/// Redirected streams are moved into place with [`dup2`] in a forked child before it calls
+/// [`exec`]. The child keeps only its standard input, output and error, every other
+/// descriptor is marked close-on-exec with [`close_range`] first. The process has no
+/// environment to pass on, the kernel keeps none.
+#[derive(Clone, Debug)]
+pub struct Command {
+    path: String,
+    args: Vec<String>,
+    stdin: Stdio,
+    stdout: Stdio,
+}
+
+impl Command {
+    /// A command running the program at `path`, which is also its first argument
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: String::from(path),
+            args: vec![String::from(path)],
+            stdin: Stdio::Inherit,
+            stdout: Stdio::Inherit,
+        }
+    }
+
+    /// Adds an argument after the ones given so far
+    pub fn arg(&mut self, arg: &str) -> &mut Self {
+        self.args.push(String::from(arg));
+        self
+    }
+
+    /// Adds arguments after the ones given so far
+    pub fn args<T: AsRef<str>>(&mut self, args: &[T]) -> &mut Self {
+        self.args
+            .extend(args.iter().map(|arg| String::from(arg.as_ref())));
+        self
+    }
+
+    /// Sets where the standard input of the child comes from
+    pub fn stdin(&mut self, stdin: Stdio) -> &mut Self {
+        self.stdin = stdin;
+        self
+    }
+
+    /// Sets where the standard output of the child goes
+    pub fn stdout(&mut self, stdout: Stdio) -> &mut Self {
+        self.stdout = stdout;
+        self
+    }
+
+    /// Starts the program in a child process
+    ///
+    /// # Errors
+    ///
+    /// Returns the error [`exec`] fails with if the program cannot be started, the child
+    /// is then already reaped, or `-1` if a pipe cannot be created. No descriptor is left
+    /// open in the caller on failure.
+    pub fn spawn(&mut self) -> Result<Child, isize> {
+        // every end is closed on exec unless moved onto a standard stream,
+        // which clears the flag, so the child keeps nothing else of the pipes
+        let mut opened = Vec::new();
+        let streams = open_stream(self.stdin, true, &mut opened).and_then(|stdin| {
+            let stdout = open_stream(self.stdout, false, &mut opened)?;
+            let report = cloexec_pipe(&mut opened)?;
+            Ok((stdin, stdout, report))
+        });
+        let Ok(((child_stdin, stdin), (child_stdout, stdout), report)) = streams else {
+            for fd in opened {
+                close(fd);
+            }
+            return Err(-1);
+        };
+
+        let pid = fork();
+        if pid == 0 {
+            if let Some(fd) = child_stdin {
+                dup2(fd, 0);
+            }
+            if let Some(fd) = child_stdout {
+                dup2(fd, 1);
+            }
+            // the report pipe is among them, and so stays open until `exec` succeeds
+            close_range(3, u32::MAX as usize, CLOSE_RANGE_CLOEXEC);
+            // only reached if the program could not be started
+            let code = exec(&self.path, &self.args);
+            write(report[1], &code.to_ne_bytes());
+            exit(127);
+        }
+
+        // the caller keeps only its ends of the pipes
+        let ours = [stdin, stdout, Some(report[0])];
+        for &fd in opened.iter().filter(|&&fd| !ours.contains(&Some(fd))) {
+            close(fd);
+        }
+        // the report pipe reaches its end once `exec` has closed the child's copy
+        let error = if pid < 0 {
+            Some(pid)
+        } else {
+            let mut code = [0u8; core::mem::size_of::<isize>()];
+            (read(report[0], &mut code) > 0).then(|| isize::from_ne_bytes(code))
+        };
+        close(report[0]);
+        if let Some(code) = error {
+            for fd in stdin.into_iter().chain(stdout) {
+                close(fd);
+            }
+            if pid > 0 {
+                wait4(pid, None, 0, None);
+            }
+            return Err(code);
+        }
+        Ok(Child {
+            pid: pid as usize,
+            stdin,
+            stdout,
+        })
+    }
+
+    /// Runs the program to its end and reports how it ended
+    ///
+    /// The caller has no use for piped streams here, they are closed before waiting.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`Command::spawn`].
+    pub fn status(&mut self) -> Result<ExitStatus, isize> {
+        let mut child = self.spawn()?;
+        if let Some(fd) = child.stdout.take() {
+            close(fd);
+        }
+        Ok(child.wait())
+    }
+}
+
+/// A pipe whose ends are both closed on exec, recorded in `opened`
+fn cloexec_pipe(opened: &mut Vec<usize>) -> Result<[usize; 2], isize> {
+    let mut fds = [0usize; 2];
+    if pipe(&mut fds) != 0 {
+        return Err(-1);
+    }
+    for fd in fds {
+        fcntl(fd, F_SETFD, FD_CLOEXEC);
+        opened.push(fd);
+    }
+    Ok(fds)
+}
+
+/// The descriptor a child takes a stream from, and the end of a pipe the caller keeps
+fn open_stream(
+    stdio: Stdio,
+    input: bool,
+    opened: &mut Vec<usize>,
+) -> Result<(Option<usize>, Option<usize>), isize> {
+    match stdio {
+        Stdio::Inherit => Ok((None, None)),
+        Stdio::Fd(fd) => Ok((Some(fd), None)),
+        Stdio::Piped => {
+            let [read_end, write_end] = cloexec_pipe(opened)?;
+            if input {
+                Ok((Some(read_end), Some(write_end)))
+            } else {
+                Ok((Some(write_end), Some(read_end)))
+            }
+        }
+    }
+}
+
+/// A child process started by [`Command::spawn`]
+#[derive(Debug)]
+pub struct Child {
+    pid: usize,
+    /// The end of the pipe to the standard input of the child, with [`Stdio::Piped`]
+    pub stdin: Option<usize>,
+    /// The end of the pipe from the standard output of the child, with [`Stdio::Piped`]
+    pub stdout: Option<usize>,
+}
+
+impl Child {
+    /// The PID of the child
+    pub fn id(&self) -> usize {
+        self.pid
+    }
+
+    /// Waits for the child to end and reaps it
+    ///
+    /// The pipe to its standard input is closed first, so a child reading it to the end
+    /// does not wait forever. The pipe from its standard output is left to the caller.
+    pub fn wait(&mut self) -> ExitStatus {
+        if let Some(fd) = self.stdin.take() {
+            close(fd);
+        }
+        let mut status = 0;
+        wait4(self.pid as isize, Some(&mut status), 0, None);
+        ExitStatus(status)
+    }
+}
+
+/// How a child process ended, as reported by [`wait4`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExitStatus(i32);
+
+impl ExitStatus {
+    /// Whether the child exited by itself with code `0`
+    pub fn success(self) -> bool {
+        self.code() == Some(0)
+    }
+
+    /// The exit code of the child, `None` if a signal terminated it
+    pub fn code(self) -> Option<i32> {
+        wifexited(self.0).then(|| wexitstatus(self.0))
+    }
+
+    /// The signal that terminated the child, `None` if it exited by itself
+    pub fn signal(self) -> Option<i32> {
+        wifsignaled(self.0).then(|| wtermsig(self.0))
+    }
+}
+
+/// Advises the kernel about the use of the pages in `[addr, addr + len)`.
+pub fn madvise(addr: usize, len: usize, advice: usize) -> isize {
+    sys_madvise(addr, len, advice)
+}
+
+/// Lists the live processes and unreaped zombies, ordered by PID
+pub fn process_info() -> Vec<ProcessInfo> {
+    let mut infos: Vec<ProcessInfo> = Vec::with_capacity(16);
+    loop {
+        let count = sys_process_info(infos.as_mut_ptr().cast(), infos.capacity());
+        if count >= 0 {
+            // the kernel filled in `count` records
+            unsafe { infos.set_len(count as usize) };
+            return infos;
+        }
+        // the kernel reports how many processes there are when the array is too small
+        infos.reserve(count.unsigned_abs());
+    }
+}
+
+/// Uptime, memory, process and disk figures taken at the same moment
+/// Reads or changes a behavior of the calling process, `option` is one of the `PR_*` options
+pub fn prctl(option: usize, arg: usize) -> isize {
+    sys_prctl(option, arg)
+}
+
+/// Renames the calling process, the name must be shorter than [`PROCESS_NAME_LEN`] bytes
+pub fn set_name(name: &str) -> isize {
+    let name = format!("{name}\0");
+    sys_prctl(PR_SET_NAME, name.as_ptr() as usize)
+}
+
+/// Name of the calling process
+pub fn get_name() -> String {
+    let mut name = [0u8; PROCESS_NAME_LEN];
+    sys_prctl(PR_GET_NAME, name.as_mut_ptr() as usize);
+    let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+    String::from_utf8_lossy(&name[..len]).into_owned()
+}
+
+pub fn sysinfo() -> SysInfo {
+    let mut info = SysInfo::default();
+    sys_sysinfo((&raw mut info).cast());
+    info
+}
@@ -1,17 +1,58 @@
 use bitflags::bitflags;
 
-use crate::syscall::sys_kill;
+use crate::syscall::{
+    sys_kill, sys_pidfd_open, sys_pidfd_send_signal, sys_sigpending, sys_sigsuspend, sys_tgkill,
+};
 
 bitflags! {
+    #[derive(Clone, Copy, PartialEq, Eq)]
     pub struct SignalFlags: i32 {
         const SIGINT    = 1 << 2;
         const SIGILL    = 1 << 4;
         const SIGABRT   = 1 << 6;
         const SIGFPE    = 1 << 8;
         const SIGSEGV   = 1 << 11;
+        /// A child exited, pending until no exited child is left to wait for
+        const SIGCHLD   = 1 << 17;
     }
 }
 
 pub fn kill(pid: usize, signum: i32) -> isize {
     sys_kill(pid, signum)
 }
+
+/// Sends a signal to the thread `tid` of the process `pid` alone, or returns `-1` if there is
+/// no such thread.
+///
+/// A fatal signal ends only that thread, unless it is the main thread of the process.
+pub fn tgkill(pid: usize, tid: usize, signum: i32) -> isize {
+    sys_tgkill(pid, tid, signum)
+}
+
+/// Opens a file descriptor that refers to the process `pid` for as long as it runs,
+/// even if its PID is reused later, or returns `-3` if there is no such process.
+///
+/// The file descriptor becomes readable for `poll` once the process exits.
+pub fn pidfd_open(pid: usize) -> isize {
+    sys_pidfd_open(pid)
+}
+
+/// Sends a signal to the process behind `pidfd`, or returns `-3` if it has exited.
+pub fn pidfd_send_signal(pidfd: usize, signum: i32) -> isize {
+    sys_pidfd_send_signal(pidfd, signum)
+}
+
+/// The signals that are pending but blocked by the signal mask
+pub fn sigpending() -> SignalFlags {
+    let mut set = 0;
+    sys_sigpending(core::ptr::from_mut(&mut set));
+    SignalFlags::from_bits_truncate(set)
+}
+
+/// Waits with the signals in `mask` blocked until another signal arrives, then restores the mask.
+///
+/// Always returns `-2`, or `-1` if `mask` holds an unknown signal.
+pub fn sigsuspend(mask: SignalFlags) -> isize {
+    let mask = mask.bits();
+    sys_sigsuspend(core::ptr::from_ref(&mask))
+}
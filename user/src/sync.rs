@@ -1,6 +1,7 @@
 use crate::syscall::{
     sys_condvar_create, sys_condvar_signal, sys_condvar_wait, sys_mutex_create, sys_mutex_lock,
-    sys_mutex_unlock, sys_semaphore_create, sys_semaphore_down, sys_semaphore_up, sys_sleep,
+    sys_mutex_unlock, sys_semaphore_available, sys_semaphore_create, sys_semaphore_down,
+    sys_semaphore_try_down, sys_semaphore_up, sys_sleep,
 };
 
 pub fn sleep(sleep_ms: usize) {
@@ -35,6 +36,14 @@ pub fn semaphore_down(sem_id: usize) {
     sys_semaphore_down(sem_id);
 }
 
+pub fn semaphore_try_down(sem_id: usize) -> bool {
+    sys_semaphore_try_down(sem_id) == 0
+}
+
+pub fn semaphore_available(sem_id: usize) -> isize {
+    sys_semaphore_available(sem_id)
+}
+
 pub fn condvar_create() -> isize {
     sys_condvar_create()
 }
@@ -1,38 +1,87 @@
 use core::arch::asm;
 
+const SYSCALL_SETXATTR: usize = 5;
+const SYSCALL_GETXATTR: usize = 8;
+const SYSCALL_LISTXATTR: usize = 11;
 const SYSCALL_GETCWD: usize = 17;
+const SYSCALL_EVENTFD: usize = 19;
 const SYSCALL_DUP: usize = 23;
 const SYSCALL_DUP2: usize = 24;
-const SYSCALL_MKDIR: usize = 34;
-const SYSCALL_UNLINK: usize = 35;
+const SYSCALL_FCNTL: usize = 25;
+const SYSCALL_MKDIRAT: usize = 34;
+const SYSCALL_UNLINKAT: usize = 35;
+const SYSCALL_RENAMEAT: usize = 38;
+const SYSCALL_STATFS: usize = 43;
+const SYSCALL_FSTATFS: usize = 44;
+const SYSCALL_TRUNCATE: usize = 45;
+const SYSCALL_FTRUNCATE: usize = 46;
 const SYSCALL_CHDIR: usize = 49;
+const SYSCALL_FCHDIR: usize = 50;
 const SYSCALL_OPEN: usize = 56;
 const SYSCALL_CLOSE: usize = 57;
 const SYSCALL_PIPE: usize = 59;
+const SYSCALL_GETDENTS: usize = 61;
+const SYSCALL_LSEEK: usize = 62;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
+const SYSCALL_SENDFILE: usize = 71;
+const SYSCALL_PPOLL: usize = 73;
+const SYSCALL_TEE: usize = 77;
+const SYSCALL_READLINKAT: usize = 78;
 const SYSCALL_FSTAT: usize = 80;
+const SYSCALL_FSYNC: usize = 82;
+const SYSCALL_FDATASYNC: usize = 83;
+const SYSCALL_SYNC_FILE_RANGE: usize = 84;
 const SYSCALL_EXIT: usize = 93;
 const SYSCALL_SLEEP: usize = 101;
+const SYSCALL_SCHED_SETAFFINITY: usize = 122;
+const SYSCALL_SCHED_GETAFFINITY: usize = 123;
 const SYSCALL_YIELD: usize = 124;
 const SYSCALL_KILL: usize = 129;
+const SYSCALL_TGKILL: usize = 131;
+const SYSCALL_SIGSUSPEND: usize = 133;
+const SYSCALL_SIGPENDING: usize = 136;
+const SYSCALL_SETPGID: usize = 154;
+const SYSCALL_GETPGID: usize = 155;
+const SYSCALL_PRCTL: usize = 167;
 const SYSCALL_GET_TIME: usize = 169;
 const SYSCALL_GETPID: usize = 172;
+const SYSCALL_SYSINFO: usize = 179;
 const SYSCALL_FORK: usize = 220;
 const SYSCALL_EXEC: usize = 221;
+const SYSCALL_MADVISE: usize = 233;
 const SYSCALL_WAITPID: usize = 260;
+const SYSCALL_RENAMEAT2: usize = 276;
+const SYSCALL_MEMFD_CREATE: usize = 279;
+const SYSCALL_COPY_FILE_RANGE: usize = 285;
+const SYSCALL_SPAWN: usize = 400;
+const SYSCALL_PIDFD_SEND_SIGNAL: usize = 424;
+const SYSCALL_PIDFD_OPEN: usize = 434;
+const SYSCALL_CLOSE_RANGE: usize = 436;
 const SYSCALL_THREAD_CREATE: usize = 1000;
 const SYSCALL_GETTID: usize = 1001;
 const SYSCALL_WAITTID: usize = 1002;
+const SYSCALL_CLONE: usize = 1003;
+const SYSCALL_VFORK: usize = 1004;
+const SYSCALL_WAIT4: usize = 1005;
 const SYSCALL_MUTEX_CREATE: usize = 1010;
 const SYSCALL_MUTEX_LOCK: usize = 1011;
 const SYSCALL_MUTEX_UNLOCK: usize = 1012;
 const SYSCALL_SEMAPHORE_CREATE: usize = 1020;
 const SYSCALL_SEMAPHORE_UP: usize = 1021;
 const SYSCALL_SEMAPHORE_DOWN: usize = 1022;
+const SYSCALL_SEMAPHORE_TRY_DOWN: usize = 1023;
+const SYSCALL_SEMAPHORE_AVAILABLE: usize = 1024;
 const SYSCALL_CONDVAR_CREATE: usize = 1030;
 const SYSCALL_CONDVAR_SIGNAL: usize = 1031;
 const SYSCALL_CONDVAR_WAIT: usize = 1032;
+const SYSCALL_REALPATH: usize = 1040;
+const SYSCALL_ISATTY: usize = 1041;
+const SYSCALL_FINCORE: usize = 1042;
+const SYSCALL_REDIRECT_OUTPUT: usize = 1043;
+const SYSCALL_PROCESS_INFO: usize = 1050;
+const SYSCALL_TCSETPGRP: usize = 1060;
+const SYSCALL_TCGETPGRP: usize = 1061;
 const SYSCALL_FRAMEBUFFER: usize = 2000;
 const SYSCALL_FRAMEBUFFER_FLUSH: usize = 2001;
 const SYSCALL_EVENT_GET: usize = 3000;
@@ -52,10 +101,31 @@ fn syscall(id: usize, args: [usize; 3]) -> isize {
     ret
 }
 
+fn syscall6(id: usize, args: [usize; 6]) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+            "ecall",
+            inlateout("x10") args[0] => ret,
+            in("x11") args[1],
+            in("x12") args[2],
+            in("x13") args[3],
+            in("x14") args[4],
+            in("x15") args[5],
+            in("x17") id
+        );
+    }
+    ret
+}
+
 pub fn sys_getcwd(buf: &mut [u8]) -> isize {
     syscall(SYSCALL_GETCWD, [buf.as_ptr() as usize, buf.len(), 0])
 }
 
+pub fn sys_eventfd(initval: u64) -> isize {
+    syscall(SYSCALL_EVENTFD, [initval as usize, 0, 0])
+}
+
 pub fn sys_dup(fd: usize) -> isize {
     syscall(SYSCALL_DUP, [fd, 0, 0])
 }
@@ -64,18 +134,129 @@ pub fn sys_dup2(old_fd: usize, new_fd: usize) -> isize {
     syscall(SYSCALL_DUP2, [old_fd, new_fd, 0])
 }
 
-pub fn sys_mkdir(path: &str) -> isize {
-    syscall(SYSCALL_MKDIR, [path.as_ptr() as usize, 0, 0])
+pub fn sys_fcntl(fd: usize, cmd: usize, arg: usize) -> isize {
+    syscall(SYSCALL_FCNTL, [fd, cmd, arg])
+}
+
+pub fn sys_mkdirat(dirfd: isize, path: &str) -> isize {
+    syscall(SYSCALL_MKDIRAT, [dirfd as usize, path.as_ptr() as usize, 0])
+}
+
+pub fn sys_unlinkat(dirfd: isize, path: &str, flags: u32) -> isize {
+    syscall(
+        SYSCALL_UNLINKAT,
+        [dirfd as usize, path.as_ptr() as usize, flags as usize],
+    )
+}
+
+pub fn sys_renameat(olddirfd: isize, oldpath: &str, newdirfd: isize, newpath: &str) -> isize {
+    syscall6(
+        SYSCALL_RENAMEAT,
+        [
+            olddirfd as usize,
+            oldpath.as_ptr() as usize,
+            newdirfd as usize,
+            newpath.as_ptr() as usize,
+            0,
+            0,
+        ],
+    )
+}
+
+pub fn sys_renameat2(
+    olddirfd: isize,
+    oldpath: &str,
+    newdirfd: isize,
+    newpath: &str,
+    flags: u32,
+) -> isize {
+    syscall6(
+        SYSCALL_RENAMEAT2,
+        [
+            olddirfd as usize,
+            oldpath.as_ptr() as usize,
+            newdirfd as usize,
+            newpath.as_ptr() as usize,
+            flags as usize,
+            0,
+        ],
+    )
+}
+
+pub fn sys_statfs(path: &str, buf: *mut u8) -> isize {
+    syscall(SYSCALL_STATFS, [path.as_ptr() as usize, buf as usize, 0])
+}
+
+pub fn sys_fstatfs(fd: usize, buf: *mut u8) -> isize {
+    syscall(SYSCALL_FSTATFS, [fd, buf as usize, 0])
+}
+
+pub fn sys_truncate(path: &str, len: usize) -> isize {
+    syscall(SYSCALL_TRUNCATE, [path.as_ptr() as usize, len, 0])
+}
+
+pub fn sys_setxattr(path: &str, name: &str, value: &[u8]) -> isize {
+    syscall6(
+        SYSCALL_SETXATTR,
+        [
+            path.as_ptr() as usize,
+            name.as_ptr() as usize,
+            value.as_ptr() as usize,
+            value.len(),
+            0,
+            0,
+        ],
+    )
+}
+
+pub fn sys_getxattr(path: &str, name: &str, value: &mut [u8]) -> isize {
+    syscall6(
+        SYSCALL_GETXATTR,
+        [
+            path.as_ptr() as usize,
+            name.as_ptr() as usize,
+            value.as_mut_ptr() as usize,
+            value.len(),
+            0,
+            0,
+        ],
+    )
+}
+
+pub fn sys_listxattr(path: &str, list: &mut [u8]) -> isize {
+    syscall(
+        SYSCALL_LISTXATTR,
+        [
+            path.as_ptr() as usize,
+            list.as_mut_ptr() as usize,
+            list.len(),
+        ],
+    )
 }
 
-pub fn sys_unlink(path: &str, flags: u32) -> isize {
-    syscall(SYSCALL_UNLINK, [path.as_ptr() as usize, flags as usize, 0])
+pub fn sys_ftruncate(fd: usize, len: usize) -> isize {
+    syscall(SYSCALL_FTRUNCATE, [fd, len, 0])
+}
+
+pub fn sys_realpath(path: &str, buf: &mut [u8]) -> isize {
+    syscall(
+        SYSCALL_REALPATH,
+        [path.as_ptr() as usize, buf.as_mut_ptr() as usize, buf.len()],
+    )
+}
+
+pub fn sys_isatty(fd: usize) -> isize {
+    syscall(SYSCALL_ISATTY, [fd, 0, 0])
 }
 
 pub fn sys_chdir(path: &str) -> isize {
     syscall(SYSCALL_CHDIR, [path.as_ptr() as usize, 0, 0])
 }
 
+pub fn sys_fchdir(fd: usize) -> isize {
+    syscall(SYSCALL_FCHDIR, [fd, 0, 0])
+}
+
 pub fn sys_open(path: &str, flags: u32) -> isize {
     syscall(SYSCALL_OPEN, [path.as_ptr() as usize, flags as usize, 0])
 }
@@ -84,10 +265,25 @@ pub fn sys_close(fd: usize) -> isize {
     syscall(SYSCALL_CLOSE, [fd, 0, 0])
 }
 
+pub fn sys_close_range(first: usize, last: usize, flags: u32) -> isize {
+    syscall(SYSCALL_CLOSE_RANGE, [first, last, flags as usize])
+}
+
 pub fn sys_pipe(pipe: &mut [usize]) -> isize {
     syscall(SYSCALL_PIPE, [pipe.as_mut_ptr() as usize, 0, 0])
 }
 
+pub fn sys_getdents(fd: usize, buffer: &mut [u8]) -> isize {
+    syscall(
+        SYSCALL_GETDENTS,
+        [fd, buffer.as_mut_ptr() as usize, buffer.len()],
+    )
+}
+
+pub fn sys_lseek(fd: usize, offset: isize, whence: usize) -> isize {
+    syscall(SYSCALL_LSEEK, [fd, offset as usize, whence])
+}
+
 pub fn sys_read(fd: usize, buffer: &mut [u8]) -> isize {
     syscall(
         SYSCALL_READ,
@@ -99,10 +295,78 @@ pub fn sys_write(fd: usize, buffer: &[u8]) -> isize {
     syscall(SYSCALL_WRITE, [fd, buffer.as_ptr() as usize, buffer.len()])
 }
 
+pub fn sys_sendfile(out_fd: usize, in_fd: usize, offset: *mut usize, count: usize) -> isize {
+    syscall6(
+        SYSCALL_SENDFILE,
+        [out_fd, in_fd, offset as usize, count, 0, 0],
+    )
+}
+
+pub fn sys_copy_file_range(
+    fd_in: usize,
+    off_in: *mut usize,
+    fd_out: usize,
+    off_out: *mut usize,
+    len: usize,
+) -> isize {
+    syscall6(
+        SYSCALL_COPY_FILE_RANGE,
+        [fd_in, off_in as usize, fd_out, off_out as usize, len, 0],
+    )
+}
+
+pub fn sys_tee(fd_in: usize, fd_out: usize, len: usize) -> isize {
+    syscall(SYSCALL_TEE, [fd_in, fd_out, len])
+}
+
+pub fn sys_ppoll(fds: *mut u8, nfds: usize, timeout: isize, sigmask: *const i32) -> isize {
+    syscall6(
+        SYSCALL_PPOLL,
+        [fds as usize, nfds, timeout as usize, sigmask as usize, 0, 0],
+    )
+}
+
+pub fn sys_readlinkat(dirfd: isize, path: &str, buf: &mut [u8]) -> isize {
+    syscall6(
+        SYSCALL_READLINKAT,
+        [
+            dirfd as usize,
+            path.as_ptr() as usize,
+            buf.as_mut_ptr() as usize,
+            buf.len(),
+            0,
+            0,
+        ],
+    )
+}
+
 pub fn sys_fstat(fd: usize, stat: *mut u8) -> isize {
     syscall(SYSCALL_FSTAT, [fd, stat as usize, 0])
 }
 
+pub fn sys_fsync(fd: usize) -> isize {
+    syscall(SYSCALL_FSYNC, [fd, 0, 0])
+}
+
+pub fn sys_fdatasync(fd: usize) -> isize {
+    syscall(SYSCALL_FDATASYNC, [fd, 0, 0])
+}
+
+pub fn sys_sync_file_range(fd: usize, offset: usize, len: usize, flags: u32) -> isize {
+    syscall6(
+        SYSCALL_SYNC_FILE_RANGE,
+        [fd, offset, len, flags as usize, 0, 0],
+    )
+}
+
+pub fn sys_fincore(fd: usize, offset: usize, len: usize, vec: *mut u8) -> isize {
+    syscall6(SYSCALL_FINCORE, [fd, offset, len, vec as usize, 0, 0])
+}
+
+pub fn sys_redirect_output(fd: isize) -> isize {
+    syscall(SYSCALL_REDIRECT_OUTPUT, [fd as usize, 0, 0])
+}
+
 /// Terminates the current process with a given exit code.
 ///
 /// # Panics
@@ -125,6 +389,30 @@ pub fn sys_kill(pid: usize, signal: i32) -> isize {
     syscall(SYSCALL_KILL, [pid, signal as usize, 0])
 }
 
+pub fn sys_tgkill(pid: usize, tid: usize, signal: i32) -> isize {
+    syscall(SYSCALL_TGKILL, [pid, tid, signal as usize])
+}
+
+pub fn sys_pidfd_open(pid: usize) -> isize {
+    syscall(SYSCALL_PIDFD_OPEN, [pid, 0, 0])
+}
+
+pub fn sys_pidfd_send_signal(pidfd: usize, signal: i32) -> isize {
+    syscall(SYSCALL_PIDFD_SEND_SIGNAL, [pidfd, signal as usize, 0])
+}
+
+pub fn sys_sigsuspend(mask: *const i32) -> isize {
+    syscall(SYSCALL_SIGSUSPEND, [mask as usize, 0, 0])
+}
+
+pub fn sys_sigpending(set: *mut i32) -> isize {
+    syscall(SYSCALL_SIGPENDING, [set as usize, 0, 0])
+}
+
+pub fn sys_prctl(option: usize, arg: usize) -> isize {
+    syscall(SYSCALL_PRCTL, [option, arg, 0])
+}
+
 pub fn sys_get_time() -> isize {
     syscall(SYSCALL_GET_TIME, [0, 0, 0])
 }
@@ -144,10 +432,66 @@ pub fn sys_exec(path: &str, args: &[*const u8]) -> isize {
     )
 }
 
+pub fn sys_spawn(path: &str, args: &[*const u8]) -> isize {
+    syscall(
+        SYSCALL_SPAWN,
+        [path.as_ptr() as usize, args.as_ptr() as usize, 0],
+    )
+}
+
+pub fn sys_madvise(addr: usize, len: usize, advice: usize) -> isize {
+    syscall(SYSCALL_MADVISE, [addr, len, advice])
+}
+
+pub fn sys_process_info(buf: *mut u8, len: usize) -> isize {
+    syscall(SYSCALL_PROCESS_INFO, [buf as usize, len, 0])
+}
+
+pub fn sys_setpgid(pid: usize, pgid: usize) -> isize {
+    syscall(SYSCALL_SETPGID, [pid, pgid, 0])
+}
+
+pub fn sys_getpgid(pid: usize) -> isize {
+    syscall(SYSCALL_GETPGID, [pid, 0, 0])
+}
+
+pub fn sys_tcsetpgrp(pgid: usize) -> isize {
+    syscall(SYSCALL_TCSETPGRP, [pgid, 0, 0])
+}
+
+pub fn sys_tcgetpgrp() -> isize {
+    syscall(SYSCALL_TCGETPGRP, [0; 3])
+}
+
+pub fn sys_sysinfo(buf: *mut u8) -> isize {
+    syscall(SYSCALL_SYSINFO, [buf as usize, 0, 0])
+}
+
 pub fn sys_waitpid(pid: isize, exit_code: *mut i32) -> isize {
     syscall(SYSCALL_WAITPID, [pid as usize, exit_code as usize, 0])
 }
 
+pub fn sys_wait4(pid: isize, status: *mut i32, options: u32, rusage: *mut u8) -> isize {
+    syscall6(
+        SYSCALL_WAIT4,
+        [
+            pid as usize,
+            status as usize,
+            options as usize,
+            rusage as usize,
+            0,
+            0,
+        ],
+    )
+}
+
+pub fn sys_memfd_create(name: &str, flags: u32) -> isize {
+    syscall(
+        SYSCALL_MEMFD_CREATE,
+        [name.as_ptr() as usize, flags as usize, 0],
+    )
+}
+
 pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
     syscall(SYSCALL_THREAD_CREATE, [entry, arg, 0])
 }
@@ -160,6 +504,59 @@ pub fn sys_waittid(tid: usize) -> isize {
     syscall(SYSCALL_WAITTID, [tid, 0, 0])
 }
 
+pub fn sys_clone(flags: usize, stack: usize) -> isize {
+    syscall(SYSCALL_CLONE, [flags, stack, 0])
+}
+
+/// Clones with `flags`, and makes the child jump to `entry` with `arg` right away
+///
+/// A child sharing the address space starts on a stack of its own, with no frames
+/// to return through, so it must not come back out of here.
+pub fn sys_clone_entry(flags: usize, stack: usize, entry: usize, arg: usize) -> isize {
+    let ret: isize;
+    unsafe {
+        asm!(
+            "ecall",
+            "bnez a0, 1f",
+            "mv a0, {arg}",
+            "jr {entry}",
+            "1:",
+            entry = in(reg) entry,
+            arg = in(reg) arg,
+            inlateout("x10") flags => ret,
+            in("x11") stack,
+            in("x17") SYSCALL_CLONE
+        );
+    }
+    ret
+}
+
+/// Vforks from within the caller's frame
+///
+/// Always inlined, as the child must not return from the function that called `vfork`
+/// and this one would be it otherwise.
+#[inline(always)]
+#[allow(clippy::inline_always)]
+pub fn sys_vfork() -> isize {
+    let ret: isize;
+    unsafe {
+        asm!(
+            "ecall",
+            lateout("x10") ret,
+            in("x17") SYSCALL_VFORK
+        );
+    }
+    ret
+}
+
+pub fn sys_sched_setaffinity(mask: usize) -> isize {
+    syscall(SYSCALL_SCHED_SETAFFINITY, [mask, 0, 0])
+}
+
+pub fn sys_sched_getaffinity() -> isize {
+    syscall(SYSCALL_SCHED_GETAFFINITY, [0; 3])
+}
+
 pub fn sys_mutex_create(blocking: bool) -> isize {
     syscall(SYSCALL_MUTEX_CREATE, [usize::from(blocking), 0, 0])
 }
@@ -184,6 +581,14 @@ pub fn sys_semaphore_down(sem_id: usize) -> isize {
     syscall(SYSCALL_SEMAPHORE_DOWN, [sem_id, 0, 0])
 }
 
+pub fn sys_semaphore_try_down(sem_id: usize) -> isize {
+    syscall(SYSCALL_SEMAPHORE_TRY_DOWN, [sem_id, 0, 0])
+}
+
+pub fn sys_semaphore_available(sem_id: usize) -> isize {
+    syscall(SYSCALL_SEMAPHORE_AVAILABLE, [sem_id, 0, 0])
+}
+
 pub fn sys_condvar_create() -> isize {
     syscall(SYSCALL_CONDVAR_CREATE, [0, 0, 0])
 }
@@ -1,5 +1,7 @@
 use crate::process::yield_;
-use crate::syscall::{sys_gettid, sys_thread_create, sys_waittid};
+use crate::syscall::{
+    sys_gettid, sys_sched_getaffinity, sys_sched_setaffinity, sys_thread_create, sys_waittid,
+};
 
 #[allow(clippy::module_name_repetitions)]
 pub fn thread_create(entry: usize, arg: usize) -> isize {
@@ -20,3 +22,13 @@ pub fn waittid(tid: usize) -> isize {
         }
     }
 }
+
+/// Restrict the current thread to the harts set in `mask`, returning -1 if none exist
+pub fn sched_setaffinity(mask: usize) -> isize {
+    sys_sched_setaffinity(mask)
+}
+
+/// Mask of the harts the current thread may run on
+pub fn sched_getaffinity() -> usize {
+    sys_sched_getaffinity() as usize
+}